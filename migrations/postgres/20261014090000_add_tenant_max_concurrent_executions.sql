@@ -0,0 +1,2 @@
+-- Modify "tenants" table
+ALTER TABLE "tenants" ADD COLUMN "max_concurrent_executions" integer NULL;
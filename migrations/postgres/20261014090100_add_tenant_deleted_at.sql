@@ -0,0 +1,2 @@
+-- Modify "tenants" table
+ALTER TABLE "tenants" ADD COLUMN "deleted_at" timestamptz NULL;
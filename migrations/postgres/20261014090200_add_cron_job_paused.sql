@@ -0,0 +1,2 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "paused" boolean NOT NULL DEFAULT false;
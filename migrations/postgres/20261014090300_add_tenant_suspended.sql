@@ -0,0 +1,2 @@
+-- Modify "tenants" table
+ALTER TABLE "tenants" ADD COLUMN "suspended" boolean NOT NULL DEFAULT false;
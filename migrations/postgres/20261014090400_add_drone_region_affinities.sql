@@ -0,0 +1,2 @@
+-- Modify "drones" table
+ALTER TABLE "drones" ADD COLUMN "region_affinities" jsonb NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "body_hash" text NULL;
@@ -0,0 +1,6 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "fresh_connection" boolean NOT NULL DEFAULT false;
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD COLUMN "fresh_connection" boolean NOT NULL DEFAULT false;
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD COLUMN "fresh_connection" boolean NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "retry_backoff_ms" integer NULL, ADD COLUMN "retry_backoff_max_ms" integer NULL;
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD COLUMN "retry_backoff_ms" integer NULL, ADD COLUMN "retry_backoff_max_ms" integer NULL;
+-- Modify "tenants" table
+ALTER TABLE "tenants" ADD COLUMN "min_retry_backoff_ms" integer NULL;
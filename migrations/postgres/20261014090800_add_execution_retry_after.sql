@@ -0,0 +1,2 @@
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "retry_after_secs" bigint NULL;
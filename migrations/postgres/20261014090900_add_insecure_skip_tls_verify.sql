@@ -0,0 +1,8 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "insecure_skip_tls_verify" boolean NOT NULL DEFAULT false;
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "tls_verify_skipped" boolean NOT NULL DEFAULT false;
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD COLUMN "insecure_skip_tls_verify" boolean NOT NULL DEFAULT false;
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD COLUMN "insecure_skip_tls_verify" boolean NOT NULL DEFAULT false;
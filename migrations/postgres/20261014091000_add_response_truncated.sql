@@ -0,0 +1,2 @@
+-- Modify "http_responses" table
+ALTER TABLE "http_responses" ADD COLUMN "truncated" boolean NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- Modify "tenants" table
+ALTER TABLE "tenants" ADD COLUMN "previous_signing_key" character varying(255) NULL, ADD COLUMN "signing_key_rotated_at" timestamptz NULL, ADD CONSTRAINT "tenants_previous_signing_key_fkey" FOREIGN KEY ("previous_signing_key") REFERENCES "secrets" ("id") ON UPDATE NO ACTION ON DELETE NO ACTION;
@@ -0,0 +1,6 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD CONSTRAINT "cron_jobs_transfer_encoding_check" CHECK (transfer_encoding = ANY (ARRAY['auto'::text, 'chunked'::text, 'length'::text])), ADD COLUMN "transfer_encoding" text NOT NULL DEFAULT 'auto';
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD CONSTRAINT "one_off_jobs_transfer_encoding_check" CHECK (transfer_encoding = ANY (ARRAY['auto'::text, 'chunked'::text, 'length'::text])), ADD COLUMN "transfer_encoding" text NOT NULL DEFAULT 'auto';
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD CONSTRAINT "scheduled_jobs_transfer_encoding_check" CHECK (transfer_encoding = ANY (ARRAY['auto'::text, 'chunked'::text, 'length'::text])), ADD COLUMN "transfer_encoding" text NOT NULL DEFAULT 'auto';
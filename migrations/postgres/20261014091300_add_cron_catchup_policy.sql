@@ -0,0 +1,2 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD CONSTRAINT "cron_jobs_catchup_policy_check" CHECK (catchup_policy = ANY (ARRAY['skip'::text, 'fire_once'::text, 'fire_all'::text])), ADD COLUMN "catchup_policy" text NOT NULL DEFAULT 'skip';
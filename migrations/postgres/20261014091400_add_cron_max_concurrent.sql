@@ -0,0 +1,2 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD CONSTRAINT "cron_jobs_max_concurrent_check" CHECK (max_concurrent > 0), ADD COLUMN "max_concurrent" integer NULL;
@@ -0,0 +1,2 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "end_at" timestamptz NULL, ADD COLUMN "completed_at" timestamptz NULL;
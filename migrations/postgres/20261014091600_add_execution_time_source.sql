@@ -0,0 +1,2 @@
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD CONSTRAINT "job_executions_time_source_check" CHECK (time_source = ANY (ARRAY['drone'::text, 'broker'::text])), ADD COLUMN "time_source" text NOT NULL DEFAULT 'drone';
@@ -0,0 +1,4 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "conditional" boolean NOT NULL DEFAULT false;
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "unchanged" boolean NOT NULL DEFAULT false;
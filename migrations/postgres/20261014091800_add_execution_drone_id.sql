@@ -0,0 +1,2 @@
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "drone_id" character varying(255) NULL;
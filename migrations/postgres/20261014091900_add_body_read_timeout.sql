@@ -0,0 +1,8 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "body_read_timeout_ms" integer NULL;
+-- Modify "http_responses" table
+ALTER TABLE "http_responses" ADD COLUMN "body_read_truncated" boolean NOT NULL DEFAULT false;
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD COLUMN "body_read_timeout_ms" integer NULL;
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD COLUMN "body_read_timeout_ms" integer NULL;
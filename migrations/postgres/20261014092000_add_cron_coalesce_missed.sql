@@ -0,0 +1,4 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "coalesce_missed" boolean NOT NULL DEFAULT false;
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD COLUMN "coalesced" boolean NOT NULL DEFAULT false;
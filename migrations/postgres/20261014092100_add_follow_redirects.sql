@@ -0,0 +1,6 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD COLUMN "follow_redirects" integer NULL;
+-- Modify "one_off_jobs" table
+ALTER TABLE "one_off_jobs" ADD COLUMN "follow_redirects" integer NULL;
+-- Modify "scheduled_jobs" table
+ALTER TABLE "scheduled_jobs" ADD COLUMN "follow_redirects" integer NULL;
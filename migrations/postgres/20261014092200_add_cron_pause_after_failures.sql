@@ -0,0 +1,2 @@
+-- Modify "cron_jobs" table
+ALTER TABLE "cron_jobs" ADD CONSTRAINT "cron_jobs_pause_after_failures_check" CHECK (pause_after_failures > 0), ADD COLUMN "pause_after_failures" integer NULL, ADD COLUMN "consecutive_failures" integer NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Modify "job_executions" table
+ALTER TABLE "job_executions" ADD COLUMN "duration_ms" bigint NULL;
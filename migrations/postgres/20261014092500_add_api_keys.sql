@@ -0,0 +1,13 @@
+-- Create "api_keys" table
+CREATE TABLE "api_keys" (
+  "id" character varying(255) NOT NULL,
+  "tenant_id" character varying(255) NOT NULL,
+  "key_hash" text NOT NULL,
+  "created_at" timestamptz NOT NULL DEFAULT now(),
+  "revoked_at" timestamptz NULL,
+  PRIMARY KEY ("id"),
+  CONSTRAINT "api_keys_key_hash_key" UNIQUE ("key_hash"),
+  CONSTRAINT "api_keys_tenant_id_fkey" FOREIGN KEY ("tenant_id") REFERENCES "tenants" ("id") ON UPDATE NO ACTION ON DELETE NO ACTION
+);
+-- Create index "idx_api_keys_tenant_id" to table: "api_keys"
+CREATE INDEX "idx_api_keys_tenant_id" ON "api_keys" ("tenant_id");
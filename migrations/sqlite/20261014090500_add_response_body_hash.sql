@@ -0,0 +1,2 @@
+-- Add column "body_hash" to table: "execution_responses"
+ALTER TABLE `execution_responses` ADD COLUMN `body_hash` text NULL;
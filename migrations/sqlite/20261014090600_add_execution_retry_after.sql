@@ -0,0 +1,2 @@
+-- Add column "retry_after_secs" to table: "executions"
+ALTER TABLE `executions` ADD COLUMN `retry_after_secs` integer NULL;
@@ -0,0 +1,2 @@
+-- Add column "tls_verify_skipped" to table: "executions"
+ALTER TABLE `executions` ADD COLUMN `tls_verify_skipped` integer NOT NULL DEFAULT 0 CHECK (tls_verify_skipped IN (0, 1));
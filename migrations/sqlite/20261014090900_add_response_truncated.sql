@@ -0,0 +1,2 @@
+-- Add column "truncated" to table: "execution_responses"
+ALTER TABLE `execution_responses` ADD COLUMN `truncated` integer NOT NULL DEFAULT 0 CHECK (truncated IN (0, 1));
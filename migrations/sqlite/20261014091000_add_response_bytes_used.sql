@@ -0,0 +1,2 @@
+-- Add column "bytes_used" to table: "execution_responses"
+ALTER TABLE `execution_responses` ADD COLUMN `bytes_used` integer NOT NULL DEFAULT 0;
@@ -0,0 +1,17 @@
+-- Create "started_executions" table
+CREATE TABLE `started_executions` (
+  `job_id` text NOT NULL,
+  `lock_nonce` integer NOT NULL,
+  `req_method` text NOT NULL,
+  `req_url` text NOT NULL,
+  `req_header_map` text NOT NULL,
+  `req_body` text NULL,
+  `executed_at` integer NOT NULL,
+  `tls_verify_skipped` integer NOT NULL DEFAULT 0,
+  PRIMARY KEY (`job_id`),
+  CHECK (
+    json_valid(req_header_map) AND
+    json_type(req_header_map) = 'object'
+  ),
+  CHECK (tls_verify_skipped IN (0, 1))
+) STRICT;
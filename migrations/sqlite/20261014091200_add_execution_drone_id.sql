@@ -0,0 +1,2 @@
+-- Add column "drone_id" to table: "executions"
+ALTER TABLE `executions` ADD COLUMN `drone_id` text NULL;
@@ -0,0 +1,2 @@
+-- Add column "body_read_truncated" to table: "execution_responses"
+ALTER TABLE `execution_responses` ADD COLUMN `body_read_truncated` integer NOT NULL DEFAULT 0 CHECK (body_read_truncated IN (0, 1));
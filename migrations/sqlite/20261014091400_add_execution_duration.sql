@@ -0,0 +1,2 @@
+-- Add column "duration_ms" to table: "executions"
+ALTER TABLE `executions` ADD COLUMN `duration_ms` integer NULL;
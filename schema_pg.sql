@@ -6,6 +6,7 @@ CREATE TABLE drones (
   region TEXT NOT NULL,
   last_checkin TIMESTAMPTZ NOT NULL,
   checkin_by TIMESTAMPTZ NOT NULL,
+  region_affinities JSONB NOT NULL DEFAULT '{}',
   CONSTRAINT checkin_by_after_last_checkin CHECK (checkin_by > last_checkin)
 );
 
@@ -35,14 +36,31 @@ CREATE TABLE tenants (
   retain_for_days INTEGER NOT NULL,
   max_delay_days INTEGER NOT NULL,
   max_cron_jobs INTEGER NOT NULL,
+  max_concurrent_executions INTEGER,
+  min_retry_backoff_ms INTEGER,
+  suspended BOOLEAN NOT NULL DEFAULT FALSE,
+  deleted_at TIMESTAMPTZ,
+  purge_after TIMESTAMPTZ,
   current_signing_key VARCHAR(255) REFERENCES secrets(id),
   next_signing_key VARCHAR(255) REFERENCES secrets(id),
+  previous_signing_key VARCHAR(255) REFERENCES secrets(id),
+  signing_key_rotated_at TIMESTAMPTZ,
   CONSTRAINT both_signing_keys_or_just_one CHECK (
     (current_signing_key IS NULL AND next_signing_key IS NULL) OR
     (current_signing_key IS NOT NULL AND next_signing_key IS NOT NULL)
   )
 );
 
+CREATE TABLE api_keys (
+  id VARCHAR(255) NOT NULL PRIMARY KEY,
+  tenant_id VARCHAR(255) NOT NULL REFERENCES tenants(id),
+  key_hash TEXT NOT NULL UNIQUE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_api_keys_tenant_id ON api_keys (tenant_id);
+
 CREATE TABLE http_requests (
   id VARCHAR(255) NOT NULL PRIMARY KEY,
   method VARCHAR(10) NOT NULL,
@@ -63,6 +81,8 @@ CREATE TABLE http_responses (
   bytes_used INTEGER NOT NULL GENERATED ALWAYS AS (
     COALESCE(octet_length(body), 0)
   ) STORED,
+  truncated BOOLEAN NOT NULL DEFAULT FALSE,
+  body_read_truncated BOOLEAN NOT NULL DEFAULT FALSE,
   deleted_at TIMESTAMPTZ
 );
 
@@ -75,6 +95,14 @@ CREATE TABLE one_off_jobs (
   timeout_ms INTEGER,
   max_retries INTEGER NOT NULL,
   max_response_bytes INTEGER,
+  fresh_connection BOOLEAN NOT NULL DEFAULT FALSE,
+  insecure_skip_tls_verify BOOLEAN NOT NULL DEFAULT FALSE,
+  transfer_encoding TEXT NOT NULL DEFAULT 'auto' CHECK (transfer_encoding IN ('auto', 'chunked', 'length')),
+  body_read_timeout_ms INTEGER,
+  follow_redirects INTEGER,
+  resolve_override TEXT,
+  retry_backoff_ms INTEGER,
+  retry_backoff_max_ms INTEGER,
   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
   deleted_at TIMESTAMPTZ
 );
@@ -88,9 +116,26 @@ CREATE TABLE cron_jobs (
   timeout_ms INTEGER,
   max_retries INTEGER NOT NULL,
   max_response_bytes INTEGER,
+  fresh_connection BOOLEAN NOT NULL DEFAULT FALSE,
+  insecure_skip_tls_verify BOOLEAN NOT NULL DEFAULT FALSE,
+  transfer_encoding TEXT NOT NULL DEFAULT 'auto' CHECK (transfer_encoding IN ('auto', 'chunked', 'length')),
+  body_read_timeout_ms INTEGER,
+  follow_redirects INTEGER,
+  retry_backoff_ms INTEGER,
+  retry_backoff_max_ms INTEGER,
   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
   start_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  end_at TIMESTAMPTZ,
+  completed_at TIMESTAMPTZ,
+  catchup_policy TEXT NOT NULL DEFAULT 'skip' CHECK (catchup_policy IN ('skip', 'fire_once', 'fire_all')),
+  max_concurrent INTEGER CHECK (max_concurrent > 0),
+  pause_after_failures INTEGER CHECK (pause_after_failures > 0),
+  consecutive_failures INTEGER NOT NULL DEFAULT 0,
+  conditional BOOLEAN NOT NULL DEFAULT FALSE,
+  coalesce_missed BOOLEAN NOT NULL DEFAULT FALSE,
   error TEXT,
+  paused BOOLEAN NOT NULL DEFAULT FALSE,
+  resumed_at TIMESTAMPTZ,
   deleted_at TIMESTAMPTZ
 );
 
@@ -157,7 +202,14 @@ CREATE TABLE job_executions (
   success BOOLEAN,
   request_id VARCHAR(255) NOT NULL UNIQUE REFERENCES http_requests(id),
   response_id VARCHAR(255) UNIQUE REFERENCES http_responses(id),
-  response_error TEXT
+  response_error TEXT,
+  body_hash TEXT,
+  retry_after_secs BIGINT,
+  tls_verify_skipped BOOLEAN NOT NULL DEFAULT FALSE,
+  time_source TEXT NOT NULL DEFAULT 'drone' CHECK (time_source IN ('drone', 'broker')),
+  unchanged BOOLEAN NOT NULL DEFAULT FALSE,
+  drone_id VARCHAR(255),
+  duration_ms BIGINT
 );
 
 CREATE TABLE scheduled_jobs (
@@ -178,6 +230,13 @@ CREATE TABLE scheduled_jobs (
   timeout_ms INTEGER,
   max_retries INTEGER NOT NULL,
   max_response_bytes INTEGER,
+  fresh_connection BOOLEAN NOT NULL DEFAULT FALSE,
+  insecure_skip_tls_verify BOOLEAN NOT NULL DEFAULT FALSE,
+  transfer_encoding TEXT NOT NULL DEFAULT 'auto' CHECK (transfer_encoding IN ('auto', 'chunked', 'length')),
+  body_read_timeout_ms INTEGER,
+  follow_redirects INTEGER,
+  resolve_override TEXT,
+  coalesced BOOLEAN NOT NULL DEFAULT FALSE,
   deleted_at TIMESTAMPTZ,
   CONSTRAINT workflow_and_workflow_execution CHECK (
     (workflow_id IS NULL AND workflow_execution_id IS NULL) OR
@@ -19,10 +19,13 @@ CREATE TABLE executions (
     CHECK (sync_status IN ('local', 'pending', 'synced')),
   sync_time INTEGER,
   sync_nonce INTEGER,
+  retry_after_secs INTEGER,
+  tls_verify_skipped INTEGER NOT NULL DEFAULT 0 CHECK (tls_verify_skipped IN (0, 1)),
+  drone_id TEXT,
+  duration_ms INTEGER,
 
-  CONSTRAINT one_of_response_id_or_response_error CHECK (
-    (response_id IS NOT NULL AND response_error IS NULL) OR
-    (response_id IS NULL AND response_error IS NOT NULL)
+  CONSTRAINT response_id_or_response_error CHECK (
+    response_id IS NOT NULL OR response_error IS NOT NULL
   )
 ) STRICT;
 
@@ -33,5 +36,23 @@ CREATE TABLE execution_responses (
     json_valid(header_map) AND
     json_type(header_map) = 'object'
   ),
-  body TEXT NOT NULL
+  body TEXT NOT NULL,
+  body_hash TEXT,
+  truncated INTEGER NOT NULL DEFAULT 0 CHECK (truncated IN (0, 1)),
+  bytes_used INTEGER NOT NULL DEFAULT 0,
+  body_read_truncated INTEGER NOT NULL DEFAULT 0 CHECK (body_read_truncated IN (0, 1))
+) STRICT;
+
+CREATE TABLE started_executions (
+  job_id TEXT NOT NULL PRIMARY KEY,
+  lock_nonce INTEGER NOT NULL,
+  req_method TEXT NOT NULL,
+  req_url TEXT NOT NULL,
+  req_header_map TEXT NOT NULL CHECK (
+    json_valid(req_header_map) AND
+    json_type(req_header_map) = 'object'
+  ),
+  req_body TEXT,
+  executed_at INTEGER NOT NULL,
+  tls_verify_skipped INTEGER NOT NULL DEFAULT 0 CHECK (tls_verify_skipped IN (0, 1))
 ) STRICT;
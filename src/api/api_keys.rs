@@ -0,0 +1,369 @@
+use axum::extract::{Path, State};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    api::{
+        ApiError, ApiListResponse, Context, TenantId,
+        models::{ApiKey, CreatedApiKey},
+    },
+    id,
+};
+
+/// Keys are only stored hashed, the secret itself is shown once on creation.
+fn hash_key(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn generate_secret() -> String {
+    format!("rk_{}", hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// The tenant an active api key belongs to, if `secret` is one.
+pub(super) async fn tenant_for_key(
+    pool: &Pool<Postgres>,
+    secret: &str,
+) -> sqlx::Result<Option<String>> {
+    sqlx::query_scalar!(
+        r#"
+      SELECT api_key.tenant_id
+      FROM api_keys as api_key
+      INNER JOIN tenants as tenant
+        ON tenant.id = api_key.tenant_id
+      WHERE api_key.key_hash = $1
+        AND api_key.revoked_at IS NULL
+        AND tenant.deleted_at IS NULL
+      "#,
+        hash_key(secret)
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/tenants/{tenant_id}/api_keys",
+  params(("tenant_id", description = "Id of the tenant the key acts as")),
+  responses(
+    (status = 200, description = "Api key created, with its secret", body = CreatedApiKey),
+    (status = "4XX", description = "Tenant not found", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "api keys"
+)]
+#[tracing::instrument(name = "api_create_api_key")]
+async fn create_api_key(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+) -> Result<CreatedApiKey, ApiError> {
+    if let Some(requesting_tenant_id) = requesting_tenant_id
+        && requesting_tenant_id != tenant_id
+    {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let secret = generate_secret();
+
+    let key = sqlx::query!(
+        r#"
+      INSERT INTO api_keys (id, tenant_id, key_hash)
+      SELECT $1, id, $3
+      FROM tenants
+      WHERE id = $2 AND deleted_at IS NULL
+      RETURNING id, tenant_id, created_at
+      "#,
+        id::generate("api_key"),
+        tenant_id,
+        hash_key(&secret)
+    )
+    .fetch_optional(&ctx.pool)
+    .await?
+    .ok_or_else(ApiError::not_found)?;
+
+    Ok(CreatedApiKey {
+        key: ApiKey {
+            id: key.id,
+            tenant_id: key.tenant_id,
+            created_at: key.created_at.timestamp(),
+            revoked_at: None,
+        },
+        secret,
+    })
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/tenants/{tenant_id}/api_keys",
+  params(("tenant_id", description = "Id of the tenant")),
+  responses(
+    (status = 200, description = "The tenant's api keys", body = ApiListResponse<ApiKey>),
+    (status = "4XX", body = ApiError),
+    (status = "5XX", body = ApiError)),
+  tag = "api keys"
+)]
+#[tracing::instrument(name = "api_list_api_keys")]
+async fn list_api_keys(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+) -> Result<ApiListResponse<ApiKey>, ApiError> {
+    if let Some(requesting_tenant_id) = requesting_tenant_id
+        && requesting_tenant_id != tenant_id
+    {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let keys = sqlx::query!(
+        r#"
+      SELECT id, tenant_id, created_at, revoked_at
+      FROM api_keys
+      WHERE tenant_id = $1
+      ORDER BY id DESC
+      "#,
+        tenant_id
+    )
+    .fetch_all(&ctx.pool)
+    .await?;
+
+    let keys: Vec<ApiKey> = keys
+        .into_iter()
+        .map(|key| ApiKey {
+            id: key.id,
+            tenant_id: key.tenant_id,
+            created_at: key.created_at.timestamp(),
+            revoked_at: key.revoked_at.map(|t| t.timestamp()),
+        })
+        .collect();
+
+    Ok(ApiListResponse {
+        count: keys.len(),
+        data: keys,
+        cursor: None,
+        total: None,
+    })
+}
+
+#[utoipa::path(
+  delete,
+  path = "/api/tenants/{tenant_id}/api_keys/{key_id}",
+  params(
+    ("tenant_id", description = "Id of the tenant"),
+    ("key_id", description = "Id of the api key")),
+  responses(
+    (status = 200, description = "Api key revoked", body = ApiKey),
+    (status = "4XX", description = "Api key not found", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "api keys"
+)]
+#[tracing::instrument(name = "api_revoke_api_key")]
+async fn revoke_api_key(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path((tenant_id, key_id)): Path<(String, String)>,
+) -> Result<ApiKey, ApiError> {
+    if let Some(requesting_tenant_id) = requesting_tenant_id
+        && requesting_tenant_id != tenant_id
+    {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let key = sqlx::query!(
+        r#"
+      UPDATE api_keys
+      SET revoked_at = COALESCE(revoked_at, now())
+      WHERE id = $1 AND tenant_id = $2
+      RETURNING id, tenant_id, created_at, revoked_at
+      "#,
+        key_id,
+        tenant_id
+    )
+    .fetch_optional(&ctx.pool)
+    .await?
+    .ok_or_else(ApiError::not_found)?;
+
+    Ok(ApiKey {
+        id: key.id,
+        tenant_id: key.tenant_id,
+        created_at: key.created_at.timestamp(),
+        revoked_at: key.revoked_at.map(|t| t.timestamp()),
+    })
+}
+
+pub fn init_router() -> OpenApiRouter<Context> {
+    OpenApiRouter::new()
+        .routes(routes!(create_api_key, list_api_keys))
+        .routes(routes!(revoke_api_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::{auth_middleware, create_spec, test_context};
+
+    async fn insert_tenant(pool: &PgPool, id: &str) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs)
+            VALUES ($1, 10, 10, 1, '1 minute', 1000, 0, 0, 1024, 1024, 7, 7, 10)
+            "#,
+            id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_api_key_routes_are_documented() {
+        let spec = create_spec();
+
+        let keys = &spec.paths.paths["/api/tenants/{tenant_id}/api_keys"];
+        assert!(keys.post.is_some());
+        assert!(keys.get.is_some());
+        assert!(
+            spec.paths.paths["/api/tenants/{tenant_id}/api_keys/{key_id}"]
+                .delete
+                .is_some()
+        );
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_api_key_resolves_until_revoked(pool: PgPool) -> anyhow::Result<()> {
+        insert_tenant(&pool, "tenant_a").await?;
+        let ctx = test_context(pool);
+
+        let created = create_api_key(
+            State(ctx.clone()),
+            TenantId(None),
+            Path("tenant_a".to_string()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            tenant_for_key(&ctx.pool, &created.secret).await?.as_deref(),
+            Some("tenant_a")
+        );
+        assert!(tenant_for_key(&ctx.pool, "rk_unknown").await?.is_none());
+
+        let listed = list_api_keys(
+            State(ctx.clone()),
+            TenantId(Some("tenant_a".to_string())),
+            Path("tenant_a".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed.count, 1);
+
+        let revoked = revoke_api_key(
+            State(ctx.clone()),
+            TenantId(Some("tenant_a".to_string())),
+            Path(("tenant_a".to_string(), created.key.id.clone())),
+        )
+        .await
+        .unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert!(tenant_for_key(&ctx.pool, &created.secret).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_api_keys_are_scoped_to_their_tenant(pool: PgPool) -> anyhow::Result<()> {
+        insert_tenant(&pool, "tenant_a").await?;
+        let ctx = test_context(pool);
+
+        let rejected = create_api_key(
+            State(ctx.clone()),
+            TenantId(Some("tenant_b".to_string())),
+            Path("tenant_a".to_string()),
+        )
+        .await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::FORBIDDEN));
+
+        let missing = create_api_key(
+            State(ctx.clone()),
+            TenantId(None),
+            Path("tenant_missing".to_string()),
+        )
+        .await;
+        assert!(missing.is_err_and(|err| err.code == StatusCode::NOT_FOUND));
+
+        let created = create_api_key(
+            State(ctx.clone()),
+            TenantId(None),
+            Path("tenant_a".to_string()),
+        )
+        .await
+        .unwrap();
+
+        sqlx::query!("UPDATE tenants SET deleted_at = now() WHERE id = 'tenant_a'")
+            .execute(&ctx.pool)
+            .await?;
+        assert!(tenant_for_key(&ctx.pool, &created.secret).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_api_key_pins_tenant_without_auth_keys(pool: PgPool) -> anyhow::Result<()> {
+        insert_tenant(&pool, "tenant_a").await?;
+        let ctx = test_context(pool);
+
+        let created = create_api_key(
+            State(ctx.clone()),
+            TenantId(None),
+            Path("tenant_a".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let app = axum::Router::new()
+            .route(
+                "/whoami",
+                axum::routing::get(|TenantId(tenant_id): TenantId| async move {
+                    tenant_id.unwrap_or_default()
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                ctx.clone(),
+                auth_middleware,
+            ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/whoami", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+
+        let pinned = client.get(&url).bearer_auth(&created.secret).send().await?;
+        assert_eq!(pinned.status(), StatusCode::OK);
+        assert_eq!(pinned.text().await?, "tenant_a");
+
+        let spoofed = client
+            .get(&url)
+            .bearer_auth(&created.secret)
+            .header("tenant-id", "tenant_b")
+            .send()
+            .await?;
+        assert_eq!(spoofed.status(), StatusCode::FORBIDDEN);
+
+        // Without a key the header is trusted, as auth is off.
+        let anonymous = client
+            .get(&url)
+            .header("tenant-id", "tenant_b")
+            .send()
+            .await?;
+        assert_eq!(anonymous.text().await?, "tenant_b");
+
+        Ok(())
+    }
+}
@@ -1,7 +1,14 @@
-use axum::extract::{Path, Query, State};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
 use chrono::{DateTime, Utc};
-use croner::Cron;
-use serde::Deserialize;
+use croner::{
+    Cron, CronIterator, Direction,
+    parser::{CronParser, Seconds},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
 use std::str::FromStr;
 use utoipa::{IntoParams, ToSchema};
 use utoipa_axum::{router::OpenApiRouter, routes};
@@ -9,9 +16,16 @@ use utoipa_axum::{router::OpenApiRouter, routes};
 use crate::{
     api::{
         ApiError, ApiListResponse, Context, JsonBody, TenantId, executions,
-        models::{CronJob, Execution, HttpRequest},
+        models::{
+            CronJob, Execution, HttpRequest, verify_catchup_policy,
+            verify_insecure_skip_tls_verify, verify_job_limits, verify_retry_backoff,
+            verify_transfer_encoding,
+        },
     },
-    id, util,
+    id,
+    secrets::Secret,
+    signing::SignatureBuilder,
+    util,
 };
 
 struct IntermediateCronJob {
@@ -27,8 +41,25 @@ struct IntermediateCronJob {
     timeout_ms: Option<i32>,
     max_retries: i32,
     max_response_bytes: Option<i32>,
+    fresh_connection: bool,
+    insecure_skip_tls_verify: bool,
+    transfer_encoding: String,
+    catchup_policy: String,
+    max_concurrent: Option<i32>,
+    start_at: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+    conditional: bool,
+    coalesce_missed: bool,
+    retry_backoff_ms: Option<i32>,
+    retry_backoff_max_ms: Option<i32>,
+    body_read_timeout_ms: Option<i32>,
+    follow_redirects: Option<i32>,
+    pause_after_failures: Option<i32>,
+    consecutive_failures: i32,
     created_at: DateTime<Utc>,
     error: Option<String>,
+    paused: bool,
     deleted_at: Option<DateTime<Utc>>,
 }
 
@@ -65,7 +96,24 @@ impl IntermediateCronJob {
             timeout_ms: self.timeout_ms,
             max_retries: self.max_retries,
             max_response_bytes: self.max_response_bytes,
+            fresh_connection: self.fresh_connection,
+            insecure_skip_tls_verify: self.insecure_skip_tls_verify,
+            transfer_encoding: self.transfer_encoding.clone(),
+            catchup_policy: self.catchup_policy.clone(),
+            max_concurrent: self.max_concurrent,
+            start_at: self.start_at.timestamp(),
+            end_at: self.end_at.as_ref().map(DateTime::timestamp),
+            completed_at: self.completed_at.as_ref().map(DateTime::timestamp),
+            conditional: self.conditional,
+            coalesce_missed: self.coalesce_missed,
+            retry_backoff_ms: self.retry_backoff_ms,
+            retry_backoff_max_ms: self.retry_backoff_max_ms,
+            body_read_timeout_ms: self.body_read_timeout_ms,
+            follow_redirects: self.follow_redirects,
+            pause_after_failures: self.pause_after_failures,
+            consecutive_failures: self.consecutive_failures,
             tenant_id: self.tenant_id.clone(),
+            paused: self.paused,
             deleted_at: self.deleted_at.as_ref().map(DateTime::timestamp),
         }
     }
@@ -79,6 +127,47 @@ struct CreateCronJob {
     timeout_ms: Option<i32>,
     max_retries: Option<i32>,
     max_response_bytes: Option<i32>,
+    /// Execute every run on a brand-new connection instead of a pooled one.
+    fresh_connection: Option<bool>,
+    /// Accept invalid or self-signed tls certificates from the target. Only
+    /// allowed when the deployment runs with `--allow-insecure-jobs`.
+    insecure_skip_tls_verify: Option<bool>,
+    /// How the request body is framed: `auto` (the default) and `length`
+    /// send a `Content-Length`, `chunked` streams it with chunked encoding.
+    transfer_encoding: Option<String>,
+    /// What happens to runs missed while the scheduler was down: `skip` (the
+    /// default) drops them, `fire_once` runs once right away, and `fire_all`
+    /// runs each of them, up to the most recent 60.
+    catchup_policy: Option<String>,
+    /// Most runs that may be in flight at once. Further due runs wait until
+    /// one finishes, so a slow target doesn't get overlapping runs.
+    max_concurrent: Option<i32>,
+    /// Unix time before which no runs are scheduled, defaults to now.
+    start_at: Option<i64>,
+    /// Unix time after which no runs are scheduled, for crons that only run
+    /// for a bounded window. The cron job is marked completed once it passes.
+    end_at: Option<i64>,
+    /// Send `If-None-Match`/`If-Modified-Since` from the last successful
+    /// response, so a target that hasn't changed can answer `304` without a
+    /// body. Such runs are recorded as unchanged rather than failed.
+    conditional: Option<bool>,
+    /// When several runs are overdue at once, for instance after the target
+    /// was down, only the most recent is sent and the older ones are skipped.
+    coalesce_missed: Option<bool>,
+    /// Delay before the first retry of a failed run, doubled on each further attempt.
+    retry_backoff_ms: Option<i32>,
+    /// Upper bound on the delay between retries.
+    retry_backoff_max_ms: Option<i32>,
+    /// Stop reading the response body this long after it starts arriving,
+    /// keeping what was read so far.
+    body_read_timeout_ms: Option<i32>,
+    /// Follow up to this many redirects instead of recording the redirect
+    /// response itself. Redirects to private addresses are refused.
+    follow_redirects: Option<i32>,
+    /// Pause the cron job once this many executions in a row have failed,
+    /// retries included, so a target that's permanently broken stops being
+    /// called. Resuming the cron job starts the count over.
+    pause_after_failures: Option<i32>,
 }
 
 #[utoipa::path(
@@ -98,83 +187,130 @@ async fn create_cron_job(
     TenantId(tenant_id): TenantId,
     JsonBody(create_opts): JsonBody<CreateCronJob>,
 ) -> Result<CronJob, ApiError> {
-    let region = create_opts
-        .region
-        .or(ctx.valid_regions.first().cloned())
-        .expect("There are no valid regions.");
-
-    if !ctx.valid_regions.contains(&region) {
-        let region_list = ctx.valid_regions.join(", ");
-
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid region: {}, choose one of the following: {}",
-            region, region_list
-        ))));
-    }
+    let region = ctx.resolve_region(create_opts.region.clone())?;
 
     if let Err(e) = Cron::from_str(&create_opts.schedule) {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid cron schedule '{}': {e}",
-            create_opts.schedule
-        ))));
+        return Err(ApiError::bad_request(
+            "invalid_cron",
+            Some(&format!(
+                "Invalid cron schedule '{}': {e}",
+                create_opts.schedule
+            )),
+        ));
     }
 
     create_opts.request.verify()?;
 
+    verify_insecure_skip_tls_verify(
+        create_opts.insecure_skip_tls_verify,
+        ctx.allow_insecure_jobs,
+    )?;
+    verify_transfer_encoding(create_opts.transfer_encoding.as_deref())?;
+    verify_catchup_policy(create_opts.catchup_policy.as_deref())?;
+    verify_max_concurrent(create_opts.max_concurrent)?;
+    verify_pause_after_failures(create_opts.pause_after_failures)?;
+
+    let start_at = match create_opts.start_at {
+        Some(start_at) => parse_timestamp(start_at)?,
+        None => Utc::now(),
+    };
+    let end_at = create_opts.end_at.map(parse_timestamp).transpose()?;
+    verify_run_window(start_at, end_at)?;
+
+    verify_job_limits(
+        create_opts.timeout_ms,
+        create_opts.max_retries,
+        create_opts.max_response_bytes,
+        create_opts.body_read_timeout_ms,
+        create_opts.follow_redirects,
+    )?;
+
     let mut txn = ctx.pool.begin().await?;
+    // The tenant row stays locked until the cron job is inserted, so
+    // concurrent creates can't both pass the max_cron_jobs check below.
     let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
     } else {
         None
     };
 
     if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
+    }
+
+    if let Some(tenant) = &tenant
+        && tenant.suspended
+    {
+        return Err(ApiError::tenant_suspended());
     }
 
     if let Some(input_timeout) = create_opts.timeout_ms
         && let Some(tenant) = &tenant
         && input_timeout > tenant.max_timeout
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
-            tenant.max_timeout
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
+                tenant.max_timeout
+            )),
+        ));
     }
 
     if let Some(input_max_retries) = create_opts.max_retries
         && let Some(tenant) = &tenant
         && input_max_retries > tenant.max_retries
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max retries of {input_max_retries} is higher than your limit of {}",
-            tenant.max_retries
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max retries of {input_max_retries} is higher than your limit of {}",
+                tenant.max_retries
+            )),
+        ));
     }
 
     if let Some(input_max_response_bytes) = create_opts.max_response_bytes
         && let Some(tenant) = &tenant
         && input_max_response_bytes > tenant.max_max_response_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
-            tenant.max_max_response_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
+                tenant.max_max_response_bytes
+            )),
+        ));
     }
 
     if let Some(body_text) = &create_opts.request.body
         && let Some(tenant) = &tenant
         && body_text.len() as i32 > tenant.max_request_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your request body of {} bytes is higher than your limit of {}",
-            body_text.len(),
-            tenant.max_request_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your request body of {} bytes is higher than your limit of {}",
+                body_text.len(),
+                tenant.max_request_bytes
+            )),
+        ));
     }
 
+    verify_retry_backoff(
+        create_opts.retry_backoff_ms,
+        create_opts.retry_backoff_max_ms,
+        tenant.as_ref().and_then(|t| t.min_retry_backoff_ms),
+    )?;
+
     if let Some(tenant) = &tenant {
         let cron_count = sqlx::query!(
             r#"
@@ -192,10 +328,13 @@ async fn create_cron_job(
 
         if let Some(count) = cron_count.tenant_cron_count {
             if count >= tenant.max_cron_jobs as i64 {
-                return Err(ApiError::bad_request(Some(&format!(
-                    "As part of your plan, you are limited to {} active cron jobs. You currently have {} cron jobs.",
-                    tenant.max_cron_jobs, count
-                ))));
+                return Err(ApiError::bad_request(
+                    "tenant_limit_exceeded",
+                    Some(&format!(
+                        "As part of your plan, you are limited to {} active cron jobs. You currently have {} cron jobs.",
+                        tenant.max_cron_jobs, count
+                    )),
+                ));
             }
         } else {
             return Err(ApiError::internal_server_error(Some(
@@ -233,9 +372,22 @@ async fn create_cron_job(
         .or(tenant.map(|t| t.default_retries))
         .unwrap_or(3);
 
+    let fresh_connection = create_opts.fresh_connection.unwrap_or(false);
+    let conditional = create_opts.conditional.unwrap_or(false);
+    let coalesce_missed = create_opts.coalesce_missed.unwrap_or(false);
+    let insecure_skip_tls_verify = create_opts.insecure_skip_tls_verify.unwrap_or(false);
+    let transfer_encoding = create_opts
+        .transfer_encoding
+        .clone()
+        .unwrap_or_else(|| "auto".to_string());
+    let catchup_policy = create_opts
+        .catchup_policy
+        .clone()
+        .unwrap_or_else(|| "skip".to_string());
+
     sqlx::query!(r#"
-      INSERT INTO cron_jobs (id, region, tenant_id, request_id, schedule, timeout_ms, max_retries, max_response_bytes)
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+      INSERT INTO cron_jobs (id, region, tenant_id, request_id, schedule, timeout_ms, max_retries, max_response_bytes, fresh_connection, insecure_skip_tls_verify, retry_backoff_ms, retry_backoff_max_ms, transfer_encoding, catchup_policy, max_concurrent, start_at, end_at, conditional, body_read_timeout_ms, coalesce_missed, follow_redirects, pause_after_failures)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
       "#,
         job_id,
         region,
@@ -244,13 +396,29 @@ async fn create_cron_job(
         create_opts.schedule,
         create_opts.timeout_ms,
         max_retries,
-        create_opts.max_response_bytes
+        create_opts.max_response_bytes,
+        fresh_connection,
+        insecure_skip_tls_verify,
+        create_opts.retry_backoff_ms,
+        create_opts.retry_backoff_max_ms,
+        transfer_encoding,
+        catchup_policy,
+        create_opts.max_concurrent,
+        start_at,
+        end_at,
+        conditional,
+        create_opts.body_read_timeout_ms,
+        coalesce_missed,
+        create_opts.follow_redirects,
+        create_opts.pause_after_failures
     )
     .execute(&mut *txn)
     .await?;
 
     txn.commit().await?;
 
+    ctx.metrics.record_cron_job_created();
+
     let job = CronJob {
         id: job_id,
         region,
@@ -260,7 +428,24 @@ async fn create_cron_job(
         timeout_ms: create_opts.timeout_ms,
         max_retries,
         max_response_bytes: create_opts.max_response_bytes,
+        fresh_connection,
+        insecure_skip_tls_verify,
+        transfer_encoding,
+        catchup_policy,
+        max_concurrent: create_opts.max_concurrent,
+        start_at: start_at.timestamp(),
+        end_at: end_at.as_ref().map(DateTime::timestamp),
+        completed_at: None,
+        conditional,
+        coalesce_missed,
+        retry_backoff_ms: create_opts.retry_backoff_ms,
+        retry_backoff_max_ms: create_opts.retry_backoff_max_ms,
+        body_read_timeout_ms: create_opts.body_read_timeout_ms,
+        follow_redirects: create_opts.follow_redirects,
+        pause_after_failures: create_opts.pause_after_failures,
+        consecutive_failures: 0,
         tenant_id,
+        paused: false,
         deleted_at: None,
     };
 
@@ -271,6 +456,7 @@ async fn create_cron_job(
 struct QueryParams {
     cursor: Option<String>,
     limit: Option<i64>,
+    with_total: Option<bool>,
 }
 
 #[utoipa::path(
@@ -306,8 +492,25 @@ async fn list_cron_jobs(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.catchup_policy,
+        job.max_concurrent,
+        job.start_at,
+        job.end_at,
+        job.completed_at,
+        job.conditional,
+        job.coalesce_missed,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.pause_after_failures,
+        job.consecutive_failures,
         job.created_at,
         job.error,
+        job.paused,
         job.deleted_at
       FROM cron_jobs as job
       INNER JOIN http_requests as req
@@ -323,15 +526,22 @@ async fn list_cron_jobs(
         tenant_id.clone(),
         params.cursor
     )
-    .fetch_all(&ctx.pool)
+    .fetch_all(ctx.read_pool())
     .await?;
 
     let job_ids: Vec<String> = jobs.iter().map(|j| j.id.clone()).collect();
 
     let completed_executions =
-        executions::get_executions(job_ids.clone(), tenant_id.clone(), true, 5, &ctx.pool).await?;
-    let not_yet_executed =
-        executions::get_executions(job_ids.clone(), tenant_id.clone(), false, 2, &ctx.pool).await?;
+        executions::get_executions(job_ids.clone(), tenant_id.clone(), true, 5, ctx.read_pool())
+            .await?;
+    let not_yet_executed = executions::get_executions(
+        job_ids.clone(),
+        tenant_id.clone(),
+        false,
+        2,
+        ctx.read_pool(),
+    )
+    .await?;
 
     let executions = completed_executions
         .into_iter()
@@ -340,12 +550,32 @@ async fn list_cron_jobs(
 
     let jobs: Vec<CronJob> = jobs.iter().map(|j| j.to_cron_job(&executions)).collect();
 
+    let total = if params.with_total.unwrap_or(false) {
+        let total = sqlx::query_scalar!(
+            r#"
+      SELECT COUNT(*) as "total!"
+      FROM cron_jobs as job
+      WHERE
+        job.deleted_at IS NULL
+        AND ($1::text IS NULL OR job.tenant_id = $1)
+      "#,
+            tenant_id.clone()
+        )
+        .fetch_one(ctx.read_pool())
+        .await?;
+
+        Some(total)
+    } else {
+        None
+    };
+
     let last_job = jobs.last().map(|j| j.id.clone());
 
     Ok(ApiListResponse {
         count: jobs.len(),
         data: jobs,
         cursor: last_job,
+        total,
     })
 }
 
@@ -357,6 +587,20 @@ struct UpdateCronJob {
     timeout_ms: Option<i32>,
     max_retries: Option<i32>,
     max_response_bytes: Option<i32>,
+    fresh_connection: Option<bool>,
+    insecure_skip_tls_verify: Option<bool>,
+    transfer_encoding: Option<String>,
+    catchup_policy: Option<String>,
+    max_concurrent: Option<i32>,
+    start_at: Option<i64>,
+    end_at: Option<i64>,
+    conditional: Option<bool>,
+    coalesce_missed: Option<bool>,
+    retry_backoff_ms: Option<i32>,
+    retry_backoff_max_ms: Option<i32>,
+    body_read_timeout_ms: Option<i32>,
+    follow_redirects: Option<i32>,
+    pause_after_failures: Option<i32>,
 }
 
 #[utoipa::path(
@@ -380,73 +624,111 @@ async fn update_cron_job(
     if let Some(region) = update_opts.region.clone()
         && !ctx.valid_regions.contains(&region)
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid region: {region}, choose one of the following: {}",
-            ctx.valid_regions.join(", ")
-        ))));
+        return Err(ApiError::bad_request(
+            "invalid_region",
+            Some(&format!(
+                "Invalid region: {region}, choose one of the following: {}",
+                ctx.valid_regions.join(", ")
+            )),
+        ));
     }
 
     if let Some(schedule) = &update_opts.schedule
         && let Err(e) = Cron::from_str(schedule)
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid cron schedule '{}': {e}",
-            schedule
-        ))));
+        return Err(ApiError::bad_request(
+            "invalid_cron",
+            Some(&format!("Invalid cron schedule '{}': {e}", schedule)),
+        ));
     }
 
+    verify_insecure_skip_tls_verify(
+        update_opts.insecure_skip_tls_verify,
+        ctx.allow_insecure_jobs,
+    )?;
+    verify_transfer_encoding(update_opts.transfer_encoding.as_deref())?;
+    verify_catchup_policy(update_opts.catchup_policy.as_deref())?;
+    verify_max_concurrent(update_opts.max_concurrent)?;
+    verify_pause_after_failures(update_opts.pause_after_failures)?;
+
+    verify_job_limits(
+        update_opts.timeout_ms,
+        update_opts.max_retries,
+        update_opts.max_response_bytes,
+        update_opts.body_read_timeout_ms,
+        update_opts.follow_redirects,
+    )?;
+
     let mut txn = ctx.pool.begin().await?;
     let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
     } else {
         None
     };
 
     if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
     }
 
     if let Some(input_timeout) = update_opts.timeout_ms
         && let Some(tenant) = &tenant
         && input_timeout > tenant.max_timeout
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
-            tenant.max_timeout
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
+                tenant.max_timeout
+            )),
+        ));
     }
 
     if let Some(input_max_retries) = update_opts.max_retries
         && let Some(tenant) = &tenant
         && input_max_retries > tenant.max_retries
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max retries of {input_max_retries} is higher than your limit of {}",
-            tenant.max_retries
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max retries of {input_max_retries} is higher than your limit of {}",
+                tenant.max_retries
+            )),
+        ));
     }
 
     if let Some(input_max_response_bytes) = update_opts.max_response_bytes
         && let Some(tenant) = &tenant
         && input_max_response_bytes > tenant.max_max_response_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
-            tenant.max_max_response_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
+                tenant.max_max_response_bytes
+            )),
+        ));
     }
 
     if let Some(body_text) = update_opts.request.as_ref().and_then(|r| r.body.clone())
         && let Some(tenant) = &tenant
         && body_text.len() as i32 > tenant.max_request_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your request body of {} bytes is higher than your limit of {}",
-            body_text.len(),
-            tenant.max_request_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your request body of {} bytes is higher than your limit of {}",
+                body_text.len(),
+                tenant.max_request_bytes
+            )),
+        ));
     }
 
     let existing = sqlx::query!(
@@ -458,6 +740,21 @@ async fn update_cron_job(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.catchup_policy,
+        job.max_concurrent,
+        job.start_at,
+        job.end_at,
+        job.completed_at,
+        job.conditional,
+        job.coalesce_missed,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.pause_after_failures,
         job.request_id as req_id
       FROM cron_jobs as job
       WHERE job.deleted_at IS NULL AND job.id = $1 AND ($2::text IS NULL OR job.tenant_id = $2)
@@ -517,6 +814,53 @@ async fn update_cron_job(
     let new_max_response_bytes = update_opts
         .max_response_bytes
         .or(existing_data.max_response_bytes);
+    let new_fresh_connection = update_opts
+        .fresh_connection
+        .unwrap_or(existing_data.fresh_connection);
+    let new_insecure_skip_tls_verify = update_opts
+        .insecure_skip_tls_verify
+        .unwrap_or(existing_data.insecure_skip_tls_verify);
+    let new_transfer_encoding = update_opts
+        .transfer_encoding
+        .unwrap_or(existing_data.transfer_encoding);
+    let new_catchup_policy = update_opts
+        .catchup_policy
+        .unwrap_or(existing_data.catchup_policy);
+    let new_max_concurrent = update_opts.max_concurrent.or(existing_data.max_concurrent);
+    let new_start_at = match update_opts.start_at {
+        Some(start_at) => parse_timestamp(start_at)?,
+        None => existing_data.start_at,
+    };
+    let new_end_at = match update_opts.end_at {
+        Some(end_at) => Some(parse_timestamp(end_at)?),
+        None => existing_data.end_at,
+    };
+    verify_run_window(new_start_at, new_end_at)?;
+    let new_conditional = update_opts.conditional.unwrap_or(existing_data.conditional);
+    let new_coalesce_missed = update_opts
+        .coalesce_missed
+        .unwrap_or(existing_data.coalesce_missed);
+    let new_retry_backoff_ms = update_opts
+        .retry_backoff_ms
+        .or(existing_data.retry_backoff_ms);
+    let new_retry_backoff_max_ms = update_opts
+        .retry_backoff_max_ms
+        .or(existing_data.retry_backoff_max_ms);
+    let new_body_read_timeout_ms = update_opts
+        .body_read_timeout_ms
+        .or(existing_data.body_read_timeout_ms);
+    let new_follow_redirects = update_opts
+        .follow_redirects
+        .or(existing_data.follow_redirects);
+    let new_pause_after_failures = update_opts
+        .pause_after_failures
+        .or(existing_data.pause_after_failures);
+
+    verify_retry_backoff(
+        new_retry_backoff_ms,
+        new_retry_backoff_max_ms,
+        tenant.as_ref().and_then(|t| t.min_retry_backoff_ms),
+    )?;
 
     let new_job = sqlx::query_as!(
         IntermediateCronJob,
@@ -528,6 +872,21 @@ async fn update_cron_job(
         timeout_ms = $4,
         max_retries = $5,
         max_response_bytes = $6,
+        fresh_connection = $7,
+        insecure_skip_tls_verify = $8,
+        retry_backoff_ms = $9,
+        retry_backoff_max_ms = $10,
+        transfer_encoding = $11,
+        catchup_policy = $12,
+        max_concurrent = $13,
+        start_at = $14,
+        end_at = $15,
+        conditional = $16,
+        body_read_timeout_ms = $17,
+        coalesce_missed = $18,
+        follow_redirects = $19,
+        pause_after_failures = $20,
+        completed_at = NULL,
         error = NULL
       FROM http_requests AS req
       WHERE cron_jobs.id = $1 AND req.id = cron_jobs.request_id
@@ -544,8 +903,25 @@ async fn update_cron_job(
         cron_jobs.timeout_ms,
         cron_jobs.max_retries,
         cron_jobs.max_response_bytes,
+        cron_jobs.fresh_connection,
+        cron_jobs.insecure_skip_tls_verify,
+        cron_jobs.transfer_encoding,
+        cron_jobs.catchup_policy,
+        cron_jobs.max_concurrent,
+        cron_jobs.start_at,
+        cron_jobs.end_at,
+        cron_jobs.completed_at,
+        cron_jobs.conditional,
+        cron_jobs.coalesce_missed,
+        cron_jobs.retry_backoff_ms,
+        cron_jobs.retry_backoff_max_ms,
+        cron_jobs.body_read_timeout_ms,
+        cron_jobs.follow_redirects,
+        cron_jobs.pause_after_failures,
+        cron_jobs.consecutive_failures,
         cron_jobs.created_at,
         cron_jobs.error,
+        cron_jobs.paused,
         cron_jobs.deleted_at
       "#,
         job_id.clone(),
@@ -553,7 +929,21 @@ async fn update_cron_job(
         new_schedule,
         new_timeout_ms,
         new_max_retries,
-        new_max_response_bytes
+        new_max_response_bytes,
+        new_fresh_connection,
+        new_insecure_skip_tls_verify,
+        new_retry_backoff_ms,
+        new_retry_backoff_max_ms,
+        new_transfer_encoding,
+        new_catchup_policy,
+        new_max_concurrent,
+        new_start_at,
+        new_end_at,
+        new_conditional,
+        new_body_read_timeout_ms,
+        new_coalesce_missed,
+        new_follow_redirects,
+        new_pause_after_failures
     )
     .fetch_one(&mut *txn)
     .await?;
@@ -596,15 +986,21 @@ async fn delete_cron_job(
 ) -> Result<CronJob, ApiError> {
     let mut txn = ctx.pool.begin().await?;
     let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
     } else {
         None
     };
 
     if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
     }
 
     let existing = sqlx::query_as!(
@@ -623,8 +1019,25 @@ async fn delete_cron_job(
     job.timeout_ms,
     job.max_retries,
     job.max_response_bytes,
+    job.fresh_connection,
+    job.insecure_skip_tls_verify,
+    job.transfer_encoding,
+    job.catchup_policy,
+    job.max_concurrent,
+    job.start_at,
+    job.end_at,
+    job.completed_at,
+    job.conditional,
+    job.coalesce_missed,
+    job.retry_backoff_ms,
+    job.retry_backoff_max_ms,
+    job.body_read_timeout_ms,
+    job.follow_redirects,
+    job.pause_after_failures,
+    job.consecutive_failures,
     job.created_at,
     job.error,
+    job.paused,
     job.deleted_at
   FROM cron_jobs as job
   INNER JOIN http_requests as req
@@ -698,7 +1111,20 @@ async fn get_cron_job(
     Path(job_id): Path<String>,
     TenantId(tenant_id): TenantId,
 ) -> Result<CronJob, ApiError> {
-    let job = sqlx::query_as!(
+    let job = ctx
+        .read_or_primary(|pool| fetch_cron_job(pool, &job_id, tenant_id.as_deref()))
+        .await?
+        .ok_or_else(ApiError::not_found)?;
+
+    with_executions(job, tenant_id, ctx.read_pool()).await
+}
+
+async fn fetch_cron_job(
+    pool: &Pool<Postgres>,
+    job_id: &str,
+    tenant_id: Option<&str>,
+) -> sqlx::Result<Option<IntermediateCronJob>> {
+    sqlx::query_as!(
         IntermediateCronJob,
         r#"
     SELECT
@@ -714,8 +1140,25 @@ async fn get_cron_job(
       job.timeout_ms,
       job.max_retries,
       job.max_response_bytes,
+      job.fresh_connection,
+      job.insecure_skip_tls_verify,
+      job.transfer_encoding,
+      job.catchup_policy,
+      job.max_concurrent,
+      job.start_at,
+      job.end_at,
+      job.completed_at,
+      job.conditional,
+      job.coalesce_missed,
+      job.retry_backoff_ms,
+      job.retry_backoff_max_ms,
+      job.body_read_timeout_ms,
+      job.follow_redirects,
+      job.pause_after_failures,
+      job.consecutive_failures,
       job.created_at,
       job.error,
+      job.paused,
       job.deleted_at
     FROM cron_jobs as job
     INNER JOIN http_requests as req
@@ -724,7 +1167,287 @@ async fn get_cron_job(
       job.id = $1 AND
       ($2::text IS NULL OR job.tenant_id = $2);
     "#,
+        job_id,
+        tenant_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Attaches the job's latest runs, read from the same pool as the job so a
+/// write path doesn't mix in a lagging replica's view.
+async fn with_executions(
+    job: IntermediateCronJob,
+    tenant_id: Option<String>,
+    pool: &Pool<Postgres>,
+) -> Result<CronJob, ApiError> {
+    let completed_executions =
+        executions::get_executions(vec![job.id.clone()], tenant_id.clone(), true, 5, pool).await?;
+    let not_yet_executed =
+        executions::get_executions(vec![job.id.clone()], tenant_id, false, 2, pool).await?;
+
+    let executions = completed_executions
+        .into_iter()
+        .chain(not_yet_executed.into_iter())
+        .collect::<Vec<_>>();
+
+    Ok(job.to_cron_job(&executions))
+}
+
+async fn set_cron_job_paused(
+    ctx: Context,
+    job_id: String,
+    tenant_id: Option<String>,
+    paused: bool,
+) -> Result<CronJob, ApiError> {
+    let mut txn = ctx.pool.begin().await?;
+
+    let updated = sqlx::query!(
+        r#"
+      UPDATE cron_jobs
+      SET
+        paused = $3,
+        consecutive_failures = CASE WHEN $3 THEN consecutive_failures ELSE 0 END,
+        resumed_at = CASE WHEN paused AND NOT $3 THEN now() ELSE resumed_at END
+      WHERE
+        id = $1
+        AND deleted_at IS NULL
+        AND ($2::text IS NULL OR tenant_id = $2)
+      RETURNING id
+      "#,
         job_id.clone(),
+        tenant_id.clone(),
+        paused
+    )
+    .fetch_optional(&mut *txn)
+    .await?;
+
+    if updated.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    // Pending runs are dropped both when pausing and when resuming, and the
+    // scheduler only catches up on runs missed since `resumed_at`, so nothing
+    // from the paused stretch gets backfilled.
+    sqlx::query!(
+        r#"
+      DELETE FROM scheduled_jobs
+      WHERE cron_job_id = $1
+        AND lock_nonce IS NULL
+        AND execution_id IS NULL
+      "#,
+        job_id.clone()
+    )
+    .execute(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    // Read back from the primary, a replica may still have the old state.
+    let job = fetch_cron_job(&ctx.pool, &job_id, tenant_id.as_deref())
+        .await?
+        .ok_or_else(ApiError::not_found)?;
+
+    with_executions(job, tenant_id, &ctx.pool).await
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/cron/{job_id}/pause",
+  params(("job_id", description = "Id of the cron job")),
+  responses(
+    (status = 200, description = "Cron job paused", body = CronJob),
+    (status = "4XX", description = "Job not found", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "cron jobs"
+)]
+#[tracing::instrument(name = "api_pause_cron_job")]
+async fn pause_cron_job(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+) -> Result<CronJob, ApiError> {
+    set_cron_job_paused(ctx, job_id, tenant_id, true).await
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/cron/{job_id}/resume",
+  params(("job_id", description = "Id of the cron job")),
+  responses(
+    (status = 200, description = "Cron job resumed", body = CronJob),
+    (status = "4XX", description = "Job not found", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "cron jobs"
+)]
+#[tracing::instrument(name = "api_resume_cron_job")]
+async fn resume_cron_job(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+) -> Result<CronJob, ApiError> {
+    set_cron_job_paused(ctx, job_id, tenant_id, false).await
+}
+
+fn parse_timestamp(timestamp: i64) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::from_timestamp_secs(timestamp).ok_or(ApiError::bad_request(
+        "invalid_time",
+        Some(&format!("Invalid time {timestamp}")),
+    ))
+}
+
+/// A window that closes before it opens would never run.
+fn verify_run_window(
+    start_at: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+) -> Result<(), ApiError> {
+    if let Some(end_at) = end_at
+        && end_at <= start_at
+    {
+        return Err(ApiError::bad_request(
+            "invalid_time",
+            Some("Your end_at must be after the cron job's start_at"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A cron job limited to no runs at all would never fire again.
+fn verify_max_concurrent(max_concurrent: Option<i32>) -> Result<(), ApiError> {
+    if let Some(max_concurrent) = max_concurrent
+        && max_concurrent <= 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_max_concurrent",
+            Some(&format!(
+                "Your max concurrent runs of {max_concurrent} must be positive"
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+fn verify_pause_after_failures(pause_after_failures: Option<i32>) -> Result<(), ApiError> {
+    if let Some(pause_after_failures) = pause_after_failures
+        && pause_after_failures <= 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_pause_after_failures",
+            Some(&format!(
+                "Your pause after failures of {pause_after_failures} must be positive"
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+const MAX_PREVIEW_COUNT: usize = 100;
+
+fn upcoming_fire_times(
+    schedule: &str,
+    from: DateTime<Utc>,
+    count: usize,
+) -> Result<Vec<i64>, String> {
+    let cron_parser = CronParser::builder().seconds(Seconds::Optional).build();
+
+    let schedule = cron_parser
+        .parse(schedule)
+        .map_err(|err| format!("{schedule} is not a valid cron expression: {err}"))?;
+
+    let times = CronIterator::new(schedule, from, false, Direction::Forward)
+        .take(count)
+        .map(|datetime| datetime.timestamp())
+        .collect();
+
+    Ok(times)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct NextRunsParams {
+    /// Number of upcoming runs to return, capped at 100.
+    count: Option<usize>,
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/cron/{job_id}/next",
+  params(("job_id", description = "Id of the cron job"), NextRunsParams),
+  responses(
+    (status = 200, description = "Upcoming run times as UTC unix timestamps", body = Vec<i64>),
+    (status = "4XX", description = "Job not found or invalid schedule", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "cron jobs"
+)]
+#[tracing::instrument(name = "api_get_cron_job_next_runs")]
+async fn get_cron_job_next_runs(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+    Query(params): Query<NextRunsParams>,
+) -> Result<Json<Vec<i64>>, ApiError> {
+    let job = ctx
+        .read_or_primary(|pool| {
+            sqlx::query!(
+                r#"
+    SELECT schedule FROM cron_jobs
+    WHERE
+      id = $1
+      AND deleted_at IS NULL
+      AND ($2::text IS NULL OR tenant_id = $2);
+    "#,
+                job_id,
+                tenant_id
+            )
+            .fetch_optional(pool)
+        })
+        .await?;
+
+    if job.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    let count = params.count.unwrap_or(10).min(MAX_PREVIEW_COUNT);
+
+    let times = upcoming_fire_times(&job.unwrap().schedule, Utc::now(), count)
+        .map_err(|err| ApiError::bad_request("invalid_cron", Some(&err)))?;
+
+    Ok(Json(times))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct ContentChangesParams {
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/cron/{job_id}/content-changes",
+  params(("job_id", description = "Id of the cron job"), ContentChangesParams),
+  responses(
+    (status = 200, description = "Executions whose response body changed from the previous execution", body = ApiListResponse<Execution>),
+    (status = "4XX", description = "Job not found", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "cron jobs"
+)]
+#[tracing::instrument(name = "api_get_cron_job_content_changes")]
+async fn get_cron_job_content_changes(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+    Query(params): Query<ContentChangesParams>,
+) -> Result<ApiListResponse<Execution>, ApiError> {
+    let job = sqlx::query!(
+        r#"
+    SELECT id FROM cron_jobs
+    WHERE
+      id = $1
+      AND deleted_at IS NULL
+      AND ($2::text IS NULL OR tenant_id = $2);
+    "#,
+        job_id,
         tenant_id
     )
     .fetch_optional(&ctx.pool)
@@ -734,25 +1457,531 @@ async fn get_cron_job(
         return Err(ApiError::not_found());
     }
 
-    let completed_executions =
-        executions::get_executions(vec![job_id.clone()], tenant_id.clone(), true, 5, &ctx.pool)
-            .await?;
-    let not_yet_executed =
-        executions::get_executions(vec![job_id.clone()], tenant_id.clone(), false, 2, &ctx.pool)
-            .await?;
+    let limit = params.limit.unwrap_or(15).min(250);
+    let changes = executions::get_content_changes(job_id, tenant_id, limit, &ctx.pool).await?;
 
-    let executions = completed_executions
-        .into_iter()
-        .chain(not_yet_executed.into_iter())
-        .collect::<Vec<_>>();
+    Ok(ApiListResponse {
+        count: changes.len(),
+        data: changes,
+        cursor: None,
+        total: None,
+    })
+}
 
-    let job = job.unwrap().to_cron_job(&executions);
+#[derive(Debug, Deserialize, IntoParams)]
+struct SignaturePreviewParams {
+    /// Unix timestamp to sign with, defaults to now.
+    timestamp: Option<i64>,
+}
 
-    Ok(job)
+#[derive(Debug, Serialize, ToSchema)]
+struct SignaturePreview {
+    /// Value of the `Rocktick-Signature` header.
+    signature_header: String,
+    /// The canonical string the signature is computed over.
+    signing_string: String,
+    timestamp: i64,
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/cron/{job_id}/signature-preview",
+  params(("job_id", description = "Id of the cron job"), SignaturePreviewParams),
+  responses(
+    (status = 200, description = "Signature the job would be sent with", body = SignaturePreview),
+    (status = "4XX", description = "Job not found or not signed with a tenant key", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "cron jobs"
+)]
+#[tracing::instrument(name = "api_get_cron_job_signature_preview")]
+async fn get_cron_job_signature_preview(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+    Query(params): Query<SignaturePreviewParams>,
+) -> Result<Json<SignaturePreview>, ApiError> {
+    let job = sqlx::query!(
+        r#"
+    SELECT
+      req.method,
+      req.url,
+      req.body,
+      tenant.current_signing_key as "current_signing_key?"
+    FROM cron_jobs job
+    INNER JOIN http_requests req
+      ON req.id = job.request_id
+    LEFT JOIN tenants tenant
+      ON tenant.id = job.tenant_id
+    WHERE
+      job.id = $1
+      AND job.deleted_at IS NULL
+      AND ($2::text IS NULL OR job.tenant_id = $2);
+    "#,
+        job_id,
+        tenant_id
+    )
+    .fetch_optional(&ctx.pool)
+    .await?;
+
+    if job.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    let job = job.unwrap();
+
+    // Jobs without a tenant are signed with the broker's fallback secret,
+    // which the api never sees.
+    let Some(secret_id) = job.current_signing_key else {
+        return Err(ApiError::bad_request(
+            "unsigned_job",
+            Some("This job is not signed with a tenant signing key"),
+        ));
+    };
+
+    let signing_key = Secret::get(&secret_id, &ctx.pool)
+        .await?
+        .decrypt(&ctx.key_ring)?;
+
+    let time = match params.timestamp {
+        Some(timestamp) => {
+            DateTime::from_timestamp_secs(timestamp).ok_or(ApiError::bad_request(
+                "invalid_time",
+                Some(&format!("Invalid timestamp {timestamp}")),
+            ))?
+        }
+        None => Utc::now(),
+    };
+
+    let builder = SignatureBuilder {
+        signing_key,
+        time,
+        method: job.method,
+        url: job.url,
+        body: job.body,
+    };
+
+    let signing_string = builder
+        .signing_string()
+        .map_err(|err| ApiError::bad_request("invalid_signature", Some(&err.to_string())))?;
+    let signature_header = builder
+        .signature_header()
+        .map_err(|err| ApiError::bad_request("invalid_signature", Some(&err.to_string())))?;
+
+    Ok(Json(SignaturePreview {
+        signature_header,
+        signing_string,
+        timestamp: time.timestamp(),
+    }))
 }
 
 pub fn init_router() -> OpenApiRouter<Context> {
     OpenApiRouter::new()
         .routes(routes!(create_cron_job, list_cron_jobs))
         .routes(routes!(update_cron_job, get_cron_job, delete_cron_job))
+        .routes(routes!(pause_cron_job))
+        .routes(routes!(resume_cron_job))
+        .routes(routes!(get_cron_job_next_runs))
+        .routes(routes!(get_cron_job_content_changes))
+        .routes(routes!(get_cron_job_signature_preview))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::TimeZone;
+    use sqlx::{PgPool, postgres::PgPoolOptions};
+
+    use super::*;
+    use crate::api::test_context;
+
+    fn create_opts() -> CreateCronJob {
+        CreateCronJob {
+            region: None,
+            schedule: "* * * * *".to_string(),
+            request: HttpRequest {
+                method: "GET".to_string(),
+                url: "https://example.com".to_string(),
+                headers: HashMap::new(),
+                body: None,
+            },
+            timeout_ms: None,
+            max_retries: None,
+            max_response_bytes: None,
+            fresh_connection: None,
+            insecure_skip_tls_verify: None,
+            transfer_encoding: None,
+            catchup_policy: None,
+            max_concurrent: None,
+            start_at: None,
+            end_at: None,
+            conditional: None,
+            coalesce_missed: None,
+            retry_backoff_ms: None,
+            retry_backoff_max_ms: None,
+            body_read_timeout_ms: None,
+            follow_redirects: None,
+            pause_after_failures: None,
+        }
+    }
+
+    async fn insert_cron_execution(
+        pool: &PgPool,
+        idx: i64,
+        body: &str,
+        body_hash: &str,
+    ) -> anyhow::Result<()> {
+        let request_id = format!("request_{idx}");
+        let response_id = format!("response_{idx}");
+        let execution_id = format!("execution_{idx}");
+
+        sqlx::query!(
+            "INSERT INTO http_requests (id, method, url, headers) VALUES ($1, 'GET', 'https://example.com', '{}')",
+            request_id
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO http_responses (id, status, headers, body) VALUES ($1, 200, '{}', $2)",
+            response_id,
+            body
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO job_executions (id, executed_at, success, request_id, response_id, body_hash)
+          VALUES ($1, to_timestamp($2::bigint), true, $3, $4, $5)
+          "#,
+            execution_id,
+            1_700_000_000 + idx * 60,
+            request_id,
+            response_id,
+            body_hash
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO scheduled_jobs
+            (id, hash, region, cron_job_id, scheduled_at, request_id, execution_id, max_retries)
+          VALUES ($1, 0, 'na-east', 'cron_a', to_timestamp($2::bigint), $3, $4, 0)
+          "#,
+            format!("scheduled_{idx}"),
+            1_700_000_000 + idx * 60,
+            request_id,
+            execution_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_signature_preview_matches_broker_signature(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let current = Secret::new(
+            "secret_current".to_string(),
+            "signature_current".to_string(),
+            &ctx.key_ring,
+        )?;
+        let next = Secret::new(
+            "secret_next".to_string(),
+            "signature_next".to_string(),
+            &ctx.key_ring,
+        )?;
+        current.put(&ctx.pool).await?;
+        next.put(&ctx.pool).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs,
+              current_signing_key, next_signing_key)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10,
+              'secret_current', 'secret_next')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO http_requests (id, method, url, headers, body)
+            VALUES ('request_cron', 'POST', 'https://example.com/hooks/cron?x=1', '{}', '{"a":1}')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO cron_jobs (id, region, tenant_id, request_id, schedule, max_retries)
+          VALUES ('cron_a', 'na-east', 'tenant_a', 'request_cron', '* * * * *', 0)
+          "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let timestamp = 1_700_000_000;
+
+        let Json(preview) = get_cron_job_signature_preview(
+            State(ctx.clone()),
+            Path("cron_a".to_string()),
+            TenantId(Some("tenant_a".to_string())),
+            Query(SignaturePreviewParams {
+                timestamp: Some(timestamp),
+            }),
+        )
+        .await
+        .unwrap();
+
+        let expected = SignatureBuilder {
+            signing_key: "signature_current".to_string(),
+            time: DateTime::from_timestamp_secs(timestamp).unwrap(),
+            method: "POST".to_string(),
+            url: "https://example.com/hooks/cron?x=1".to_string(),
+            body: Some("{\"a\":1}".to_string()),
+        }
+        .signature_header()?;
+
+        assert_eq!(preview.signature_header, expected);
+        assert_eq!(
+            preview.signing_string,
+            "post.1700000000./hooks/cron.{\"a\":1}"
+        );
+
+        let other_tenant = get_cron_job_signature_preview(
+            State(ctx),
+            Path("cron_a".to_string()),
+            TenantId(Some("tenant_b".to_string())),
+            Query(SignaturePreviewParams { timestamp: None }),
+        )
+        .await;
+        assert!(other_tenant.is_err_and(|err| err.code == http::StatusCode::NOT_FOUND));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_cron_jobs_pages_by_descending_id(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        for _ in 0..5 {
+            create_cron_job(State(ctx.clone()), TenantId(None), JsonBody(create_opts()))
+                .await
+                .unwrap();
+        }
+
+        let page = |cursor: Option<String>| {
+            list_cron_jobs(
+                State(ctx.clone()),
+                TenantId(None),
+                Query(QueryParams {
+                    cursor,
+                    limit: Some(3),
+                    with_total: None,
+                }),
+            )
+        };
+
+        let first = page(None).await.unwrap();
+        assert_eq!(first.count, 3);
+        assert!(first.data.is_sorted_by(|a, b| a.id > b.id));
+
+        let cursor = first.cursor.clone().unwrap();
+        let second = page(Some(cursor.clone())).await.unwrap();
+        assert_eq!(second.count, 2);
+        assert!(second.data.iter().all(|job| job.id < cursor));
+        assert!(
+            second
+                .data
+                .iter()
+                .all(|job| first.data.iter().all(|seen| seen.id != job.id))
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_respects_max_cron_jobs(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 2)
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let tenant = || TenantId(Some("tenant_a".to_string()));
+
+        let mut created = Vec::new();
+        for _ in 0..2 {
+            let job = create_cron_job(State(ctx.clone()), tenant(), JsonBody(create_opts()))
+                .await
+                .unwrap();
+            created.push(job.id);
+        }
+
+        let over_limit =
+            create_cron_job(State(ctx.clone()), tenant(), JsonBody(create_opts())).await;
+        assert!(over_limit.is_err_and(|err| err.code == http::StatusCode::BAD_REQUEST));
+
+        // Deleted cron jobs don't count towards the limit.
+        sqlx::query!(
+            "UPDATE cron_jobs SET deleted_at = now() WHERE id = $1",
+            created[0]
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        create_cron_job(State(ctx), tenant(), JsonBody(create_opts()))
+            .await
+            .unwrap();
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_content_changes_between_executions(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO http_requests (id, method, url, headers) VALUES ('request_cron', 'GET', 'https://example.com', '{}')"
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries)
+          VALUES ('cron_a', 'na-east', 'request_cron', '* * * * *', 0)
+          "#
+        )
+        .execute(&pool)
+        .await?;
+
+        insert_cron_execution(&pool, 1, "v1", "hash_v1").await?;
+        insert_cron_execution(&pool, 2, "v1", "hash_v1").await?;
+        insert_cron_execution(&pool, 3, "v2", "hash_v2").await?;
+
+        let changes = get_cron_job_content_changes(
+            State(test_context(pool)),
+            Path("cron_a".to_string()),
+            TenantId(None),
+            Query(ContentChangesParams { limit: None }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(changes.count, 1);
+        assert_eq!(changes.data[0].id, "scheduled_3");
+        assert_eq!(changes.data[0].body_hash.as_deref(), Some("hash_v2"));
+        assert_eq!(changes.data[0].response.as_ref().unwrap().body, "v2");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_pause_reads_back_from_the_primary(pool: PgPool) -> anyhow::Result<()> {
+        // A schema shadowing the cron tables stands in for a replica that
+        // still has the job as it was before the pause.
+        sqlx::raw_sql(
+            "
+            CREATE SCHEMA replica;
+            CREATE TABLE replica.http_requests (LIKE public.http_requests INCLUDING DEFAULTS);
+            CREATE TABLE replica.http_responses (LIKE public.http_responses INCLUDING DEFAULTS);
+            CREATE TABLE replica.cron_jobs (LIKE public.cron_jobs INCLUDING DEFAULTS);
+            CREATE TABLE replica.job_executions (LIKE public.job_executions INCLUDING DEFAULTS);
+            CREATE TABLE replica.scheduled_jobs (LIKE public.scheduled_jobs INCLUDING DEFAULTS);
+            ",
+        )
+        .execute(&pool)
+        .await?;
+
+        let replica_options = (*pool.connect_options())
+            .clone()
+            .options([("search_path", "replica")]);
+        let read_replica = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(replica_options)
+            .await?;
+
+        let ctx = Context {
+            read_replica: Some(read_replica),
+            ..test_context(pool)
+        };
+
+        let job = create_cron_job(State(ctx.clone()), TenantId(None), JsonBody(create_opts()))
+            .await
+            .unwrap();
+        sqlx::raw_sql(
+            "
+            INSERT INTO replica.http_requests SELECT * FROM public.http_requests;
+            INSERT INTO replica.cron_jobs SELECT * FROM public.cron_jobs;
+            ",
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let paused = pause_cron_job(State(ctx.clone()), Path(job.id.clone()), TenantId(None))
+            .await
+            .unwrap();
+        assert!(paused.paused);
+
+        // Plain reads still go to the replica.
+        let fetched = get_cron_job(State(ctx.clone()), Path(job.id), TenantId(None))
+            .await
+            .unwrap();
+        assert!(!fetched.paused);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upcoming_fire_times() {
+        let from = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 30).unwrap();
+        let times = upcoming_fire_times("*/15 * * * *", from, 3).unwrap();
+
+        let expected: Vec<i64> = [15, 30, 45]
+            .into_iter()
+            .map(|minute| {
+                Utc.with_ymd_and_hms(2026, 1, 1, 0, minute, 0)
+                    .unwrap()
+                    .timestamp()
+            })
+            .collect();
+
+        assert_eq!(times, expected);
+    }
+
+    #[test]
+    fn test_upcoming_fire_times_with_seconds() {
+        let from = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let times = upcoming_fire_times("*/20 * * * * *", from, 2).unwrap();
+
+        assert_eq!(times, vec![from.timestamp() + 20, from.timestamp() + 40]);
+    }
+
+    #[test]
+    fn test_upcoming_fire_times_invalid_schedule() {
+        assert!(upcoming_fire_times("not a cron", Utc::now(), 5).is_err());
+    }
 }
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, routing::get};
+use utoipa_axum::router::OpenApiRouter;
+
+use crate::api::{ApiError, ApiListResponse, Context, TenantId, models::Drone};
+
+#[tracing::instrument(name = "api_list_drones")]
+async fn list_drones(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+) -> Result<ApiListResponse<Drone>, ApiError> {
+    if requesting_tenant_id.is_some() {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let drones = sqlx::query!(
+        r#"
+      SELECT
+        id,
+        ip,
+        port,
+        region,
+        last_checkin,
+        checkin_by,
+        region_affinities,
+        checkin_by > now() as "active!"
+      FROM drones
+      ORDER BY region, id;
+      "#
+    )
+    .fetch_all(ctx.read_pool())
+    .await?;
+
+    let drones: Vec<Drone> = drones
+        .into_iter()
+        .map(|drone| Drone {
+            id: drone.id,
+            ip: drone.ip.ip().to_string(),
+            port: drone.port,
+            region: drone.region,
+            last_checkin: drone.last_checkin.timestamp(),
+            checkin_by: drone.checkin_by.timestamp(),
+            active: drone.active,
+            region_affinities: serde_json::from_value::<HashMap<String, i32>>(
+                drone.region_affinities,
+            )
+            .unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(ApiListResponse {
+        count: drones.len(),
+        data: drones,
+        cursor: None,
+        total: None,
+    })
+}
+
+pub fn init_router() -> OpenApiRouter<Context> {
+    OpenApiRouter::new().route("/api/drones", get(list_drones))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::test_context;
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_drones_surfaces_region_affinities(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO drones (id, ip, port, region, last_checkin, checkin_by, region_affinities)
+            VALUES (
+              'drone_a', '10.0.0.1', 30002, 'na-east',
+              now(), now() + interval '15 seconds',
+              '{"na-east": 100, "na-west": 20}')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let drones = list_drones(State(ctx.clone()), TenantId(None))
+            .await
+            .unwrap();
+
+        assert_eq!(drones.count, 1);
+        assert_eq!(drones.data[0].ip, "10.0.0.1");
+        assert!(drones.data[0].active);
+        assert_eq!(
+            drones.data[0].region_affinities,
+            HashMap::from([("na-east".to_string(), 100), ("na-west".to_string(), 20)])
+        );
+
+        let rejected = list_drones(State(ctx), TenantId(Some("tenant_a".to_string()))).await;
+        assert!(rejected.is_err());
+
+        Ok(())
+    }
+}
@@ -18,6 +18,12 @@ struct IntermediateExecution {
     success: Option<bool>,
     executed_at: Option<DateTime<Utc>>,
     response_error: Option<String>,
+    body_hash: Option<String>,
+    tls_verify_skipped: Option<bool>,
+    time_source: Option<String>,
+    unchanged: Option<bool>,
+    drone_id: Option<String>,
+    duration_ms: Option<i64>,
     method: String,
     url: String,
     req_headers: Vec<String>,
@@ -25,6 +31,9 @@ struct IntermediateExecution {
     status: Option<i32>,
     res_headers: Option<Vec<String>>,
     res_body: Option<String>,
+    res_bytes_used: Option<i32>,
+    res_truncated: Option<bool>,
+    res_body_read_truncated: Option<bool>,
     timeout_ms: Option<i32>,
     max_retries: i32,
     max_response_bytes: Option<i32>,
@@ -70,10 +79,19 @@ impl IntermediateExecution {
                         })
                         .collect(),
                     body,
+                    bytes_used: self.res_bytes_used.unwrap_or(0),
+                    truncated: self.res_truncated.unwrap_or(false),
+                    body_read_truncated: self.res_body_read_truncated.unwrap_or(false),
                 }),
                 _ => None,
             },
             response_error: self.response_error.clone(),
+            body_hash: self.body_hash.clone(),
+            tls_verify_skipped: self.tls_verify_skipped.unwrap_or(false),
+            time_source: self.time_source.clone(),
+            unchanged: self.unchanged.unwrap_or(false),
+            drone_id: self.drone_id.clone(),
+            duration_ms: self.duration_ms,
             timeout_ms: self.timeout_ms,
             max_retries: self.max_retries,
             max_response_bytes: self.max_response_bytes,
@@ -94,6 +112,10 @@ struct QueryParams {
     limit: Option<i64>,
     one_off_job_id: Option<String>,
     cron_id: Option<String>,
+    success: Option<bool>,
+    status_min: Option<i32>,
+    status_max: Option<i32>,
+    with_total: Option<bool>,
 }
 
 #[utoipa::path(
@@ -123,6 +145,12 @@ async fn list_executions(
         exe.success as "success?",
         exe.executed_at as "executed_at?",
         exe.response_error as "response_error?",
+        exe.body_hash as "body_hash?",
+        exe.tls_verify_skipped as "tls_verify_skipped?",
+        exe.time_source as "time_source?",
+        exe.unchanged as "unchanged?",
+        exe.drone_id as "drone_id?",
+        exe.duration_ms as "duration_ms?",
         req.method,
         req.url,
         req.headers as req_headers,
@@ -130,6 +158,9 @@ async fn list_executions(
         res.status as "status?",
         res.headers as "res_headers?",
         res.body as "res_body?",
+        res.bytes_used as "res_bytes_used?",
+        res.truncated as "res_truncated?",
+        res.body_read_truncated as "res_body_read_truncated?",
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
@@ -155,6 +186,9 @@ async fn list_executions(
         AND ($6::bigint IS NULL OR job.scheduled_at <= to_timestamp($6))
         AND ($7::text IS NULL OR job.one_off_job_id = $7)
         AND ($8::text IS NULL OR job.cron_job_id = $8)
+        AND ($9::bool IS NULL OR exe.success = $9)
+        AND ($10::int IS NULL OR res.status >= $10)
+        AND ($11::int IS NULL OR res.status <= $11)
       ORDER BY job.id DESC
       LIMIT $1;
       "#,
@@ -166,10 +200,55 @@ async fn list_executions(
         params.to,
         params.one_off_job_id,
         params.cron_id,
+        params.success,
+        params.status_min,
+        params.status_max,
     )
-    .fetch_all(&ctx.pool)
+    .fetch_all(ctx.read_pool())
     .await?;
 
+    // The same filters as the page, without the cursor.
+    let total = if params.with_total.unwrap_or(false) {
+        let total = sqlx::query_scalar!(
+            r#"
+      SELECT COUNT(*) as "total!"
+      FROM scheduled_jobs as job
+      LEFT JOIN job_executions exe
+        ON job.execution_id = exe.id
+      LEFT JOIN http_responses res
+        ON exe.response_id = res.id
+      WHERE
+        job.deleted_at IS NULL
+        AND ($1::text IS NULL OR job.tenant_id = $1)
+        AND ($2::bool IS NULL OR
+          ($2 = true AND exe.id IS NOT NULL) OR
+          ($2 = false AND exe.id IS NULL))
+        AND ($3::bigint IS NULL OR job.scheduled_at >= to_timestamp($3))
+        AND ($4::bigint IS NULL OR job.scheduled_at <= to_timestamp($4))
+        AND ($5::text IS NULL OR job.one_off_job_id = $5)
+        AND ($6::text IS NULL OR job.cron_job_id = $6)
+        AND ($7::bool IS NULL OR exe.success = $7)
+        AND ($8::int IS NULL OR res.status >= $8)
+        AND ($9::int IS NULL OR res.status <= $9)
+      "#,
+            tenant_id,
+            params.completed,
+            params.from,
+            params.to,
+            params.one_off_job_id,
+            params.cron_id,
+            params.success,
+            params.status_min,
+            params.status_max,
+        )
+        .fetch_one(ctx.read_pool())
+        .await?;
+
+        Some(total)
+    } else {
+        None
+    };
+
     let executions: Vec<Execution> = results
         .iter()
         .map(IntermediateExecution::to_execution)
@@ -181,6 +260,7 @@ async fn list_executions(
         count: executions.len(),
         data: executions,
         cursor: last_execution,
+        total,
     })
 }
 
@@ -203,9 +283,11 @@ async fn get_execution(
     Path(execution_id): Path<String>,
     TenantId(tenant_id): TenantId,
 ) -> Result<Execution, ApiError> {
-    let execution = sqlx::query_as!(
-        IntermediateExecution,
-        r#"
+    let execution = ctx
+        .read_or_primary(|pool| {
+            sqlx::query_as!(
+                IntermediateExecution,
+                r#"
     SELECT
       job.id,
       job.region,
@@ -213,6 +295,12 @@ async fn get_execution(
       exe.success as "success?",
       exe.executed_at as "executed_at?",
       exe.response_error as "response_error?",
+      exe.body_hash as "body_hash?",
+      exe.tls_verify_skipped as "tls_verify_skipped?",
+      exe.time_source as "time_source?",
+      exe.unchanged as "unchanged?",
+      exe.drone_id as "drone_id?",
+      exe.duration_ms as "duration_ms?",
       req.method,
       req.url,
       req.headers as req_headers,
@@ -220,6 +308,9 @@ async fn get_execution(
       res.status as "status?",
       res.headers as "res_headers?",
       res.body as "res_body?",
+      res.bytes_used as "res_bytes_used?",
+      res.truncated as "res_truncated?",
+      res.body_read_truncated as "res_body_read_truncated?",
       job.timeout_ms,
       job.max_retries,
       job.max_response_bytes,
@@ -237,18 +328,125 @@ async fn get_execution(
     WHERE
       job.id = $1 AND job.deleted_at IS NULL
       AND ($2::text IS NULL OR job.tenant_id = $2)
+    "#,
+                execution_id,
+                tenant_id
+            )
+            .fetch_optional(pool)
+        })
+        .await?;
+
+    if execution.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    let execution = execution.unwrap();
+
+    Ok(execution.to_execution())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/executions/{execution_id}",
+    responses(
+        (status = 200, description = "Scheduled execution cancelled", body = Execution),
+        (status = 404, description = "Execution not found", body = ApiError),
+        (status = 409, description = "Execution already dispatched or completed", body = ApiError),
+        (status = "5XX", description = "Internal Server Error", body = ApiError),
+    ),
+    params(
+        ("execution_id" = String, Path, description = "Execution ID"),
+    ),
+    tag = "executions"
+)]
+#[tracing::instrument(name = "api_cancel_execution")]
+async fn cancel_execution(
+    State(ctx): State<Context>,
+    Path(execution_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+) -> Result<Execution, ApiError> {
+    let mut txn = ctx.pool.begin().await?;
+
+    let scheduled = sqlx::query!(
+        r#"
+    SELECT lock_nonce, execution_id
+    FROM scheduled_jobs
+    WHERE
+      id = $1 AND deleted_at IS NULL
+      AND ($2::text IS NULL OR tenant_id = $2)
+    FOR UPDATE
     "#,
         execution_id,
         tenant_id
     )
-    .fetch_optional(&ctx.pool)
+    .fetch_optional(&mut *txn)
     .await?;
 
-    if execution.is_none() {
+    let Some(scheduled) = scheduled else {
         return Err(ApiError::not_found());
+    };
+
+    if scheduled.execution_id.is_some() {
+        return Err(ApiError::conflict(Some("Execution has already completed")));
     }
 
-    let execution = execution.unwrap();
+    if scheduled.lock_nonce.is_some() {
+        return Err(ApiError::conflict(Some(
+            "Execution has already been dispatched to a drone",
+        )));
+    }
+
+    // The row is soft deleted rather than removed so the schedulers still see
+    // this slot as taken and don't schedule it again.
+    let execution = sqlx::query_as!(
+        IntermediateExecution,
+        r#"
+    WITH cancelled AS (
+      UPDATE scheduled_jobs
+      SET deleted_at = now()
+      WHERE id = $1
+      RETURNING *
+    )
+    SELECT
+      job.id as "id!",
+      job.region as "region!",
+      job.scheduled_at as "scheduled_at!",
+      NULL::bool as "success?",
+      NULL::timestamptz as "executed_at?",
+      NULL::text as "response_error?",
+      NULL::text as "body_hash?",
+      NULL::bool as "tls_verify_skipped?",
+      NULL::text as "time_source?",
+      NULL::bool as "unchanged?",
+      NULL::text as "drone_id?",
+      NULL::bigint as "duration_ms?",
+      req.method,
+      req.url,
+      req.headers as req_headers,
+      req.body as req_body,
+      NULL::int as "status?",
+      NULL::text[] as "res_headers?",
+      NULL::text as "res_body?",
+      NULL::int as "res_bytes_used?",
+      NULL::bool as "res_truncated?",
+      NULL::bool as "res_body_read_truncated?",
+      job.timeout_ms,
+      job.max_retries as "max_retries!",
+      job.max_response_bytes,
+      job.tenant_id,
+      job.one_off_job_id,
+      job.cron_job_id,
+      job.retry_for_id
+    FROM cancelled as job
+    INNER JOIN http_requests as req
+      ON req.id = job.request_id
+    "#,
+        execution_id
+    )
+    .fetch_one(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
 
     Ok(execution.to_execution())
 }
@@ -273,6 +471,12 @@ where
     exe.success as "success?",
     exe.executed_at as "executed_at?",
     exe.response_error as "response_error?",
+    exe.body_hash as "body_hash?",
+    exe.tls_verify_skipped as "tls_verify_skipped?",
+    exe.time_source as "time_source?",
+    exe.unchanged as "unchanged?",
+    exe.drone_id as "drone_id?",
+    exe.duration_ms as "duration_ms?",
     req.method,
     req.url,
     req.headers as req_headers,
@@ -280,6 +484,9 @@ where
     res.status as "status?",
     res.headers as "res_headers?",
     res.body as "res_body?",
+    res.bytes_used as "res_bytes_used?",
+    res.truncated as "res_truncated?",
+    res.body_read_truncated as "res_body_read_truncated?",
     job.timeout_ms,
     job.max_retries,
     job.max_response_bytes,
@@ -335,8 +542,321 @@ where
         .collect())
 }
 
+/// Executions of a cron job whose response body hash differs from the
+/// execution before it, most recent first.
+pub async fn get_content_changes<'a, E>(
+    cron_job_id: String,
+    tenant_id: Option<String>,
+    limit: i64,
+    executor: E,
+) -> Result<Vec<Execution>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let executions = sqlx::query_as!(
+        IntermediateExecution,
+        r#"
+  WITH hashed AS (
+    SELECT
+      job.id,
+      exe.executed_at,
+      exe.body_hash,
+      LAG(exe.body_hash) OVER (ORDER BY exe.executed_at ASC, job.id ASC) as prev_hash
+    FROM scheduled_jobs job
+    INNER JOIN job_executions exe
+      ON job.execution_id = exe.id
+    WHERE
+      job.deleted_at IS NULL
+      AND job.cron_job_id = $1
+      AND ($2::text IS NULL OR job.tenant_id = $2)
+      AND exe.body_hash IS NOT NULL
+  )
+  SELECT
+    job.id,
+    job.region,
+    job.scheduled_at,
+    exe.success as "success?",
+    exe.executed_at as "executed_at?",
+    exe.response_error as "response_error?",
+    exe.body_hash as "body_hash?",
+    exe.tls_verify_skipped as "tls_verify_skipped?",
+    exe.time_source as "time_source?",
+    exe.unchanged as "unchanged?",
+    exe.drone_id as "drone_id?",
+    exe.duration_ms as "duration_ms?",
+    req.method,
+    req.url,
+    req.headers as req_headers,
+    req.body as req_body,
+    res.status as "status?",
+    res.headers as "res_headers?",
+    res.body as "res_body?",
+    res.bytes_used as "res_bytes_used?",
+    res.truncated as "res_truncated?",
+    res.body_read_truncated as "res_body_read_truncated?",
+    job.timeout_ms,
+    job.max_retries,
+    job.max_response_bytes,
+    job.tenant_id,
+    job.one_off_job_id,
+    job.cron_job_id,
+    job.retry_for_id
+  FROM hashed
+  INNER JOIN scheduled_jobs as job
+    ON job.id = hashed.id
+  INNER JOIN http_requests as req
+    ON req.id = job.request_id
+  LEFT JOIN job_executions exe
+    ON job.execution_id = exe.id
+  LEFT JOIN http_responses res
+    ON exe.response_id = res.id
+  WHERE
+    hashed.prev_hash IS NOT NULL
+    AND hashed.prev_hash <> hashed.body_hash
+  ORDER BY hashed.executed_at DESC, hashed.id DESC
+  LIMIT $3
+  "#,
+        cron_job_id,
+        tenant_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(executions
+        .iter()
+        .map(IntermediateExecution::to_execution)
+        .collect())
+}
+
 pub fn init_router() -> OpenApiRouter<Context> {
     OpenApiRouter::new()
         .routes(routes!(list_executions))
-        .routes(routes!(get_execution))
+        .routes(routes!(get_execution, cancel_execution))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::test_context;
+
+    async fn insert_execution(pool: &PgPool, idx: i64, status: i32) -> anyhow::Result<()> {
+        let request_id = format!("request_{idx}");
+        let response_id = format!("response_{idx}");
+        let execution_id = format!("execution_{idx}");
+
+        sqlx::query!(
+            "INSERT INTO http_requests (id, method, url, headers) VALUES ($1, 'GET', 'https://example.com', '{}')",
+            request_id
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO http_responses (id, status, headers, body) VALUES ($1, $2, '{}', '')",
+            response_id,
+            status
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO job_executions (id, executed_at, success, request_id, response_id)
+          VALUES ($1, NOW(), $2, $3, $4)
+          "#,
+            execution_id,
+            (200..300).contains(&status),
+            request_id,
+            response_id
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO scheduled_jobs
+            (id, hash, region, scheduled_at, request_id, execution_id, max_retries)
+          VALUES ($1, 0, 'na-east', NOW(), $2, $3, 0)
+          "#,
+            format!("scheduled_{idx}"),
+            request_id,
+            execution_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn params(
+        success: Option<bool>,
+        status_min: Option<i32>,
+        status_max: Option<i32>,
+    ) -> QueryParams {
+        QueryParams {
+            cursor: None,
+            from: None,
+            to: None,
+            completed: None,
+            limit: None,
+            one_off_job_id: None,
+            cron_id: None,
+            success,
+            status_min,
+            status_max,
+            with_total: None,
+        }
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_executions_filters_by_result(pool: PgPool) -> anyhow::Result<()> {
+        insert_execution(&pool, 1, 200).await?;
+        insert_execution(&pool, 2, 404).await?;
+        insert_execution(&pool, 3, 503).await?;
+
+        let ctx = test_context(pool);
+        let list = |params: QueryParams| {
+            list_executions(State(ctx.clone()), TenantId(None), Query(params))
+        };
+
+        let failed = list(params(Some(false), None, None)).await.unwrap();
+        assert_eq!(failed.count, 2);
+
+        let succeeded = list(params(Some(true), None, None)).await.unwrap();
+        assert_eq!(succeeded.count, 1);
+        assert_eq!(succeeded.data[0].id, "scheduled_1");
+
+        let server_errors = list(params(None, Some(500), Some(599))).await.unwrap();
+        assert_eq!(server_errors.count, 1);
+        assert_eq!(server_errors.data[0].id, "scheduled_3");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_execution_reports_truncated_response(pool: PgPool) -> anyhow::Result<()> {
+        insert_execution(&pool, 1, 200).await?;
+
+        sqlx::query!(
+            "
+            UPDATE http_responses
+            SET body = 'cut of', truncated = true, body_read_truncated = true
+            WHERE id = 'response_1'
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = test_context(pool);
+        let list = list_executions(State(ctx), TenantId(None), Query(params(None, None, None)))
+            .await
+            .unwrap();
+
+        let response = list.data[0].response.as_ref().unwrap();
+        assert_eq!(response.bytes_used, 6);
+        assert!(response.truncated);
+        assert!(response.body_read_truncated);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_execution_surfaces_drone_id(pool: PgPool) -> anyhow::Result<()> {
+        insert_execution(&pool, 1, 200).await?;
+
+        sqlx::query!("UPDATE job_executions SET drone_id = 'drone_a' WHERE id = 'execution_1'")
+            .execute(&pool)
+            .await?;
+
+        let execution = get_execution(
+            State(test_context(pool)),
+            Path("scheduled_1".to_string()),
+            TenantId(None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(execution.drone_id.as_deref(), Some("drone_a"));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_execution_surfaces_duration(pool: PgPool) -> anyhow::Result<()> {
+        insert_execution(&pool, 1, 200).await?;
+
+        sqlx::query!("UPDATE job_executions SET duration_ms = 842 WHERE id = 'execution_1'")
+            .execute(&pool)
+            .await?;
+
+        let execution = get_execution(
+            State(test_context(pool)),
+            Path("scheduled_1".to_string()),
+            TenantId(None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(execution.duration_ms, Some(842));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_cancel_execution(pool: PgPool) -> anyhow::Result<()> {
+        insert_execution(&pool, 1, 200).await?;
+
+        sqlx::query!(
+            "INSERT INTO http_requests (id, method, url, headers) VALUES ('request_pending', 'GET', 'https://example.com', '{}')"
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+          INSERT INTO scheduled_jobs
+            (id, hash, region, scheduled_at, request_id, lock_nonce, max_retries)
+          VALUES
+            ('scheduled_pending', 0, 'na-east', NOW() + interval '1 day', 'request_pending', NULL, 0),
+            ('scheduled_locked', 0, 'na-east', NOW(), 'request_pending', 1234, 0)
+          "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = test_context(pool);
+        let cancel =
+            |id: &str| cancel_execution(State(ctx.clone()), Path(id.to_string()), TenantId(None));
+
+        let cancelled = cancel("scheduled_pending").await.unwrap();
+        assert_eq!(cancelled.id, "scheduled_pending");
+
+        let deleted_at = sqlx::query_scalar!(
+            "SELECT deleted_at FROM scheduled_jobs WHERE id = 'scheduled_pending'"
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert!(deleted_at.is_some());
+
+        let again = cancel("scheduled_pending").await;
+        assert!(again.is_err_and(|err| err.code == StatusCode::NOT_FOUND));
+
+        let locked = cancel("scheduled_locked").await;
+        assert!(locked.is_err_and(|err| err.code == StatusCode::CONFLICT));
+
+        let completed = cancel("scheduled_1").await;
+        assert!(completed.is_err_and(|err| err.code == StatusCode::CONFLICT));
+
+        let other_tenant = cancel_execution(
+            State(ctx.clone()),
+            Path("scheduled_locked".to_string()),
+            TenantId(Some("tenant_a".to_string())),
+        )
+        .await;
+        assert!(other_tenant.is_err_and(|err| err.code == StatusCode::NOT_FOUND));
+
+        Ok(())
+    }
 }
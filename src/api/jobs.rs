@@ -1,13 +1,18 @@
 use axum::extract::{Path, Query, State};
 use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
+use sqlx::{Postgres, Transaction};
 use utoipa::{IntoParams, ToSchema};
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::{
     api::{
         ApiError, ApiListResponse, Context, JsonBody, TenantId, executions,
-        models::{Execution, HttpRequest, OneOffJob},
+        models::{
+            CreatedOneOffJobs, Execution, HttpRequest, OneOffJob, verify_insecure_skip_tls_verify,
+            verify_job_limits, verify_resolve_override, verify_retry_backoff,
+            verify_transfer_encoding,
+        },
     },
     id, util,
 };
@@ -25,6 +30,14 @@ struct IntermediateOneOffJob {
     timeout_ms: Option<i32>,
     max_retries: i32,
     max_response_bytes: Option<i32>,
+    fresh_connection: bool,
+    insecure_skip_tls_verify: bool,
+    transfer_encoding: String,
+    retry_backoff_ms: Option<i32>,
+    retry_backoff_max_ms: Option<i32>,
+    body_read_timeout_ms: Option<i32>,
+    follow_redirects: Option<i32>,
+    resolve_override: Option<String>,
     created_at: DateTime<Utc>,
     deleted_at: Option<DateTime<Utc>>,
 }
@@ -62,6 +75,14 @@ impl IntermediateOneOffJob {
             timeout_ms: self.timeout_ms,
             max_retries: self.max_retries,
             max_response_bytes: self.max_response_bytes,
+            fresh_connection: self.fresh_connection,
+            insecure_skip_tls_verify: self.insecure_skip_tls_verify,
+            transfer_encoding: self.transfer_encoding.clone(),
+            retry_backoff_ms: self.retry_backoff_ms,
+            retry_backoff_max_ms: self.retry_backoff_max_ms,
+            body_read_timeout_ms: self.body_read_timeout_ms,
+            follow_redirects: self.follow_redirects,
+            resolve_override: self.resolve_override.clone(),
             tenant_id: self.tenant_id.clone(),
             deleted_at: self.deleted_at.as_ref().map(DateTime::timestamp),
         }
@@ -71,119 +92,258 @@ impl IntermediateOneOffJob {
 #[derive(Debug, Clone, Deserialize, ToSchema)]
 struct CreateJob {
     region: Option<String>,
+    /// Fan the job out to every listed region. Mutually exclusive with `region`.
+    regions: Option<Vec<String>>,
     execute_at: i64,
     request: HttpRequest,
     timeout_ms: Option<i32>,
     max_retries: Option<i32>,
     max_response_bytes: Option<i32>,
+    /// Execute every attempt on a brand-new connection instead of a pooled one.
+    fresh_connection: Option<bool>,
+    /// Accept invalid or self-signed tls certificates from the target. Only
+    /// allowed when the deployment runs with `--allow-insecure-jobs`.
+    insecure_skip_tls_verify: Option<bool>,
+    /// How the request body is framed: `auto` (the default) and `length`
+    /// send a `Content-Length`, `chunked` streams it with chunked encoding.
+    transfer_encoding: Option<String>,
+    /// Delay before the first retry, doubled on each further attempt.
+    retry_backoff_ms: Option<i32>,
+    /// Upper bound on the delay between retries.
+    retry_backoff_max_ms: Option<i32>,
+    /// Stop reading the response body this long after it starts arriving,
+    /// keeping what was read so far.
+    body_read_timeout_ms: Option<i32>,
+    /// Follow up to this many redirects instead of recording the redirect
+    /// response itself. Redirects to private addresses are refused.
+    follow_redirects: Option<i32>,
+    /// Pin the request's host to an address, as `host:ip`, instead of
+    /// resolving it. For targets behind split-horizon dns. Private addresses
+    /// are refused like they are for resolved hosts.
+    resolve_override: Option<String>,
 }
 
-#[utoipa::path(
-  post,
-  path = "/api/jobs",
-  request_body = CreateJob,
-  responses(
-    (status = 200, description = "Job created", body = OneOffJob),
-    (status = "4XX", description = "Bad request", body = ApiError),
-    (status = "5XX", description = "Internal server error", body = ApiError)
-  ),
-  tag = "one off jobs"
-)]
-#[tracing::instrument(name = "api_create_job")]
-async fn create_job(
-    State(ctx): State<Context>,
-    TenantId(tenant_id): TenantId,
-    JsonBody(create_opts): JsonBody<CreateJob>,
-) -> Result<OneOffJob, ApiError> {
-    let region = create_opts
-        .region
-        .or(ctx.valid_regions.first().cloned())
-        .expect("There are no valid regions.");
+const MAX_BATCH_SIZE: usize = 500;
 
-    if !ctx.valid_regions.contains(&region) {
-        let region_list = ctx.valid_regions.join(", ");
+struct TenantLimits {
+    max_timeout: i32,
+    default_retries: i32,
+    max_retries: i32,
+    max_max_response_bytes: i32,
+    max_request_bytes: i32,
+    max_delay_days: i32,
+    min_retry_backoff_ms: Option<i32>,
+    suspended: bool,
+}
 
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid region: {}, choose one of the following: {}",
-            region, region_list
-        ))));
-    }
+async fn fetch_tenant_limits(
+    tenant_id: &Option<String>,
+    txn: &mut Transaction<'_, Postgres>,
+) -> Result<Option<TenantLimits>, ApiError> {
+    let Some(tenant_id) = tenant_id else {
+        return Ok(None);
+    };
 
-    create_opts.request.verify()?;
+    let tenant = sqlx::query_as!(
+        TenantLimits,
+        r#"
+      SELECT
+        max_timeout,
+        default_retries,
+        max_retries,
+        max_max_response_bytes,
+        max_request_bytes,
+        max_delay_days,
+        min_retry_backoff_ms,
+        suspended
+      FROM tenants
+      WHERE id = $1 AND deleted_at IS NULL
+      "#,
+        tenant_id
+    )
+    .fetch_optional(&mut **txn)
+    .await?;
 
-    let mut txn = ctx.pool.begin().await?;
-    let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
-    } else {
-        None
+    let Some(tenant) = tenant else {
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
     };
 
-    if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+    if tenant.suspended {
+        return Err(ApiError::tenant_suspended());
     }
 
+    Ok(Some(tenant))
+}
+
+/// Checks a job against the tenant's limits and returns the regions it
+/// should be created in.
+fn validate_create_job(
+    ctx: &Context,
+    tenant: Option<&TenantLimits>,
+    create_opts: &CreateJob,
+) -> Result<Vec<String>, ApiError> {
+    let regions = match (create_opts.region.clone(), create_opts.regions.clone()) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request(
+                "invalid_region",
+                Some("Specify either region or regions, not both"),
+            ));
+        }
+        (None, Some(regions)) if regions.is_empty() => {
+            return Err(ApiError::bad_request(
+                "invalid_region",
+                Some("Regions cannot be empty"),
+            ));
+        }
+        (None, Some(regions)) => {
+            let mut resolved: Vec<String> = Vec::new();
+
+            for region in regions {
+                let region = ctx.resolve_region(Some(region))?;
+                if !resolved.contains(&region) {
+                    resolved.push(region);
+                }
+            }
+
+            resolved
+        }
+        (region, None) => vec![ctx.resolve_region(region)?],
+    };
+
+    create_opts.request.verify()?;
+
+    verify_insecure_skip_tls_verify(
+        create_opts.insecure_skip_tls_verify,
+        ctx.allow_insecure_jobs,
+    )?;
+    verify_transfer_encoding(create_opts.transfer_encoding.as_deref())?;
+    verify_resolve_override(
+        create_opts.resolve_override.as_deref(),
+        &create_opts.request.url,
+    )?;
+
+    verify_job_limits(
+        create_opts.timeout_ms,
+        create_opts.max_retries,
+        create_opts.max_response_bytes,
+        create_opts.body_read_timeout_ms,
+        create_opts.follow_redirects,
+    )?;
+
     if let Some(input_timeout) = create_opts.timeout_ms
-        && let Some(tenant) = &tenant
+        && let Some(tenant) = tenant
         && input_timeout > tenant.max_timeout
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
-            tenant.max_timeout
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
+                tenant.max_timeout
+            )),
+        ));
     }
 
     if let Some(input_max_retries) = create_opts.max_retries
-        && let Some(tenant) = &tenant
+        && let Some(tenant) = tenant
         && input_max_retries > tenant.max_retries
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max retries of {input_max_retries} is higher than your limit of {}",
-            tenant.max_retries
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max retries of {input_max_retries} is higher than your limit of {}",
+                tenant.max_retries
+            )),
+        ));
     }
 
     if let Some(input_max_response_bytes) = create_opts.max_response_bytes
-        && let Some(tenant) = &tenant
+        && let Some(tenant) = tenant
         && input_max_response_bytes > tenant.max_max_response_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
-            tenant.max_max_response_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
+                tenant.max_max_response_bytes
+            )),
+        ));
     }
 
     if let Some(body_text) = &create_opts.request.body
-        && let Some(tenant) = &tenant
+        && let Some(tenant) = tenant
         && body_text.len() as i32 > tenant.max_request_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your request body of {} bytes is higher than your limit of {}",
-            body_text.len(),
-            tenant.max_request_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your request body of {} bytes is higher than your limit of {}",
+                body_text.len(),
+                tenant.max_request_bytes
+            )),
+        ));
     }
 
-    let scheduled_for = DateTime::from_timestamp_secs(create_opts.execute_at).ok_or(
-        ApiError::bad_request(Some(&format!("Invalid time {}", create_opts.execute_at))),
+    verify_retry_backoff(
+        create_opts.retry_backoff_ms,
+        create_opts.retry_backoff_max_ms,
+        tenant.and_then(|t| t.min_retry_backoff_ms),
     )?;
+
+    verify_execute_at(create_opts.execute_at, tenant.map(|t| t.max_delay_days))?;
+
+    Ok(regions)
+}
+
+/// How far in the past a job may be scheduled, to allow for clock skew and
+/// requests that take a moment to arrive. It then runs right away.
+const PAST_EXECUTE_AT_GRACE_SECS: i64 = 5 * 60;
+
+/// Rejects times that aren't valid, are too far in the past, or are further
+/// out than the tenant's `max_delay_days`.
+fn verify_execute_at(execute_at: i64, max_delay_days: Option<i32>) -> Result<(), ApiError> {
+    let scheduled_for = DateTime::from_timestamp_secs(execute_at).ok_or(ApiError::bad_request(
+        "invalid_time",
+        Some(&format!("Invalid time {execute_at}")),
+    ))?;
     let time_until = scheduled_for - Utc::now();
 
-    if let Some(tenant) = &tenant
-        && time_until > Duration::days(tenant.max_delay_days as i64)
+    if time_until < -Duration::seconds(PAST_EXECUTE_AT_GRACE_SECS) {
+        return Err(ApiError::bad_request(
+            "invalid_time",
+            Some(&format!(
+                "Your request is scheduled {} seconds in the past, jobs may be at most {PAST_EXECUTE_AT_GRACE_SECS} seconds late",
+                -time_until.num_seconds()
+            )),
+        ));
+    }
+
+    if let Some(max_delay_days) = max_delay_days
+        && time_until > Duration::days(max_delay_days as i64)
     {
-        let over_by = time_until - Duration::days(tenant.max_delay_days as i64);
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your request is scheduled {} days in the future, which is higher than your limit of {} days by {} seconds",
-            time_until.num_days(),
-            tenant.max_delay_days,
-            over_by.num_seconds()
-        ))));
+        let over_by = time_until - Duration::days(max_delay_days as i64);
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your request is scheduled {} days in the future, which is higher than your limit of {max_delay_days} days by {} seconds",
+                time_until.num_days(),
+                over_by.num_seconds()
+            )),
+        ));
     }
 
-    let request_id = id::generate("request");
+    Ok(())
+}
 
+async fn insert_one_off_jobs(
+    txn: &mut Transaction<'_, Postgres>,
+    tenant_id: &Option<String>,
+    tenant: Option<&TenantLimits>,
+    create_opts: &CreateJob,
+    regions: Vec<String>,
+) -> Result<Vec<OneOffJob>, ApiError> {
     let headers: Vec<String> = create_opts
         .request
         .headers
@@ -191,64 +351,197 @@ async fn create_job(
         .map(|(k, v)| format!("{k}: {v}"))
         .collect();
 
-    sqlx::query!(
-        r#"
-      INSERT INTO http_requests (id, method, url, headers, body)
-      VALUES ($1, $2, $3, $4, $5)
-      "#,
-        request_id,
-        create_opts.request.method,
-        create_opts.request.url,
-        &headers,
-        create_opts.request.body
-    )
-    .execute(&mut *txn)
-    .await?;
-
-    let job_id = id::generate("one_off_job");
     let max_retries = create_opts
         .max_retries
         .or(tenant.map(|t| t.default_retries))
         .unwrap_or(3);
 
-    sqlx::query!(r#"
-      INSERT INTO one_off_jobs (id, region, tenant_id, request_id, execute_at, timeout_ms, max_retries, max_response_bytes)
-      VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    let fresh_connection = create_opts.fresh_connection.unwrap_or(false);
+    let insecure_skip_tls_verify = create_opts.insecure_skip_tls_verify.unwrap_or(false);
+    let transfer_encoding = create_opts
+        .transfer_encoding
+        .clone()
+        .unwrap_or_else(|| "auto".to_string());
+
+    let mut jobs = Vec::with_capacity(regions.len());
+
+    // Each region gets its own request row, since requests are scrubbed
+    // per job on deletion and retention.
+    for region in regions {
+        let request_id = id::generate("request");
+
+        sqlx::query!(
+            r#"
+      INSERT INTO http_requests (id, method, url, headers, body)
+      VALUES ($1, $2, $3, $4, $5)
       "#,
-        job_id,
-        region,
-        tenant_id,
-        request_id,
-        create_opts.execute_at,
-        create_opts.timeout_ms,
-        max_retries,
-        create_opts.max_response_bytes
-    )
-    .execute(&mut *txn)
-    .await?;
+            request_id,
+            create_opts.request.method,
+            create_opts.request.url,
+            &headers,
+            create_opts.request.body
+        )
+        .execute(&mut **txn)
+        .await?;
+
+        let job_id = id::generate("one_off_job");
+
+        sqlx::query!(r#"
+      INSERT INTO one_off_jobs (id, region, tenant_id, request_id, execute_at, timeout_ms, max_retries, max_response_bytes, fresh_connection, insecure_skip_tls_verify, retry_backoff_ms, retry_backoff_max_ms, transfer_encoding, body_read_timeout_ms, follow_redirects, resolve_override)
+      VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+      "#,
+            job_id,
+            region,
+            tenant_id.clone(),
+            request_id,
+            create_opts.execute_at,
+            create_opts.timeout_ms,
+            max_retries,
+            create_opts.max_response_bytes,
+            fresh_connection,
+            insecure_skip_tls_verify,
+            create_opts.retry_backoff_ms,
+            create_opts.retry_backoff_max_ms,
+            transfer_encoding,
+            create_opts.body_read_timeout_ms,
+            create_opts.follow_redirects,
+            create_opts.resolve_override
+        )
+        .execute(&mut **txn)
+        .await?;
+
+        jobs.push(OneOffJob {
+            id: job_id,
+            region,
+            execute_at: create_opts.execute_at,
+            request: create_opts.request.clone(),
+            executions: Vec::new(),
+            timeout_ms: create_opts.timeout_ms,
+            max_retries,
+            max_response_bytes: create_opts.max_response_bytes,
+            fresh_connection,
+            insecure_skip_tls_verify,
+            transfer_encoding: transfer_encoding.clone(),
+            retry_backoff_ms: create_opts.retry_backoff_ms,
+            retry_backoff_max_ms: create_opts.retry_backoff_max_ms,
+            body_read_timeout_ms: create_opts.body_read_timeout_ms,
+            follow_redirects: create_opts.follow_redirects,
+            resolve_override: create_opts.resolve_override.clone(),
+            tenant_id: tenant_id.clone(),
+            deleted_at: None,
+        });
+    }
+
+    Ok(jobs)
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/jobs",
+  request_body = CreateJob,
+  responses(
+    (status = 200, description = "Job created", body = CreatedOneOffJobs),
+    (status = "4XX", description = "Bad request", body = ApiError),
+    (status = "5XX", description = "Internal server error", body = ApiError)
+  ),
+  tag = "one off jobs"
+)]
+#[tracing::instrument(name = "api_create_job")]
+async fn create_job(
+    State(ctx): State<Context>,
+    TenantId(tenant_id): TenantId,
+    JsonBody(create_opts): JsonBody<CreateJob>,
+) -> Result<CreatedOneOffJobs, ApiError> {
+    let mut txn = ctx.pool.begin().await?;
+    let tenant = fetch_tenant_limits(&tenant_id, &mut txn).await?;
+
+    let regions = validate_create_job(&ctx, tenant.as_ref(), &create_opts)?;
+    let mut jobs =
+        insert_one_off_jobs(&mut txn, &tenant_id, tenant.as_ref(), &create_opts, regions).await?;
 
     txn.commit().await?;
 
-    let job = OneOffJob {
-        id: job_id,
-        region,
-        execute_at: create_opts.execute_at,
-        request: create_opts.request,
-        executions: Vec::new(),
-        timeout_ms: create_opts.timeout_ms,
-        max_retries,
-        max_response_bytes: create_opts.max_response_bytes,
-        tenant_id,
-        deleted_at: None,
-    };
+    ctx.metrics.record_one_off_jobs_created(jobs.len() as u64);
 
-    Ok(job)
+    if create_opts.regions.is_none()
+        && let Some(job) = jobs.pop()
+    {
+        return Ok(CreatedOneOffJobs::Single(job));
+    }
+
+    Ok(CreatedOneOffJobs::FanOut { jobs })
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/jobs/batch",
+  request_body = Vec<CreateJob>,
+  responses(
+    (status = 200, description = "Jobs created", body = ApiListResponse<OneOffJob>),
+    (status = "4XX", description = "Bad request", body = ApiError),
+    (status = "5XX", description = "Internal server error", body = ApiError)
+  ),
+  tag = "one off jobs"
+)]
+#[tracing::instrument(name = "api_create_jobs_batch", skip(batch))]
+async fn create_jobs_batch(
+    State(ctx): State<Context>,
+    TenantId(tenant_id): TenantId,
+    JsonBody(batch): JsonBody<Vec<CreateJob>>,
+) -> Result<ApiListResponse<OneOffJob>, ApiError> {
+    if batch.is_empty() {
+        return Err(ApiError::bad_request(
+            "invalid_batch",
+            Some("Batch cannot be empty"),
+        ));
+    }
+
+    if batch.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::bad_request(
+            "invalid_batch",
+            Some(&format!(
+                "Batch of {} jobs is larger than the limit of {MAX_BATCH_SIZE}",
+                batch.len()
+            )),
+        ));
+    }
+
+    let mut txn = ctx.pool.begin().await?;
+    let tenant = fetch_tenant_limits(&tenant_id, &mut txn).await?;
+
+    let mut validated = Vec::with_capacity(batch.len());
+    for (idx, create_opts) in batch.iter().enumerate() {
+        let regions = validate_create_job(&ctx, tenant.as_ref(), create_opts).map_err(|err| {
+            ApiError::bad_request(err.error_code, Some(&format!("Job {idx}: {}", err.message)))
+        })?;
+        validated.push(regions);
+    }
+
+    let mut jobs = Vec::new();
+    for (create_opts, regions) in batch.iter().zip(validated) {
+        let created =
+            insert_one_off_jobs(&mut txn, &tenant_id, tenant.as_ref(), create_opts, regions)
+                .await?;
+        jobs.extend(created);
+    }
+
+    txn.commit().await?;
+
+    ctx.metrics.record_one_off_jobs_created(jobs.len() as u64);
+
+    Ok(ApiListResponse {
+        count: jobs.len(),
+        data: jobs,
+        cursor: None,
+        total: None,
+    })
 }
 
 #[derive(Debug, Deserialize, IntoParams)]
 struct QueryParams {
     cursor: Option<String>,
     limit: Option<i64>,
+    with_total: Option<bool>,
 }
 
 #[utoipa::path(
@@ -284,6 +577,14 @@ async fn list_jobs(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.resolve_override,
         job.created_at,
         job.deleted_at
       FROM one_off_jobs as job
@@ -300,7 +601,7 @@ async fn list_jobs(
         tenant_id.clone(),
         params.cursor
     )
-    .fetch_all(&ctx.pool)
+    .fetch_all(ctx.read_pool())
     .await?;
 
     let job_ids: Vec<String> = jobs.iter().map(|j| j.id.clone()).collect();
@@ -308,10 +609,17 @@ async fn list_jobs(
     // dbg!(&job_ids);
 
     let completed_executions =
-        executions::get_executions(job_ids.clone(), tenant_id.clone(), true, 3, &ctx.pool).await?;
+        executions::get_executions(job_ids.clone(), tenant_id.clone(), true, 3, ctx.read_pool())
+            .await?;
 
-    let not_yet_executed =
-        executions::get_executions(job_ids.clone(), tenant_id.clone(), false, 2, &ctx.pool).await?;
+    let not_yet_executed = executions::get_executions(
+        job_ids.clone(),
+        tenant_id.clone(),
+        false,
+        2,
+        ctx.read_pool(),
+    )
+    .await?;
 
     let executions = completed_executions
         .into_iter()
@@ -322,12 +630,32 @@ async fn list_jobs(
 
     let jobs: Vec<OneOffJob> = jobs.iter().map(|j| j.to_one_off_job(&executions)).collect();
 
+    let total = if params.with_total.unwrap_or(false) {
+        let total = sqlx::query_scalar!(
+            r#"
+      SELECT COUNT(*) as "total!"
+      FROM one_off_jobs as job
+      WHERE
+        job.deleted_at IS NULL
+        AND ($1::text IS NULL OR job.tenant_id = $1)
+      "#,
+            tenant_id.clone()
+        )
+        .fetch_one(ctx.read_pool())
+        .await?;
+
+        Some(total)
+    } else {
+        None
+    };
+
     let last_job = jobs.last().map(|j| j.id.clone());
 
     Ok(ApiListResponse {
         count: jobs.len(),
         data: jobs,
         cursor: last_job,
+        total,
     })
 }
 
@@ -339,6 +667,14 @@ struct UpdateJob {
     timeout_ms: Option<i32>,
     max_retries: Option<i32>,
     max_response_bytes: Option<i32>,
+    fresh_connection: Option<bool>,
+    insecure_skip_tls_verify: Option<bool>,
+    transfer_encoding: Option<String>,
+    retry_backoff_ms: Option<i32>,
+    retry_backoff_max_ms: Option<i32>,
+    body_read_timeout_ms: Option<i32>,
+    follow_redirects: Option<i32>,
+    resolve_override: Option<String>,
 }
 
 #[utoipa::path(
@@ -362,83 +698,103 @@ async fn update_job(
     if let Some(region) = update_opts.region.clone()
         && !ctx.valid_regions.contains(&region)
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Invalid region: {region}, choose one of the following: {}",
-            ctx.valid_regions.join(", ")
-        ))));
+        return Err(ApiError::bad_request(
+            "invalid_region",
+            Some(&format!(
+                "Invalid region: {region}, choose one of the following: {}",
+                ctx.valid_regions.join(", ")
+            )),
+        ));
     }
 
     let mut txn = ctx.pool.begin().await?;
     let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
     } else {
         None
     };
 
     if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
     }
 
+    verify_insecure_skip_tls_verify(
+        update_opts.insecure_skip_tls_verify,
+        ctx.allow_insecure_jobs,
+    )?;
+    verify_transfer_encoding(update_opts.transfer_encoding.as_deref())?;
+
+    verify_job_limits(
+        update_opts.timeout_ms,
+        update_opts.max_retries,
+        update_opts.max_response_bytes,
+        update_opts.body_read_timeout_ms,
+        update_opts.follow_redirects,
+    )?;
+
     if let Some(input_timeout) = update_opts.timeout_ms
         && let Some(tenant) = &tenant
         && input_timeout > tenant.max_timeout
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
-            tenant.max_timeout
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your timeout of {input_timeout}ms is higher than your limit of {}ms",
+                tenant.max_timeout
+            )),
+        ));
     }
 
     if let Some(input_max_retries) = update_opts.max_retries
         && let Some(tenant) = &tenant
         && input_max_retries > tenant.max_retries
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max retries of {input_max_retries} is higher than your limit of {}",
-            tenant.max_retries
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max retries of {input_max_retries} is higher than your limit of {}",
+                tenant.max_retries
+            )),
+        ));
     }
 
     if let Some(input_max_response_bytes) = update_opts.max_response_bytes
         && let Some(tenant) = &tenant
         && input_max_response_bytes > tenant.max_max_response_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
-            tenant.max_max_response_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max response bytes of {input_max_response_bytes} is higher than your limit of {}",
+                tenant.max_max_response_bytes
+            )),
+        ));
     }
 
-    if let Some(input_scheduled_for) = update_opts.execute_at {
-        let scheduled_for = DateTime::from_timestamp_secs(input_scheduled_for).ok_or(
-            ApiError::bad_request(Some(&format!("Invalid time {}", input_scheduled_for))),
-        )?;
-        let time_until = scheduled_for - Utc::now();
-
-        if let Some(tenant) = &tenant
-            && time_until > Duration::days(tenant.max_delay_days as i64)
-        {
-            let over_by = time_until - Duration::days(tenant.max_delay_days as i64);
-            return Err(ApiError::bad_request(Some(&format!(
-                "Your request is scheduled {} days in the future, which is higher than your limit of {} days by {} seconds",
-                time_until.num_days(),
-                tenant.max_delay_days,
-                over_by.num_seconds()
-            ))));
-        }
+    if let Some(execute_at) = update_opts.execute_at {
+        verify_execute_at(execute_at, tenant.as_ref().map(|t| t.max_delay_days))?;
     }
 
     if let Some(body_text) = update_opts.request.as_ref().and_then(|r| r.body.clone())
         && let Some(tenant) = &tenant
         && body_text.len() as i32 > tenant.max_request_bytes
     {
-        return Err(ApiError::bad_request(Some(&format!(
-            "Your request body of {} bytes is higher than your limit of {}",
-            body_text.len(),
-            tenant.max_request_bytes
-        ))));
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your request body of {} bytes is higher than your limit of {}",
+                body_text.len(),
+                tenant.max_request_bytes
+            )),
+        ));
     }
 
     let existing = sqlx::query!(
@@ -464,6 +820,14 @@ async fn update_job(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.resolve_override,
         req.id as req_id,
         req.method,
         req.url,
@@ -526,6 +890,46 @@ async fn update_job(
     let new_max_response_bytes = update_opts
         .max_response_bytes
         .or(existing_data.max_response_bytes);
+    let new_fresh_connection = update_opts
+        .fresh_connection
+        .unwrap_or(existing_data.fresh_connection);
+    let new_insecure_skip_tls_verify = update_opts
+        .insecure_skip_tls_verify
+        .unwrap_or(existing_data.insecure_skip_tls_verify);
+    let new_transfer_encoding = update_opts
+        .transfer_encoding
+        .unwrap_or(existing_data.transfer_encoding);
+    let new_retry_backoff_ms = update_opts
+        .retry_backoff_ms
+        .or(existing_data.retry_backoff_ms);
+    let new_retry_backoff_max_ms = update_opts
+        .retry_backoff_max_ms
+        .or(existing_data.retry_backoff_max_ms);
+    let new_body_read_timeout_ms = update_opts
+        .body_read_timeout_ms
+        .or(existing_data.body_read_timeout_ms);
+    let new_follow_redirects = update_opts
+        .follow_redirects
+        .or(existing_data.follow_redirects);
+    let new_resolve_override = update_opts
+        .resolve_override
+        .or(existing_data.resolve_override);
+
+    // Checked against the updated url, so changing the host can't leave an
+    // override behind that pins a host the job no longer sends to.
+    verify_resolve_override(
+        new_resolve_override.as_deref(),
+        update_opts
+            .request
+            .as_ref()
+            .map_or(&existing_data.url, |request| &request.url),
+    )?;
+
+    verify_retry_backoff(
+        new_retry_backoff_ms,
+        new_retry_backoff_max_ms,
+        tenant.as_ref().and_then(|t| t.min_retry_backoff_ms),
+    )?;
 
     let new_job = sqlx::query_as!(
         IntermediateOneOffJob,
@@ -536,7 +940,15 @@ async fn update_job(
         execute_at = $3,
         timeout_ms = $4,
         max_retries = $5,
-        max_response_bytes = $6
+        max_response_bytes = $6,
+        fresh_connection = $7,
+        insecure_skip_tls_verify = $8,
+        retry_backoff_ms = $9,
+        retry_backoff_max_ms = $10,
+        transfer_encoding = $11,
+        body_read_timeout_ms = $12,
+        follow_redirects = $13,
+        resolve_override = $14
       FROM one_off_jobs as job
       JOIN http_requests AS req
         ON req.id = job.request_id
@@ -554,6 +966,14 @@ async fn update_job(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.resolve_override,
         job.created_at,
         job.deleted_at
       "#,
@@ -562,7 +982,15 @@ async fn update_job(
         new_execute_at,
         new_timeout_ms,
         new_max_retries,
-        new_max_response_bytes
+        new_max_response_bytes,
+        new_fresh_connection,
+        new_insecure_skip_tls_verify,
+        new_retry_backoff_ms,
+        new_retry_backoff_max_ms,
+        new_transfer_encoding,
+        new_body_read_timeout_ms,
+        new_follow_redirects,
+        new_resolve_override
     )
     .fetch_one(&mut *txn)
     .await?;
@@ -601,9 +1029,11 @@ async fn get_job(
     Path(job_id): Path<String>,
     TenantId(tenant_id): TenantId,
 ) -> Result<OneOffJob, ApiError> {
-    let job = sqlx::query_as!(
-        IntermediateOneOffJob,
-        r#"
+    let job = ctx
+        .read_or_primary(|pool| {
+            sqlx::query_as!(
+                IntermediateOneOffJob,
+                r#"
     SELECT
       job.id,
       job.region,
@@ -617,6 +1047,14 @@ async fn get_job(
       job.timeout_ms,
       job.max_retries,
       job.max_response_bytes,
+      job.fresh_connection,
+      job.insecure_skip_tls_verify,
+      job.transfer_encoding,
+      job.retry_backoff_ms,
+      job.retry_backoff_max_ms,
+      job.body_read_timeout_ms,
+      job.follow_redirects,
+      job.resolve_override,
       job.created_at,
       job.deleted_at
     FROM one_off_jobs as job
@@ -626,22 +1064,33 @@ async fn get_job(
       job.id = $1 AND
       ($2::text IS NULL OR job.tenant_id = $2);
     "#,
-        job_id.clone(),
-        tenant_id
-    )
-    .fetch_optional(&ctx.pool)
-    .await?;
+                job_id.clone(),
+                tenant_id
+            )
+            .fetch_optional(pool)
+        })
+        .await?;
 
     if job.is_none() {
         return Err(ApiError::not_found());
     }
 
-    let completed_executions =
-        executions::get_executions(vec![job_id.clone()], tenant_id.clone(), true, 5, &ctx.pool)
-            .await?;
-    let not_yet_executed =
-        executions::get_executions(vec![job_id.clone()], tenant_id.clone(), false, 2, &ctx.pool)
-            .await?;
+    let completed_executions = executions::get_executions(
+        vec![job_id.clone()],
+        tenant_id.clone(),
+        true,
+        5,
+        ctx.read_pool(),
+    )
+    .await?;
+    let not_yet_executed = executions::get_executions(
+        vec![job_id.clone()],
+        tenant_id.clone(),
+        false,
+        2,
+        ctx.read_pool(),
+    )
+    .await?;
 
     let executions = completed_executions
         .into_iter()
@@ -671,15 +1120,21 @@ async fn delete_job(
 ) -> Result<OneOffJob, ApiError> {
     let mut txn = ctx.pool.begin().await?;
     let tenant = if let Some(tenant_id) = tenant_id.clone() {
-        sqlx::query!("SELECT * FROM tenants WHERE id = $1", tenant_id)
-            .fetch_optional(&mut *txn)
-            .await?
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
     } else {
         None
     };
 
     if tenant_id.is_some() && tenant.is_none() {
-        return Err(ApiError::bad_request(Some("Invalid tenant id")));
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
     }
 
     let existing = sqlx::query_as!(
@@ -698,6 +1153,14 @@ async fn delete_job(
         job.timeout_ms,
         job.max_retries,
         job.max_response_bytes,
+        job.fresh_connection,
+        job.insecure_skip_tls_verify,
+        job.transfer_encoding,
+        job.retry_backoff_ms,
+        job.retry_backoff_max_ms,
+        job.body_read_timeout_ms,
+        job.follow_redirects,
+        job.resolve_override,
         job.created_at,
         job.deleted_at
       FROM one_off_jobs as job
@@ -759,5 +1222,702 @@ async fn delete_job(
 pub fn init_router() -> OpenApiRouter<Context> {
     OpenApiRouter::new()
         .routes(routes!(create_job, list_jobs))
+        .routes(routes!(create_jobs_batch))
         .routes(routes!(update_job, get_job, delete_job))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use http::StatusCode;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::{models::MAX_FOLLOW_REDIRECTS, test_context};
+
+    fn create_opts(region: Option<&str>, regions: Option<Vec<&str>>) -> CreateJob {
+        CreateJob {
+            region: region.map(str::to_string),
+            regions: regions.map(|regions| regions.into_iter().map(str::to_string).collect()),
+            execute_at: Utc::now().timestamp() + 60,
+            request: HttpRequest {
+                method: "GET".to_string(),
+                url: "https://example.com".to_string(),
+                headers: HashMap::new(),
+                body: None,
+            },
+            timeout_ms: None,
+            max_retries: None,
+            max_response_bytes: None,
+            fresh_connection: None,
+            insecure_skip_tls_verify: None,
+            transfer_encoding: None,
+            retry_backoff_ms: None,
+            retry_backoff_max_ms: None,
+            body_read_timeout_ms: None,
+            follow_redirects: None,
+            resolve_override: None,
+        }
+    }
+
+    fn multi_region_context(pool: PgPool) -> Context {
+        Context {
+            valid_regions: vec![
+                "na-east".to_string(),
+                "na-west".to_string(),
+                "eu-west".to_string(),
+            ],
+            ..test_context(pool)
+        }
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_fans_out_to_regions(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = multi_region_context(pool);
+
+        let created = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(create_opts(
+                None,
+                Some(vec!["na-east", "na-west", "eu-west"]),
+            )),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::FanOut { jobs } = created else {
+            panic!("Expected a fan-out response");
+        };
+        assert_eq!(jobs.len(), 3);
+
+        let rows = sqlx::query!("SELECT region, request_id FROM one_off_jobs ORDER BY region")
+            .fetch_all(&ctx.pool)
+            .await?;
+
+        let regions: Vec<&str> = rows.iter().map(|row| row.region.as_str()).collect();
+        assert_eq!(regions, vec!["eu-west", "na-east", "na-west"]);
+
+        let request_ids: HashSet<&str> = rows.iter().map(|row| row.request_id.as_str()).collect();
+        assert_eq!(request_ids.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_rejects_invalid_fan_out(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = multi_region_context(pool);
+
+        let invalid_region = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(create_opts(None, Some(vec!["na-east", "mars-north"]))),
+        )
+        .await;
+        assert!(invalid_region.is_err());
+
+        let both = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(create_opts(Some("na-east"), Some(vec!["na-west"]))),
+        )
+        .await;
+        assert!(both.is_err());
+
+        let count = sqlx::query_scalar!("SELECT count(*) FROM one_off_jobs")
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(count, Some(0));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_rejected_for_suspended_tenant(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            "
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs, suspended)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10, true)
+            "
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let tenant_id = || TenantId(Some("tenant_a".to_string()));
+
+        let rejected = create_job(
+            State(ctx.clone()),
+            tenant_id(),
+            JsonBody(create_opts(None, None)),
+        )
+        .await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::FORBIDDEN));
+
+        sqlx::query!("UPDATE tenants SET suspended = false WHERE id = 'tenant_a'")
+            .execute(&ctx.pool)
+            .await?;
+
+        let created = create_job(State(ctx), tenant_id(), JsonBody(create_opts(None, None))).await;
+        assert!(created.is_ok());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_insecure_skip_tls_verify_requires_deployment_opt_in(
+        pool: PgPool,
+    ) -> anyhow::Result<()> {
+        let insecure = || CreateJob {
+            insecure_skip_tls_verify: Some(true),
+            ..create_opts(None, None)
+        };
+
+        let ctx = test_context(pool);
+        let rejected = create_job(State(ctx.clone()), TenantId(None), JsonBody(insecure())).await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        let ctx = Context {
+            allow_insecure_jobs: true,
+            ..ctx
+        };
+        let created = create_job(State(ctx.clone()), TenantId(None), JsonBody(insecure()))
+            .await
+            .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert!(job.insecure_skip_tls_verify);
+
+        let stored = sqlx::query_scalar!(
+            "SELECT insecure_skip_tls_verify FROM one_off_jobs WHERE id = $1",
+            job.id
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert!(stored);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_validates_transfer_encoding(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let with_encoding = |transfer_encoding: &str| CreateJob {
+            transfer_encoding: Some(transfer_encoding.to_string()),
+            ..create_opts(None, None)
+        };
+
+        let rejected = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(with_encoding("gzip")),
+        )
+        .await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        let created = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(with_encoding("chunked")),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.transfer_encoding, "chunked");
+
+        let defaulted = create_job(
+            State(ctx),
+            TenantId(None),
+            JsonBody(create_opts(None, None)),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = defaulted else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.transfer_encoding, "auto");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_validates_http_method(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let with_method = |method: &str| {
+            let mut opts = create_opts(None, None);
+            opts.request.method = method.to_string();
+            opts
+        };
+
+        for method in ["get", "PURGE", "CONNECT", ""] {
+            let rejected = create_job(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(with_method(method)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let created = create_job(State(ctx), TenantId(None), JsonBody(with_method("PATCH")))
+            .await
+            .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.request.method, "PATCH");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_rejects_negative_limits(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        for opts in [
+            CreateJob {
+                max_response_bytes: Some(-1),
+                ..create_opts(None, None)
+            },
+            CreateJob {
+                timeout_ms: Some(-1),
+                ..create_opts(None, None)
+            },
+        ] {
+            let rejected = create_job(State(ctx.clone()), TenantId(None), JsonBody(opts)).await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let count = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM one_off_jobs"#)
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(count, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_validates_body_read_timeout(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let with_timeout = |body_read_timeout_ms| CreateJob {
+            body_read_timeout_ms: Some(body_read_timeout_ms),
+            ..create_opts(None, None)
+        };
+
+        let rejected = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(with_timeout(0)),
+        )
+        .await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        let created = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(with_timeout(2000)),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.body_read_timeout_ms, Some(2000));
+
+        let stored = sqlx::query_scalar!(
+            "SELECT body_read_timeout_ms FROM one_off_jobs WHERE id = $1",
+            job.id
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert_eq!(stored, Some(2000));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_jobs_pages_by_descending_id(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        for _ in 0..5 {
+            create_job(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(create_opts(None, None)),
+            )
+            .await
+            .unwrap();
+        }
+
+        let page = |cursor: Option<String>| {
+            list_jobs(
+                State(ctx.clone()),
+                TenantId(None),
+                Query(QueryParams {
+                    cursor,
+                    limit: Some(3),
+                    with_total: None,
+                }),
+            )
+        };
+
+        let first = page(None).await.unwrap();
+        assert_eq!(first.count, 3);
+        assert!(first.data.is_sorted_by(|a, b| a.id > b.id));
+
+        let cursor = first.cursor.clone().unwrap();
+        let second = page(Some(cursor.clone())).await.unwrap();
+        assert_eq!(second.count, 2);
+        assert!(second.data.iter().all(|job| job.id < cursor));
+        assert!(
+            second
+                .data
+                .iter()
+                .all(|job| first.data.iter().all(|seen| seen.id != job.id))
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_jobs_counts_total_on_request(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        for _ in 0..5 {
+            create_job(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(create_opts(None, None)),
+            )
+            .await
+            .unwrap();
+        }
+
+        let page = |with_total: Option<bool>| {
+            list_jobs(
+                State(ctx.clone()),
+                TenantId(None),
+                Query(QueryParams {
+                    cursor: None,
+                    limit: Some(2),
+                    with_total,
+                }),
+            )
+        };
+
+        let counted = page(Some(true)).await.unwrap();
+        assert_eq!(counted.count, 2);
+        assert_eq!(counted.total, Some(5));
+
+        assert_eq!(page(None).await.unwrap().total, None);
+        assert_eq!(page(Some(false)).await.unwrap().total, None);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_validates_follow_redirects(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let following = |follow_redirects| CreateJob {
+            follow_redirects: Some(follow_redirects),
+            ..create_opts(None, None)
+        };
+
+        for follow_redirects in [-1, MAX_FOLLOW_REDIRECTS + 1] {
+            let rejected = create_job(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(following(follow_redirects)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let created = create_job(State(ctx.clone()), TenantId(None), JsonBody(following(3)))
+            .await
+            .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.follow_redirects, Some(3));
+
+        let stored = sqlx::query_scalar!(
+            "SELECT follow_redirects FROM one_off_jobs WHERE id = $1",
+            job.id
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert_eq!(stored, Some(3));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_validates_resolve_override(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let pinned = |resolve_override: &str| CreateJob {
+            resolve_override: Some(resolve_override.to_string()),
+            ..create_opts(None, None)
+        };
+
+        for resolve_override in [
+            "example.com",
+            "example.org:93.184.216.34",
+            "example.com:10.0.0.5",
+        ] {
+            let rejected = create_job(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(pinned(resolve_override)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let created = create_job(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(pinned("example.com:93.184.216.34")),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(
+            job.resolve_override.as_deref(),
+            Some("example.com:93.184.216.34")
+        );
+
+        let stored = sqlx::query_scalar!(
+            "SELECT resolve_override FROM one_off_jobs WHERE id = $1",
+            job.id
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert_eq!(stored.as_deref(), Some("example.com:93.184.216.34"));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_enforces_tenant_retry_backoff(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            "
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs, min_retry_backoff_ms)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10, 10000)
+            "
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let tenant_id = || TenantId(Some("tenant_a".to_string()));
+        let with_backoff = |backoff_ms, backoff_max_ms| CreateJob {
+            retry_backoff_ms: backoff_ms,
+            retry_backoff_max_ms: backoff_max_ms,
+            ..create_opts(None, None)
+        };
+
+        for (backoff_ms, backoff_max_ms) in [(Some(5_000), None), (Some(20_000), Some(15_000))] {
+            let rejected = create_job(
+                State(ctx.clone()),
+                tenant_id(),
+                JsonBody(with_backoff(backoff_ms, backoff_max_ms)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let created = create_job(
+            State(ctx),
+            tenant_id(),
+            JsonBody(with_backoff(Some(15_000), Some(60_000))),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("expected a single job");
+        };
+        assert_eq!(job.retry_backoff_ms, Some(15_000));
+        assert_eq!(job.retry_backoff_max_ms, Some(60_000));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_enforces_max_retries(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            "
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10)
+            "
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let with_retries = |max_retries| CreateJob {
+            max_retries: Some(max_retries),
+            ..create_opts(None, None)
+        };
+
+        for (tenant_id, max_retries) in [(Some("tenant_a"), 6), (Some("tenant_a"), -1), (None, -1)]
+        {
+            let rejected = create_job(
+                State(ctx.clone()),
+                TenantId(tenant_id.map(str::to_string)),
+                JsonBody(with_retries(max_retries)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        let created = create_job(
+            State(ctx),
+            TenantId(Some("tenant_a".to_string())),
+            JsonBody(with_retries(5)),
+        )
+        .await
+        .unwrap();
+
+        let CreatedOneOffJobs::Single(job) = created else {
+            panic!("Expected a single job");
+        };
+        assert_eq!(job.max_retries, 5);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_job_enforces_execute_at_window(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        sqlx::query!(
+            "
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10)
+            "
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let day = Duration::days(1).num_seconds();
+        let at = |offset_secs: i64| CreateJob {
+            execute_at: Utc::now().timestamp() + offset_secs,
+            ..create_opts(None, None)
+        };
+
+        for offset_secs in [31 * day, -3600] {
+            let rejected = create_job(
+                State(ctx.clone()),
+                TenantId(Some("tenant_a".to_string())),
+                JsonBody(at(offset_secs)),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        for offset_secs in [29 * day, -60] {
+            create_job(
+                State(ctx.clone()),
+                TenantId(Some("tenant_a".to_string())),
+                JsonBody(at(offset_secs)),
+            )
+            .await
+            .unwrap();
+        }
+
+        // Jobs without a tenant have no delay limit, but still can't be late.
+        let far_out = create_job(State(ctx.clone()), TenantId(None), JsonBody(at(365 * day))).await;
+        assert!(far_out.is_ok());
+        let late = create_job(State(ctx), TenantId(None), JsonBody(at(-3600))).await;
+        assert!(late.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_jobs_batch_is_atomic(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let created = create_jobs_batch(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(vec![create_opts(None, None), create_opts(None, None)]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.count, 2);
+
+        let mut invalid = create_opts(None, None);
+        invalid.region = Some("mars-north".to_string());
+
+        let rejected = create_jobs_batch(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(vec![create_opts(None, None), invalid]),
+        )
+        .await;
+        assert!(rejected.is_err_and(|err| err.message.starts_with("Job 1:")));
+
+        let count = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM one_off_jobs"#)
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(count, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_jobs_batch_rejects_oversized_batch(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let batch = (0..=MAX_BATCH_SIZE)
+            .map(|_| create_opts(None, None))
+            .collect();
+
+        let rejected = create_jobs_batch(State(ctx), TenantId(None), JsonBody(batch)).await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use http::{StatusCode, header::CONTENT_TYPE};
+
+use crate::api::{ApiError, Context};
+
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (idx, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if value <= *bound {
+                self.buckets[idx] += 1;
+            }
+        }
+
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// In-process metrics for the api, rendered in the prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    one_off_jobs_created: AtomicU64,
+    cron_jobs_created: AtomicU64,
+    request_latency: Mutex<BTreeMap<(String, String), Histogram>>,
+}
+
+impl Metrics {
+    pub fn record_one_off_jobs_created(&self, count: u64) {
+        self.one_off_jobs_created
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_cron_job_created(&self) {
+        self.cron_jobs_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn observe_request(&self, method: &str, path: &str, seconds: f64) {
+        let mut latency = self
+            .request_latency
+            .lock()
+            .expect("Request latency metrics poisoned.");
+
+        latency
+            .entry((method.to_string(), path.to_string()))
+            .or_default()
+            .observe(seconds);
+    }
+
+    fn render(&self, executions_recorded: i64, backlog: &[(String, i64)]) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP rocktick_jobs_created_total Jobs created through the api."
+        );
+        let _ = writeln!(out, "# TYPE rocktick_jobs_created_total counter");
+        let _ = writeln!(
+            out,
+            "rocktick_jobs_created_total{{kind=\"one_off\"}} {}",
+            self.one_off_jobs_created.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "rocktick_jobs_created_total{{kind=\"cron\"}} {}",
+            self.cron_jobs_created.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP rocktick_executions_recorded_total Job executions recorded by the broker."
+        );
+        let _ = writeln!(out, "# TYPE rocktick_executions_recorded_total counter");
+        let _ = writeln!(
+            out,
+            "rocktick_executions_recorded_total {executions_recorded}"
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP rocktick_scheduled_jobs_backlog Scheduled jobs waiting to be executed."
+        );
+        let _ = writeln!(out, "# TYPE rocktick_scheduled_jobs_backlog gauge");
+        for (region, depth) in backlog {
+            let _ = writeln!(
+                out,
+                "rocktick_scheduled_jobs_backlog{{region=\"{region}\"}} {depth}"
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP rocktick_api_request_duration_seconds Api request latency."
+        );
+        let _ = writeln!(
+            out,
+            "# TYPE rocktick_api_request_duration_seconds histogram"
+        );
+
+        let latency = self
+            .request_latency
+            .lock()
+            .expect("Request latency metrics poisoned.");
+
+        for ((method, path), histogram) in latency.iter() {
+            let labels = format!("method=\"{method}\",path=\"{path}\"");
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    "rocktick_api_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}"
+                );
+            }
+
+            let _ = writeln!(
+                out,
+                "rocktick_api_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "rocktick_api_request_duration_seconds_sum{{{labels}}} {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "rocktick_api_request_duration_seconds_count{{{labels}}} {}",
+                histogram.count
+            );
+        }
+
+        out
+    }
+}
+
+pub async fn metrics_middleware(State(ctx): State<Context>, req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Utc::now();
+    let response = next.run(req).await;
+
+    let elapsed = (Utc::now() - start)
+        .to_std()
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0);
+
+    ctx.metrics.observe_request(&method, &path, elapsed);
+
+    response
+}
+
+pub async fn metrics_handler(State(ctx): State<Context>) -> Result<Response, ApiError> {
+    let executions_recorded = sqlx::query_scalar!("SELECT count(*) FROM job_executions")
+        .fetch_one(&ctx.pool)
+        .await?
+        .unwrap_or(0);
+
+    let backlog = sqlx::query!(
+        r#"
+    SELECT region, count(*) as "depth!"
+    FROM scheduled_jobs
+    WHERE execution_id IS NULL AND deleted_at IS NULL
+    GROUP BY region
+    ORDER BY region
+    "#
+    )
+    .fetch_all(&ctx.pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.region, row.depth))
+    .collect::<Vec<_>>();
+
+    let body = ctx.metrics.render(executions_recorded, &backlog);
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters_and_backlog() {
+        let metrics = Metrics::default();
+        metrics.record_one_off_jobs_created(3);
+        metrics.record_cron_job_created();
+
+        let out = metrics.render(7, &[("na-east".to_string(), 4)]);
+
+        assert!(out.contains("rocktick_jobs_created_total{kind=\"one_off\"} 3\n"));
+        assert!(out.contains("rocktick_jobs_created_total{kind=\"cron\"} 1\n"));
+        assert!(out.contains("rocktick_executions_recorded_total 7\n"));
+        assert!(out.contains("rocktick_scheduled_jobs_backlog{region=\"na-east\"} 4\n"));
+    }
+
+    #[test]
+    fn test_render_latency_histogram_is_cumulative() {
+        let metrics = Metrics::default();
+        metrics.observe_request("GET", "/api/jobs", 0.02);
+        metrics.observe_request("GET", "/api/jobs", 3.0);
+
+        let out = metrics.render(0, &[]);
+        let labels = "method=\"GET\",path=\"/api/jobs\"";
+
+        assert!(out.contains(&format!(
+            "rocktick_api_request_duration_seconds_bucket{{{labels},le=\"0.01\"}} 0\n"
+        )));
+        assert!(out.contains(&format!(
+            "rocktick_api_request_duration_seconds_bucket{{{labels},le=\"0.025\"}} 1\n"
+        )));
+        assert!(out.contains(&format!(
+            "rocktick_api_request_duration_seconds_bucket{{{labels},le=\"5\"}} 2\n"
+        )));
+        assert!(out.contains(&format!(
+            "rocktick_api_request_duration_seconds_count{{{labels}}} 2\n"
+        )));
+    }
+}
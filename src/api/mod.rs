@@ -1,22 +1,40 @@
+mod api_keys;
 mod cron;
+mod drones;
 mod executions;
 mod jobs;
+mod metrics;
 mod models;
+mod rate_limit;
 mod tenants;
+mod time_format;
+mod verify;
+mod workflows;
 
 use axum::{
     Json, Router,
-    extract::{FromRequest, FromRequestParts, Request, State, rejection::JsonRejection},
+    body::Bytes,
+    extract::{
+        DefaultBodyLimit, FromRequest, FromRequestParts, Request, State, rejection::JsonRejection,
+    },
     middleware::Next,
     response::{IntoResponse, Response},
     routing::get,
 };
 
+use std::{
+    future::IntoFuture,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
 use futures::never::Never;
-use http::StatusCode;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header};
 use serde::Serialize;
-use serde_json::Value;
+use sha2::{Digest, Sha256};
 use sqlx::{Pool, Postgres};
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use utoipa::{
     Modify, OpenApi, ToSchema,
     openapi::{
@@ -28,7 +46,7 @@ use utoipa_axum::router::OpenApiRouter;
 use utoipa_scalar::{Scalar, Servable};
 
 use crate::{
-    ApiOptions,
+    ApiOptions, pg,
     secrets::{self, KeyRing},
 };
 
@@ -37,9 +55,20 @@ pub struct Config {
     port: usize,
     hostname: String,
     pool: Pool<Postgres>,
+    pool_size: u32,
+    read_replica_url: Option<String>,
     valid_regions: Vec<String>,
+    default_region: Option<String>,
     auth_keys: Option<Vec<String>>,
     key_ring: KeyRing,
+    allow_insecure_jobs: bool,
+    signing_key_grace: Duration,
+    max_valid_regions: usize,
+    rate_limit: Option<u32>,
+    rate_limit_burst: Option<u32>,
+    cors_allow_origin: Vec<String>,
+    max_body_bytes: usize,
+    drain_timeout: Duration,
 }
 
 impl Config {
@@ -48,11 +77,76 @@ impl Config {
             port: options.port,
             hostname: options.hostname,
             pool,
-            valid_regions: options.valid_regions,
+            pool_size: options.pool_size,
+            read_replica_url: options.read_replica_url,
+            valid_regions: clean_regions(options.valid_regions),
+            default_region: options.default_region,
             auth_keys: options.auth_keys,
             key_ring: options.key_ring,
+            allow_insecure_jobs: options.allow_insecure_jobs,
+            signing_key_grace: Duration::from_secs(options.signing_key_grace_secs),
+            max_valid_regions: options.max_valid_regions,
+            rate_limit: options.api_rate_limit,
+            rate_limit_burst: options.api_rate_limit_burst,
+            cors_allow_origin: options.cors_allow_origin,
+            max_body_bytes: options.max_body_bytes,
+            drain_timeout: Duration::from_secs(options.api_drain_timeout_secs),
+        }
+    }
+
+    fn verify_regions(&self) -> anyhow::Result<()> {
+        if self.valid_regions.is_empty() {
+            return Err(anyhow::anyhow!("No valid regions provided"));
+        }
+
+        if self.valid_regions.len() > self.max_valid_regions {
+            return Err(anyhow::anyhow!(
+                "{} valid regions provided, at most {} are allowed",
+                self.valid_regions.len(),
+                self.max_valid_regions
+            ));
+        }
+
+        if let Some(region) = self.valid_regions.iter().find(|r| !is_valid_region(r)) {
+            return Err(anyhow::anyhow!(
+                "Invalid region {region:?}, regions may only contain lowercase letters, digits and dashes"
+            ));
+        }
+
+        if let Some(default_region) = &self.default_region
+            && !self.valid_regions.contains(default_region)
+        {
+            return Err(anyhow::anyhow!(
+                "Default region {default_region} is not one of the valid regions"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Trims each region and drops empty and repeated entries, keeping the order
+/// they were given in.
+fn clean_regions(regions: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::with_capacity(regions.len());
+
+    for region in regions {
+        let region = region.trim();
+
+        if !region.is_empty() && !cleaned.iter().any(|r| r == region) {
+            cleaned.push(region.to_string());
         }
     }
+
+    cleaned
+}
+
+fn is_valid_region(region: &str) -> bool {
+    !region.starts_with('-')
+        && !region.ends_with('-')
+        && region
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
 }
 
 #[derive(OpenApi)]
@@ -83,9 +177,93 @@ impl Modify for BearerAuth {
 #[derive(Debug, Clone)]
 pub struct Context {
     pub pool: Pool<Postgres>,
+    /// Serves the read-only handlers when configured. Writes, and reads
+    /// that have to see them, stay on `pool`.
+    read_replica: Option<Pool<Postgres>>,
     pub valid_regions: Vec<String>,
+    pub default_region: Option<String>,
     auth_keys: Option<Vec<String>>,
     pub key_ring: KeyRing,
+    pub metrics: Arc<metrics::Metrics>,
+    pub allow_insecure_jobs: bool,
+    /// How long a tenant's previous signing key keeps verifying after a
+    /// rotation.
+    pub signing_key_grace: Duration,
+    /// Throttles each tenant's requests, when a rate limit is configured.
+    rate_limiter: Option<Arc<rate_limit::RateLimiter>>,
+}
+
+impl Context {
+    /// Picks the region for a new job, falling back to the configured default
+    /// region, or the only valid region when there is just one.
+    pub fn resolve_region(&self, region: Option<String>) -> Result<String, ApiError> {
+        let region = match (region, &self.default_region) {
+            (Some(region), _) => region,
+            (None, Some(default_region)) => default_region.clone(),
+            (None, None) if self.valid_regions.len() == 1 => self.valid_regions[0].clone(),
+            (None, None) => {
+                return Err(ApiError::bad_request(
+                    "invalid_region",
+                    Some(&format!(
+                        "No region specified, choose one of the following: {}",
+                        self.valid_regions.join(", ")
+                    )),
+                ));
+            }
+        };
+
+        if !self.valid_regions.contains(&region) {
+            return Err(ApiError::bad_request(
+                "invalid_region",
+                Some(&format!(
+                    "Invalid region: {}, choose one of the following: {}",
+                    region,
+                    self.valid_regions.join(", ")
+                )),
+            ));
+        }
+
+        Ok(region)
+    }
+
+    /// The pool read-only handlers query, the replica when there is one.
+    pub fn read_pool(&self) -> &Pool<Postgres> {
+        self.read_replica.as_ref().unwrap_or(&self.pool)
+    }
+
+    /// Looks something up on the read pool, retrying on the primary when
+    /// the replica doesn't have it, as it may not have caught up with a
+    /// resource that was only just created.
+    pub async fn read_or_primary<'a, T, F, Fut>(&'a self, fetch: F) -> sqlx::Result<Option<T>>
+    where
+        F: Fn(&'a Pool<Postgres>) -> Fut,
+        Fut: Future<Output = sqlx::Result<Option<T>>>,
+    {
+        let Some(read_replica) = &self.read_replica else {
+            return fetch(&self.pool).await;
+        };
+
+        match fetch(read_replica).await? {
+            Some(found) => Ok(Some(found)),
+            None => fetch(&self.pool).await,
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn test_context(pool: Pool<Postgres>) -> Context {
+    Context {
+        pool,
+        read_replica: None,
+        valid_regions: vec!["na-east".to_string()],
+        default_region: None,
+        auth_keys: None,
+        key_ring: KeyRing::dev(),
+        metrics: Arc::default(),
+        allow_insecure_jobs: false,
+        signing_key_grace: Duration::from_secs(crate::DEFAULT_SIGNING_KEY_GRACE_SECS),
+        rate_limiter: None,
+    }
 }
 
 #[derive(FromRequest)]
@@ -96,18 +274,23 @@ impl From<JsonRejection> for ApiError {
     fn from(value: JsonRejection) -> Self {
         match value {
             JsonRejection::JsonDataError(json_data_error) => {
-                ApiError::bad_request(Some(&json_data_error.body_text()))
+                ApiError::bad_request("invalid_json", Some(&json_data_error.body_text()))
             }
             JsonRejection::JsonSyntaxError(json_syntax_error) => {
-                ApiError::bad_request(Some(&json_syntax_error.body_text()))
+                ApiError::bad_request("invalid_json", Some(&json_syntax_error.body_text()))
             }
             JsonRejection::MissingJsonContentType(missing_json_content_type) => {
-                ApiError::bad_request(Some(&missing_json_content_type.body_text()))
+                ApiError::bad_request("invalid_json", Some(&missing_json_content_type.body_text()))
+            }
+            JsonRejection::BytesRejection(bytes_rejection)
+                if bytes_rejection.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+            {
+                ApiError::payload_too_large(Some(&bytes_rejection.body_text()))
             }
             JsonRejection::BytesRejection(bytes_rejection) => {
-                ApiError::bad_request(Some(&bytes_rejection.body_text()))
+                ApiError::bad_request("invalid_json", Some(&bytes_rejection.body_text()))
             }
-            _ => ApiError::bad_request(None),
+            _ => ApiError::bad_request("invalid_json", None),
         }
     }
 }
@@ -143,6 +326,7 @@ impl From<secrets::SecretError> for ApiError {
     fn from(_value: secrets::SecretError) -> Self {
         ApiError {
             code: StatusCode::INTERNAL_SERVER_ERROR,
+            error_code: "internal_error",
             message: "InternalServerCryptoError".to_string(),
         }
     }
@@ -153,6 +337,11 @@ pub struct ApiError {
     #[serde(skip_serializing)]
     #[schema(ignore)]
     code: StatusCode,
+    /// A stable, machine-readable reason for the error, such as
+    /// `invalid_region` or `tenant_limit_exceeded`. Unlike `message` it
+    /// doesn't change wording between releases.
+    #[schema(value_type = String)]
+    error_code: &'static str,
     message: String,
 }
 
@@ -166,6 +355,7 @@ impl ApiError {
     pub fn internal_server_error(message: Option<&str>) -> Self {
         ApiError {
             code: StatusCode::INTERNAL_SERVER_ERROR,
+            error_code: "internal_error",
             message: message.unwrap_or("Internal server error").to_string(),
         }
     }
@@ -173,30 +363,78 @@ impl ApiError {
     pub fn not_found() -> Self {
         ApiError {
             code: StatusCode::NOT_FOUND,
+            error_code: "not_found",
             message: "Not Found".to_string(),
         }
     }
 
-    pub fn bad_request(message: Option<&str>) -> Self {
+    pub fn bad_request(error_code: &'static str, message: Option<&str>) -> Self {
         ApiError {
             code: StatusCode::BAD_REQUEST,
+            error_code,
             message: message.unwrap_or("Bad request").to_string(),
         }
     }
 
+    pub fn payload_too_large(message: Option<&str>) -> Self {
+        ApiError {
+            code: StatusCode::PAYLOAD_TOO_LARGE,
+            error_code: "payload_too_large",
+            message: message.unwrap_or("Payload too large").to_string(),
+        }
+    }
+
+    pub fn conflict(message: Option<&str>) -> Self {
+        ApiError {
+            code: StatusCode::CONFLICT,
+            error_code: "conflict",
+            message: message.unwrap_or("Conflict").to_string(),
+        }
+    }
+
+    pub fn unauthorized() -> Self {
+        ApiError {
+            code: StatusCode::UNAUTHORIZED,
+            error_code: "unauthorized",
+            message: "UNAUTHORIZED".to_string(),
+        }
+    }
+
     pub fn tenant_not_allowed() -> Self {
         ApiError {
             code: StatusCode::FORBIDDEN,
+            error_code: "tenant_not_allowed",
             message: "Tenant not allowed".to_string(),
         }
     }
+
+    pub fn too_many_requests() -> Self {
+        ApiError {
+            code: StatusCode::TOO_MANY_REQUESTS,
+            error_code: "rate_limited",
+            message: "Too many requests".to_string(),
+        }
+    }
+
+    pub fn tenant_suspended() -> Self {
+        ApiError {
+            code: StatusCode::FORBIDDEN,
+            error_code: "tenant_suspended",
+            message: "Tenant is suspended".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ApiListResponse<T: Serialize + ToSchema> {
     data: Vec<T>,
+    /// Number of items on this page.
     count: usize,
     cursor: Option<String>,
+    /// Number of items matching the request across every page. Only counted
+    /// when the request asks for it with `with_total=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<i64>,
 }
 
 impl<T> IntoResponse for ApiListResponse<T>
@@ -211,6 +449,23 @@ where
 #[derive(Debug, Clone)]
 pub struct TenantId(Option<String>);
 
+/// The tenant whose api key authenticated the request.
+#[derive(Debug, Clone)]
+struct AuthenticatedTenant(String);
+
+/// The tenant a request acts as. A tenant's api key pins it to that tenant,
+/// only requests made with an admin key may pick one with the `tenant-id`
+/// header.
+fn requested_tenant_id<'a>(
+    headers: &'a HeaderMap,
+    extensions: &'a http::Extensions,
+) -> Option<&'a str> {
+    match extensions.get::<AuthenticatedTenant>() {
+        Some(AuthenticatedTenant(tenant_id)) => Some(tenant_id),
+        None => headers.get("tenant-id").and_then(|v| v.to_str().ok()),
+    }
+}
+
 impl<S> FromRequestParts<S> for TenantId
 where
     S: Send + Sync,
@@ -222,58 +477,154 @@ where
         _state: &S,
     ) -> Result<Self, Self::Rejection> {
         Ok(TenantId(
-            parts
-                .headers
-                .get("tenant-id")
-                .and_then(|v| v.to_str().ok())
-                .map(|v| v.to_string()),
+            requested_tenant_id(&parts.headers, &parts.extensions).map(|v| v.to_string()),
         ))
     }
 }
 
-pub async fn start(config: Config) -> anyhow::Result<()> {
+/// Serves the api until `shutdown` is cancelled, then stops accepting
+/// connections and waits up to the drain timeout for in-flight requests.
+pub async fn start(config: Config, shutdown: CancellationToken) -> anyhow::Result<()> {
     println!("Valid Regions: {:?}", &config.valid_regions);
 
-    if config.valid_regions.is_empty() {
-        return Err(anyhow::anyhow!("No valid regions provided"));
-    }
+    config.verify_regions()?;
+    let cors = cors_layer(&config.cors_allow_origin)?;
+
+    let read_replica = match config.read_replica_url {
+        Some(read_replica_url) => Some(pg::create_pool(read_replica_url, config.pool_size).await?),
+        None => None,
+    };
 
     let context = Context {
         pool: config.pool,
+        read_replica,
         valid_regions: config.valid_regions,
+        default_region: config.default_region,
         auth_keys: config.auth_keys,
         key_ring: config.key_ring,
+        metrics: Arc::default(),
+        allow_insecure_jobs: config.allow_insecure_jobs,
+        signing_key_grace: config.signing_key_grace,
+        rate_limiter: config.rate_limit.map(|per_sec| {
+            Arc::new(rate_limit::RateLimiter::new(
+                per_sec,
+                config.rate_limit_burst.unwrap_or(per_sec),
+            ))
+        }),
     };
 
     let router = create_router();
     let spec = create_spec();
 
     let scalar = Scalar::with_url("/docs", spec);
+    // Serialized now rather than on the first request for it.
+    openapi_document();
 
     let app = router
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .layer(axum::middleware::from_fn(
+            time_format::time_format_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            context.clone(),
+            rate_limit::rate_limit_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             context.clone(),
             auth_middleware,
         ))
+        .layer(axum::middleware::from_fn_with_state(
+            context.clone(),
+            metrics::metrics_middleware,
+        ))
         .route("/docs/openapi.json", get(openapi_json))
-        .merge(scalar)
-        .with_state(context);
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .merge(scalar);
+
+    // Outermost, so preflight requests are answered before auth sees them.
+    let app = match cors {
+        Some(cors) => app.layer(cors),
+        None => app,
+    }
+    .with_state(context);
 
     let listener =
         tokio::net::TcpListener::bind(format!("{}:{}", config.hostname, config.port)).await?;
     println!("Listening on {}", listener.local_addr().unwrap());
 
-    axum::serve(listener, app).await?;
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+        .into_future();
+
+    let drain_timeout = config.drain_timeout;
+    let drain_deadline = async {
+        shutdown.cancelled().await;
+        println!("Draining api requests...");
+        tokio::time::sleep(drain_timeout).await;
+    };
+
+    tokio::select! {
+      server_res = server => {server_res?;},
+      _ = drain_deadline => {
+        tracing::warn!(?drain_timeout, "Api requests were still running at the drain timeout.");
+      }
+    }
 
     Ok(())
 }
 
+/// Lets browsers on the given origins call the api, or leaves cross-origin
+/// requests refused when there are none.
+fn cors_layer(origins: &[String]) -> anyhow::Result<Option<CorsLayer>> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .map_err(|_| anyhow::anyhow!("Invalid CORS origin {origin:?}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        AllowOrigin::list(origins)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static("tenant-id"),
+            ])
+            .expose_headers([header::RETRY_AFTER]),
+    ))
+}
+
 fn init_router() -> OpenApiRouter<Context> {
     OpenApiRouter::new()
         .merge(tenants::init_router())
+        .merge(api_keys::init_router())
         .merge(jobs::init_router())
         .merge(cron::init_router())
         .merge(executions::init_router())
+        .merge(drones::init_router())
+        .merge(workflows::init_router())
+        .merge(verify::init_router())
 }
 
 fn create_router() -> Router<Context> {
@@ -288,22 +639,92 @@ fn create_spec() -> OpenApiSpec {
     MyOpenApiSpec::openapi().merge_from(spec)
 }
 
-async fn openapi_json() -> Json<Value> {
-    let spec = create_spec();
-    Json(serde_json::to_value(spec).unwrap())
+/// The serialized spec, which can't change while the process is running.
+struct OpenApiDocument {
+    body: Bytes,
+    etag: HeaderValue,
 }
 
-async fn auth_middleware(State(ctx): State<Context>, req: Request, next: Next) -> Response {
-    if ctx.auth_keys.is_none() {
-        return next.run(req).await;
+fn openapi_document() -> &'static OpenApiDocument {
+    static DOCUMENT: OnceLock<OpenApiDocument> = OnceLock::new();
+
+    DOCUMENT.get_or_init(|| {
+        let body = serde_json::to_vec(&create_spec()).unwrap();
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+
+        OpenApiDocument {
+            body: Bytes::from(body),
+            etag: HeaderValue::from_str(&etag).unwrap(),
+        }
+    })
+}
+
+async fn openapi_json(headers: HeaderMap) -> Response {
+    let document = openapi_document();
+
+    let unchanged = headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag.as_bytes() == document.etag.as_bytes());
+
+    if unchanged {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, document.etag.clone())],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            ),
+            (header::ETAG, document.etag.clone()),
+        ],
+        document.body.clone(),
+    )
+        .into_response()
+}
+
+async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+async fn readyz(State(ctx): State<Context>) -> StatusCode {
+    match sqlx::query("SELECT 1").execute(&ctx.pool).await {
+        Ok(_) => StatusCode::OK,
+        Err(err) => {
+            tracing::warn!(error = %err, "Readiness check failed to reach postgres.");
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// A tenant's api key may leave out the `tenant-id` header, but can't use it
+/// to act as another tenant.
+fn check_tenant_header(headers: &HeaderMap, tenant_id: &str) -> Result<(), ApiError> {
+    match headers.get("tenant-id") {
+        Some(header) if header.to_str().ok() != Some(tenant_id) => {
+            Err(ApiError::tenant_not_allowed())
+        }
+        _ => Ok(()),
     }
+}
 
+/// Lets through requests bearing one of the configured auth keys, which can
+/// act as any tenant, or a tenant's api key, which acts as that tenant only.
+/// A tenant's api key pins it to that tenant even when no auth keys are
+/// configured and everything else is let through.
+async fn auth_middleware(State(ctx): State<Context>, mut req: Request, next: Next) -> Response {
     if req.uri().path().starts_with("/docs") {
         return next.run(req).await;
     }
 
-    let expected_tokens = ctx.auth_keys.unwrap();
-
     let header_token = req
         .headers()
         .get(axum::http::header::AUTHORIZATION)
@@ -311,15 +732,246 @@ async fn auth_middleware(State(ctx): State<Context>, req: Request, next: Next) -
         .and_then(|v| v.strip_prefix("Bearer "))
         .map(|s| s.to_string());
 
-    if let Some(token) = header_token
-        && expected_tokens.contains(&token)
-    {
-        next.run(req).await
-    } else {
-        ApiError {
-            code: StatusCode::UNAUTHORIZED,
-            message: "UNAUTHORIZED".to_string(),
+    if let Some(token) = &header_token {
+        if ctx
+            .auth_keys
+            .as_ref()
+            .is_some_and(|expected_tokens| expected_tokens.contains(token))
+        {
+            return next.run(req).await;
         }
-        .into_response()
+
+        match api_keys::tenant_for_key(&ctx.pool, token).await {
+            Ok(Some(tenant_id)) => {
+                if let Err(err) = check_tenant_header(req.headers(), &tenant_id) {
+                    return err.into_response();
+                }
+
+                req.extensions_mut().insert(AuthenticatedTenant(tenant_id));
+                return next.run(req).await;
+            }
+            Ok(None) => {}
+            Err(err) => return ApiError::from(err).into_response(),
+        }
+    }
+
+    if ctx.auth_keys.is_none() {
+        return next.run(req).await;
+    }
+
+    ApiError::unauthorized().into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    fn context_with_regions(regions: &[&str], default_region: Option<&str>) -> Context {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/rocktick")
+            .unwrap();
+
+        Context {
+            valid_regions: regions.iter().map(|r| r.to_string()).collect(),
+            default_region: default_region.map(|r| r.to_string()),
+            ..test_context(pool)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_region_single_region_default() {
+        let ctx = context_with_regions(&["na-east"], None);
+
+        assert_eq!(ctx.resolve_region(None).unwrap(), "na-east");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_region_requires_choice_with_many_regions() {
+        let ctx = context_with_regions(&["na-east", "eu-west"], None);
+
+        assert!(ctx.resolve_region(None).is_err());
+        assert_eq!(
+            ctx.resolve_region(Some("eu-west".to_string())).unwrap(),
+            "eu-west"
+        );
+    }
+
+    #[test]
+    fn test_api_error_carries_error_code() {
+        let body = serde_json::to_value(ApiError::bad_request(
+            "invalid_region",
+            Some("Invalid region: mars"),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error_code": "invalid_region",
+                "message": "Invalid region: mars",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_region_uses_configured_default() {
+        let ctx = context_with_regions(&["na-east", "eu-west"], Some("eu-west"));
+
+        assert_eq!(ctx.resolve_region(None).unwrap(), "eu-west");
+        assert!(ctx.resolve_region(Some("asia-east".to_string())).is_err());
+    }
+
+    fn config_with_regions(regions: &[&str]) -> Config {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/rocktick")
+            .unwrap();
+
+        Config {
+            port: 3000,
+            hostname: "[::0]".to_string(),
+            pool,
+            pool_size: 1,
+            read_replica_url: None,
+            valid_regions: clean_regions(regions.iter().map(|r| r.to_string()).collect()),
+            default_region: None,
+            auth_keys: None,
+            key_ring: KeyRing::dev(),
+            allow_insecure_jobs: false,
+            signing_key_grace: Duration::from_secs(crate::DEFAULT_SIGNING_KEY_GRACE_SECS),
+            max_valid_regions: 3,
+            rate_limit: None,
+            rate_limit_burst: None,
+            cors_allow_origin: Vec::new(),
+            max_body_bytes: crate::DEFAULT_MAX_BODY_BYTES,
+            drain_timeout: Duration::from_secs(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_valid_regions_are_trimmed_and_deduped() {
+        let config = config_with_regions(&["na-east", " na-east", "", " eu-west ", "  "]);
+
+        assert_eq!(config.valid_regions, vec!["na-east", "eu-west"]);
+        assert!(config.verify_regions().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_regions_rejects_unusable_sets() {
+        assert!(
+            config_with_regions(&["", " ", ","])
+                .verify_regions()
+                .is_err()
+        );
+        assert!(
+            config_with_regions(&["na-east", "Eu West"])
+                .verify_regions()
+                .is_err()
+        );
+        assert!(config_with_regions(&["-na"]).verify_regions().is_err());
+        assert!(
+            config_with_regions(&["a", "b", "c", "d"])
+                .verify_regions()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cors_is_off_unless_origins_are_given() {
+        assert!(cors_layer(&[]).unwrap().is_none());
+        assert!(cors_layer(&["*".to_string()]).unwrap().is_some());
+        assert!(
+            cors_layer(&["https://app.example.com".to_string()])
+                .unwrap()
+                .is_some()
+        );
+        assert!(cors_layer(&["https://app.example.com\n".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_json_body_is_payload_too_large() {
+        let body = format!("\"{}\"", "a".repeat(crate::DEFAULT_MAX_BODY_BYTES));
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+
+        let rejected = JsonBody::<serde_json::Value>::from_request(req, &()).await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    #[tokio::test]
+    async fn test_openapi_json_honors_if_none_match() {
+        let fresh = openapi_json(HeaderMap::new()).await;
+        assert_eq!(fresh.status(), StatusCode::OK);
+        let etag = fresh.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let cached = openapi_json(headers).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], etag);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        assert_eq!(openapi_json(headers).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_tenant_wins_over_header() {
+        let (mut parts, _) = Request::builder()
+            .header("tenant-id", "tenant_b")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let TenantId(from_header) = TenantId::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(from_header.as_deref(), Some("tenant_b"));
+
+        parts
+            .extensions
+            .insert(AuthenticatedTenant("tenant_a".to_string()));
+        let TenantId(authenticated) = TenantId::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(authenticated.as_deref(), Some("tenant_a"));
+    }
+
+    #[test]
+    fn test_tenant_header_must_match_api_key() {
+        let with_header = |tenant_id: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("tenant-id", HeaderValue::from_static(tenant_id));
+            headers
+        };
+
+        assert!(check_tenant_header(&with_header("tenant_a"), "tenant_a").is_ok());
+        assert!(check_tenant_header(&HeaderMap::new(), "tenant_a").is_ok());
+
+        let spoofed = check_tenant_header(&with_header("tenant_b"), "tenant_a");
+        assert!(spoofed.is_err_and(|err| err.code == StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_healthz_always_ok() {
+        assert_eq!(healthz().await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_unavailable_without_postgres() {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_millis(500))
+            .connect_lazy("postgres://localhost:1/rocktick")
+            .unwrap();
+
+        let status = readyz(State(test_context(pool))).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_readyz_ok_with_postgres(pool: Pool<Postgres>) {
+        let status = readyz(State(test_context(pool))).await;
+
+        assert_eq!(status, StatusCode::OK);
     }
 }
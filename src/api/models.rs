@@ -1,11 +1,16 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, net::IpAddr};
 
 use axum::{Json, response::IntoResponse};
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
+use url::Host;
 use utoipa::ToSchema;
 
-use crate::api::ApiError;
+use crate::{
+    GLOBAL_CONFIG,
+    api::{ApiError, time_format},
+    drone::util::{is_private_ip, parse_resolve_override},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CronJob {
@@ -17,7 +22,40 @@ pub struct CronJob {
     pub timeout_ms: Option<i32>,
     pub max_retries: i32,
     pub max_response_bytes: Option<i32>,
+    pub fresh_connection: bool,
+    /// Accept invalid or self-signed tls certificates from the target.
+    pub insecure_skip_tls_verify: bool,
+    /// How the request body is framed, one of `auto`, `chunked` or `length`.
+    pub transfer_encoding: String,
+    /// What happens to runs missed during scheduler downtime, one of `skip`,
+    /// `fire_once` or `fire_all`.
+    pub catchup_policy: String,
+    /// Most runs that may be in flight at once, unlimited when unset.
+    pub max_concurrent: Option<i32>,
+    #[serde(serialize_with = "time_format::serialize")]
+    pub start_at: i64,
+    #[serde(serialize_with = "time_format::serialize_option")]
+    pub end_at: Option<i64>,
+    /// When the cron job passed `end_at` and stopped being scheduled.
+    #[serde(serialize_with = "time_format::serialize_option")]
+    pub completed_at: Option<i64>,
+    /// Whether runs are sent as conditional requests.
+    pub conditional: bool,
+    /// Whether a backlog of overdue runs is collapsed into the latest one.
+    pub coalesce_missed: bool,
+    pub retry_backoff_ms: Option<i32>,
+    pub retry_backoff_max_ms: Option<i32>,
+    pub body_read_timeout_ms: Option<i32>,
+    pub follow_redirects: Option<i32>,
+    /// Failed executions in a row, retries included, after which the cron
+    /// job pauses itself.
+    pub pause_after_failures: Option<i32>,
+    /// Failed executions since the last successful one. Resuming the cron
+    /// job starts the count over.
+    pub consecutive_failures: i32,
     pub tenant_id: Option<String>,
+    pub paused: bool,
+    #[serde(serialize_with = "time_format::serialize_option")]
     pub deleted_at: Option<i64>,
 }
 
@@ -31,13 +69,26 @@ impl IntoResponse for CronJob {
 pub struct OneOffJob {
     pub id: String,
     pub region: String,
+    #[serde(serialize_with = "time_format::serialize")]
     pub execute_at: i64,
     pub request: HttpRequest,
     pub executions: Vec<Execution>,
     pub timeout_ms: Option<i32>,
     pub max_retries: i32,
     pub max_response_bytes: Option<i32>,
+    pub fresh_connection: bool,
+    /// Accept invalid or self-signed tls certificates from the target.
+    pub insecure_skip_tls_verify: bool,
+    /// How the request body is framed, one of `auto`, `chunked` or `length`.
+    pub transfer_encoding: String,
+    pub retry_backoff_ms: Option<i32>,
+    pub retry_backoff_max_ms: Option<i32>,
+    pub body_read_timeout_ms: Option<i32>,
+    pub follow_redirects: Option<i32>,
+    /// `host:ip` pin the request is sent to instead of resolving the host.
+    pub resolve_override: Option<String>,
     pub tenant_id: Option<String>,
+    #[serde(serialize_with = "time_format::serialize_option")]
     pub deleted_at: Option<i64>,
 }
 
@@ -47,16 +98,45 @@ impl IntoResponse for OneOffJob {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum CreatedOneOffJobs {
+    Single(OneOffJob),
+    FanOut { jobs: Vec<OneOffJob> },
+}
+
+impl IntoResponse for CreatedOneOffJobs {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Execution {
     pub id: String,
     pub region: String,
+    #[serde(serialize_with = "time_format::serialize")]
     pub scheduled_at: i64,
+    #[serde(serialize_with = "time_format::serialize_option")]
     pub executed_at: Option<i64>,
     pub success: Option<bool>,
     pub request: HttpRequest,
     pub response: Option<HttpResponse>,
     pub response_error: Option<String>,
+    /// Hex encoded sha256 of the captured response body.
+    pub body_hash: Option<String>,
+    /// Whether the drone skipped tls certificate verification.
+    pub tls_verify_skipped: bool,
+    /// `drone` when executed_at is the time the drone reported, `broker`
+    /// when the drone's time was invalid and the broker's was recorded.
+    pub time_source: Option<String>,
+    /// Set when a conditional cron run got `304 Not Modified`.
+    pub unchanged: bool,
+    /// Drone that executed the request.
+    pub drone_id: Option<String>,
+    /// Milliseconds from sending the request until its response headers
+    /// arrived, or until it failed.
+    pub duration_ms: Option<i64>,
     pub timeout_ms: Option<i32>,
     pub max_retries: i32,
     pub max_response_bytes: Option<i32>,
@@ -75,40 +155,305 @@ impl IntoResponse for Execution {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HttpRequest {
     pub method: String,
+    /// `{{scheduled_at}}` and `{{job_id}}` are replaced with the execution's
+    /// scheduled unix time and id when it's sent.
     pub url: String,
     pub headers: HashMap<String, String>,
+    /// Supports the same placeholders as `url`.
     pub body: Option<String>,
 }
 
+pub const HTTP_METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
 impl HttpRequest {
     pub fn verify(&self) -> Result<(), ApiError> {
-        if self.method.len() > 10 {
-            return Err(ApiError::bad_request(Some(&format!(
-                "{} is not a valid http method (max-length: 10). Do you really need a custom http method?",
-                self.method
-            ))));
+        if !HTTP_METHODS.contains(&self.method.as_str()) {
+            return Err(ApiError::bad_request(
+                "invalid_method",
+                Some(&format!(
+                    "{} is not a supported http method, expected one of {}",
+                    self.method,
+                    HTTP_METHODS.join(", ")
+                )),
+            ));
         }
 
-        let method = http::Method::from_str(&self.method);
+        let url = url::Url::parse(&self.url).map_err(|error| {
+            ApiError::bad_request(
+                "invalid_url",
+                Some(&format!("{} is not a valid url: {error}", self.url)),
+            )
+        })?;
+
+        let allow_private_hosts = GLOBAL_CONFIG.get().is_some_and(|config| config.is_dev);
+        verify_public_url(&url, allow_private_hosts)
+    }
+}
+
+/// Hostnames that point back at the drone's own machine or network.
+const PRIVATE_HOSTNAMES: [&str; 3] = ["localhost", "metadata", "metadata.google.internal"];
+
+/// Catches urls the drone would refuse to dispatch to, so they fail at
+/// creation rather than on every execution. The drone still checks what the
+/// hostname resolves to.
+fn verify_public_url(url: &url::Url, allow_private_hosts: bool) -> Result<(), ApiError> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(ApiError::bad_request(
+            "invalid_url",
+            Some(&format!("{url} must use http or https")),
+        ));
+    }
+
+    if allow_private_hosts {
+        return Ok(());
+    }
+
+    let is_private = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
 
-        if method.is_err() {
-            return Err(ApiError::bad_request(Some(&format!(
-                "{} is not a valid http method.",
-                self.method
-            ))));
+            PRIVATE_HOSTNAMES.contains(&domain.as_str())
+                || domain.ends_with(".localhost")
+                || domain.ends_with(".internal")
         }
+        Some(Host::Ipv4(ip)) => is_private_ip(&IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_private_ip(&IpAddr::V6(ip)),
+        None => true,
+    };
+
+    if is_private {
+        return Err(ApiError::bad_request(
+            "private_host",
+            Some(&format!("{url} points at a private or internal host")),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Refuses `insecure_skip_tls_verify` unless the deployment opted into
+/// insecure jobs with `--allow-insecure-jobs`.
+pub fn verify_insecure_skip_tls_verify(
+    insecure_skip_tls_verify: Option<bool>,
+    allow_insecure_jobs: bool,
+) -> Result<(), ApiError> {
+    if insecure_skip_tls_verify == Some(true) && !allow_insecure_jobs {
+        return Err(ApiError::bad_request(
+            "insecure_jobs_disabled",
+            Some("insecure_skip_tls_verify is disabled on this deployment"),
+        ));
+    }
 
-        let url = url::Url::parse(&self.url);
+    Ok(())
+}
+
+pub const TRANSFER_ENCODINGS: [&str; 3] = ["auto", "chunked", "length"];
+
+/// Accepts the framings the drone knows how to send a body with.
+pub fn verify_transfer_encoding(transfer_encoding: Option<&str>) -> Result<(), ApiError> {
+    if let Some(transfer_encoding) = transfer_encoding
+        && !TRANSFER_ENCODINGS.contains(&transfer_encoding)
+    {
+        return Err(ApiError::bad_request(
+            "invalid_transfer_encoding",
+            Some(&format!(
+                "{transfer_encoding} is not a valid transfer encoding, expected one of {}",
+                TRANSFER_ENCODINGS.join(", ")
+            )),
+        ));
+    }
 
-        if let Err(error) = url {
-            return Err(ApiError::bad_request(Some(&format!(
-                "{} is not a valid url: {error}",
-                self.url,
-            ))));
+    Ok(())
+}
+
+pub const CATCHUP_POLICIES: [&str; 3] = ["skip", "fire_once", "fire_all"];
+
+/// Accepts the ways the cron scheduler knows to handle missed runs.
+pub fn verify_catchup_policy(catchup_policy: Option<&str>) -> Result<(), ApiError> {
+    if let Some(catchup_policy) = catchup_policy
+        && !CATCHUP_POLICIES.contains(&catchup_policy)
+    {
+        return Err(ApiError::bad_request(
+            "invalid_catchup_policy",
+            Some(&format!(
+                "{catchup_policy} is not a valid catch-up policy, expected one of {}",
+                CATCHUP_POLICIES.join(", ")
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A resolve override has to pin the request's own host, and is held to the
+/// same private address rule as the hosts the drone resolves itself.
+pub fn verify_resolve_override(resolve_override: Option<&str>, url: &str) -> Result<(), ApiError> {
+    let Some(resolve_override) = resolve_override else {
+        return Ok(());
+    };
+
+    let Some((host, ip)) = parse_resolve_override(resolve_override) else {
+        return Err(ApiError::bad_request(
+            "invalid_resolve_override",
+            Some(&format!(
+                "{resolve_override} is not a valid resolve override, expected host:ip"
+            )),
+        ));
+    };
+
+    let url_host = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+
+    if !url_host.is_some_and(|url_host| url_host.eq_ignore_ascii_case(host)) {
+        return Err(ApiError::bad_request(
+            "invalid_resolve_override",
+            Some(&format!(
+                "Your resolve override is for {host}, which isn't the host of {url}"
+            )),
+        ));
+    }
+
+    let allow_private_addrs = GLOBAL_CONFIG.get().is_some_and(|config| config.is_dev);
+
+    if !allow_private_addrs && is_private_ip(&ip) {
+        return Err(ApiError::bad_request(
+            "invalid_resolve_override",
+            Some(&format!(
+                "Your resolve override points at {ip}, which is a private address"
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Most redirects a job may follow before the drone gives up on it.
+pub const MAX_FOLLOW_REDIRECTS: i32 = 10;
+
+/// Rejects limits the drone would misread once cast to unsigned types.
+pub fn verify_job_limits(
+    timeout_ms: Option<i32>,
+    max_retries: Option<i32>,
+    max_response_bytes: Option<i32>,
+    body_read_timeout_ms: Option<i32>,
+    follow_redirects: Option<i32>,
+) -> Result<(), ApiError> {
+    if let Some(timeout_ms) = timeout_ms
+        && timeout_ms <= 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_timeout",
+            Some(&format!("Your timeout of {timeout_ms}ms must be positive")),
+        ));
+    }
+
+    if let Some(max_retries) = max_retries
+        && max_retries < 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_max_retries",
+            Some(&format!(
+                "Your max retries of {max_retries} cannot be negative"
+            )),
+        ));
+    }
+
+    if let Some(max_response_bytes) = max_response_bytes
+        && max_response_bytes < 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_max_response_bytes",
+            Some(&format!(
+                "Your max response bytes of {max_response_bytes} cannot be negative"
+            )),
+        ));
+    }
+
+    if let Some(body_read_timeout_ms) = body_read_timeout_ms
+        && body_read_timeout_ms <= 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_body_read_timeout",
+            Some(&format!(
+                "Your body read timeout of {body_read_timeout_ms}ms must be positive"
+            )),
+        ));
+    }
+
+    if let Some(follow_redirects) = follow_redirects
+        && !(0..=MAX_FOLLOW_REDIRECTS).contains(&follow_redirects)
+    {
+        return Err(ApiError::bad_request(
+            "invalid_follow_redirects",
+            Some(&format!(
+                "You can follow between 0 and {MAX_FOLLOW_REDIRECTS} redirects, not {follow_redirects}"
+            )),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The retry scheduler never waits longer than a day between attempts.
+const MAX_RETRY_BACKOFF_MS: i32 = 24 * 60 * 60 * 1000;
+
+/// Checks a job's retry backoff against the tenant's minimum. Either bound
+/// may be left unset to fall back to the scheduler's default.
+pub fn verify_retry_backoff(
+    retry_backoff_ms: Option<i32>,
+    retry_backoff_max_ms: Option<i32>,
+    min_retry_backoff_ms: Option<i32>,
+) -> Result<(), ApiError> {
+    for (name, value) in [
+        ("retry backoff", retry_backoff_ms),
+        ("max retry backoff", retry_backoff_max_ms),
+    ] {
+        let Some(value) = value else {
+            continue;
         };
 
-        Ok(())
+        if value <= 0 {
+            return Err(ApiError::bad_request(
+                "invalid_retry_backoff",
+                Some(&format!("Your {name} of {value}ms must be positive")),
+            ));
+        }
+
+        if value > MAX_RETRY_BACKOFF_MS {
+            return Err(ApiError::bad_request(
+                "invalid_retry_backoff",
+                Some(&format!(
+                    "Your {name} of {value}ms is higher than the limit of {MAX_RETRY_BACKOFF_MS}ms"
+                )),
+            ));
+        }
+
+        if let Some(min_backoff) = min_retry_backoff_ms
+            && value < min_backoff
+        {
+            return Err(ApiError::bad_request(
+                "invalid_retry_backoff",
+                Some(&format!(
+                    "Your {name} of {value}ms is lower than your minimum of {min_backoff}ms"
+                )),
+            ));
+        }
+    }
+
+    if let Some(backoff) = retry_backoff_ms
+        && let Some(max_backoff) = retry_backoff_max_ms
+        && max_backoff < backoff
+    {
+        return Err(ApiError::bad_request(
+            "invalid_retry_backoff",
+            Some(&format!(
+                "Your max retry backoff of {max_backoff}ms is lower than your retry backoff of {backoff}ms"
+            )),
+        ));
     }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -116,6 +461,12 @@ pub struct HttpResponse {
     pub status: i32,
     pub headers: HashMap<String, String>,
     pub body: String,
+    /// Size of the recorded body in bytes.
+    pub bytes_used: i32,
+    /// The body was cut off at the job's max_response_bytes.
+    pub truncated: bool,
+    /// The body was cut off at the job's body_read_timeout_ms.
+    pub body_read_truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -132,6 +483,9 @@ pub struct Tenant {
     pub retain_for_days: i32,
     pub max_delay_days: i32,
     pub max_cron_jobs: i32,
+    pub max_concurrent_executions: Option<i32>,
+    pub min_retry_backoff_ms: Option<i32>,
+    pub suspended: bool,
 }
 
 impl IntoResponse for Tenant {
@@ -139,3 +493,103 @@ impl IntoResponse for Tenant {
         (StatusCode::OK, Json(self)).into_response()
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Drone {
+    pub id: String,
+    pub ip: String,
+    pub port: i32,
+    pub region: String,
+    #[serde(serialize_with = "time_format::serialize")]
+    pub last_checkin: i64,
+    #[serde(serialize_with = "time_format::serialize")]
+    pub checkin_by: i64,
+    pub active: bool,
+    pub region_affinities: HashMap<String, i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: String,
+    pub tenant_id: String,
+    #[serde(serialize_with = "time_format::serialize")]
+    pub created_at: i64,
+    #[serde(serialize_with = "time_format::serialize_option")]
+    pub revoked_at: Option<i64>,
+}
+
+impl IntoResponse for ApiKey {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+/// A newly created api key, the only time its secret is returned.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+impl IntoResponse for CreatedApiKey {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Workflow {
+    pub id: String,
+    pub region: String,
+    pub tenant_id: Option<String>,
+    pub implementation_url: String,
+    #[schema(value_type = Object)]
+    pub input: serde_json::Value,
+    #[schema(value_type = Object)]
+    pub context: serde_json::Value,
+    pub status: String,
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub max_retries: i32,
+}
+
+impl IntoResponse for Workflow {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verify(url: &str, allow_private_hosts: bool) -> Result<(), ApiError> {
+        verify_public_url(&url::Url::parse(url).unwrap(), allow_private_hosts)
+    }
+
+    #[test]
+    fn test_verify_public_url_rejects_private_hosts() {
+        for url in [
+            "http://localhost:8080/hook",
+            "http://api.localhost/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://metadata.google.internal/computeMetadata/v1",
+            "http://10.0.0.1/hook",
+            "http://127.0.0.1/hook",
+            "http://[::1]/hook",
+            "http://[fdaa::3]/hook",
+        ] {
+            assert!(
+                verify(url, false).is_err_and(|err| err.code == StatusCode::BAD_REQUEST),
+                "{url} was accepted"
+            );
+            assert!(verify(url, true).is_ok(), "{url} was rejected in dev");
+        }
+
+        assert!(verify("https://example.com/hook", false).is_ok());
+        assert!(verify("http://93.184.215.14/hook", false).is_ok());
+        assert!(verify("ftp://example.com/file", true).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, HeaderValue, header::RETRY_AFTER};
+use tokio::time::Instant;
+
+use crate::api::{ApiError, AuthenticatedTenant, Context};
+
+/// Past this many tenants the limiter forgets those whose buckets have filled
+/// back up, as they'd start over from a full bucket anyway.
+const MAX_TRACKED_TENANTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token buckets keyed by tenant id, so one tenant can't flood the api.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allows `per_sec` requests a second per tenant, and up to `burst` at
+    /// once after a quiet spell.
+    pub fn new(per_sec: u32, burst: u32) -> Self {
+        Self {
+            per_sec: f64::from(per_sec.max(1)),
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes a token from the tenant's bucket, or returns how long until it
+    /// has one again.
+    fn acquire(&self, tenant_id: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("Rate limit buckets poisoned.");
+
+        if buckets.len() >= MAX_TRACKED_TENANTS && !buckets.contains_key(tenant_id) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(tenant_id.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            refilled_at: now,
+        });
+
+        bucket.tokens = self.refill(bucket, now);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_sec,
+            ))
+        }
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+
+        (bucket.tokens + elapsed.as_secs_f64() * self.per_sec).min(self.burst)
+    }
+
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+}
+
+/// The tenant whose bucket a request draws from. Admin keys may name any
+/// tenant in the `tenant-id` header, so the header only counts when auth is
+/// off and nothing else says who is calling.
+fn limited_tenant_id<'a>(
+    auth_enabled: bool,
+    headers: &'a HeaderMap,
+    extensions: &'a http::Extensions,
+) -> Option<&'a str> {
+    match extensions.get::<AuthenticatedTenant>() {
+        Some(AuthenticatedTenant(tenant_id)) => Some(tenant_id),
+        None if auth_enabled => None,
+        None => headers.get("tenant-id").and_then(|v| v.to_str().ok()),
+    }
+}
+
+/// Rejects requests from tenants that are over their rate with a 429. Requests
+/// without a tenant aren't limited.
+pub async fn rate_limit_middleware(
+    State(ctx): State<Context>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(limiter) = &ctx.rate_limiter else {
+        return next.run(req).await;
+    };
+
+    let limited = limited_tenant_id(ctx.auth_keys.is_some(), req.headers(), req.extensions())
+        .map(|tenant_id| limiter.acquire(tenant_id, Instant::now()));
+
+    let Some(Err(retry_after)) = limited else {
+        return next.run(req).await;
+    };
+
+    let mut response = ApiError::too_many_requests().into_response();
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let limiter = RateLimiter::new(2, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.acquire("tenant_a", start).is_ok());
+        }
+
+        let retry_after = limiter.acquire("tenant_a", start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+
+        // Other tenants have buckets of their own.
+        assert!(limiter.acquire("tenant_b", start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.acquire("tenant_a", later).is_ok());
+        assert!(limiter.acquire("tenant_a", later).is_err());
+    }
+
+    #[test]
+    fn test_full_buckets_are_forgotten_once_tracking_is_full() {
+        let limiter = RateLimiter::new(1, 1);
+        let start = Instant::now();
+
+        for idx in 0..MAX_TRACKED_TENANTS {
+            limiter.acquire(&format!("tenant_{idx}"), start).unwrap();
+        }
+        assert_eq!(limiter.tracked(), MAX_TRACKED_TENANTS);
+
+        let later = start + Duration::from_secs(1);
+        limiter.acquire("tenant_new", later).unwrap();
+        assert_eq!(limiter.tracked(), 1);
+    }
+
+    #[test]
+    fn test_header_tenant_is_only_limited_without_auth() {
+        let mut headers = HeaderMap::new();
+        headers.insert("tenant-id", HeaderValue::from_static("tenant_b"));
+        let mut extensions = http::Extensions::new();
+
+        assert_eq!(
+            limited_tenant_id(false, &headers, &extensions),
+            Some("tenant_b")
+        );
+        assert_eq!(limited_tenant_id(true, &headers, &extensions), None);
+
+        extensions.insert(AuthenticatedTenant("tenant_a".to_string()));
+        assert_eq!(
+            limited_tenant_id(true, &headers, &extensions),
+            Some("tenant_a")
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
-use chrono::TimeDelta;
+use chrono::{DateTime, TimeDelta};
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
@@ -13,13 +13,17 @@ use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 
 use crate::{
-    api::{ApiError, Context, JsonBody, TenantId, models::Tenant},
+    api::{ApiError, ApiListResponse, Context, JsonBody, TenantId, models::Tenant},
     id,
     secrets::Secret,
+    util,
 };
 
 #[derive(Debug, Clone, Deserialize, ToSchema)]
 struct CreateTenant {
+    /// Id to create the tenant with, in the `tenant_` format. Creating a
+    /// tenant again with the same id returns the existing tenant.
+    id: Option<String>,
     max_tokens: i32,
     tok_per_day: i32,
     max_timeout: i32,
@@ -30,6 +34,9 @@ struct CreateTenant {
     retain_for_days: i32,
     max_delay_days: i32,
     max_cron_jobs: i32,
+    max_concurrent_executions: Option<i32>,
+    /// Shortest retry backoff the tenant's jobs may request.
+    min_retry_backoff_ms: Option<i32>,
 }
 
 #[tracing::instrument(name = "api_create_tenant")]
@@ -43,7 +50,18 @@ async fn create_tenant(
     }
 
     let (period, increment) = compute_incr_and_period(create_opts.tok_per_day)?;
-    let new_id = id::generate("tenant");
+    let new_id = match create_opts.id.clone() {
+        Some(supplied_id) if id::is_valid("tenant", &supplied_id) => supplied_id,
+        Some(supplied_id) => {
+            return Err(ApiError::bad_request(
+                "invalid_tenant",
+                Some(&format!(
+                    "{supplied_id} is not a valid tenant id, expected tenant_ followed by letters, digits, - or _"
+                )),
+            ));
+        }
+        None => id::generate("tenant"),
+    };
     let starting_tokens = create_opts.tok_per_day;
     let new_tenant = sqlx::query!(
         r#"
@@ -60,7 +78,9 @@ async fn create_tenant(
       max_request_bytes,
       retain_for_days,
       max_delay_days,
-      max_cron_jobs)
+      max_cron_jobs,
+      max_concurrent_executions,
+      min_retry_backoff_ms)
     VALUES
       ($1,
       $2,
@@ -74,7 +94,10 @@ async fn create_tenant(
       $10,
       $11,
       $12,
-      $13)
+      $13,
+      $14,
+      $15)
+    ON CONFLICT (id) DO NOTHING
     RETURNING *;
     "#,
         new_id,
@@ -90,10 +113,25 @@ async fn create_tenant(
         create_opts.retain_for_days,
         create_opts.max_delay_days,
         create_opts.max_cron_jobs,
+        create_opts.max_concurrent_executions,
+        create_opts.min_retry_backoff_ms,
     )
-    .fetch_one(&ctx.pool)
+    .fetch_optional(&ctx.pool)
     .await?;
 
+    // A retried creation returns the tenant the first attempt created, as it
+    // is now.
+    let Some(new_tenant) = new_tenant else {
+        return get_tenant(State(ctx), TenantId(None), Path(new_id))
+            .await
+            .map_err(|err| match err.code {
+                StatusCode::NOT_FOUND => {
+                    ApiError::conflict(Some("A deleted tenant already has this id"))
+                }
+                _ => err,
+            });
+    };
+
     let tenant = Tenant {
         id: new_tenant.id,
         tokens: new_tenant.tokens,
@@ -107,6 +145,9 @@ async fn create_tenant(
         retain_for_days: new_tenant.retain_for_days,
         max_delay_days: new_tenant.max_delay_days,
         max_cron_jobs: new_tenant.max_cron_jobs,
+        max_concurrent_executions: new_tenant.max_concurrent_executions,
+        min_retry_backoff_ms: new_tenant.min_retry_backoff_ms,
+        suspended: new_tenant.suspended,
     };
 
     Ok(tenant)
@@ -124,16 +165,19 @@ async fn get_tenant(
         return Err(ApiError::tenant_not_allowed());
     }
 
-    let tenant = sqlx::query!(
-        r#"
+    let tenant = ctx
+        .read_or_primary(|pool| {
+            sqlx::query!(
+                r#"
     SELECT *
     FROM tenants
-    WHERE id = $1;
+    WHERE id = $1 AND deleted_at IS NULL;
     "#,
-        tenant_id
-    )
-    .fetch_optional(&ctx.pool)
-    .await?;
+                tenant_id
+            )
+            .fetch_optional(pool)
+        })
+        .await?;
 
     if tenant.is_none() {
         return Err(ApiError::not_found());
@@ -141,15 +185,11 @@ async fn get_tenant(
 
     let tenant = tenant.unwrap();
 
-    let period_secs = TimeDelta::microseconds(tenant.period.microseconds).as_seconds_f64();
-    let tok_per_sec = tenant.increment as f64 / period_secs;
-    let tok_per_day = tok_per_sec * 60. * 60. * 24.;
-
     let res = Tenant {
+        tok_per_day: tok_per_day(tenant.increment, &tenant.period),
         id: tenant.id,
         tokens: tenant.tokens,
         max_tokens: tenant.max_tokens,
-        tok_per_day: tok_per_day as i32,
         max_timeout: tenant.max_timeout,
         default_retries: tenant.default_retries,
         max_retries: tenant.max_retries,
@@ -158,11 +198,81 @@ async fn get_tenant(
         retain_for_days: tenant.retain_for_days,
         max_delay_days: tenant.max_delay_days,
         max_cron_jobs: tenant.max_cron_jobs,
+        max_concurrent_executions: tenant.max_concurrent_executions,
+        min_retry_backoff_ms: tenant.min_retry_backoff_ms,
+        suspended: tenant.suspended,
     };
 
     Ok(res)
 }
 
+#[derive(Debug, Deserialize)]
+struct ListTenantsParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
+    out_of_tokens: Option<bool>,
+}
+
+#[tracing::instrument(name = "api_list_tenants")]
+async fn list_tenants(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Query(params): Query<ListTenantsParams>,
+) -> Result<ApiListResponse<Tenant>, ApiError> {
+    if requesting_tenant_id.is_some() {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let limit = params.limit.unwrap_or(15).min(250);
+
+    let tenants = sqlx::query!(
+        r#"
+      SELECT * FROM tenants
+      WHERE
+        deleted_at IS NULL
+        AND ($2::text IS NULL OR id < $2)
+        AND ($3::bool IS NULL OR (tokens = 0) = $3)
+      ORDER BY id DESC
+      LIMIT $1;
+      "#,
+        limit,
+        params.cursor,
+        params.out_of_tokens
+    )
+    .fetch_all(ctx.read_pool())
+    .await?;
+
+    let tenants: Vec<Tenant> = tenants
+        .into_iter()
+        .map(|tenant| Tenant {
+            tok_per_day: tok_per_day(tenant.increment, &tenant.period),
+            id: tenant.id,
+            tokens: tenant.tokens,
+            max_tokens: tenant.max_tokens,
+            max_timeout: tenant.max_timeout,
+            default_retries: tenant.default_retries,
+            max_retries: tenant.max_retries,
+            max_max_response_bytes: tenant.max_max_response_bytes,
+            max_request_bytes: tenant.max_request_bytes,
+            retain_for_days: tenant.retain_for_days,
+            max_delay_days: tenant.max_delay_days,
+            max_cron_jobs: tenant.max_cron_jobs,
+            max_concurrent_executions: tenant.max_concurrent_executions,
+            min_retry_backoff_ms: tenant.min_retry_backoff_ms,
+            suspended: tenant.suspended,
+        })
+        .collect();
+
+    let last_tenant = tenants.last().map(|t| t.id.clone());
+
+    Ok(ApiListResponse {
+        count: tenants.len(),
+        data: tenants,
+        cursor: last_tenant,
+        total: None,
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct UsageResult {
     usage: i64,
@@ -203,7 +313,7 @@ async fn get_tenant_usage(
         BigDecimal::from(start_time),
         BigDecimal::from(end_time)
     )
-    .fetch_one(&ctx.pool)
+    .fetch_one(ctx.read_pool())
     .await?;
 
     if let Some(count) = result.usage_count {
@@ -219,6 +329,203 @@ async fn get_tenant_usage(
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct UsageSeriesParams {
+    from: i64,
+    to: i64,
+    /// `hour` or `day`, defaults to `day`.
+    bucket: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsageBucket {
+    bucket_start: i64,
+    count: i64,
+}
+
+/// Most buckets one usage series request can span.
+const MAX_USAGE_BUCKETS: i64 = 1000;
+
+/// Executions per hour or day between `from` and `to`, with empty buckets
+/// included so a dashboard can plot the series as is.
+#[tracing::instrument(name = "api_get_tenant_usage_series")]
+async fn get_tenant_usage_series(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+    Query(params): Query<UsageSeriesParams>,
+) -> Result<Json<Vec<UsageBucket>>, ApiError> {
+    if let Some(requesting_tenant_id) = requesting_tenant_id
+        && requesting_tenant_id != tenant_id
+    {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let bucket = params.bucket.as_deref().unwrap_or("day");
+    let bucket_secs = match bucket {
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        _ => {
+            return Err(ApiError::bad_request(
+                "invalid_bucket",
+                Some(&format!(
+                    "{bucket} is not a valid bucket, expected hour or day"
+                )),
+            ));
+        }
+    };
+
+    let (Some(from), Some(to)) = (
+        DateTime::from_timestamp_secs(params.from),
+        DateTime::from_timestamp_secs(params.to),
+    ) else {
+        return Err(ApiError::bad_request(
+            "invalid_time",
+            Some("Invalid usage series window"),
+        ));
+    };
+
+    if from >= to {
+        return Err(ApiError::bad_request(
+            "invalid_time",
+            Some("The usage series has to end after it starts"),
+        ));
+    }
+
+    // One more than the window divides into, for a start partway through a bucket.
+    let bucket_count = (params.to - params.from) / bucket_secs + 1;
+
+    if bucket_count > MAX_USAGE_BUCKETS {
+        return Err(ApiError::bad_request(
+            "too_many_buckets",
+            Some(&format!(
+                "Your usage series spans {bucket_count} {bucket} buckets, more than the limit of {MAX_USAGE_BUCKETS}"
+            )),
+        ));
+    }
+
+    let buckets = sqlx::query_as!(
+        UsageBucket,
+        r#"
+      WITH buckets AS (
+        SELECT generate_series(
+          date_trunc($4, $2::timestamptz, 'UTC'),
+          $3::timestamptz - interval '1 microsecond',
+          ('1 ' || $4)::interval
+        ) AS bucket_start
+      ),
+      usage AS (
+        SELECT
+          date_trunc($4, exec.executed_at, 'UTC') AS bucket_start,
+          COUNT(*) AS count
+        FROM job_executions as exec
+        JOIN scheduled_jobs as sched
+          ON exec.id = sched.execution_id
+        WHERE
+          sched.tenant_id = $1 AND
+          exec.executed_at >= $2 AND
+          exec.executed_at < $3
+        GROUP BY 1
+      )
+      SELECT
+        EXTRACT(EPOCH FROM buckets.bucket_start)::bigint as "bucket_start!",
+        COALESCE(usage.count, 0) as "count!"
+      FROM buckets
+      LEFT JOIN usage
+        ON usage.bucket_start = buckets.bucket_start
+      ORDER BY buckets.bucket_start
+      "#,
+        tenant_id,
+        from,
+        to,
+        bucket
+    )
+    .fetch_all(ctx.read_pool())
+    .await?;
+
+    Ok(Json(buckets))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PurgeExecutionsParams {
+    before: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PurgeResult {
+    purged: i64,
+}
+
+impl IntoResponse for PurgeResult {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, Json(self)).into_response()
+    }
+}
+
+const PURGE_BATCH_SIZE: i64 = 500;
+
+/// Scrubs and removes a tenant's execution history from before `before`
+/// the same way retention does, one batch per transaction so a large purge
+/// doesn't hold its locks for long.
+#[tracing::instrument(name = "api_purge_tenant_executions")]
+async fn purge_tenant_executions(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+    Query(params): Query<PurgeExecutionsParams>,
+) -> Result<PurgeResult, ApiError> {
+    if let Some(requesting_tenant_id) = requesting_tenant_id
+        && requesting_tenant_id != tenant_id
+    {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let before = DateTime::from_timestamp_secs(params.before).ok_or(ApiError::bad_request(
+        "invalid_time",
+        Some(&format!("Invalid time {}", params.before)),
+    ))?;
+
+    let mut purged = 0;
+
+    loop {
+        let mut txn = ctx.pool.begin().await?;
+
+        let job_ids = sqlx::query_scalar!(
+            r#"
+          SELECT job.id
+          FROM scheduled_jobs job
+          JOIN job_executions exec
+            ON exec.id = job.execution_id
+          WHERE
+            job.tenant_id = $1 AND
+            job.deleted_at IS NULL AND
+            exec.executed_at < $2
+          LIMIT $3 FOR UPDATE OF job SKIP LOCKED
+          "#,
+            tenant_id,
+            before,
+            PURGE_BATCH_SIZE
+        )
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let batch_size = job_ids.len() as i64;
+
+        for job_id in job_ids {
+            util::scheduled_jobs::delete_scheduled_job(job_id, &mut txn).await?;
+        }
+
+        txn.commit().await?;
+        purged += batch_size;
+
+        if batch_size < PURGE_BATCH_SIZE {
+            break;
+        }
+    }
+
+    Ok(PurgeResult { purged })
+}
+
 #[derive(Debug, Clone, Deserialize, ToSchema)]
 struct UpdateTenant {
     tokens: Option<i32>,
@@ -231,6 +538,8 @@ struct UpdateTenant {
     max_request_bytes: Option<i32>,
     retain_for_days: Option<i32>,
     max_delay_days: Option<i32>,
+    max_concurrent_executions: Option<i32>,
+    min_retry_backoff_ms: Option<i32>,
 }
 
 #[tracing::instrument(name = "api_update_tenant")]
@@ -265,8 +574,10 @@ async fn update_tenant(
         max_max_response_bytes = COALESCE($8, max_max_response_bytes),
         max_request_bytes = COALESCE($9, max_request_bytes),
         retain_for_days = COALESCE($10, retain_for_days),
-        max_delay_days = COALESCE($11, max_delay_days)
-      WHERE id = $12 RETURNING *
+        max_delay_days = COALESCE($11, max_delay_days),
+        max_concurrent_executions = COALESCE($12, max_concurrent_executions),
+        min_retry_backoff_ms = COALESCE($13, min_retry_backoff_ms)
+      WHERE id = $14 AND deleted_at IS NULL RETURNING *
       "#,
         update_opts.tokens,
         update_opts.max_tokens,
@@ -279,6 +590,8 @@ async fn update_tenant(
         update_opts.max_request_bytes,
         update_opts.retain_for_days,
         update_opts.max_delay_days,
+        update_opts.max_concurrent_executions,
+        update_opts.min_retry_backoff_ms,
         tenant_id
     )
     .fetch_optional(&ctx.pool)
@@ -290,15 +603,165 @@ async fn update_tenant(
 
     let tenant = new_tenant.unwrap();
 
-    let period_secs = TimeDelta::microseconds(tenant.period.microseconds).as_seconds_f64();
-    let tok_per_sec = tenant.increment as f64 / period_secs;
-    let tok_per_day = tok_per_sec * 60. * 60. * 24.;
+    let res = Tenant {
+        tok_per_day: tok_per_day(tenant.increment, &tenant.period),
+        id: tenant.id,
+        tokens: tenant.tokens,
+        max_tokens: tenant.max_tokens,
+        max_timeout: tenant.max_timeout,
+        default_retries: tenant.default_retries,
+        max_retries: tenant.max_retries,
+        max_max_response_bytes: tenant.max_max_response_bytes,
+        max_request_bytes: tenant.max_request_bytes,
+        retain_for_days: tenant.retain_for_days,
+        max_delay_days: tenant.max_delay_days,
+        max_cron_jobs: tenant.max_cron_jobs,
+        max_concurrent_executions: tenant.max_concurrent_executions,
+        min_retry_backoff_ms: tenant.min_retry_backoff_ms,
+        suspended: tenant.suspended,
+    };
+
+    Ok(res)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DeleteTenantParams {
+    /// Hard-deletes the tenant and everything it owns once its retention has
+    /// run out, instead of keeping the soft-deleted rows around.
+    purge: Option<bool>,
+}
+
+#[tracing::instrument(name = "api_delete_tenant")]
+async fn delete_tenant(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+    Query(params): Query<DeleteTenantParams>,
+) -> Result<Tenant, ApiError> {
+    if requesting_tenant_id.is_some() {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let mut txn = ctx.pool.begin().await?;
+
+    let deleted_tenant = sqlx::query!(
+        r#"
+      UPDATE tenants
+      SET
+        deleted_at = NOW(),
+        purge_after = CASE WHEN $2 THEN NOW() + retain_for_days * interval '1 day' END
+      WHERE id = $1 AND deleted_at IS NULL
+      RETURNING *
+      "#,
+        tenant_id,
+        params.purge.unwrap_or(false)
+    )
+    .fetch_optional(&mut *txn)
+    .await?;
+
+    if deleted_tenant.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    let tenant = deleted_tenant.unwrap();
+
+    let cron_requests = sqlx::query_scalar!(
+        r#"
+      UPDATE cron_jobs
+      SET deleted_at = NOW()
+      WHERE tenant_id = $1 AND deleted_at IS NULL
+      RETURNING request_id
+      "#,
+        tenant_id
+    )
+    .fetch_all(&mut *txn)
+    .await?;
+
+    let one_off_requests = sqlx::query_scalar!(
+        r#"
+      UPDATE one_off_jobs
+      SET deleted_at = NOW()
+      WHERE tenant_id = $1 AND deleted_at IS NULL
+      RETURNING request_id
+      "#,
+        tenant_id
+    )
+    .fetch_all(&mut *txn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+      DELETE FROM scheduled_jobs
+      WHERE tenant_id = $1
+        AND lock_nonce IS NULL
+        AND execution_id IS NULL
+      "#,
+        tenant_id
+    )
+    .execute(&mut *txn)
+    .await?;
+
+    for req_id in cron_requests.into_iter().chain(one_off_requests) {
+        util::http_requests::delete_http_req(req_id, &mut txn).await?;
+    }
+
+    txn.commit().await?;
+
+    let res = Tenant {
+        tok_per_day: tok_per_day(tenant.increment, &tenant.period),
+        id: tenant.id,
+        tokens: tenant.tokens,
+        max_tokens: tenant.max_tokens,
+        max_timeout: tenant.max_timeout,
+        default_retries: tenant.default_retries,
+        max_retries: tenant.max_retries,
+        max_max_response_bytes: tenant.max_max_response_bytes,
+        max_request_bytes: tenant.max_request_bytes,
+        retain_for_days: tenant.retain_for_days,
+        max_delay_days: tenant.max_delay_days,
+        max_cron_jobs: tenant.max_cron_jobs,
+        max_concurrent_executions: tenant.max_concurrent_executions,
+        min_retry_backoff_ms: tenant.min_retry_backoff_ms,
+        suspended: tenant.suspended,
+    };
+
+    Ok(res)
+}
+
+async fn set_tenant_suspended(
+    ctx: Context,
+    requesting_tenant_id: Option<String>,
+    tenant_id: String,
+    suspended: bool,
+) -> Result<Tenant, ApiError> {
+    if requesting_tenant_id.is_some() {
+        return Err(ApiError::tenant_not_allowed());
+    }
+
+    let tenant = sqlx::query!(
+        r#"
+      UPDATE tenants
+      SET suspended = $2
+      WHERE id = $1 AND deleted_at IS NULL
+      RETURNING *
+      "#,
+        tenant_id,
+        suspended
+    )
+    .fetch_optional(&ctx.pool)
+    .await?;
+
+    if tenant.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    let tenant = tenant.unwrap();
 
     let res = Tenant {
+        tok_per_day: tok_per_day(tenant.increment, &tenant.period),
         id: tenant.id,
         tokens: tenant.tokens,
         max_tokens: tenant.max_tokens,
-        tok_per_day: tok_per_day as i32,
         max_timeout: tenant.max_timeout,
         default_retries: tenant.default_retries,
         max_retries: tenant.max_retries,
@@ -307,17 +770,38 @@ async fn update_tenant(
         retain_for_days: tenant.retain_for_days,
         max_delay_days: tenant.max_delay_days,
         max_cron_jobs: tenant.max_cron_jobs,
+        max_concurrent_executions: tenant.max_concurrent_executions,
+        min_retry_backoff_ms: tenant.min_retry_backoff_ms,
+        suspended: tenant.suspended,
     };
 
     Ok(res)
 }
 
+#[tracing::instrument(name = "api_suspend_tenant")]
+async fn suspend_tenant(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+) -> Result<Tenant, ApiError> {
+    set_tenant_suspended(ctx, requesting_tenant_id, tenant_id, true).await
+}
+
+#[tracing::instrument(name = "api_unsuspend_tenant")]
+async fn unsuspend_tenant(
+    State(ctx): State<Context>,
+    TenantId(requesting_tenant_id): TenantId,
+    Path(tenant_id): Path<String>,
+) -> Result<Tenant, ApiError> {
+    set_tenant_suspended(ctx, requesting_tenant_id, tenant_id, false).await
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 // struct SigningSecretPair {
 //     current_signing_key: String,
 //     next_signing_key: String,
 // }
-enum SigningSecretData {
+pub(super) enum SigningSecretData {
     NotInitialized,
     Pair {
         current_signing_key: String,
@@ -354,7 +838,7 @@ impl IntoResponse for SigningSecretData {
 }
 
 #[tracing::instrument(name = "api_rotate_secrets")]
-async fn rotate_secrets(
+pub(super) async fn rotate_secrets(
     State(ctx): State<Context>,
     TenantId(requesting_tenant_id): TenantId,
     Path(tenant_id): Path<String>,
@@ -369,7 +853,7 @@ async fn rotate_secrets(
 
     let current = sqlx::query!(
         r#"
-      SELECT id, current_signing_key, next_signing_key FROM tenants
+      SELECT id, current_signing_key, next_signing_key, previous_signing_key FROM tenants
       WHERE id = $1
       "#,
         tenant_id
@@ -410,23 +894,28 @@ async fn rotate_secrets(
       UPDATE tenants
       SET
         current_signing_key = $2,
-        next_signing_key = $3
+        next_signing_key = $3,
+        previous_signing_key = $4,
+        signing_key_rotated_at = now()
       WHERE id = $1
       "#,
         tenant_id,
         new_current_secret.id,
-        new_next_secret.id
+        new_next_secret.id,
+        current.current_signing_key
     )
     .execute(&mut *tx)
     .await?;
 
-    if let Some(old_current_signing_key) = current.current_signing_key {
+    // The old current key keeps verifying requests signed just before this
+    // rotation, only the one it replaces is dropped.
+    if let Some(old_previous_signing_key) = current.previous_signing_key {
         sqlx::query!(
             r#"
         DELETE FROM secrets
         WHERE id = $1
         "#,
-            old_current_signing_key
+            old_previous_signing_key
         )
         .execute(&mut *tx)
         .await?;
@@ -480,15 +969,25 @@ async fn get_secrets(
 
 pub fn init_router() -> OpenApiRouter<Context> {
     OpenApiRouter::new()
-        .route("/api/tenants", post(create_tenant))
+        .route("/api/tenants", post(create_tenant).get(list_tenants))
         .route(
             "/api/tenants/{tenant_id}",
-            get(get_tenant).post(update_tenant),
+            get(get_tenant).post(update_tenant).delete(delete_tenant),
+        )
+        .route("/api/tenants/{tenant_id}/suspend", post(suspend_tenant))
+        .route("/api/tenants/{tenant_id}/unsuspend", post(unsuspend_tenant))
+        .route(
+            "/api/tenants/{tenant_id}/executions",
+            delete(purge_tenant_executions),
         )
         .route(
             "/api/tenants/{tenant_id}/usage/{start}/{end}",
             get(get_tenant_usage),
         )
+        .route(
+            "/api/tenants/{tenant_id}/usage/series",
+            get(get_tenant_usage_series),
+        )
         .route(
             "/api/tenants/{tenant_id}/signing_secrets/rotate",
             post(rotate_secrets),
@@ -496,16 +995,36 @@ pub fn init_router() -> OpenApiRouter<Context> {
         .route("/api/tenants/{tenant_id}/signing_secrets", get(get_secrets))
 }
 
-const MIN_PERIOD_MS: f32 = 60_000.;
-const DAY_MS: f32 = 24. * 60. * 60. * 1000.;
+fn tok_per_day(increment: i32, period: &PgInterval) -> i32 {
+    let period_secs = TimeDelta::microseconds(period.microseconds).as_seconds_f64();
+    let tok_per_sec = increment as f64 / period_secs;
+
+    (tok_per_sec * 60. * 60. * 24.) as i32
+}
+
+const MIN_PERIOD_MS: f64 = 60_000.;
+const DAY_MS: f64 = 24. * 60. * 60. * 1000.;
+
+/// Splits `tokens_per_day` into an increment handed out every period, with
+/// periods no shorter than a minute so refills don't happen constantly.
+fn compute_incr_and_period(tokens_per_day: i32) -> Result<(PgInterval, i32), ApiError> {
+    if tokens_per_day < 1 {
+        return Err(ApiError::bad_request(
+            "invalid_tokens_per_day",
+            Some(&format!(
+                "Your tok_per_day of {tokens_per_day} must be at least 1"
+            )),
+        ));
+    }
 
-fn compute_incr_and_period(tokens_per_day: i32) -> Result<(PgInterval, i32), String> {
-    let base_period = DAY_MS / tokens_per_day as f32;
+    let base_period = DAY_MS / tokens_per_day as f64;
     let base_factor = MIN_PERIOD_MS / base_period;
 
     let factor = base_factor.ceil();
-    let increment = factor as i32;
-    let period = base_period * factor;
+    // Rounding can leave either side just short of its bound, which would
+    // give a tenant that never refills or refills on every tick.
+    let increment = (factor as i32).max(1);
+    let period = (base_period * factor).round().max(MIN_PERIOD_MS);
 
     let micros = TimeDelta::milliseconds(period as i64)
         .num_microseconds()
@@ -519,3 +1038,586 @@ fn compute_incr_and_period(tokens_per_day: i32) -> Result<(PgInterval, i32), Str
 
     Ok((interval, increment))
 }
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{PgPool, postgres::PgPoolOptions};
+
+    use super::*;
+    use crate::api::test_context;
+
+    fn tenant_opts(id: Option<&str>) -> CreateTenant {
+        CreateTenant {
+            id: id.map(str::to_string),
+            max_tokens: 1000,
+            tok_per_day: 1000,
+            max_timeout: 30_000,
+            default_retries: 3,
+            max_retries: 5,
+            max_max_response_bytes: 1024,
+            max_request_bytes: 1024,
+            retain_for_days: 7,
+            max_delay_days: 30,
+            max_cron_jobs: 10,
+            max_concurrent_executions: None,
+            min_retry_backoff_ms: None,
+        }
+    }
+
+    async fn insert_tenant(ctx: &Context) -> Tenant {
+        create_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(tenant_opts(None)),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compute_incr_and_period_stays_sane() {
+        for tokens_per_day in [1, 2, 1439, 1440, 1441, 86_400, 1_000_000, i32::MAX] {
+            let (period, increment) = compute_incr_and_period(tokens_per_day).unwrap();
+
+            assert!(increment >= 1, "{tokens_per_day}: increment {increment}");
+            assert!(
+                period.microseconds >= MIN_PERIOD_MS as i64 * 1000,
+                "{tokens_per_day}: period {}",
+                period.microseconds
+            );
+
+            let refilled = tok_per_day(increment, &period) as f64;
+            assert!(
+                (refilled - tokens_per_day as f64).abs() <= (tokens_per_day as f64 * 0.001).max(1.),
+                "{tokens_per_day}: refills {refilled} per day"
+            );
+        }
+
+        for tokens_per_day in [0, -1, i32::MIN] {
+            let rejected = compute_incr_and_period(tokens_per_day);
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+    }
+
+    fn list_params(cursor: Option<String>, limit: i64) -> ListTenantsParams {
+        ListTenantsParams {
+            cursor,
+            limit: Some(limit),
+            out_of_tokens: None,
+        }
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_tenants_paginates(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        for _ in 0..3 {
+            insert_tenant(&ctx).await;
+        }
+
+        let first_page = list_tenants(
+            State(ctx.clone()),
+            TenantId(None),
+            Query(list_params(None, 2)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(first_page.count, 2);
+
+        let second_page = list_tenants(
+            State(ctx.clone()),
+            TenantId(None),
+            Query(list_params(first_page.cursor.clone(), 2)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(second_page.count, 1);
+        assert!(
+            first_page
+                .data
+                .iter()
+                .all(|tenant| tenant.id != second_page.data[0].id)
+        );
+
+        let listed = &second_page.data[0];
+        let fetched = get_tenant(State(ctx.clone()), TenantId(None), Path(listed.id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(listed.tok_per_day, fetched.tok_per_day);
+        assert_eq!(listed.max_cron_jobs, fetched.max_cron_jobs);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_reads_use_the_read_replica(pool: PgPool) -> anyhow::Result<()> {
+        // A schema shadowing the tenants table stands in for a replica, so
+        // what it lacks shows which pool a read went to.
+        sqlx::raw_sql(
+            "
+            CREATE SCHEMA replica;
+            CREATE TABLE replica.tenants (LIKE public.tenants INCLUDING DEFAULTS);
+            ",
+        )
+        .execute(&pool)
+        .await?;
+
+        let replica_options = (*pool.connect_options())
+            .clone()
+            .options([("search_path", "replica")]);
+        let read_replica = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(replica_options)
+            .await?;
+
+        let ctx = Context {
+            read_replica: Some(read_replica),
+            ..test_context(pool)
+        };
+
+        let replicated = create_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(tenant_opts(Some("tenant_replicated"))),
+        )
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO replica.tenants SELECT * FROM public.tenants")
+            .execute(&ctx.pool)
+            .await?;
+        let lagging = create_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(tenant_opts(Some("tenant_lagging"))),
+        )
+        .await
+        .unwrap();
+
+        let listed = list_tenants(
+            State(ctx.clone()),
+            TenantId(None),
+            Query(list_params(None, 10)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed.count, 1);
+        assert_eq!(listed.data[0].id, replicated.id);
+
+        // The replica hasn't caught up with the new tenant, so the lookup
+        // falls back to the primary rather than reporting it missing.
+        let fetched = get_tenant(State(ctx.clone()), TenantId(None), Path(lagging.id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(fetched.id, lagging.id);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_tenants_out_of_tokens(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let throttled = insert_tenant(&ctx).await;
+        insert_tenant(&ctx).await;
+
+        sqlx::query!("UPDATE tenants SET tokens = 0 WHERE id = $1", throttled.id)
+            .execute(&ctx.pool)
+            .await?;
+
+        let result = list_tenants(
+            State(ctx.clone()),
+            TenantId(None),
+            Query(ListTenantsParams {
+                cursor: None,
+                limit: None,
+                out_of_tokens: Some(true),
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.count, 1);
+        assert_eq!(result.data[0].id, throttled.id);
+        assert_eq!(result.data[0].tokens, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_list_tenants_rejects_tenants(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let result = list_tenants(
+            State(ctx),
+            TenantId(Some("tenant_123".to_string())),
+            Query(list_params(None, 10)),
+        )
+        .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_delete_tenant_cascades(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers, body)
+            VALUES ('request_a', 'POST', 'https://example.com', '{\"a: b\"}', 'secret')
+            "
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO one_off_jobs (id, region, tenant_id, request_id, execute_at, max_retries)
+            VALUES ('job_a', 'na-east', $1, 'request_a', 0, 0)
+            ",
+            tenant.id
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, tenant_id, one_off_job_id, scheduled_at, request_id, max_retries)
+            VALUES ('sched_a', 0, 'na-east', $1, 'job_a', now(), 'request_a', 0)
+            ",
+            tenant.id
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        let deleted = delete_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            Path(tenant.id.clone()),
+            Query(DeleteTenantParams { purge: None }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(deleted.id, tenant.id);
+
+        let job = sqlx::query!("SELECT deleted_at FROM one_off_jobs WHERE id = 'job_a'")
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert!(job.deleted_at.is_some());
+
+        let req = sqlx::query!("SELECT body FROM http_requests WHERE id = 'request_a'")
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(req.body.as_deref(), Some(""));
+
+        let scheduled = sqlx::query_scalar!("SELECT count(*) FROM scheduled_jobs")
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(scheduled, Some(0));
+
+        let refetch = get_tenant(State(ctx.clone()), TenantId(None), Path(tenant.id.clone())).await;
+        assert!(refetch.is_err());
+
+        let purge_after =
+            sqlx::query_scalar!("SELECT purge_after FROM tenants WHERE id = $1", tenant.id)
+                .fetch_one(&ctx.pool)
+                .await?;
+        assert!(purge_after.is_none());
+
+        let again = delete_tenant(
+            State(ctx),
+            TenantId(None),
+            Path(tenant.id),
+            Query(DeleteTenantParams { purge: None }),
+        )
+        .await;
+        assert!(again.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_delete_tenant_with_purge_schedules_it_after_retention(
+        pool: PgPool,
+    ) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+
+        delete_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            Path(tenant.id.clone()),
+            Query(DeleteTenantParams { purge: Some(true) }),
+        )
+        .await
+        .unwrap();
+
+        let purge = sqlx::query!(
+            r#"
+            SELECT purge_after - deleted_at = retain_for_days * interval '1 day' as "after_retention!"
+            FROM tenants
+            WHERE id = $1
+            "#,
+            tenant.id
+        )
+        .fetch_one(&ctx.pool)
+        .await?;
+        assert!(purge.after_retention);
+
+        Ok(())
+    }
+
+    async fn insert_executed_job(
+        ctx: &Context,
+        tenant_id: &str,
+        id: &str,
+        executed_days_ago: i32,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers, body)
+            VALUES ($1, 'POST', 'https://example.com', '{\"a: b\"}', 'secret')
+            ",
+            format!("request_{id}")
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO http_responses (id, status, headers, body)
+            VALUES ($1, 200, '{}', 'private')
+            ",
+            format!("response_{id}")
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO job_executions (id, executed_at, success, request_id, response_id)
+            VALUES ($1, now() - make_interval(days => $2), true, $3, $4)
+            ",
+            format!("execution_{id}"),
+            executed_days_ago,
+            format!("request_{id}"),
+            format!("response_{id}")
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, tenant_id, scheduled_at, request_id, execution_id, max_retries)
+            VALUES ($1, 0, 'na-east', $2, now(), $3, $4, 0)
+            ",
+            id,
+            tenant_id,
+            format!("request_{id}"),
+            format!("execution_{id}")
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_purge_executions_respects_cutoff(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+
+        insert_executed_job(&ctx, &tenant.id, "sched_old", 10).await?;
+        insert_executed_job(&ctx, &tenant.id, "sched_new", 1).await?;
+
+        let before = (chrono::Utc::now() - TimeDelta::days(5)).timestamp();
+        let purge = |requesting_tenant_id: Option<String>| {
+            purge_tenant_executions(
+                State(ctx.clone()),
+                TenantId(requesting_tenant_id),
+                Path(tenant.id.clone()),
+                Query(PurgeExecutionsParams { before }),
+            )
+        };
+
+        let rejected = purge(Some("tenant_other".to_string())).await;
+        assert!(rejected.is_err_and(|err| err.code == StatusCode::FORBIDDEN));
+
+        let result = purge(Some(tenant.id.clone())).await.unwrap();
+        assert_eq!(result.purged, 1);
+
+        let history = sqlx::query!(
+            r#"
+            SELECT job.id, job.deleted_at, req.body as req_body, res.body as res_body
+            FROM scheduled_jobs job
+            JOIN job_executions exec ON exec.id = job.execution_id
+            JOIN http_requests req ON req.id = exec.request_id
+            JOIN http_responses res ON res.id = exec.response_id
+            ORDER BY job.id
+            "#
+        )
+        .fetch_all(&ctx.pool)
+        .await?;
+
+        assert_eq!(history[0].id, "sched_new");
+        assert!(history[0].deleted_at.is_none());
+        assert_eq!(history[0].res_body, "private");
+
+        assert_eq!(history[1].id, "sched_old");
+        assert!(history[1].deleted_at.is_some());
+        assert_eq!(history[1].req_body.as_deref(), Some(""));
+        assert_eq!(history[1].res_body, "");
+
+        let again = purge(None).await.unwrap();
+        assert_eq!(again.purged, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_usage_series_buckets_executions(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+        let other = insert_tenant(&ctx).await;
+
+        insert_executed_job(&ctx, &tenant.id, "sched_a", 1).await?;
+        insert_executed_job(&ctx, &tenant.id, "sched_b", 1).await?;
+        insert_executed_job(&ctx, &tenant.id, "sched_c", 3).await?;
+        insert_executed_job(&ctx, &other.id, "sched_other", 1).await?;
+
+        let now = chrono::Utc::now().timestamp();
+        let series = |requesting_tenant_id: Option<String>, from: i64, bucket: &str| {
+            get_tenant_usage_series(
+                State(ctx.clone()),
+                TenantId(requesting_tenant_id),
+                Path(tenant.id.clone()),
+                Query(UsageSeriesParams {
+                    from,
+                    to: now + 1,
+                    bucket: Some(bucket.to_string()),
+                }),
+            )
+        };
+
+        let from = now - TimeDelta::days(5).num_seconds();
+        let Json(buckets) = series(Some(tenant.id.clone()), from, "day").await.unwrap();
+
+        assert_eq!(buckets.len(), 6);
+        assert!(buckets.is_sorted_by_key(|bucket| bucket.bucket_start));
+        assert!(
+            buckets
+                .iter()
+                .all(|bucket| bucket.bucket_start % 86_400 == 0)
+        );
+        assert_eq!(buckets.iter().map(|bucket| bucket.count).sum::<i64>(), 3);
+
+        let counts: Vec<i64> = buckets.iter().map(|bucket| bucket.count).collect();
+        assert!(counts.contains(&2));
+        assert_eq!(counts.iter().filter(|count| **count == 0).count(), 4);
+
+        let other_tenant = series(Some(other.id.clone()), from, "day").await;
+        assert!(other_tenant.is_err_and(|err| err.code == StatusCode::FORBIDDEN));
+
+        let invalid_bucket = series(None, from, "week").await;
+        assert!(invalid_bucket.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        let too_many = series(None, now - TimeDelta::days(365).num_seconds(), "hour").await;
+        assert!(too_many.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_delete_tenant_rejects_tenants(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+
+        let result = delete_tenant(
+            State(ctx),
+            TenantId(Some(tenant.id.clone())),
+            Path(tenant.id),
+            Query(DeleteTenantParams { purge: None }),
+        )
+        .await;
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_tenant_with_supplied_id_is_idempotent(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let created = create_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(tenant_opts(Some("tenant_user-42"))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(created.id, "tenant_user-42");
+
+        let retried = create_tenant(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(CreateTenant {
+                max_tokens: 5,
+                ..tenant_opts(Some("tenant_user-42"))
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(retried.id, created.id);
+        assert_eq!(retried.max_tokens, created.max_tokens);
+
+        let count = sqlx::query_scalar!("SELECT count(*) as \"count!\" FROM tenants")
+            .fetch_one(&ctx.pool)
+            .await?;
+        assert_eq!(count, 1);
+
+        for malformed in ["user-42", "tenant_", "tenant_user 42", "cron_user"] {
+            let rejected = create_tenant(
+                State(ctx.clone()),
+                TenantId(None),
+                JsonBody(tenant_opts(Some(malformed))),
+            )
+            .await;
+            assert!(rejected.is_err_and(|err| err.code == StatusCode::BAD_REQUEST));
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_suspend_tenant_toggles_flag(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        let tenant = insert_tenant(&ctx).await;
+        assert!(!tenant.suspended);
+
+        let rejected = suspend_tenant(
+            State(ctx.clone()),
+            TenantId(Some(tenant.id.clone())),
+            Path(tenant.id.clone()),
+        )
+        .await;
+        assert!(rejected.is_err());
+
+        let suspended = suspend_tenant(State(ctx.clone()), TenantId(None), Path(tenant.id.clone()))
+            .await
+            .unwrap();
+        assert!(suspended.suspended);
+
+        let restored = unsuspend_tenant(State(ctx), TenantId(None), Path(tenant.id))
+            .await
+            .unwrap();
+        assert!(!restored.suspended);
+
+        Ok(())
+    }
+}
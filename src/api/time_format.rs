@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Query, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serializer};
+
+use crate::api::ApiError;
+
+/// How timestamps in api responses are rendered, picked per request with
+/// `?time_format=`. Unix seconds stay the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    #[default]
+    Unix,
+    Rfc3339,
+}
+
+tokio::task_local! {
+    static TIME_FORMAT: TimeFormat;
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeFormatParams {
+    time_format: Option<TimeFormat>,
+}
+
+fn current() -> TimeFormat {
+    TIME_FORMAT.try_with(|format| *format).unwrap_or_default()
+}
+
+/// Serializes unix seconds in the format requested for the current response.
+pub fn serialize<S: Serializer>(timestamp: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+    let rfc3339 = match current() {
+        TimeFormat::Unix => None,
+        TimeFormat::Rfc3339 => DateTime::from_timestamp_secs(*timestamp),
+    };
+
+    match rfc3339 {
+        Some(time) => serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        None => serializer.serialize_i64(*timestamp),
+    }
+}
+
+pub fn serialize_option<S: Serializer>(
+    timestamp: &Option<i64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serialize(timestamp, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub async fn time_format_middleware(req: Request, next: Next) -> Response {
+    let Ok(Query(params)) = Query::<TimeFormatParams>::try_from_uri(req.uri()) else {
+        return ApiError::bad_request(
+            "invalid_time_format",
+            Some("Invalid time_format, choose one of the following: unix, rfc3339"),
+        )
+        .into_response();
+    };
+
+    TIME_FORMAT
+        .scope(params.time_format.unwrap_or_default(), next.run(req))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::models::{Execution, HttpRequest};
+
+    fn execution() -> Execution {
+        Execution {
+            id: "execution_1".to_string(),
+            region: "na-east".to_string(),
+            scheduled_at: 1_700_000_000,
+            executed_at: Some(1_700_000_005),
+            success: Some(true),
+            request: HttpRequest {
+                method: "GET".to_string(),
+                url: "https://example.com".to_string(),
+                headers: HashMap::new(),
+                body: None,
+            },
+            response: None,
+            response_error: None,
+            body_hash: None,
+            tls_verify_skipped: false,
+            time_source: Some("drone".to_string()),
+            unchanged: false,
+            drone_id: None,
+            duration_ms: None,
+            timeout_ms: None,
+            max_retries: 3,
+            max_response_bytes: None,
+            tenant_id: None,
+            one_off_job_id: Some("one_off_job_1".to_string()),
+            cron_job_id: None,
+            retry_for: None,
+        }
+    }
+
+    #[test]
+    fn test_execution_timestamps_follow_time_format() {
+        let unix = serde_json::to_value(execution()).unwrap();
+        assert_eq!(unix["scheduled_at"], json!(1_700_000_000));
+        assert_eq!(unix["executed_at"], json!(1_700_000_005));
+
+        let rfc3339 = TIME_FORMAT.sync_scope(TimeFormat::Rfc3339, || {
+            serde_json::to_value(execution()).unwrap()
+        });
+        assert_eq!(rfc3339["scheduled_at"], json!("2023-11-14T22:13:20Z"));
+        assert_eq!(rfc3339["executed_at"], json!("2023-11-14T22:13:25Z"));
+    }
+
+    #[test]
+    fn test_missing_timestamp_stays_null() {
+        let mut execution = execution();
+        execution.executed_at = None;
+
+        let value = TIME_FORMAT.sync_scope(TimeFormat::Rfc3339, || {
+            serde_json::to_value(execution).unwrap()
+        });
+        assert!(value["executed_at"].is_null());
+    }
+}
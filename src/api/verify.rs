@@ -0,0 +1,343 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    api::{ApiError, Context, JsonBody, TenantId},
+    secrets::Secret,
+    signing::SignatureBuilder,
+};
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+struct VerifySignature {
+    /// Value of the `Rocktick-Signature` header as it was received.
+    signature_header: String,
+    /// Unix timestamp the request was signed at, the header's `t`.
+    timestamp: i64,
+    /// Body as it was received, defaults to the body the job was sent with.
+    body: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct VerifyResult {
+    verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureHeader {
+    t: i64,
+    v1: String,
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/verify/{job_id}",
+  params(("job_id", description = "Value of the `Rocktick-Job-Id` header")),
+  request_body = VerifySignature,
+  responses(
+    (status = 200, description = "Whether the signature is authentic", body = VerifyResult),
+    (status = "4XX", description = "Job not found or not signed with a tenant key", body = ApiError),
+    (status = "5XX", description = "Invalid request", body = ApiError)),
+  tag = "verify"
+)]
+#[tracing::instrument(name = "api_verify_signature")]
+async fn verify_signature(
+    State(ctx): State<Context>,
+    Path(job_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+    JsonBody(verify_opts): JsonBody<VerifySignature>,
+) -> Result<Json<VerifyResult>, ApiError> {
+    let job = sqlx::query!(
+        r#"
+    SELECT
+      req.method,
+      req.url,
+      req.body,
+      tenant.current_signing_key as "current_signing_key?",
+      CASE
+        WHEN tenant.signing_key_rotated_at > now() - make_interval(secs => $3::double precision)
+        THEN tenant.previous_signing_key
+      END as "previous_signing_key?"
+    FROM scheduled_jobs job
+    INNER JOIN http_requests req
+      ON req.id = job.request_id
+    LEFT JOIN tenants tenant
+      ON tenant.id = job.tenant_id
+    WHERE
+      job.id = $1
+      AND ($2::text IS NULL OR job.tenant_id = $2);
+    "#,
+        job_id,
+        tenant_id,
+        ctx.signing_key_grace.as_secs_f64()
+    )
+    .fetch_optional(&ctx.pool)
+    .await?
+    .ok_or(ApiError::not_found())?;
+
+    // Jobs without a tenant are signed with the broker's fallback secret,
+    // which the api never sees.
+    let Some(secret_id) = job.current_signing_key else {
+        return Err(ApiError::bad_request(
+            "unsigned_job",
+            Some("This job is not signed with a tenant signing key"),
+        ));
+    };
+
+    let header: SignatureHeader =
+        serde_json::from_str(&verify_opts.signature_header).map_err(|_| {
+            ApiError::bad_request(
+                "invalid_signature",
+                Some("Invalid Rocktick-Signature header"),
+            )
+        })?;
+
+    if header.t != verify_opts.timestamp {
+        return Ok(Json(VerifyResult { verified: false }));
+    }
+
+    let time =
+        DateTime::from_timestamp_secs(verify_opts.timestamp).ok_or(ApiError::bad_request(
+            "invalid_time",
+            Some(&format!("Invalid timestamp {}", verify_opts.timestamp)),
+        ))?;
+
+    let mut builder = SignatureBuilder {
+        signing_key: String::new(),
+        time,
+        method: job.method,
+        url: job.url,
+        body: verify_opts.body.or(job.body),
+    };
+
+    // Requests signed just before a rotation still carry the previous key's
+    // signature, so that key is tried while it's within the grace period.
+    for secret_id in std::iter::once(secret_id).chain(job.previous_signing_key) {
+        builder.signing_key = Secret::get(&secret_id, &ctx.pool)
+            .await?
+            .decrypt(&ctx.key_ring)?;
+
+        let verified = builder
+            .verify(&header.v1)
+            .map_err(|err| ApiError::bad_request("invalid_signature", Some(&err.to_string())))?;
+
+        if verified {
+            return Ok(Json(VerifyResult { verified: true }));
+        }
+    }
+
+    Ok(Json(VerifyResult { verified: false }))
+}
+
+pub fn init_router() -> OpenApiRouter<Context> {
+    OpenApiRouter::new().routes(routes!(verify_signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::{
+        tenants::{SigningSecretData, rotate_secrets},
+        test_context,
+    };
+
+    const TIMESTAMP: i64 = 1_700_000_000;
+
+    async fn insert_signed_job(ctx: &Context) -> anyhow::Result<()> {
+        let current = Secret::new(
+            "secret_current".to_string(),
+            "signature_current".to_string(),
+            &ctx.key_ring,
+        )?;
+        let next = Secret::new(
+            "secret_next".to_string(),
+            "signature_next".to_string(),
+            &ctx.key_ring,
+        )?;
+        current.put(&ctx.pool).await?;
+        next.put(&ctx.pool).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs,
+              current_signing_key, next_signing_key)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10,
+              'secret_current', 'secret_next')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO http_requests (id, method, url, headers, body)
+            VALUES ('request_a', 'POST', 'https://example.com/hooks?x=1', '{}', '{"a":1}')
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO scheduled_jobs (
+              id, hash, region, tenant_id, scheduled_at, request_id, max_retries)
+            VALUES ('scheduled_a', 0, 'na-east', 'tenant_a', now(), 'request_a', 0)
+            "#
+        )
+        .execute(&ctx.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn sign(signing_key: &str, timestamp: i64) -> String {
+        SignatureBuilder {
+            signing_key: signing_key.to_string(),
+            time: DateTime::from_timestamp_secs(timestamp).unwrap(),
+            method: "POST".to_string(),
+            url: "https://example.com/hooks?x=1".to_string(),
+            body: Some("{\"a\":1}".to_string()),
+        }
+        .signature_header()
+        .unwrap()
+    }
+
+    async fn verify(
+        ctx: &Context,
+        tenant_id: &str,
+        signature_header: String,
+        timestamp: i64,
+        body: Option<&str>,
+    ) -> Result<bool, ApiError> {
+        let Json(result) = verify_signature(
+            State(ctx.clone()),
+            Path("scheduled_a".to_string()),
+            TenantId(Some(tenant_id.to_string())),
+            JsonBody(VerifySignature {
+                signature_header,
+                timestamp,
+                body: body.map(str::to_string),
+            }),
+        )
+        .await?;
+
+        Ok(result.verified)
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_verify_signature_checks_hmac(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        insert_signed_job(&ctx).await?;
+
+        let signature_header = sign("signature_current", TIMESTAMP);
+
+        assert!(
+            verify(&ctx, "tenant_a", signature_header.clone(), TIMESTAMP, None)
+                .await
+                .unwrap()
+        );
+
+        let tampered = verify(
+            &ctx,
+            "tenant_a",
+            signature_header.clone(),
+            TIMESTAMP,
+            Some("{\"a\":2}"),
+        )
+        .await
+        .unwrap();
+        assert!(!tampered);
+
+        let replayed = verify(
+            &ctx,
+            "tenant_a",
+            signature_header.clone(),
+            TIMESTAMP + 60,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(!replayed);
+
+        let other_tenant = verify(&ctx, "tenant_b", signature_header, TIMESTAMP, None).await;
+        assert!(other_tenant.is_err_and(|err| err.code == http::StatusCode::NOT_FOUND));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_signed_before_rotation_verifies_within_grace(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+        insert_signed_job(&ctx).await?;
+
+        let signed_before_rotation = sign("signature_current", TIMESTAMP);
+
+        let rotated = rotate_secrets(
+            State(ctx.clone()),
+            TenantId(None),
+            Path("tenant_a".to_string()),
+        )
+        .await
+        .unwrap();
+        let SigningSecretData::Pair {
+            current_signing_key,
+            ..
+        } = rotated
+        else {
+            panic!("Expected rotated signing keys");
+        };
+        assert_eq!(current_signing_key, "signature_next");
+
+        let signed_after_rotation = sign(&current_signing_key, TIMESTAMP);
+
+        assert!(
+            verify(
+                &ctx,
+                "tenant_a",
+                signed_before_rotation.clone(),
+                TIMESTAMP,
+                None
+            )
+            .await
+            .unwrap()
+        );
+        assert!(
+            verify(&ctx, "tenant_a", signed_after_rotation, TIMESTAMP, None)
+                .await
+                .unwrap()
+        );
+
+        let expired = Context {
+            signing_key_grace: Duration::ZERO,
+            ..ctx
+        };
+        assert!(
+            !verify(
+                &expired,
+                "tenant_a",
+                signed_before_rotation,
+                TIMESTAMP,
+                None
+            )
+            .await
+            .unwrap()
+        );
+
+        Ok(())
+    }
+}
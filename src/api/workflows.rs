@@ -0,0 +1,273 @@
+use axum::extract::{Path, State};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    api::{ApiError, Context, JsonBody, TenantId, models::Workflow},
+    id,
+    util::workflow::WorkflowContext,
+};
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+struct CreateWorkflow {
+    region: Option<String>,
+    implementation_url: String,
+    #[schema(value_type = Object)]
+    input: serde_json::Value,
+    max_retries: Option<i32>,
+}
+
+#[utoipa::path(
+  post,
+  path = "/api/workflows",
+  request_body = CreateWorkflow,
+  responses(
+    (status = 200, description = "Workflow created", body = Workflow),
+    (status = "4XX", description = "Bad request", body = ApiError),
+    (status = "5XX", description = "Internal server error", body = ApiError)
+  ),
+  tag = "workflows"
+)]
+#[tracing::instrument(name = "api_create_workflow")]
+async fn create_workflow(
+    State(ctx): State<Context>,
+    TenantId(tenant_id): TenantId,
+    JsonBody(create_opts): JsonBody<CreateWorkflow>,
+) -> Result<Workflow, ApiError> {
+    let region = ctx.resolve_region(create_opts.region)?;
+
+    let implementation_url = url::Url::parse(&create_opts.implementation_url).map_err(|err| {
+        ApiError::bad_request(
+            "invalid_url",
+            Some(&format!(
+                "{} is not a valid url: {err}",
+                create_opts.implementation_url
+            )),
+        )
+    })?;
+
+    if !matches!(implementation_url.scheme(), "http" | "https") {
+        return Err(ApiError::bad_request(
+            "invalid_url",
+            Some("Implementation url must use http or https"),
+        ));
+    }
+
+    let mut txn = ctx.pool.begin().await?;
+
+    let tenant = if let Some(tenant_id) = tenant_id.clone() {
+        sqlx::query!(
+            "SELECT * FROM tenants WHERE id = $1 AND deleted_at IS NULL",
+            tenant_id
+        )
+        .fetch_optional(&mut *txn)
+        .await?
+    } else {
+        None
+    };
+
+    if tenant_id.is_some() && tenant.is_none() {
+        return Err(ApiError::bad_request(
+            "invalid_tenant",
+            Some("Invalid tenant id"),
+        ));
+    }
+
+    if let Some(tenant) = &tenant
+        && tenant.suspended
+    {
+        return Err(ApiError::tenant_suspended());
+    }
+
+    if let Some(input_max_retries) = create_opts.max_retries
+        && input_max_retries < 0
+    {
+        return Err(ApiError::bad_request(
+            "invalid_max_retries",
+            Some("Max retries cannot be negative"),
+        ));
+    }
+
+    if let Some(input_max_retries) = create_opts.max_retries
+        && let Some(tenant) = &tenant
+        && input_max_retries > tenant.max_retries
+    {
+        return Err(ApiError::bad_request(
+            "tenant_limit_exceeded",
+            Some(&format!(
+                "Your max retries of {input_max_retries} is higher than your limit of {}",
+                tenant.max_retries
+            )),
+        ));
+    }
+
+    let max_retries = create_opts
+        .max_retries
+        .or(tenant.map(|t| t.default_retries))
+        .unwrap_or(9);
+
+    let context = serde_json::to_value(WorkflowContext::new(create_opts.input.clone()))
+        .map_err(|_| ApiError::internal_server_error(None))?;
+
+    let workflow_id = id::generate("workflow");
+
+    sqlx::query!(
+        r#"
+      INSERT INTO workflows
+        (id, region, tenant_id, implementation_url, input, context, status, max_retries)
+      VALUES
+        ($1, $2, $3, $4, $5, $6, 'pending', $7)
+      "#,
+        workflow_id,
+        region,
+        tenant_id,
+        implementation_url.to_string(),
+        create_opts.input,
+        context,
+        max_retries
+    )
+    .execute(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(Workflow {
+        id: workflow_id,
+        region,
+        tenant_id,
+        implementation_url: implementation_url.to_string(),
+        input: create_opts.input,
+        context,
+        status: "pending".to_string(),
+        result: None,
+        error: None,
+        max_retries,
+    })
+}
+
+#[utoipa::path(
+  get,
+  path = "/api/workflows/{workflow_id}",
+  params(("workflow_id", description = "Id of the workflow")),
+  responses(
+    (status = 200, description = "Workflow", body = Workflow),
+    (status = "4XX", description = "Workflow not found", body = ApiError),
+    (status = "5XX", description = "Internal server error", body = ApiError)
+  ),
+  tag = "workflows"
+)]
+#[tracing::instrument(name = "api_get_workflow")]
+async fn get_workflow(
+    State(ctx): State<Context>,
+    Path(workflow_id): Path<String>,
+    TenantId(tenant_id): TenantId,
+) -> Result<Workflow, ApiError> {
+    let workflow = ctx
+        .read_or_primary(|pool| {
+            sqlx::query_as!(
+                Workflow,
+                r#"
+      SELECT
+        id,
+        region,
+        tenant_id,
+        implementation_url,
+        input,
+        context,
+        status,
+        result,
+        error,
+        max_retries
+      FROM workflows
+      WHERE id = $1
+        AND ($2::text IS NULL OR tenant_id = $2)
+      "#,
+                workflow_id,
+                tenant_id
+            )
+            .fetch_optional(pool)
+        })
+        .await?;
+
+    if workflow.is_none() {
+        return Err(ApiError::not_found());
+    }
+
+    Ok(workflow.unwrap())
+}
+
+pub fn init_router() -> OpenApiRouter<Context> {
+    OpenApiRouter::new()
+        .routes(routes!(create_workflow))
+        .routes(routes!(get_workflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::api::test_context;
+
+    fn create_opts(implementation_url: &str) -> CreateWorkflow {
+        CreateWorkflow {
+            region: None,
+            implementation_url: implementation_url.to_string(),
+            input: json!({ "order_id": 42 }),
+            max_retries: None,
+        }
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_and_get_workflow(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let created = create_workflow(
+            State(ctx.clone()),
+            TenantId(None),
+            JsonBody(create_opts("https://example.com/workflow")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(created.status, "pending");
+        assert_eq!(created.region, "na-east");
+
+        let fetched = get_workflow(State(ctx.clone()), Path(created.id.clone()), TenantId(None))
+            .await
+            .unwrap();
+
+        assert_eq!(fetched.id, created.id);
+        assert_eq!(fetched.input, json!({ "order_id": 42 }));
+        assert_eq!(fetched.context["input"], json!({ "order_id": 42 }));
+        assert_eq!(fetched.max_retries, 9);
+        assert!(fetched.result.is_none());
+
+        let other_tenant = get_workflow(
+            State(ctx),
+            Path(created.id),
+            TenantId(Some("tenant_b".to_string())),
+        )
+        .await;
+        assert!(other_tenant.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_create_workflow_rejects_invalid_url(pool: PgPool) -> anyhow::Result<()> {
+        let ctx = test_context(pool);
+
+        let rejected = create_workflow(
+            State(ctx),
+            TenantId(None),
+            JsonBody(create_opts("ftp://example.com/workflow")),
+        )
+        .await;
+        assert!(rejected.is_err());
+
+        Ok(())
+    }
+}
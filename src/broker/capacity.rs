@@ -0,0 +1,224 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use sqlx::{Pool, Postgres};
+
+use crate::{broker::job, grpc};
+
+pub const NO_CAPACITY_ERROR: &str = "no drone capacity in region";
+
+/// Which regions fail their due jobs once they have no live drones, rather
+/// than leaving the jobs to wait for one.
+#[derive(Debug, Clone)]
+pub struct NoCapacityPolicy {
+    pub regions: Vec<String>,
+    /// How long a region may go without a drone check-in before it counts as
+    /// having no capacity.
+    pub window: Duration,
+    /// How overdue a job in such a region has to be before it's failed.
+    pub grace: Duration,
+}
+
+/// Records a no-capacity failure for every job stranded in a region covered
+/// by `policy`, returning how many were failed.
+async fn fail_stranded_jobs(
+    pool: &Pool<Postgres>,
+    policy: &NoCapacityPolicy,
+) -> anyhow::Result<u64> {
+    // Locking the jobs first keeps another region's drone from picking them
+    // up while the failures are recorded.
+    let stranded = sqlx::query!(
+        r#"
+        WITH live_regions AS (
+          SELECT DISTINCT region FROM drones
+          WHERE last_checkin > now() - make_interval(secs => $2::double precision)
+        ),
+        stranded_jobs AS (
+          SELECT id FROM scheduled_jobs
+          WHERE region = ANY($1)
+            AND region NOT IN (SELECT region FROM live_regions)
+            AND lock_nonce IS NULL
+            AND execution_id IS NULL
+            AND deleted_at IS NULL
+            AND scheduled_at <= now() - make_interval(secs => $3::double precision)
+          ORDER BY scheduled_at ASC
+          LIMIT 100
+          FOR UPDATE SKIP LOCKED
+        )
+        UPDATE scheduled_jobs job
+        SET
+          lock_nonce = extract(epoch from now()),
+          times_locked = times_locked + 1
+        FROM http_requests req
+        WHERE job.id IN (SELECT id FROM stranded_jobs)
+          AND req.id = job.request_id
+        RETURNING job.id, job.lock_nonce, req.method, req.url, req.headers, req.body
+        "#,
+        &policy.regions,
+        policy.window.as_secs_f64(),
+        policy.grace.as_secs_f64()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut failed = 0;
+
+    for job in stranded {
+        let req_headers = job
+            .headers
+            .iter()
+            .filter_map(|s| {
+                let mut parts = s.splitn(2, ":");
+                let key = parts.next()?.trim().to_string();
+                let value = parts.next()?.trim().to_string();
+                Some((key, value))
+            })
+            .collect::<HashMap<String, String>>();
+
+        let execution = grpc::JobExecution {
+            job_id: job.id.clone(),
+            success: false,
+            lock_nonce: job.lock_nonce.unwrap_or_default() as i64,
+            response: None,
+            response_error: Some(NO_CAPACITY_ERROR.to_string()),
+            req_method: job.method,
+            req_url: job.url,
+            req_headers,
+            req_body: job.body,
+            executed_at: Utc::now().timestamp(),
+            retry_after_secs: None,
+            tls_verify_skipped: false,
+            drone_id: None,
+            duration_ms: None,
+        };
+
+        match job::record_job_execution(pool, &execution, job::InvalidExecutedAtPolicy::default())
+            .await
+        {
+            Ok(()) => failed += 1,
+            Err(error) => {
+                tracing::error! {
+                  job_id = job.id,
+                  %error,
+                  "Error recording no-capacity failure for job."
+                };
+            }
+        }
+    }
+
+    Ok(failed)
+}
+
+pub async fn run_no_capacity_loop(
+    pool: Pool<Postgres>,
+    interval: Duration,
+    policy: NoCapacityPolicy,
+) -> anyhow::Result<()> {
+    if policy.regions.is_empty() {
+        return std::future::pending().await;
+    }
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let failed = fail_stranded_jobs(&pool, &policy).await?;
+
+        if failed > 0 {
+            tracing::warn! {
+              count = failed,
+              "Failed jobs in regions with no live drones."
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_job(
+        pool: &Pool<Postgres>,
+        id: &str,
+        region: &str,
+        overdue_secs: f64,
+    ) -> anyhow::Result<()> {
+        let request_id = format!("request_{id}");
+
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ($1, 'GET', 'https://example.com', '{\"Accept: text/plain\"}')
+            ",
+            request_id
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, scheduled_at, request_id, max_retries)
+            VALUES ($1, 0, $2, now() - make_interval(secs => $3), $4, 0)
+            ",
+            id,
+            region,
+            overdue_secs,
+            request_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn response_error(pool: &Pool<Postgres>, id: &str) -> anyhow::Result<Option<String>> {
+        let error = sqlx::query_scalar!(
+            "
+            SELECT exec.response_error
+            FROM scheduled_jobs job
+            JOIN job_executions exec ON exec.id = job.execution_id
+            WHERE job.id = $1
+            ",
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(error.flatten())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_stranded_jobs_fail_after_grace(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO drones (id, ip, port, region, last_checkin, checkin_by)
+            VALUES ('drone_eu', '127.0.0.1', 3000, 'eu-west', now(), now() + interval '15 seconds')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        insert_job(&pool, "job_overdue", "na-east", 600.0).await?;
+        insert_job(&pool, "job_in_grace", "na-east", 10.0).await?;
+        insert_job(&pool, "job_live_region", "eu-west", 600.0).await?;
+        insert_job(&pool, "job_not_opted_in", "na-west", 600.0).await?;
+
+        let policy = NoCapacityPolicy {
+            regions: vec!["na-east".to_string(), "eu-west".to_string()],
+            window: Duration::from_secs(120),
+            grace: Duration::from_secs(300),
+        };
+
+        assert_eq!(fail_stranded_jobs(&pool, &policy).await?, 1);
+
+        assert_eq!(
+            response_error(&pool, "job_overdue").await?.as_deref(),
+            Some(NO_CAPACITY_ERROR)
+        );
+        assert!(response_error(&pool, "job_in_grace").await?.is_none());
+        assert!(response_error(&pool, "job_live_region").await?.is_none());
+        assert!(response_error(&pool, "job_not_opted_in").await?.is_none());
+
+        Ok(())
+    }
+}
@@ -1,14 +1,53 @@
-use std::net::IpAddr;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use chrono::DateTime;
 use replace_err::ReplaceErr;
 use sqlx::types::ipnetwork::IpNetwork;
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time::Instant};
 use tokio_stream::{StreamExt, wrappers::ReceiverStream};
 use tonic::Status;
 
 use crate::{broker::BrokerService, grpc};
 
+/// Remembers when each drone's check-in was last written, so a drone that
+/// ignores `checkin_again_at` can't turn every request into an upsert.
+#[derive(Debug, Clone)]
+pub struct CheckinLimiter {
+    min_interval: Duration,
+    last_written: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl CheckinLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_written: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether a check-in from `drone_id` should be written now.
+    pub fn allow(&self, drone_id: &str) -> bool {
+        let now = Instant::now();
+        let mut last_written = self.last_written.lock().expect("Checkin limiter poisoned.");
+
+        if let Some(written_at) = last_written.get(drone_id)
+            && now.duration_since(*written_at) < self.min_interval
+        {
+            return false;
+        }
+
+        last_written.retain(|_, written_at| now.duration_since(*written_at) < self.min_interval);
+        last_written.insert(drone_id.to_string(), now);
+
+        true
+    }
+}
+
 pub async fn handle_checkin(
     svc: &BrokerService,
     req: tonic::Request<grpc::DroneCheckinRequest>,
@@ -24,36 +63,79 @@ pub async fn handle_checkin(
         )))?;
     let ip_network: IpNetwork = drone_ip.into();
 
+    let region_affinities = serde_json::to_value(&drone_info.region_affinities)
+        .replace_err(Status::invalid_argument("Invalid region affinities."))?;
+
+    let drone_time = DateTime::from_timestamp_millis(drone_info.drone_time_ms)
+        .expect("Received invalid time from drone???");
+
+    let report_back_in = drone_time + chrono::Duration::seconds(9);
+
+    // The last written check-in is still well inside its window, so a
+    // throttled drone gets the usual answer without another write.
+    if !svc.checkins.allow(&drone_info.drone_id) {
+        tracing::debug! {
+          drone_id = drone_info.drone_id,
+          "Throttled drone check-in."
+        };
+
+        return Ok(tonic::Response::new(grpc::DroneCheckinResponse {
+            checkin_again_at: report_back_in.timestamp_millis(),
+        }));
+    }
+
     sqlx::query!(
         r#"
-    INSERT INTO drones (id, ip, port, region, last_checkin, checkin_by)
-    VALUES ($1, $2, $3, $4, now(), now() + interval '15 seconds')
+    INSERT INTO drones (id, ip, port, region, last_checkin, checkin_by, region_affinities)
+    VALUES ($1, $2, $3, $4, now(), now() + interval '15 seconds', $5)
     ON CONFLICT (id) DO UPDATE SET
       ip = EXCLUDED.ip,
       port = EXCLUDED.port,
       region = EXCLUDED.region,
       last_checkin = now(),
-      checkin_by = now() + interval '15 seconds';
+      checkin_by = now() + interval '15 seconds',
+      region_affinities = EXCLUDED.region_affinities;
   "#,
         drone_info.drone_id,
         ip_network,
         drone_info.drone_port as i32,
-        drone_info.drone_region
+        drone_info.drone_region,
+        region_affinities
     )
     .execute(&svc.pool)
     .await
     .replace_err(Status::internal("Unable to upsert drone for some reason."))?;
 
-    let drone_time = DateTime::from_timestamp_millis(drone_info.drone_time_ms)
-        .expect("Received invalid time from drone???");
-
-    let report_back_in = drone_time + chrono::Duration::seconds(9);
-
     Ok(tonic::Response::new(grpc::DroneCheckinResponse {
         checkin_again_at: report_back_in.timestamp_millis(),
     }))
 }
 
+/// Expires a drone's check-in immediately, so it drops out of `get_drones`
+/// as soon as it has drained instead of after its check-in window.
+pub async fn handle_shutdown(
+    svc: &BrokerService,
+    req: tonic::Request<grpc::DroneShutdownRequest>,
+) -> Result<tonic::Response<grpc::DroneShutdownResponse>, Status> {
+    let drone_id = req.into_inner().drone_id;
+
+    sqlx::query!(
+        r#"
+    UPDATE drones
+    SET
+      last_checkin = LEAST(last_checkin, now() - interval '1 millisecond'),
+      checkin_by = now()
+    WHERE id = $1;
+  "#,
+        drone_id
+    )
+    .execute(&svc.pool)
+    .await
+    .replace_err(Status::internal("Unable to mark drone as shut down."))?;
+
+    Ok(tonic::Response::new(grpc::DroneShutdownResponse {}))
+}
+
 pub type GetDronesStream = ReceiverStream<Result<grpc::GetDronesResponse, Status>>;
 
 pub async fn handle_get_drones(
@@ -98,3 +180,149 @@ pub async fn handle_get_drones(
 
     Ok(tonic::Response::new(ReceiverStream::new(rx)))
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::{Pool, Postgres};
+
+    use super::*;
+    use crate::{broker::job::RecordStreamLimiter, secrets::KeyRing};
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_checkin_records_region_affinities(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let svc = BrokerService {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: crate::broker::InvalidExecutedAtPolicy::default(),
+        };
+
+        let checkin = |affinities: HashMap<String, i32>| grpc::DroneCheckinRequest {
+            drone_id: "drone_a".to_string(),
+            drone_ip: "10.0.0.1".to_string(),
+            drone_port: 30002,
+            drone_region: "na-east".to_string(),
+            drone_time_ms: Utc::now().timestamp_millis(),
+            region_affinities: affinities,
+        };
+
+        let affinities = HashMap::from([("na-east".to_string(), 100), ("na-west".to_string(), 20)]);
+        handle_checkin(&svc, tonic::Request::new(checkin(affinities))).await?;
+
+        let drone = sqlx::query!("SELECT region_affinities FROM drones WHERE id = 'drone_a'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(
+            drone.region_affinities,
+            serde_json::json!({ "na-east": 100, "na-west": 20 })
+        );
+
+        handle_checkin(&svc, tonic::Request::new(checkin(HashMap::new()))).await?;
+
+        let drone = sqlx::query!("SELECT region_affinities FROM drones WHERE id = 'drone_a'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(drone.region_affinities, serde_json::json!({}));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_checkin_flood_is_throttled(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let svc = BrokerService {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: CheckinLimiter::new(Duration::from_secs(60)),
+            invalid_executed_at: crate::broker::InvalidExecutedAtPolicy::default(),
+        };
+
+        let checkin = |drone_id: &str, drone_port: i64| grpc::DroneCheckinRequest {
+            drone_id: drone_id.to_string(),
+            drone_ip: "10.0.0.1".to_string(),
+            drone_port,
+            drone_region: "na-east".to_string(),
+            drone_time_ms: Utc::now().timestamp_millis(),
+            region_affinities: HashMap::new(),
+        };
+
+        for attempt in 0..50 {
+            let res = handle_checkin(
+                &svc,
+                tonic::Request::new(checkin("drone_a", 30000 + attempt)),
+            )
+            .await?;
+            assert!(res.into_inner().checkin_again_at > Utc::now().timestamp_millis());
+        }
+
+        handle_checkin(&svc, tonic::Request::new(checkin("drone_b", 40000))).await?;
+
+        let ports = sqlx::query_scalar!("SELECT port FROM drones ORDER BY id")
+            .fetch_all(&pool)
+            .await?;
+
+        // Only the first of drone_a's check-ins was written, and the flood
+        // didn't hold back other drones.
+        assert_eq!(ports, vec![30000, 40000]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_shutdown_removes_drone_from_peers(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let svc = BrokerService {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: crate::broker::InvalidExecutedAtPolicy::default(),
+        };
+
+        handle_checkin(
+            &svc,
+            tonic::Request::new(grpc::DroneCheckinRequest {
+                drone_id: "drone_a".to_string(),
+                drone_ip: "10.0.0.1".to_string(),
+                drone_port: 30002,
+                drone_region: "na-east".to_string(),
+                drone_time_ms: Utc::now().timestamp_millis(),
+                region_affinities: HashMap::new(),
+            }),
+        )
+        .await?;
+
+        let peers = || async {
+            let req = tonic::Request::new(grpc::GetDronesRequest {
+                drone_id: "drone_b".to_string(),
+            });
+            let stream = handle_get_drones(&svc, req).await?.into_inner();
+
+            Ok::<_, Status>(stream.collect::<Vec<_>>().await.len())
+        };
+
+        assert_eq!(peers().await?, 1);
+
+        handle_shutdown(
+            &svc,
+            tonic::Request::new(grpc::DroneShutdownRequest {
+                drone_id: "drone_a".to_string(),
+            }),
+        )
+        .await?;
+
+        assert_eq!(peers().await?, 0);
+
+        Ok(())
+    }
+}
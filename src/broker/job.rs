@@ -1,20 +1,117 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
 
 use chrono::{DateTime, Utc};
-use sqlx::{Pool, Postgres};
+use opentelemetry::{global, metrics::Counter};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, Transaction};
 use tokio::sync::mpsc;
 use tokio_stream::{StreamExt, wrappers::ReceiverStream};
 use tonic::Status;
 
 use crate::{
-    broker::{BrokerService, workflow},
+    broker::{BrokerService, DRONE_ID_METADATA, workflow},
     grpc, id,
     secrets::Secret,
     signing::SignatureBuilder,
+    telemetry,
 };
 
 pub type GetJobsStream = ReceiverStream<Result<grpc::JobSpec, Status>>;
 
+/// The job's limit, falling back to the tenant's, never above the ceiling.
+fn response_bytes_limit(
+    job_limit: Option<i32>,
+    tenant_limit: Option<i32>,
+    ceiling: i64,
+) -> Option<i64> {
+    let limit = job_limit
+        .or(tenant_limit)
+        .map_or(ceiling, |bytes| (bytes as i64).min(ceiling));
+
+    Some(limit.max(0))
+}
+
+/// Jobs locked per request for drones that don't declare their capacity.
+const DEFAULT_JOB_BATCH: i64 = 100;
+/// The most jobs one request can lock, whatever capacity a drone declares.
+const MAX_JOB_BATCH: i64 = 1000;
+
+fn job_batch_limit(max_jobs: Option<u32>) -> i64 {
+    max_jobs.map_or(DEFAULT_JOB_BATCH, |max_jobs| {
+        (max_jobs as i64).min(MAX_JOB_BATCH)
+    })
+}
+
+/// Adds `If-None-Match` and `If-Modified-Since` from the validators of a
+/// conditional cron job's last successful response, unless the job sets
+/// them itself.
+fn add_conditional_headers(req_headers: &mut HashMap<String, String>, last_headers: &[String]) {
+    for entry in last_headers {
+        let Some((key, value)) = entry.split_once(':') else {
+            continue;
+        };
+
+        let conditional_header = match key.trim().to_ascii_lowercase().as_str() {
+            "etag" => "If-None-Match",
+            "last-modified" => "If-Modified-Since",
+            _ => continue,
+        };
+
+        if !req_headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case(conditional_header))
+        {
+            req_headers.insert(conditional_header.to_string(), value.trim().to_string());
+        }
+    }
+}
+
+/// Skips every overdue run of a `coalesce_missed` cron job but its latest,
+/// so a backlog built up while the target was down goes out as one request.
+async fn coalesce_missed_cron_runs(pool: &Pool<Postgres>) -> sqlx::Result<u64> {
+    let coalesced = sqlx::query!(
+        r#"
+        WITH overdue AS (
+          SELECT job.id, job.cron_job_id, job.scheduled_at
+          FROM scheduled_jobs job
+          JOIN cron_jobs cron
+            ON cron.id = job.cron_job_id
+          WHERE cron.coalesce_missed
+            AND job.lock_nonce IS NULL
+            AND job.execution_id IS NULL
+            AND job.deleted_at IS NULL
+            AND job.scheduled_at <= now()
+          FOR UPDATE OF job SKIP LOCKED
+        ),
+        superseded AS (
+          SELECT id FROM (
+            SELECT
+              id,
+              row_number() OVER (
+                PARTITION BY cron_job_id
+                ORDER BY scheduled_at DESC, id DESC
+              ) AS recency
+            FROM overdue
+          ) ranked
+          WHERE recency > 1
+        )
+        UPDATE scheduled_jobs
+        SET
+          coalesced = true,
+          deleted_at = now()
+        WHERE id IN (SELECT id FROM superseded)
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(coalesced.rows_affected())
+}
+
 pub async fn get_jobs(
     svc: &BrokerService,
     req: tonic::Request<grpc::GetJobsRequest>,
@@ -24,21 +121,74 @@ pub async fn get_jobs(
     let data = req.into_inner();
 
     let region = data.region;
+    let batch_limit = job_batch_limit(data.max_jobs);
     let pool = svc.pool.clone();
 
     let key_ring = svc.key_ring.clone();
     let fallback_signing_secret = svc.fallback_signing_secret.clone();
+    let max_response_bytes_ceiling = svc.max_response_bytes_ceiling;
+    let traceparent = telemetry::current_traceparent();
     tokio::spawn(async move {
-        let mut stream = sqlx::query!(
+        // Older runs are skipped before locking, so they can't be handed out
+        // alongside the latest one.
+        match coalesce_missed_cron_runs(&pool).await {
+            Ok(0) => {}
+            Ok(coalesced) => {
+                tracing::info! {
+                  count = coalesced,
+                  "Coalesced missed cron runs."
+                };
+            }
+            Err(err) => {
+                tracing::error!(%err, "Error coalescing missed cron runs.");
+            }
+        }
+
+        // Capped cron jobs are locked like tenants are, so two brokers can't
+        // both see the same free slot. Each is then handed no more jobs than
+        // it has slots left, even when several of its runs are due at once.
+        let jobs = sqlx::query!(
             r#"
         WITH active_tenants AS (
-          SELECT id, tokens FROM tenants
-          WHERE tokens > 0
+          SELECT id, tokens, max_concurrent_executions FROM tenants
+          WHERE tokens > 0 AND deleted_at IS NULL AND NOT suspended
+          FOR UPDATE SKIP LOCKED
+        ),
+        tenant_capacity AS (
+          SELECT
+            t.id,
+            CASE
+              WHEN t.max_concurrent_executions IS NULL THEN t.tokens
+              ELSE LEAST(t.tokens, GREATEST(0, t.max_concurrent_executions - (
+                SELECT count(*)
+                FROM scheduled_jobs in_flight
+                WHERE in_flight.tenant_id = t.id
+                  AND in_flight.lock_nonce IS NOT NULL
+                  AND in_flight.execution_id IS NULL
+              )))
+            END AS capacity
+          FROM active_tenants t
+        ),
+        capped_crons AS (
+          SELECT id, max_concurrent FROM cron_jobs
+          WHERE max_concurrent IS NOT NULL
           FOR UPDATE SKIP LOCKED
         ),
+        cron_capacity AS (
+          SELECT
+            cron.id,
+            GREATEST(0, cron.max_concurrent - (
+              SELECT count(*)
+              FROM scheduled_jobs in_flight
+              WHERE in_flight.cron_job_id = cron.id
+                AND in_flight.lock_nonce IS NOT NULL
+                AND in_flight.execution_id IS NULL
+            )) AS capacity
+          FROM capped_crons cron
+        ),
         candidate_ids AS (
           SELECT job_lat.*
-          FROM active_tenants t
+          FROM tenant_capacity t
           CROSS JOIN LATERAL (
             SELECT
               job.id,
@@ -47,12 +197,18 @@ pub async fn get_jobs(
             WHERE job.tenant_id = t.id
               AND job.lock_nonce IS NULL
               AND job.execution_id IS NULL
+              AND job.deleted_at IS NULL
               AND (
                 (job.region = $1 AND job.scheduled_at <= now() + interval '3 seconds')
                 OR (job.scheduled_at <= now() - interval '5 seconds')
               )
+              AND (
+                job.cron_job_id IS NULL
+                OR job.cron_job_id NOT IN (SELECT id FROM cron_jobs WHERE max_concurrent IS NOT NULL)
+                OR job.cron_job_id IN (SELECT id FROM cron_capacity WHERE capacity > 0)
+              )
             ORDER BY job.scheduled_at ASC, job.id ASC
-            LIMIT t.tokens
+            LIMIT t.capacity
           ) job_lat
           UNION ALL
           SELECT id, scheduled_at
@@ -60,16 +216,40 @@ pub async fn get_jobs(
           WHERE tenant_id IS NULL
             AND lock_nonce IS NULL
             AND execution_id IS NULL
+            AND deleted_at IS NULL
             AND (
               (region = $1 AND scheduled_at <= now() + interval '3 seconds')
               OR (scheduled_At <= now() - interval '5 seconds')
             )
+            AND (
+              cron_job_id IS NULL
+              OR cron_job_id NOT IN (SELECT id FROM cron_jobs WHERE max_concurrent IS NOT NULL)
+              OR cron_job_id IN (SELECT id FROM cron_capacity WHERE capacity > 0)
+            )
           ORDER BY scheduled_at ASC, id ASC
-          LIMIT 100
+          LIMIT $2
+        ),
+        ranked_candidates AS (
+          SELECT
+            candidate.id,
+            job.cron_job_id,
+            row_number() OVER (
+              PARTITION BY job.cron_job_id
+              ORDER BY candidate.scheduled_at ASC, candidate.id ASC
+            ) AS cron_rank
+          FROM candidate_ids candidate
+          JOIN scheduled_jobs job
+            ON job.id = candidate.id
         ),
         jobs_to_lock AS (
           SELECT id FROM scheduled_jobs
-          WHERE id IN (SELECT id FROM candidate_ids)
+          WHERE id IN (
+            SELECT ranked.id
+            FROM ranked_candidates ranked
+            LEFT JOIN cron_capacity cron
+              ON cron.id = ranked.cron_job_id
+            WHERE cron.id IS NULL OR ranked.cron_rank <= cron.capacity
+          )
           FOR UPDATE SKIP LOCKED
         ),
         updated_jobs AS (
@@ -98,6 +278,14 @@ pub async fn get_jobs(
           job.scheduled_at,
           job.timeout_ms,
           job.max_response_bytes,
+          job.fresh_connection,
+          job.insecure_skip_tls_verify,
+          job.transfer_encoding,
+          job.body_read_timeout_ms,
+          job.follow_redirects,
+          job.resolve_override,
+          cron.conditional as "conditional?",
+          validators.headers as "validator_headers?",
           tenant.id as "tenant_id?",
           tenant.max_timeout as "max_timeout?",
           tenant.max_max_response_bytes as "max_max_response_bytes?",
@@ -122,123 +310,160 @@ pub async fn get_jobs(
           ON tenant.id = job.tenant_id
         LEFT JOIN secrets secret
           ON secret.id = tenant.current_signing_key
+        LEFT JOIN cron_jobs cron
+          ON cron.id = job.cron_job_id
+        LEFT JOIN LATERAL (
+          SELECT res.headers
+          FROM scheduled_jobs prev
+          JOIN job_executions exe
+            ON exe.id = prev.execution_id
+          JOIN http_responses res
+            ON res.id = exe.response_id
+          WHERE cron.conditional
+            AND prev.cron_job_id = cron.id
+            AND res.status BETWEEN 200 AND 299
+          ORDER BY exe.executed_at DESC
+          LIMIT 1
+        ) validators ON true
         ORDER BY job.scheduled_at ASC;
         "#,
-            region
+            region,
+            batch_limit
         )
-        .fetch(&pool);
-
-        while let Some(next) = stream.next().await {
-            if let Ok(job) = next {
-                let timeout = job.timeout_ms.or(job.max_timeout).unwrap_or(60_000);
-                // default 32mb if no tenant limit is set.
-                let max_response_bytes = job
-                    .max_response_bytes
-                    .or(job.max_max_response_bytes)
-                    .map(|bytes| bytes as i64);
-
-                let tenant_signing_secret: Option<Secret> = if let Some(id) = job.secret_id
-                    && let Some(master_key_id) = job.master_key_id
-                    && let Some(secret_version) = job.secret_version
-                    && let Some(encrypted_dek) = job.encrypted_dek
-                    && let Some(encrypted_data) = job.encrypted_data
-                    && let Some(dek_nonce) = job.dek_nonce
-                    && let Some(data_nonce) = job.data_nonce
-                    && let Some(algorithm) = job.algorithm
-                {
-                    Some(Secret {
-                        id,
-                        master_key_id,
-                        secret_version,
-                        encrypted_dek,
-                        encrypted_data,
-                        dek_nonce,
-                        data_nonce,
-                        algorithm,
-                    })
-                } else {
-                    None
-                };
+        .fetch_all(&pool)
+        .await;
 
-                let signing_secret: Option<String> = if let Some(tenant_id) = job.tenant_id {
-                    if let Some(signing_secret) = tenant_signing_secret {
-                        match signing_secret.decrypt(&key_ring) {
-                            Ok(decrypted) => Some(decrypted),
-                            Err(err) => {
-                                tracing::error! {
-                                  %err,
-                                  %tenant_id,
-                                  signing_secret_id = signing_secret.id,
-                                  "Error decrypting signing secret for tenant."
-                                };
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    Some(fallback_signing_secret.clone())
-                };
+        // The jobs are locked once the query returns, so the connection goes
+        // back to the pool before a slow drone can hold it open.
+        let jobs = match jobs {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                tracing::error!(%err, "Error locking jobs for drone.");
+                let _ = tx.send(Err(Status::internal("Could not fetch jobs"))).await;
+                return;
+            }
+        };
 
-                let signature: Option<String> = if let Some(signing_key) = signing_secret {
-                    let signature_result = SignatureBuilder {
-                        signing_key,
-                        method: job.method.clone(),
-                        time: Utc::now(),
-                        url: job.url.clone(),
-                        body: job.body.clone(),
-                    }
-                    .signature_header();
+        for job in jobs {
+            let timeout = job.timeout_ms.or(job.max_timeout).unwrap_or(60_000);
+            let max_response_bytes = response_bytes_limit(
+                job.max_response_bytes,
+                job.max_max_response_bytes,
+                max_response_bytes_ceiling,
+            );
 
-                    match signature_result {
-                        Ok(signature) => Some(signature),
-                        Err(signing_error) => {
+            let tenant_signing_secret: Option<Secret> = if let Some(id) = job.secret_id
+                && let Some(master_key_id) = job.master_key_id
+                && let Some(secret_version) = job.secret_version
+                && let Some(encrypted_dek) = job.encrypted_dek
+                && let Some(encrypted_data) = job.encrypted_data
+                && let Some(dek_nonce) = job.dek_nonce
+                && let Some(data_nonce) = job.data_nonce
+                && let Some(algorithm) = job.algorithm
+            {
+                Some(Secret {
+                    id,
+                    master_key_id,
+                    secret_version,
+                    encrypted_dek,
+                    encrypted_data,
+                    dek_nonce,
+                    data_nonce,
+                    algorithm,
+                })
+            } else {
+                None
+            };
+
+            let signing_secret: Option<String> = if let Some(tenant_id) = job.tenant_id {
+                if let Some(signing_secret) = tenant_signing_secret {
+                    match signing_secret.decrypt(&key_ring) {
+                        Ok(decrypted) => Some(decrypted),
+                        Err(err) => {
                             tracing::error! {
-                              job_id = job.job_id.clone(),
-                              %signing_error,
-                              "Error signing request",
+                              %err,
+                              %tenant_id,
+                              signing_secret_id = signing_secret.id,
+                              "Error decrypting signing secret for tenant."
                             };
                             None
                         }
                     }
                 } else {
                     None
-                };
-
-                let mut req_headers = job
-                    .headers
-                    .iter()
-                    .filter_map(|s| {
-                        let mut parts = s.splitn(2, ":");
-                        let key = parts.next()?.trim().to_string();
-                        let value = parts.next()?.trim().to_string();
-                        Some((key, value))
-                    })
-                    .collect::<HashMap<String, String>>();
-
-                req_headers.insert("Rocktick-Job-Id".to_string(), job.job_id.clone());
-
-                if let Some(signature_header) = signature {
-                    req_headers.insert("Rocktick-Signature".to_string(), signature_header);
                 }
+            } else {
+                Some(fallback_signing_secret.clone())
+            };
 
-                let job_spec = grpc::JobSpec {
-                    job_id: job.job_id,
-                    lock_nonce: job.lock_nonce.unwrap() as i64,
-                    scheduled_at: job.scheduled_at.timestamp(),
-                    method: job.method,
-                    url: job.url,
-                    headers: req_headers,
-                    body: job.body,
-                    timeout_ms: timeout,
-                    max_response_bytes,
-                };
+            let signature: Option<String> = if let Some(signing_key) = signing_secret {
+                let signature_result = SignatureBuilder {
+                    signing_key,
+                    method: job.method.clone(),
+                    time: Utc::now(),
+                    url: job.url.clone(),
+                    body: job.body.clone(),
+                }
+                .signature_header();
 
-                if tx.send(Ok(job_spec)).await.is_err() {
-                    break;
+                match signature_result {
+                    Ok(signature) => Some(signature),
+                    Err(signing_error) => {
+                        tracing::error! {
+                          job_id = job.job_id.clone(),
+                          %signing_error,
+                          "Error signing request",
+                        };
+                        None
+                    }
                 }
             } else {
+                None
+            };
+
+            let mut req_headers = job
+                .headers
+                .iter()
+                .filter_map(|s| {
+                    let mut parts = s.splitn(2, ":");
+                    let key = parts.next()?.trim().to_string();
+                    let value = parts.next()?.trim().to_string();
+                    Some((key, value))
+                })
+                .collect::<HashMap<String, String>>();
+
+            req_headers.insert("Rocktick-Job-Id".to_string(), job.job_id.clone());
+
+            let conditional = job.conditional.unwrap_or(false);
+            if conditional && let Some(validator_headers) = &job.validator_headers {
+                add_conditional_headers(&mut req_headers, validator_headers);
+            }
+
+            if let Some(signature_header) = signature {
+                req_headers.insert("Rocktick-Signature".to_string(), signature_header);
+            }
+
+            let job_spec = grpc::JobSpec {
+                job_id: job.job_id,
+                lock_nonce: job.lock_nonce.unwrap() as i64,
+                scheduled_at: job.scheduled_at.timestamp(),
+                method: job.method,
+                url: job.url,
+                headers: req_headers,
+                body: job.body,
+                timeout_ms: timeout,
+                max_response_bytes,
+                fresh_connection: job.fresh_connection,
+                insecure_skip_tls_verify: job.insecure_skip_tls_verify,
+                transfer_encoding: job.transfer_encoding,
+                conditional,
+                body_read_timeout_ms: job.body_read_timeout_ms,
+                follow_redirects: job.follow_redirects,
+                resolve_override: job.resolve_override,
+                traceparent: traceparent.clone(),
+            };
+
+            if tx.send(Ok(job_spec)).await.is_err() {
                 break;
             }
         }
@@ -247,165 +472,410 @@ pub async fn get_jobs(
     Ok(tonic::Response::new(ReceiverStream::new(rx)))
 }
 
+/// Tracks how many `record_execution` streams each drone currently has open,
+/// keyed by the peer the streams came from.
+#[derive(Debug, Clone)]
+pub struct RecordStreamLimiter {
+    max_per_drone: usize,
+    active: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+/// Held for the lifetime of a `record_execution` stream, releases its slot on drop.
+pub struct RecordStreamPermit {
+    peer: String,
+    active: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl RecordStreamLimiter {
+    pub fn new(max_per_drone: usize) -> Self {
+        Self {
+            max_per_drone,
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn acquire(&self, peer: &str) -> Option<RecordStreamPermit> {
+        let mut active = self.active.lock().expect("Record stream limiter poisoned.");
+        let count = active.entry(peer.to_string()).or_insert(0);
+
+        if *count >= self.max_per_drone {
+            return None;
+        }
+
+        *count += 1;
+
+        Some(RecordStreamPermit {
+            peer: peer.to_string(),
+            active: self.active.clone(),
+        })
+    }
+}
+
+impl Drop for RecordStreamPermit {
+    fn drop(&mut self) {
+        let mut active = self.active.lock().expect("Record stream limiter poisoned.");
+
+        if let Some(count) = active.get_mut(&self.peer) {
+            *count = count.saturating_sub(1);
+
+            if *count == 0 {
+                active.remove(&self.peer);
+            }
+        }
+    }
+}
+
 pub type RecordExecutionStream = ReceiverStream<Result<grpc::RecordExecutionResponse, Status>>;
 
+static STALE_EXECUTIONS: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    global::meter("rocktick")
+        .u64_counter("rocktick_stale_executions_rejected")
+        .with_description("Executions dropped because their job already had one.")
+        .build()
+});
+
+/// What the broker does with an execution whose `executed_at` isn't a
+/// valid time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum InvalidExecutedAtPolicy {
+    /// Drop the execution without acknowledging it, so the drone holds on
+    /// to it until its clock is looked into.
+    Reject,
+    /// Record it at the broker's time and log an error.
+    #[default]
+    Clamp,
+    /// Record it at the broker's time without logging.
+    Accept,
+}
+
+/// Time an execution is recorded at, and whether the drone reported it or
+/// the broker substituted its own.
+fn resolve_executed_at(
+    execution: &grpc::JobExecution,
+    policy: InvalidExecutedAtPolicy,
+) -> anyhow::Result<(DateTime<Utc>, &'static str)> {
+    if let Some(executed_at) = DateTime::from_timestamp_secs(execution.executed_at) {
+        return Ok((executed_at, "drone"));
+    }
+
+    match policy {
+        InvalidExecutedAtPolicy::Reject => Err(anyhow::anyhow!(
+            "Drone reported invalid executed_at time {} for job {}",
+            execution.executed_at,
+            execution.job_id
+        )),
+        InvalidExecutedAtPolicy::Clamp => {
+            tracing::error! {
+              job_id = execution.job_id,
+              execution_executed_at = execution.executed_at,
+              "Drone returned invalid executed_at time, recording the broker's time instead."
+            };
+            Ok((Utc::now(), "broker"))
+        }
+        InvalidExecutedAtPolicy::Accept => Ok((Utc::now(), "broker")),
+    }
+}
+
+/// Keeps count of a cron job's failed executions in a row, pausing it once
+/// the count reaches its `pause_after_failures`. Its pending runs are
+/// dropped like a manual pause drops them.
+async fn track_cron_failures(
+    tx: &mut Transaction<'_, Postgres>,
+    cron_job_id: &str,
+    success: bool,
+) -> anyhow::Result<()> {
+    let cron = sqlx::query!(
+        r#"
+        UPDATE cron_jobs
+        SET
+          consecutive_failures = CASE WHEN $2 THEN 0 ELSE consecutive_failures + 1 END,
+          paused = paused OR COALESCE(NOT $2 AND consecutive_failures + 1 >= pause_after_failures, false)
+        WHERE id = $1
+        RETURNING consecutive_failures, pause_after_failures
+        "#,
+        cron_job_id,
+        success
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    // The count only lands on the threshold once before a resume resets it.
+    if cron.pause_after_failures != Some(cron.consecutive_failures) {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        DELETE FROM scheduled_jobs
+        WHERE cron_job_id = $1
+          AND lock_nonce IS NULL
+          AND execution_id IS NULL
+        "#,
+        cron_job_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    tracing::warn! {
+      cron_job_id,
+      consecutive_failures = cron.consecutive_failures,
+      "Paused cron job after too many failed executions in a row."
+    };
+
+    Ok(())
+}
+
+/// Stores a drone's result for a scheduled job. A result for a job that
+/// already has an execution, e.g. from a drone that kept running after its
+/// lock was released, is dropped so it's acknowledged without a second row.
+pub async fn record_job_execution(
+    pool: &Pool<Postgres>,
+    execution: &grpc::JobExecution,
+    invalid_executed_at: InvalidExecutedAtPolicy,
+) -> anyhow::Result<()> {
+    let (executed_at, time_source) = resolve_executed_at(execution, invalid_executed_at)?;
+
+    let mut tx = pool.begin().await?;
+
+    let scheduled = sqlx::query!(
+        r#"
+        SELECT
+          job.id,
+          job.lock_nonce,
+          job.execution_id,
+          job.tenant_id,
+          job.workflow_execution_id,
+          job.cron_job_id,
+          COALESCE(cron.conditional, false) as "conditional!"
+        FROM scheduled_jobs job
+        LEFT JOIN cron_jobs cron
+          ON cron.id = job.cron_job_id
+        WHERE job.id = $1
+        FOR UPDATE OF job;
+        "#,
+        execution.job_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(execution_id) = scheduled.execution_id {
+        STALE_EXECUTIONS.add(1, &[]);
+        tracing::warn! {
+          job_id = scheduled.id,
+          execution_id,
+          lock_nonce = execution.lock_nonce,
+          "Dropping duplicate execution for job that already has one."
+        };
+        return Ok(());
+    }
+
+    if scheduled.lock_nonce != Some(execution.lock_nonce as i32) {
+        // The lock was released while the job ran. This is still the first
+        // result, so it's kept, and the dispatch that replaced it is dropped.
+        tracing::warn! {
+          job_id = scheduled.id,
+          lock_nonce = execution.lock_nonce,
+          current_lock_nonce = scheduled.lock_nonce,
+          "Recording execution for a job whose lock was released."
+        };
+    }
+
+    let request_id = id::generate("request");
+    let req_headers: Vec<String> = execution
+        .req_headers
+        .iter()
+        .filter_map(|(k, v)| {
+            if k.starts_with("Rocktick-") {
+                None
+            } else {
+                Some(format!("{k}: {v}"))
+            }
+        })
+        .collect();
+
+    sqlx::query!(
+        r#"
+          INSERT INTO http_requests
+            (id, method, url, headers, body)
+          VALUES
+            ($1, $2, $3 ,$4, $5)
+          "#,
+        request_id,
+        execution.req_method,
+        execution.req_url,
+        &req_headers,
+        execution.req_body
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // A 304 to a conditional request means the body is the one the previous
+    // run already stored, so there's nothing to keep or hash this time.
+    let unchanged = scheduled.conditional
+        && execution
+            .response
+            .as_ref()
+            .is_some_and(|res| res.status == 304);
+
+    let mut response_id = None;
+
+    if let Some(mut response) = execution.response.clone() {
+        if unchanged {
+            response.body.clear();
+        }
+
+        let res_id = id::generate("response");
+        response_id = Some(res_id.clone());
+
+        let headers: Vec<String> = response
+            .headers
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO http_responses
+                  (id, status, headers, body, truncated, body_read_truncated)
+                VALUES
+                  ($1, $2, $3, $4, $5, $6);
+                "#,
+            res_id,
+            response.status as i64,
+            &headers,
+            response.body,
+            response.truncated,
+            response.body_read_truncated,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let execution_id = id::generate("execution");
+
+    sqlx::query!(
+        r#"
+            INSERT INTO job_executions
+              (id, executed_at, success, response_id, response_error, request_id, body_hash, retry_after_secs, tls_verify_skipped, time_source, unchanged, drone_id, duration_ms)
+            VALUES
+              ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13);
+          "#,
+        execution_id.clone(),
+        executed_at,
+        execution.success,
+        response_id,
+        execution.response_error,
+        request_id,
+        execution
+            .response
+            .as_ref()
+            .filter(|_| !unchanged)
+            .and_then(|res| res.body_hash.clone()),
+        execution.retry_after_secs,
+        execution.tls_verify_skipped,
+        time_source,
+        unchanged,
+        execution.drone_id,
+        execution.duration_ms
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(workflow_execution_id) = scheduled.workflow_execution_id {
+        let workflow_response_body: Result<String, String> =
+            if let Some(res) = execution.response.clone() {
+                Ok(res.body)
+            } else if let Some(res_error) = execution.response_error.clone() {
+                Err(res_error)
+            } else {
+                Err("Drone did not respond with an response".to_string())
+            };
+
+        workflow::handle_workflow_execution_side_effect(
+            workflow_execution_id,
+            workflow_response_body,
+            executed_at,
+            &mut tx,
+        )
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE scheduled_jobs
+            SET
+              execution_id = $2,
+              lock_nonce = NULL
+            WHERE id = $1;
+          "#,
+        scheduled.id,
+        execution_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(cron_job_id) = &scheduled.cron_job_id {
+        track_cron_failures(&mut tx, cron_job_id, execution.success).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Who a `record_execution` stream is counted against: the client
+/// certificate the drone connected with, or else the drone id it gave once
+/// it has passed drone auth.
+fn record_stream_peer<T>(req: &tonic::Request<T>, drone_id: &str) -> String {
+    match req.peer_certs().and_then(|certs| certs.first().cloned()) {
+        Some(cert) => format!("cert:{}", hex::encode(Sha256::digest(cert.as_ref()))),
+        None => format!("drone:{drone_id}"),
+    }
+}
+
 pub async fn record_execution(
     svc: &BrokerService,
     req: tonic::Request<tonic::Streaming<grpc::JobExecution>>,
 ) -> Result<tonic::Response<RecordExecutionStream>, Status> {
+    let drone_id = req
+        .metadata()
+        .get(DRONE_ID_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .ok_or(Status::unauthenticated("Missing drone id."))?;
+
+    let peer = record_stream_peer(&req, &drone_id);
+
+    let permit = svc
+        .record_streams
+        .acquire(&peer)
+        .ok_or(Status::resource_exhausted(format!(
+            "Drone {drone_id} has too many open record_execution streams."
+        )))?;
+
     let (tx, rx) = mpsc::channel(16);
 
     let mut executions = req.into_inner();
     let pool = svc.pool.clone();
+    let invalid_executed_at = svc.invalid_executed_at;
 
     tokio::spawn(async move {
+        let _permit = permit;
+
         while let Some(job_execution) = executions.next().await {
-            if let Ok(execution) = job_execution {
+            if let Ok(mut execution) = job_execution {
+                // Executions from drones that don't report who ran them were run by
+                // the drone submitting them.
+                if execution.drone_id.is_none() {
+                    execution.drone_id = Some(drone_id.clone());
+                }
+
                 let pool = pool.clone();
                 let response = tx.clone();
                 tokio::spawn(async move {
                     let id = execution.job_id.clone();
-                    let success: anyhow::Result<()> = async {
-                        let mut tx = pool.begin().await?;
-
-                        let scheduled = sqlx::query!(
-                            r#"
-                      SELECT id, lock_nonce, tenant_id, workflow_execution_id
-                      FROM scheduled_jobs
-                      WHERE id = $1
-                        AND lock_nonce = $2
-                      FOR UPDATE;
-                      "#,
-                            execution.job_id,
-                            execution.lock_nonce as i32
-                        )
-                        .fetch_one(&mut *tx)
-                        .await?;
-
-                        let request_id = id::generate("request");
-                        let req_headers: Vec<String> = execution
-                            .req_headers
-                            .iter()
-                            .filter_map(|(k, v)| {
-                                if k.starts_with("Rocktick-") {
-                                    None
-                                } else {
-                                    Some(format!("{k}: {v}"))
-                                }
-                            })
-                            .collect();
-
-                        sqlx::query!(
-                            r#"
-                        INSERT INTO http_requests
-                          (id, method, url, headers, body)
-                        VALUES
-                          ($1, $2, $3 ,$4, $5)
-                        "#,
-                            request_id,
-                            execution.req_method,
-                            execution.req_url,
-                            &req_headers,
-                            execution.req_body
-                        )
-                        .execute(&mut *tx)
-                        .await?;
-
-                        let mut response_id = None;
-
-                        if let Some(response) = execution.response.clone() {
-                            let res_id = id::generate("response");
-                            response_id = Some(res_id.clone());
-
-                            let headers: Vec<String> = response
-                                .headers
-                                .iter()
-                                .map(|(k, v)| format!("{k}: {v}"))
-                                .collect();
-
-                            sqlx::query!(
-                                r#"
-                              INSERT INTO http_responses
-                                (id, status, headers, body)
-                              VALUES
-                                ($1, $2, $3, $4);
-                              "#,
-                                res_id,
-                                response.status as i64,
-                                &headers,
-                                response.body,
-                            )
-                            .execute(&mut *tx)
-                            .await?;
-                        }
-
-                        let execution_id = id::generate("execution");
-                        let executed_at = DateTime::from_timestamp_secs(execution.executed_at);
-
-                        if executed_at.is_none() {
-                            tracing::error! {
-                              execution_executed_at = execution.executed_at,
-                                "Drone returned invalid executed_at time",
-                            };
-                        }
-
-                        let executed_at = executed_at.unwrap_or(Utc::now());
-
-                        sqlx::query!(
-                            r#"
-                          INSERT INTO job_executions
-                            (id, executed_at, success, response_id, response_error, request_id)
-                          VALUES
-                            ($1, $2, $3, $4, $5, $6);
-                        "#,
-                            execution_id.clone(),
-                            executed_at,
-                            execution.success,
-                            response_id,
-                            execution.response_error,
-                            request_id
-                        )
-                        .execute(&mut *tx)
-                        .await?;
-
-                        if let Some(workflow_execution_id) = scheduled.workflow_execution_id {
-                            let workflow_response_body: Result<String, String> =
-                                if let Some(res) = execution.response.clone() {
-                                    Ok(res.body)
-                                } else if let Some(res_error) = execution.response_error.clone() {
-                                    Err(res_error)
-                                } else {
-                                    Err("Drone did not respond with an response".to_string())
-                                };
-
-                            workflow::handle_workflow_execution_side_effect(
-                                workflow_execution_id,
-                                workflow_response_body,
-                                executed_at,
-                                &mut tx,
-                            )
-                            .await?;
-                        }
-
-                        sqlx::query!(
-                            r#"
-                          UPDATE scheduled_jobs
-                          SET
-                            execution_id = $2,
-                            lock_nonce = NULL
-                          WHERE id = $1;
-                        "#,
-                            scheduled.id,
-                            execution_id
-                        )
-                        .execute(&mut *tx)
-                        .await?;
-
-                        tx.commit().await?;
-
-                        Ok(())
-                    }
-                    .await;
+                    let success =
+                        record_job_execution(&pool, &execution, invalid_executed_at).await;
 
                     if let Err(error) = success {
                         tracing::error! {
@@ -433,12 +903,15 @@ pub async fn record_execution(
     Ok(tonic::Response::new(ReceiverStream::new(rx)))
 }
 
-pub async fn run_job_cleanup_loop(pool: Pool<Postgres>) -> anyhow::Result<()> {
-    loop {
-        let mut tx = pool.begin().await?;
+/// Releases jobs a drone locked but never reported on, once their timeout
+/// plus `safety_window` has passed, and refunds their tenants' tokens.
+async fn release_stale_locks(
+    pool: &Pool<Postgres>,
+    safety_window: Duration,
+) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
 
-        tokio::time::sleep(Duration::from_secs(15)).await;
-        let result = sqlx::query!(
+    let result = sqlx::query!(
             r#"
               WITH cleanup_candidates AS (
                 SELECT
@@ -451,8 +924,8 @@ pub async fn run_job_cleanup_loop(pool: Pool<Postgres>) -> anyhow::Result<()> {
                   AND job.execution_id IS NULL
                   AND to_timestamp(job.lock_nonce)
                       + make_interval(secs => COALESCE(job.timeout_ms, tenant.max_timeout, 120000) / 1000)
-                      -- 90 second safety interval just in case it takes a while to report or smth.
-                      + interval '90 seconds'
+                      -- safety interval just in case it takes a while to report or smth.
+                      + make_interval(secs => $1::double precision)
                       < now()
               ),
               locked_candidates AS (
@@ -477,18 +950,763 @@ pub async fn run_job_cleanup_loop(pool: Pool<Postgres>) -> anyhow::Result<()> {
               SET tokens = LEAST(tenants.max_tokens, tokens + refunds.refund_tokens)
               FROM refunds
               WHERE tenants.id = refunds.tenant_id
-          "#
+          "#,
+            safety_window.as_secs_f64()
         )
         .execute(&mut *tx)
         .await?;
 
-        if result.rows_affected() > 0 {
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn run_job_cleanup_loop(
+    pool: Pool<Postgres>,
+    interval: Duration,
+    safety_window: Duration,
+) -> anyhow::Result<()> {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let released = release_stale_locks(&pool, safety_window).await?;
+
+        if released > 0 {
             tracing::warn! {
-              count = result.rows_affected(),
+              count = released,
               "Cleaned up jobs which were not executed properly."
             };
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_bytes_limit_is_clamped_to_ceiling() {
+        let ceiling = crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING;
+
+        assert_eq!(response_bytes_limit(None, None, ceiling), Some(ceiling));
+        assert_eq!(response_bytes_limit(None, Some(1024), ceiling), Some(1024));
+        assert_eq!(
+            response_bytes_limit(Some(512), Some(1024), ceiling),
+            Some(512)
+        );
+        assert_eq!(
+            response_bytes_limit(Some(i32::MAX), None, ceiling),
+            Some(ceiling)
+        );
+        assert_eq!(response_bytes_limit(None, Some(4096), 2048), Some(2048));
+    }
+
+    #[test]
+    fn test_record_stream_limiter_rejects_excess() {
+        let limiter = RecordStreamLimiter::new(2);
+
+        let first = limiter.acquire("drone_a");
+        let second = limiter.acquire("drone_a");
+        assert!(first.is_some());
+        assert!(second.is_some());
+
+        assert!(limiter.acquire("drone_a").is_none());
+        assert!(limiter.acquire("drone_b").is_some());
+
+        drop(first);
+        assert!(limiter.acquire("drone_a").is_some());
+    }
+
+    #[test]
+    fn test_drones_sharing_an_address_are_counted_apart() {
+        let request_from = |remote_addr: &str| {
+            let mut req = tonic::Request::new(());
+            req.extensions_mut()
+                .insert(tonic::transport::server::TcpConnectInfo {
+                    local_addr: None,
+                    remote_addr: Some(remote_addr.parse().unwrap()),
+                });
+            req
+        };
+
+        // Drones behind one NAT or load balancer each get their own streams.
+        assert_ne!(
+            record_stream_peer(&request_from("10.0.0.1:5000"), "drone_a"),
+            record_stream_peer(&request_from("10.0.0.1:5001"), "drone_b")
+        );
+        assert_eq!(
+            record_stream_peer(&request_from("10.0.0.1:5000"), "drone_a"),
+            record_stream_peer(&request_from("10.0.0.2:5000"), "drone_a")
+        );
+    }
+
+    async fn insert_tenant(
+        pool: &Pool<Postgres>,
+        max_concurrent_executions: Option<i32>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs,
+              max_concurrent_executions)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10, $1)
+            ",
+            max_concurrent_executions
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_a', 'GET', 'https://example.com', '{}')
+            "
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn insert_due_jobs(pool: &Pool<Postgres>, count: i32) -> anyhow::Result<()> {
+        for idx in 0..count {
+            sqlx::query!(
+                "
+                INSERT INTO scheduled_jobs (
+                  id, hash, region, tenant_id, scheduled_at, request_id, max_retries)
+                VALUES ($1, $2, 'na-east', 'tenant_a', now(), 'request_a', 0)
+                ",
+                format!("job_{idx}"),
+                idx
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    fn test_service(pool: &Pool<Postgres>) -> BrokerService {
+        BrokerService {
+            pool: pool.clone(),
+            key_ring: crate::secrets::KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: crate::broker::drone::CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: InvalidExecutedAtPolicy::Clamp,
+        }
+    }
+
+    async fn fetch_job_count(svc: &BrokerService) -> anyhow::Result<usize> {
+        fetch_job_count_with_capacity(svc, None).await
+    }
+
+    async fn fetch_job_count_with_capacity(
+        svc: &BrokerService,
+        max_jobs: Option<u32>,
+    ) -> anyhow::Result<usize> {
+        let req = tonic::Request::new(grpc::GetJobsRequest {
+            region: "na-east".to_string(),
+            max_jobs,
+        });
+        let stream = get_jobs(svc, req).await?.into_inner();
+        let jobs: Vec<_> = stream.collect().await;
+
+        Ok(jobs.len())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_get_jobs_respects_max_concurrent_executions(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        insert_tenant(&pool, Some(2)).await?;
+        insert_due_jobs(&pool, 4).await?;
+
+        let svc = test_service(&pool);
+
+        assert_eq!(fetch_job_count(&svc).await?, 2);
+        assert_eq!(fetch_job_count(&svc).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_get_jobs_respects_cron_max_concurrent(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES
+              ('request_cron', 'GET', 'https://example.com', '{}'),
+              ('request_run', 'GET', 'https://example.com', '{}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries, max_concurrent)
+            VALUES ('cron_a', 'na-east', 'request_cron', '* * * * *', 0, 1)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        for idx in 0..3 {
+            sqlx::query!(
+                "
+                INSERT INTO scheduled_jobs (
+                  id, hash, region, cron_job_id, scheduled_at, request_id, max_retries)
+                VALUES ($1, $2, 'na-east', 'cron_a', now(), 'request_run', 0)
+                ",
+                format!("cron_run_{idx}"),
+                idx
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        let svc = test_service(&pool);
+
+        assert_eq!(fetch_job_count(&svc).await?, 1);
+        assert_eq!(fetch_job_count(&svc).await?, 0);
+
+        sqlx::query!("UPDATE cron_jobs SET max_concurrent = 2 WHERE id = 'cron_a'")
+            .execute(&pool)
+            .await?;
+
+        assert_eq!(fetch_job_count(&svc).await?, 1);
+        assert_eq!(fetch_job_count(&svc).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_get_jobs_coalesces_missed_cron_runs(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES
+              ('request_coalescing', 'GET', 'https://example.com', '{}'),
+              ('request_bursting', 'GET', 'https://example.com', '{}'),
+              ('request_run', 'GET', 'https://example.com', '{}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries, coalesce_missed)
+            VALUES
+              ('cron_coalescing', 'na-east', 'request_coalescing', '* * * * *', 0, true),
+              ('cron_bursting', 'na-east', 'request_bursting', '* * * * *', 0, false)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        for cron_job_id in ["cron_coalescing", "cron_bursting"] {
+            for minutes_ago in 1..=3 {
+                sqlx::query!(
+                    "
+                    INSERT INTO scheduled_jobs (
+                      id, hash, region, cron_job_id, scheduled_at, request_id, max_retries)
+                    VALUES (
+                      $1, $2, 'na-east', $3,
+                      now() - make_interval(mins => $2), 'request_run', 0)
+                    ",
+                    format!("{cron_job_id}_{minutes_ago}"),
+                    minutes_ago,
+                    cron_job_id
+                )
+                .execute(&pool)
+                .await?;
+            }
+        }
+
+        let svc = test_service(&pool);
+        let req = tonic::Request::new(grpc::GetJobsRequest {
+            region: "na-east".to_string(),
+            max_jobs: None,
+        });
+        let mut job_ids = get_jobs(&svc, req)
+            .await?
+            .into_inner()
+            .map(|job| job.map(|job| job.job_id))
+            .collect::<Result<Vec<_>, _>>()
+            .await?;
+        job_ids.sort();
+
+        assert_eq!(
+            job_ids,
+            [
+                "cron_bursting_1",
+                "cron_bursting_2",
+                "cron_bursting_3",
+                "cron_coalescing_1"
+            ]
+        );
+
+        let coalesced = sqlx::query_scalar!(
+            "SELECT id FROM scheduled_jobs WHERE coalesced AND deleted_at IS NOT NULL ORDER BY id"
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(coalesced, ["cron_coalescing_2", "cron_coalescing_3"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_get_jobs_locks_at_most_max_jobs(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 5).await?;
+
+        let svc = test_service(&pool);
+
+        assert_eq!(fetch_job_count_with_capacity(&svc, Some(0)).await?, 0);
+        assert_eq!(fetch_job_count_with_capacity(&svc, Some(2)).await?, 2);
+
+        let locked = sqlx::query_scalar!(
+            r#"SELECT count(*) as "count!" FROM scheduled_jobs WHERE lock_nonce IS NOT NULL"#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(locked, 2);
+
+        assert_eq!(fetch_job_count(&svc).await?, 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_slow_consumer_does_not_hold_connection(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        // More jobs than the stream buffers, so the sender blocks until read.
+        insert_due_jobs(&pool, 40).await?;
+
+        let svc = test_service(&pool);
+        let req = tonic::Request::new(grpc::GetJobsRequest {
+            region: "na-east".to_string(),
+            max_jobs: None,
+        });
+        let stream = get_jobs(&svc, req).await?.into_inner();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while pool.num_idle() < pool.size() as usize {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "get_jobs held a connection while the drone wasn't reading"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let jobs: Vec<_> = stream.collect().await;
+        assert_eq!(jobs.len(), 40);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_duplicate_execution_is_dropped(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 1).await?;
+
+        // The cleanup released the first drone's lock and re-dispatched the job.
+        sqlx::query!("UPDATE scheduled_jobs SET lock_nonce = 2, times_locked = 2")
+            .execute(&pool)
+            .await?;
+
+        let execution = |lock_nonce| grpc::JobExecution {
+            job_id: "job_0".to_string(),
+            success: true,
+            lock_nonce,
+            response_error: Some("Received status 500: boom".to_string()),
+            req_method: "GET".to_string(),
+            req_url: "https://example.com".to_string(),
+            executed_at: Utc::now().timestamp(),
+            ..Default::default()
+        };
+
+        record_job_execution(&pool, &execution(1), InvalidExecutedAtPolicy::Clamp).await?;
+        record_job_execution(&pool, &execution(2), InvalidExecutedAtPolicy::Clamp).await?;
+
+        let executions = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM job_executions"#)
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(executions, 1);
+
+        let scheduled =
+            sqlx::query!("SELECT lock_nonce, execution_id FROM scheduled_jobs WHERE id = 'job_0'")
+                .fetch_one(&pool)
+                .await?;
+        assert!(scheduled.lock_nonce.is_none());
+        assert!(scheduled.execution_id.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_execution_records_executing_drone(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 1).await?;
+
+        let execution = grpc::JobExecution {
+            job_id: "job_0".to_string(),
+            success: true,
+            lock_nonce: 1,
+            req_method: "GET".to_string(),
+            req_url: "https://example.com".to_string(),
+            executed_at: Utc::now().timestamp(),
+            drone_id: Some("drone_a".to_string()),
+            ..Default::default()
+        };
+        record_job_execution(&pool, &execution, InvalidExecutedAtPolicy::Clamp).await?;
+
+        let drone_id = sqlx::query_scalar!(
+            "
+            SELECT exe.drone_id
+            FROM scheduled_jobs job
+            JOIN job_executions exe ON exe.id = job.execution_id
+            WHERE job.id = 'job_0'
+            "
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(drone_id.as_deref(), Some("drone_a"));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_invalid_executed_at_follows_policy(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 3).await?;
+
+        let execution = |job_id: &str| grpc::JobExecution {
+            job_id: job_id.to_string(),
+            success: true,
+            lock_nonce: 1,
+            req_method: "GET".to_string(),
+            req_url: "https://example.com".to_string(),
+            executed_at: i64::MAX,
+            ..Default::default()
+        };
+
+        let rejected =
+            record_job_execution(&pool, &execution("job_0"), InvalidExecutedAtPolicy::Reject).await;
+        assert!(rejected.is_err());
+
+        record_job_execution(&pool, &execution("job_1"), InvalidExecutedAtPolicy::Clamp).await?;
+        record_job_execution(&pool, &execution("job_2"), InvalidExecutedAtPolicy::Accept).await?;
+
+        let recorded = sqlx::query!(
+            "
+            SELECT job.id, exe.time_source
+            FROM scheduled_jobs job
+            JOIN job_executions exe ON exe.id = job.execution_id
+            ORDER BY job.id
+            "
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        let recorded: Vec<_> = recorded
+            .into_iter()
+            .map(|row| (row.id, row.time_source))
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![
+                ("job_1".to_string(), "broker".to_string()),
+                ("job_2".to_string(), "broker".to_string()),
+            ]
+        );
+
+        let valid = grpc::JobExecution {
+            executed_at: Utc::now().timestamp(),
+            ..execution("job_0")
+        };
+        record_job_execution(&pool, &valid, InvalidExecutedAtPolicy::Reject).await?;
+
+        let time_source = sqlx::query_scalar!(
+            "
+            SELECT exe.time_source
+            FROM scheduled_jobs job
+            JOIN job_executions exe ON exe.id = job.execution_id
+            WHERE job.id = 'job_0'
+            "
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(time_source, "drone");
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_conditional_cron_sends_validators_and_records_304(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES
+              ('request_cron', 'GET', 'https://example.com/feed', '{}'),
+              ('request_sent', 'GET', 'https://example.com/feed', '{}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries, conditional)
+            VALUES ('cron_a', 'na-east', 'request_cron', '* * * * *', 0, true)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO http_responses (id, status, headers, body)
+            VALUES (
+              'response_first', 200,
+              ARRAY['etag: "v1"', 'last-modified: Wed, 21 Oct 2015 07:28:00 GMT'],
+              'feed contents')
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO job_executions (id, executed_at, success, request_id, response_id, body_hash)
+            VALUES ('execution_first', now() - interval '1 minute', true, 'request_sent', 'response_first', 'hash_v1')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, cron_job_id, scheduled_at, request_id, execution_id, max_retries)
+            VALUES
+              ('cron_run_0', 0, 'na-east', 'cron_a', now() - interval '1 minute', 'request_cron', 'execution_first', 0),
+              ('cron_run_1', 1, 'na-east', 'cron_a', now(), 'request_cron', NULL, 0)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        let svc = test_service(&pool);
+        let req = tonic::Request::new(grpc::GetJobsRequest {
+            region: "na-east".to_string(),
+            max_jobs: None,
+        });
+        let jobs: Vec<_> = get_jobs(&svc, req).await?.into_inner().collect().await;
+        assert_eq!(jobs.len(), 1);
+        let job = jobs.into_iter().next().unwrap()?;
+
+        assert!(job.conditional);
+        assert_eq!(
+            job.headers.get("If-None-Match").map(String::as_str),
+            Some("\"v1\"")
+        );
+        assert_eq!(
+            job.headers.get("If-Modified-Since").map(String::as_str),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+
+        let execution = grpc::JobExecution {
+            job_id: job.job_id,
+            success: true,
+            lock_nonce: job.lock_nonce,
+            response: Some(grpc::Response {
+                status: 304,
+                body_hash: Some("hash_empty".to_string()),
+                ..Default::default()
+            }),
+            req_method: job.method,
+            req_url: job.url,
+            req_headers: job.headers,
+            executed_at: Utc::now().timestamp(),
+            ..Default::default()
+        };
+        record_job_execution(&pool, &execution, InvalidExecutedAtPolicy::Clamp).await?;
+
+        let recorded = sqlx::query!(
+            "
+            SELECT exe.success, exe.unchanged, exe.body_hash, res.status, res.body
+            FROM scheduled_jobs job
+            JOIN job_executions exe ON exe.id = job.execution_id
+            JOIN http_responses res ON res.id = exe.response_id
+            WHERE job.id = 'cron_run_1'
+            "
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert_eq!(recorded.success, Some(true));
+        assert!(recorded.unchanged);
+        assert!(recorded.body_hash.is_none());
+        assert_eq!(recorded.status, 304);
+        assert!(recorded.body.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_cron_pauses_after_consecutive_failures(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_cron', 'GET', 'https://example.com', '{}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries, pause_after_failures)
+            VALUES ('cron_a', 'na-east', 'request_cron', '* * * * *', 0, 3)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, cron_job_id, scheduled_at, request_id, max_retries, lock_nonce)
+            SELECT
+              'cron_run_' || n, n, 'na-east', 'cron_a', now() - make_interval(mins => 5 - n),
+              'request_cron', 0, CASE WHEN n < 5 THEN 1 END
+            FROM generate_series(0, 5) AS n
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        let execution = |run: i32, success| grpc::JobExecution {
+            job_id: format!("cron_run_{run}"),
+            success,
+            lock_nonce: 1,
+            req_method: "GET".to_string(),
+            req_url: "https://example.com".to_string(),
+            executed_at: Utc::now().timestamp(),
+            ..Default::default()
+        };
+
+        let cron = || {
+            sqlx::query!("SELECT paused, consecutive_failures FROM cron_jobs WHERE id = 'cron_a'")
+                .fetch_one(&pool)
+        };
+
+        // A success in between starts the count over.
+        for (run, success) in [(0, false), (1, true), (2, false), (3, false)] {
+            record_job_execution(
+                &pool,
+                &execution(run, success),
+                InvalidExecutedAtPolicy::Clamp,
+            )
+            .await?;
+        }
+
+        let before = cron().await?;
+        assert!(!before.paused);
+        assert_eq!(before.consecutive_failures, 2);
+
+        record_job_execution(&pool, &execution(4, false), InvalidExecutedAtPolicy::Clamp).await?;
+
+        let after = cron().await?;
+        assert!(after.paused);
+        assert_eq!(after.consecutive_failures, 3);
+
+        let pending = sqlx::query_scalar!(
+            r#"
+            SELECT count(*) as "count!" FROM scheduled_jobs
+            WHERE cron_job_id = 'cron_a' AND execution_id IS NULL
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(pending, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_batch_limit_is_capped() {
+        assert_eq!(job_batch_limit(None), DEFAULT_JOB_BATCH);
+        assert_eq!(job_batch_limit(Some(7)), 7);
+        assert_eq!(job_batch_limit(Some(u32::MAX)), MAX_JOB_BATCH);
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_get_jobs_skips_suspended_tenants(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 1).await?;
+
+        sqlx::query!("UPDATE tenants SET suspended = true WHERE id = 'tenant_a'")
+            .execute(&pool)
+            .await?;
+
+        let svc = test_service(&pool);
+        assert_eq!(fetch_job_count(&svc).await?, 0);
+
+        sqlx::query!("UPDATE tenants SET suspended = false WHERE id = 'tenant_a'")
+            .execute(&pool)
+            .await?;
+
+        assert_eq!(fetch_job_count(&svc).await?, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_release_stale_locks_respects_safety_window(
+        pool: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        insert_tenant(&pool, None).await?;
+        insert_due_jobs(&pool, 1).await?;
+
+        // Locked 60 seconds ago against the tenant's 30 second timeout.
+        sqlx::query!(
+            "UPDATE scheduled_jobs SET lock_nonce = extract(epoch from now())::int - 60, times_locked = 1"
+        )
+        .execute(&pool)
+        .await?;
+
+        let lock_nonce = || async {
+            sqlx::query_scalar!("SELECT lock_nonce FROM scheduled_jobs WHERE id = 'job_0'")
+                .fetch_one(&pool)
+                .await
+        };
+
+        release_stale_locks(&pool, Duration::from_secs(90)).await?;
+        assert!(lock_nonce().await?.is_some());
+
+        release_stale_locks(&pool, Duration::from_secs(10)).await?;
+        assert!(lock_nonce().await?.is_none());
 
-        tx.commit().await?;
+        Ok(())
     }
 }
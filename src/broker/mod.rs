@@ -1,23 +1,48 @@
 mod actor;
+mod capacity;
 mod drone;
 mod job;
 mod workflow;
 
+pub use job::InvalidExecutedAtPolicy;
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::anyhow;
 use sqlx::{Pool, Postgres};
-use tokio::select;
+use tokio::{fs, select};
 use tonic::Status;
-use tonic::transport::Server;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 
 use crate::grpc::broker_server::{Broker as BrokerTrait, BrokerServer};
-use crate::secrets::KeyRing;
+use crate::secrets::{KeyRing, Secret};
 use crate::{BrokerOptions, GLOBAL_CONFIG, grpc};
 
+/// gRPC metadata key drones use to identify themselves to the broker.
+pub const DRONE_ID_METADATA: &str = "rocktick-drone-id";
+/// gRPC metadata key carrying the drone's `Bearer` auth token.
+pub const DRONE_AUTH_METADATA: &str = "authorization";
+
 pub struct Config {
     port: usize,
     hostname: String,
     pool: Pool<Postgres>,
     key_ring: KeyRing,
     fallback_signing_key: String,
+    max_record_streams_per_drone: usize,
+    allow_unsigned_dispatch: bool,
+    max_response_bytes_ceiling: i64,
+    drone_auth_key: Option<String>,
+    cleanup_interval: Duration,
+    cleanup_safety_window: Duration,
+    no_capacity: capacity::NoCapacityPolicy,
+    min_checkin_interval: Duration,
+    invalid_executed_at: job::InvalidExecutedAtPolicy,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    tls_client_ca: Option<PathBuf>,
+    grpc_compression: bool,
 }
 
 impl Config {
@@ -28,6 +53,23 @@ impl Config {
             port: options.port,
             key_ring: options.key_ring,
             fallback_signing_key: options.fallback_signing_key,
+            max_record_streams_per_drone: options.max_record_streams_per_drone,
+            allow_unsigned_dispatch: options.allow_unsigned_dispatch,
+            max_response_bytes_ceiling: options.max_response_bytes_ceiling,
+            drone_auth_key: options.drone_auth_key,
+            cleanup_interval: Duration::from_secs(options.cleanup_interval_secs),
+            cleanup_safety_window: Duration::from_secs(options.cleanup_safety_secs),
+            no_capacity: capacity::NoCapacityPolicy {
+                regions: options.no_capacity_regions,
+                window: Duration::from_secs(options.no_capacity_window_secs),
+                grace: Duration::from_secs(options.no_capacity_grace_secs),
+            },
+            min_checkin_interval: Duration::from_millis(options.min_checkin_interval_ms),
+            invalid_executed_at: options.invalid_executed_at,
+            tls_cert: options.tls_cert,
+            tls_key: options.tls_key,
+            tls_client_ca: options.tls_client_ca,
+            grpc_compression: options.grpc_compression,
         }
     }
 }
@@ -37,68 +79,458 @@ struct BrokerService {
     pub pool: Pool<Postgres>,
     pub key_ring: KeyRing,
     pub fallback_signing_secret: String,
+    pub record_streams: job::RecordStreamLimiter,
+    pub max_response_bytes_ceiling: i64,
+    pub drone_auth_key: Option<String>,
+    pub checkins: drone::CheckinLimiter,
+    pub invalid_executed_at: job::InvalidExecutedAtPolicy,
+}
+
+impl BrokerService {
+    /// Rejects requests that don't carry the configured drone auth key.
+    fn authenticate<T>(&self, req: &tonic::Request<T>) -> Result<(), Status> {
+        let Some(expected_key) = &self.drone_auth_key else {
+            return Ok(());
+        };
+
+        let token = req
+            .metadata()
+            .get(DRONE_AUTH_METADATA)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        if token == Some(expected_key.as_str()) {
+            Ok(())
+        } else {
+            Err(Status::unauthenticated("Invalid drone auth key"))
+        }
+    }
 }
 
 #[tonic::async_trait]
 impl BrokerTrait for BrokerService {
+    #[tracing::instrument(name = "Broker::drone_checkin", skip_all)]
     async fn drone_checkin(
         &self,
         req: tonic::Request<grpc::DroneCheckinRequest>,
     ) -> Result<tonic::Response<grpc::DroneCheckinResponse>, Status> {
+        self.authenticate(&req)?;
         drone::handle_checkin(self, req).await
     }
 
     type GetDronesStream = drone::GetDronesStream;
 
+    #[tracing::instrument(name = "Broker::get_drones", skip_all)]
     async fn get_drones(
         &self,
         req: tonic::Request<grpc::GetDronesRequest>,
     ) -> Result<tonic::Response<Self::GetDronesStream>, Status> {
+        self.authenticate(&req)?;
         drone::handle_get_drones(self, req).await
     }
 
     type GetJobsStream = job::GetJobsStream;
 
+    #[tracing::instrument(name = "Broker::get_jobs", skip_all)]
     async fn get_jobs(
         &self,
         req: tonic::Request<grpc::GetJobsRequest>,
     ) -> Result<tonic::Response<Self::GetJobsStream>, Status> {
+        self.authenticate(&req)?;
         job::get_jobs(self, req).await
     }
 
     type RecordExecutionStream = job::RecordExecutionStream;
 
+    #[tracing::instrument(name = "Broker::record_execution", skip_all)]
     async fn record_execution(
         &self,
         req: tonic::Request<tonic::Streaming<grpc::JobExecution>>,
     ) -> Result<tonic::Response<Self::RecordExecutionStream>, Status> {
+        self.authenticate(&req)?;
         job::record_execution(self, req).await
     }
+
+    #[tracing::instrument(name = "Broker::drone_shutdown", skip_all)]
+    async fn drone_shutdown(
+        &self,
+        req: tonic::Request<grpc::DroneShutdownRequest>,
+    ) -> Result<tonic::Response<grpc::DroneShutdownResponse>, Status> {
+        self.authenticate(&req)?;
+        drone::handle_shutdown(self, req).await
+    }
+}
+
+/// Decrypts one tenant signing secret per master key, so a key ring that
+/// can't sign jobs is caught before any are dispatched.
+async fn check_key_ring(pool: &Pool<Postgres>, key_ring: &KeyRing) -> anyhow::Result<()> {
+    let secrets = sqlx::query_as!(
+        Secret,
+        r#"
+      SELECT DISTINCT ON (secret.master_key_id) secret.*
+      FROM secrets secret
+      INNER JOIN tenants tenant
+        ON tenant.current_signing_key = secret.id
+      WHERE tenant.deleted_at IS NULL
+      ORDER BY secret.master_key_id
+      "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for secret in secrets {
+        secret.decrypt(key_ring).map_err(|err| {
+            anyhow!(
+                "Key ring cannot decrypt signing secrets for master key {}: {err}",
+                secret.master_key_id
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn verify_key_ring(
+    pool: &Pool<Postgres>,
+    key_ring: &KeyRing,
+    allow_unsigned_dispatch: bool,
+) -> anyhow::Result<()> {
+    match check_key_ring(pool, key_ring).await {
+        Ok(()) => Ok(()),
+        Err(err) if allow_unsigned_dispatch => {
+            tracing::warn! {
+              %err,
+              "Key ring check failed, jobs for affected tenants will be dispatched UNSIGNED."
+            };
+            Ok(())
+        }
+        Err(err) => Err(err.context(
+            "Refusing to start the broker, set ALLOW_UNSIGNED_DISPATCH to dispatch unsigned jobs instead",
+        )),
+    }
+}
+
+async fn server_tls_config(config: &Config) -> anyhow::Result<Option<ServerTlsConfig>> {
+    let (Some(cert_path), Some(key_path)) = (&config.tls_cert, &config.tls_key) else {
+        return Ok(None);
+    };
+
+    let identity = Identity::from_pem(fs::read(cert_path).await?, fs::read(key_path).await?);
+    let mut tls_config = ServerTlsConfig::new().identity(identity);
+
+    if let Some(client_ca_path) = &config.tls_client_ca {
+        tls_config =
+            tls_config.client_ca_root(Certificate::from_pem(fs::read(client_ca_path).await?));
+    }
+
+    Ok(Some(tls_config))
+}
+
+/// Compressed messages are always accepted, so drones can turn compression on
+/// without the broker having to as well.
+fn broker_server(broker: BrokerService, grpc_compression: bool) -> BrokerServer<BrokerService> {
+    let svc = BrokerServer::new(broker).accept_compressed(CompressionEncoding::Gzip);
+
+    if grpc_compression {
+        svc.send_compressed(CompressionEncoding::Gzip)
+    } else {
+        svc
+    }
 }
 
 pub async fn start(config: Config) -> anyhow::Result<()> {
     let addr = format!("{}:{}", config.hostname, config.port).parse()?;
 
+    verify_key_ring(
+        &config.pool,
+        &config.key_ring,
+        config.allow_unsigned_dispatch,
+    )
+    .await?;
+
+    let tls_config = server_tls_config(&config).await?;
+
     if GLOBAL_CONFIG.get().unwrap().is_dev {
         println!("Outgoing Signing Key: {}", &config.fallback_signing_key)
     }
 
-    let job_cleanup_fut = job::run_job_cleanup_loop(config.pool.clone());
+    let job_cleanup_fut = job::run_job_cleanup_loop(
+        config.pool.clone(),
+        config.cleanup_interval,
+        config.cleanup_safety_window,
+    );
+    let no_capacity_fut = capacity::run_no_capacity_loop(
+        config.pool.clone(),
+        config.cleanup_interval,
+        config.no_capacity,
+    );
 
     let broker = BrokerService {
         pool: config.pool,
         key_ring: config.key_ring,
         fallback_signing_secret: config.fallback_signing_key,
+        record_streams: job::RecordStreamLimiter::new(config.max_record_streams_per_drone),
+        max_response_bytes_ceiling: config.max_response_bytes_ceiling,
+        drone_auth_key: config.drone_auth_key,
+        checkins: drone::CheckinLimiter::new(config.min_checkin_interval),
+        invalid_executed_at: config.invalid_executed_at,
     };
 
-    let svc = BrokerServer::new(broker);
+    let svc = broker_server(broker, config.grpc_compression);
+
+    let mut server = Server::builder();
 
-    let server_fut = Server::builder().add_service(svc).serve(addr);
+    if let Some(tls_config) = tls_config {
+        server = server.tls_config(tls_config)?;
+    } else {
+        tracing::warn!("Broker is serving gRPC without TLS.");
+    }
+
+    let server_fut = server.add_service(svc).serve(addr);
 
     select! {
       server_res = server_fut => {server_res?;},
       job_cleanup_res = job_cleanup_fut => {job_cleanup_res?;}
+      no_capacity_res = no_capacity_fut => {no_capacity_res?;}
     };
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn insert_signed_tenant(pool: &PgPool, key_ring: &KeyRing) -> anyhow::Result<()> {
+        for id in ["secret_current", "secret_next"] {
+            Secret::new(id.to_string(), format!("signature_{id}"), key_ring)?
+                .put(pool)
+                .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs,
+              current_signing_key, next_signing_key)
+            VALUES (
+              'tenant_a', 100, 100, 1, interval '1 minute',
+              30000, 3, 5, 1024, 1024, 7, 30, 10,
+              'secret_current', 'secret_next')
+            "#
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_verify_key_ring_policy(pool: PgPool) -> anyhow::Result<()> {
+        insert_signed_tenant(&pool, &KeyRing::dev()).await?;
+
+        let wrong_key_ring = KeyRing::parse_from_string(&format!("1:{}", "11".repeat(32)))?;
+
+        assert!(verify_key_ring(&pool, &KeyRing::dev(), false).await.is_ok());
+        assert!(
+            verify_key_ring(&pool, &wrong_key_ring, false)
+                .await
+                .is_err()
+        );
+        assert!(verify_key_ring(&pool, &wrong_key_ring, true).await.is_ok());
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_checkin_rejects_unauthenticated_drones(pool: PgPool) -> anyhow::Result<()> {
+        let svc = BrokerService {
+            pool,
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: job::RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: Some("drone-secret".to_string()),
+            checkins: drone::CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: job::InvalidExecutedAtPolicy::default(),
+        };
+
+        let checkin = |token: Option<&str>| {
+            let mut req = tonic::Request::new(grpc::DroneCheckinRequest {
+                drone_id: "drone_a".to_string(),
+                drone_ip: "10.0.0.1".to_string(),
+                drone_port: 30002,
+                drone_region: "na-east".to_string(),
+                drone_time_ms: chrono::Utc::now().timestamp_millis(),
+                region_affinities: Default::default(),
+            });
+
+            if let Some(token) = token {
+                req.metadata_mut()
+                    .insert(DRONE_AUTH_METADATA, token.parse().unwrap());
+            }
+
+            req
+        };
+
+        for token in [None, Some("Bearer wrong-secret"), Some("drone-secret")] {
+            let status = svc.drone_checkin(checkin(token)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        }
+
+        assert!(
+            svc.drone_checkin(checkin(Some("Bearer drone-secret")))
+                .await
+                .is_ok()
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_record_streams_past_the_cap_are_rejected(pool: PgPool) -> anyhow::Result<()> {
+        let svc = BrokerService {
+            pool,
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: job::RecordStreamLimiter::new(1),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: drone::CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: job::InvalidExecutedAtPolicy::default(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(broker_server(svc, false))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let mut client =
+            grpc::broker_client::BrokerClient::connect(format!("http://{addr}")).await?;
+
+        // Each stream stays open for as long as its sender is alive.
+        let mut senders = Vec::new();
+        let mut open_stream = |drone_id: &str| {
+            let (sender, receiver) = tokio::sync::mpsc::channel::<grpc::JobExecution>(1);
+            senders.push(sender);
+
+            let mut req =
+                tonic::Request::new(tokio_stream::wrappers::ReceiverStream::new(receiver));
+            req.metadata_mut()
+                .insert(DRONE_ID_METADATA, drone_id.parse().unwrap());
+            req
+        };
+
+        let first = client.record_execution(open_stream("drone_a")).await;
+        assert!(first.is_ok());
+
+        let status = client
+            .record_execution(open_stream("drone_a"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        // Another drone on the same address has streams of its own.
+        assert!(
+            client
+                .record_execution(open_stream("drone_b"))
+                .await
+                .is_ok()
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_large_execution_round_trips_compressed(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_a', 'GET', 'https://example.com', '{}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "
+            INSERT INTO scheduled_jobs (
+              id, hash, region, scheduled_at, request_id, max_retries, lock_nonce)
+            VALUES ('job_a', 0, 'na-east', now(), 'request_a', 0, 1)
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        let svc = BrokerService {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            fallback_signing_secret: "fallback".to_string(),
+            record_streams: job::RecordStreamLimiter::new(4),
+            max_response_bytes_ceiling: crate::DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            drone_auth_key: None,
+            checkins: drone::CheckinLimiter::new(Duration::ZERO),
+            invalid_executed_at: job::InvalidExecutedAtPolicy::default(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(broker_server(svc, true))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let mut client = grpc::broker_client::BrokerClient::connect(format!("http://{addr}"))
+            .await?
+            .send_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Gzip);
+
+        let body = "rocktick ".repeat(200_000);
+        let execution = grpc::JobExecution {
+            job_id: "job_a".to_string(),
+            success: true,
+            lock_nonce: 1,
+            response: Some(grpc::Response {
+                status: 200,
+                body: body.clone(),
+                bytes_used: body.len() as i64,
+                ..Default::default()
+            }),
+            req_method: "GET".to_string(),
+            req_url: "https://example.com".to_string(),
+            executed_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        };
+
+        let mut req = tonic::Request::new(tokio_stream::iter([execution]));
+        req.metadata_mut()
+            .insert(DRONE_ID_METADATA, "drone_a".parse()?);
+
+        let mut acks = client.record_execution(req).await?.into_inner();
+        let ack = acks.message().await?;
+        assert_eq!(ack.map(|ack| ack.job_id).as_deref(), Some("job_a"));
+
+        let stored = sqlx::query_scalar!(
+            "
+            SELECT res.body
+            FROM scheduled_jobs job
+            JOIN job_executions exe ON exe.id = job.execution_id
+            JOIN http_responses res ON res.id = exe.response_id
+            WHERE job.id = 'job_a'
+            "
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(stored, body);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use replace_err::ReplaceErr;
+use reqwest::Client;
+
+use crate::drone::util::PublicResolver;
+
+/// Past this many clients the cache starts over, so a drone serving many
+/// distinct hosts doesn't hold on to every one of them.
+const MAX_CACHED_CLIENTS: usize = 256;
+
+/// The client settings a request can't override, jobs agreeing on all of
+/// them can share a client and the connections it has pooled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientKey {
+    pub host: String,
+    pub ip_addr: SocketAddr,
+    pub skip_tls_verify: bool,
+    pub follow_redirects: Option<i32>,
+    pub decompress: bool,
+    pub allow_private_addrs: bool,
+}
+
+impl ClientKey {
+    fn build(&self, fresh_connection: bool) -> Result<Client, String> {
+        // The job's own host is pinned to the address that was already
+        // checked, any other host a redirect leads to goes through the
+        // public resolver.
+        Client::builder()
+            .resolve(&self.host, self.ip_addr)
+            .dns_resolver(Arc::new(PublicResolver {
+                allow_private_addrs: self.allow_private_addrs,
+            }))
+            .redirect(super::jobs::redirect_policy(
+                self.follow_redirects,
+                self.allow_private_addrs,
+            ))
+            .pool_max_idle_per_host(super::jobs::max_idle_per_host(fresh_connection))
+            .danger_accept_invalid_certs(self.skip_tls_verify)
+            // Decoding happens as the body streams in, so max_response_bytes
+            // caps the decoded payload rather than the compressed one.
+            .gzip(self.decompress)
+            .deflate(self.decompress)
+            .brotli(self.decompress)
+            .build()
+            .replace_err("Unable to build client.")
+    }
+}
+
+/// Clients shared between jobs, so requests to the same host reuse pooled
+/// connections rather than paying for a new handshake every time.
+#[derive(Debug, Clone, Default)]
+pub struct ClientCache {
+    clients: Arc<Mutex<HashMap<ClientKey, Client>>>,
+}
+
+impl ClientCache {
+    /// A client for `key`. Jobs asking for a fresh connection always get a
+    /// new one that never enters the cache.
+    pub fn client(&self, key: ClientKey, fresh_connection: bool) -> Result<Client, String> {
+        if fresh_connection {
+            return key.build(true);
+        }
+
+        let mut clients = self.clients.lock().unwrap_or_else(|err| err.into_inner());
+
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        if clients.len() >= MAX_CACHED_CLIENTS {
+            clients.clear();
+        }
+
+        let client = key.build(false)?;
+        clients.insert(key, client.clone());
+
+        Ok(client)
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(host: &str, port: u16) -> ClientKey {
+        ClientKey {
+            host: host.to_string(),
+            ip_addr: SocketAddr::from(([93, 184, 216, 34], port)),
+            skip_tls_verify: false,
+            follow_redirects: None,
+            decompress: true,
+            allow_private_addrs: false,
+        }
+    }
+
+    #[test]
+    fn test_clients_are_shared_by_matching_jobs() {
+        let cache = ClientCache::default();
+
+        cache.client(key("example.com", 443), false).unwrap();
+        cache.client(key("example.com", 443), false).unwrap();
+        assert_eq!(cache.len(), 1);
+
+        cache.client(key("example.org", 443), false).unwrap();
+        cache
+            .client(
+                ClientKey {
+                    skip_tls_verify: true,
+                    ..key("example.com", 443)
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(cache.len(), 3);
+
+        cache.client(key("example.net", 443), true).unwrap();
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_cache_starts_over_once_full() {
+        let cache = ClientCache::default();
+
+        for port in 0..MAX_CACHED_CLIENTS as u16 {
+            cache.client(key("example.com", port), false).unwrap();
+        }
+        assert_eq!(cache.len(), MAX_CACHED_CLIENTS);
+
+        cache.client(key("example.org", 443), false).unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -2,24 +2,25 @@ use std::{net::IpAddr, time::Duration};
 
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
-use tonic::Request;
+use tokio::time::Instant;
 
 use crate::{
     drone::{Drone, DroneState},
-    grpc::{self, broker_client::BrokerClient},
+    grpc,
 };
 
 async fn check_in(state: &DroneState) -> anyhow::Result<Duration> {
-    let mut client = BrokerClient::connect(state.broker_url.clone()).await?;
+    let mut client = state.broker_client().await?;
 
     let checkin_response = client
-        .drone_checkin(Request::new(grpc::DroneCheckinRequest {
+        .drone_checkin(state.broker_request(grpc::DroneCheckinRequest {
             drone_id: state.id.clone(),
             drone_ip: state.ip.to_string(),
             drone_port: state.port as i64,
             drone_region: state.region.clone(),
             drone_time_ms: Utc::now().timestamp_millis(),
-        }))
+            region_affinities: state.region_affinities.clone(),
+        })?)
         .await?
         .into_inner();
 
@@ -30,22 +31,77 @@ async fn check_in(state: &DroneState) -> anyhow::Result<Duration> {
     Ok(time_until_checkin.to_std().unwrap_or(Duration::ZERO))
 }
 
-pub async fn start_checkin_loop(state: DroneState) -> anyhow::Result<()> {
-    let mut time_to_next_checkin = check_in(&state).await?;
+const INITIAL_CHECKIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_CHECKIN_BACKOFF: Duration = Duration::from_secs(5);
+
+async fn retry_until_checked_in<F, Fut>(
+    timeout: Duration,
+    initial_backoff: Duration,
+    mut attempt: F,
+) -> anyhow::Result<Duration>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<Duration>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut backoff = initial_backoff;
 
+    loop {
+        match attempt().await {
+            Ok(time_to_next_checkin) => return Ok(time_to_next_checkin),
+            Err(err) if Instant::now() + backoff < deadline => {
+                tracing::warn!(error = %err, ?backoff, "Broker not reachable, retrying check-in.");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_CHECKIN_BACKOFF);
+            }
+            Err(err) => {
+                return Err(err.context(format!("Broker was not reachable within {timeout:?}")));
+            }
+        }
+    }
+}
+
+/// Performs the drone's first check-in, retrying with backoff so a drone
+/// started alongside the broker waits for it instead of exiting.
+pub async fn initial_check_in(state: &DroneState) -> anyhow::Result<Duration> {
+    retry_until_checked_in(
+        state.startup_checkin_timeout,
+        INITIAL_CHECKIN_BACKOFF,
+        || check_in(state),
+    )
+    .await
+}
+
+pub async fn start_checkin_loop(
+    state: DroneState,
+    mut time_to_next_checkin: Duration,
+) -> anyhow::Result<()> {
     loop {
         tokio::time::sleep(time_to_next_checkin).await;
         time_to_next_checkin = check_in(&state).await?;
     }
 }
 
+/// Tells the broker this drone is gone, so peers stop counting it right away.
+pub async fn unregister(state: &DroneState) -> anyhow::Result<()> {
+    let mut client = state.broker_client().await?;
+
+    client
+        .drone_shutdown(state.broker_request(grpc::DroneShutdownRequest {
+            drone_id: state.id.clone(),
+        })?)
+        .await?;
+
+    Ok(())
+}
+
 async fn refresh_drones(state: &DroneState) -> anyhow::Result<()> {
-    let mut client = BrokerClient::connect(state.broker_url.clone()).await?;
+    let mut client = state.broker_client().await?;
 
     let mut drones_stream = client
-        .get_drones(Request::new(grpc::GetDronesRequest {
+        .get_drones(state.broker_request(grpc::GetDronesRequest {
             drone_id: state.id.clone(),
-        }))
+        })?)
         .await?
         .into_inner();
 
@@ -77,3 +133,45 @@ pub async fn start_refresh_loop(state: DroneState) -> anyhow::Result<()> {
         refresh_drones(&state).await?;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_initial_check_in_waits_for_broker() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let result =
+            retry_until_checked_in(Duration::from_secs(5), Duration::from_millis(1), || {
+                let attempts = attempts.clone();
+                async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 3 {
+                        return Err(anyhow!("transport error: connection refused"));
+                    }
+                    Ok(Duration::from_secs(30))
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), Duration::from_secs(30));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_initial_check_in_gives_up_after_timeout() {
+        let result = retry_until_checked_in(
+            Duration::from_millis(50),
+            Duration::from_millis(1),
+            || async { Err(anyhow!("transport error: connection refused")) },
+        )
+        .await;
+
+        assert!(result.is_err_and(|err| err.to_string().contains("not reachable")));
+    }
+}
@@ -1,49 +1,332 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::atomic::Ordering,
+    time::Duration,
+};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rand::random;
 use replace_err::ReplaceErr;
-use reqwest::Client;
-use tokio::{select, sync::mpsc};
-use tokio_stream::{StreamExt, wrappers::ReceiverStream};
-use tonic::Request;
+use reqwest::{
+    StatusCode,
+    header::{CONTENT_LENGTH, HeaderMap, HeaderName, HeaderValue, RETRY_AFTER, TRANSFER_ENCODING},
+};
+use sha2::{Digest, Sha256};
+use tokio::{
+    select,
+    sync::{OwnedSemaphorePermit, mpsc},
+    task::JoinHandle,
+    time::{Instant, timeout_at},
+};
+use tokio_stream::{Stream, StreamExt, wrappers::ReceiverStream};
+use tracing::Instrument;
 
 use crate::{
-    drone::{DroneState, util::resolve_public_ip},
-    grpc::{self, broker_client::BrokerClient},
+    GLOBAL_CONFIG,
+    broker::DRONE_ID_METADATA,
+    drone::{
+        DroneState,
+        clients::{ClientCache, ClientKey},
+        store::DroneStore,
+        util::{is_private_ip, resolve_override_addr, resolve_public_ip},
+    },
+    grpc, telemetry,
 };
 
+const MAX_HEADER_VALUE_BYTES: usize = 8 * 1024;
+
+/// Starts the `response_error` of a job whose redirects led back to a url it
+/// had already requested.
+const REDIRECT_LOOP_ERROR: &str = "redirect_loop";
+
+fn build_header_map(job_id: &str, headers: HashMap<String, String>) -> Result<HeaderMap, String> {
+    let mut header_map = HeaderMap::with_capacity(headers.len());
+
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| {
+            tracing::warn!(job_id, header = %name, "Rejecting invalid header name.");
+            format!("Invalid header name: {name:?}")
+        })?;
+
+        if value.len() > MAX_HEADER_VALUE_BYTES {
+            tracing::warn!(job_id, header = %name, "Rejecting oversized header value.");
+            return Err(format!(
+                "Header {name} exceeds {MAX_HEADER_VALUE_BYTES} bytes."
+            ));
+        }
+
+        // HeaderValue rejects CR, LF and other control characters, which
+        // prevents stored values from splitting into additional headers.
+        let header_value = HeaderValue::from_str(value.trim()).map_err(|_| {
+            tracing::warn!(job_id, header = %name, "Rejecting invalid header value.");
+            format!("Invalid value for header {name}.")
+        })?;
+
+        header_map.append(header_name, header_value);
+    }
+
+    Ok(header_map)
+}
+
+/// Idle connections the client may keep per host. Jobs that ask for a fresh
+/// connection keep none, so the connection is never reused by a later
+/// request; this gives up the saved handshake that pooling would otherwise
+/// provide.
+pub(super) fn max_idle_per_host(fresh_connection: bool) -> usize {
+    if fresh_connection { 0 } else { usize::MAX }
+}
+
+/// Whether a redirect to `url` may be followed after the `previous` requests
+/// of the job. Hosts given by name are checked as they resolve, so only ip
+/// literals are looked at here.
+fn check_redirect(
+    url: &reqwest::Url,
+    previous: &[reqwest::Url],
+    max_hops: usize,
+    allow_private_addrs: bool,
+) -> Result<(), String> {
+    if previous.contains(url) {
+        return Err(format!(
+            "{REDIRECT_LOOP_ERROR}: {url} was already requested."
+        ));
+    }
+
+    if previous.len() > max_hops {
+        return Err(format!("Stopped after following {max_hops} redirects."));
+    }
+
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => return Ok(()),
+    };
+
+    if !allow_private_addrs && is_private_ip(&ip) {
+        return Err(format!(
+            "Refusing to follow a redirect to private address {ip}."
+        ));
+    }
+
+    Ok(())
+}
+
+/// The redirect loop `check_redirect` stopped the request for, if any, as
+/// reqwest buries it among its own error sources.
+fn redirect_loop_error(err: &reqwest::Error) -> Option<String> {
+    let mut source = std::error::Error::source(err);
+
+    while let Some(err) = source {
+        let message = err.to_string();
+        if message.starts_with(REDIRECT_LOOP_ERROR) {
+            return Some(message);
+        }
+        source = err.source();
+    }
+
+    None
+}
+
+/// Redirects are only followed for jobs that ask to, up to their limit.
+pub(super) fn redirect_policy(
+    follow_redirects: Option<i32>,
+    allow_private_addrs: bool,
+) -> reqwest::redirect::Policy {
+    let max_hops = follow_redirects
+        .and_then(|follow_redirects| usize::try_from(follow_redirects).ok())
+        .unwrap_or(0);
+
+    if max_hops == 0 {
+        return reqwest::redirect::Policy::none();
+    }
+
+    reqwest::redirect::Policy::custom(move |attempt| {
+        match check_redirect(
+            attempt.url(),
+            attempt.previous(),
+            max_hops,
+            allow_private_addrs,
+        ) {
+            Ok(()) => attempt.follow(),
+            Err(err) => attempt.error(err),
+        }
+    })
+}
+
+/// Whether tls verification is skipped for the job. Jobs asking to skip it
+/// are refused unless the drone allows insecure jobs.
+fn skip_tls_verify(job: &grpc::JobSpec, allow_insecure_jobs: bool) -> Result<bool, String> {
+    match (job.insecure_skip_tls_verify, allow_insecure_jobs) {
+        (false, _) => Ok(false),
+        (true, true) => Ok(true),
+        (true, false) => Err(
+            "Job requested insecure_skip_tls_verify, but this drone does not allow insecure jobs."
+                .to_string(),
+        ),
+    }
+}
+
+/// Whether the target's status counts as a successful run. A conditional
+/// job's `304 Not Modified` means the resource is unchanged, not an error.
+fn response_succeeded(status: StatusCode, job: &grpc::JobSpec) -> bool {
+    status.is_success() || (job.conditional && status == StatusCode::NOT_MODIFIED)
+}
+
+/// Frames the body the way the job asked. A streamed body has no known
+/// length, so it goes out with `Transfer-Encoding: chunked`, while a sized one
+/// gets a `Content-Length`. `auto` leaves it to reqwest, which sizes a string.
+fn request_body(body: String, transfer_encoding: &str) -> reqwest::Body {
+    match transfer_encoding {
+        "chunked" => reqwest::Body::wrap_stream(futures::stream::once(async move {
+            Ok::<_, std::io::Error>(body)
+        })),
+        _ => reqwest::Body::from(body),
+    }
+}
+
+// The api rejects negative limits, but a value that slips through is clamped
+// to zero instead of wrapping to an enormous unsigned one.
+fn request_timeout(job: &grpc::JobSpec) -> Duration {
+    Duration::from_millis(u64::try_from(job.timeout_ms).unwrap_or(0))
+}
+
+fn response_bytes_limit(job: &grpc::JobSpec) -> Option<usize> {
+    job.max_response_bytes
+        .map(|max_response_bytes| usize::try_from(max_response_bytes).unwrap_or(0))
+}
+
+/// Appends `chunk` to `body` without going past `max_bytes`, returning
+/// whether any of it had to be dropped.
+fn append_capped(body: &mut Vec<u8>, chunk: &[u8], max_bytes: Option<usize>) -> bool {
+    let Some(max_bytes) = max_bytes else {
+        body.extend_from_slice(chunk);
+        return false;
+    };
+
+    let limit_left = max_bytes.saturating_sub(body.len());
+
+    if chunk.len() > limit_left {
+        body.extend_from_slice(&chunk[..limit_left]);
+        return true;
+    }
+
+    body.extend_from_slice(chunk);
+    false
+}
+
+fn body_read_budget(job: &grpc::JobSpec) -> Option<Duration> {
+    job.body_read_timeout_ms.map(|body_read_timeout_ms| {
+        Duration::from_millis(u64::try_from(body_read_timeout_ms).unwrap_or(0))
+    })
+}
+
+/// The part of a response body that was read, and why reading stopped early.
+#[derive(Debug, Default)]
+struct CapturedBody {
+    bytes: Vec<u8>,
+    /// Reading stopped at the job's max_response_bytes.
+    truncated: bool,
+    /// Reading stopped once the job's body read budget ran out.
+    body_read_truncated: bool,
+}
+
+/// Reads `stream` up to `max_bytes`, giving up once `read_budget` has passed
+/// so a slow body doesn't hold the drone for the whole request timeout.
+async fn read_body<S, B, E>(
+    mut stream: S,
+    max_bytes: Option<usize>,
+    read_budget: Option<Duration>,
+) -> CapturedBody
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let deadline = read_budget.map(|budget| Instant::now() + budget);
+    let mut captured = CapturedBody::default();
+
+    loop {
+        let item = match deadline {
+            Some(deadline) => match timeout_at(deadline, stream.next()).await {
+                Ok(item) => item,
+                Err(_) => {
+                    captured.body_read_truncated = true;
+                    break;
+                }
+            },
+            None => stream.next().await,
+        };
+
+        let Some(Ok(chunk)) = item else {
+            break;
+        };
+
+        if append_capped(&mut captured.bytes, chunk.as_ref(), max_bytes) {
+            captured.truncated = true;
+            break;
+        }
+    }
+
+    captured
+}
+
+/// Builds the response to report from the raw captured body. Bytes that
+/// aren't valid utf-8 are replaced in `body` but still count in `bytes_used`.
+fn captured_response(
+    status: i64,
+    headers: HashMap<String, String>,
+    captured: &CapturedBody,
+) -> grpc::Response {
+    grpc::Response {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&captured.bytes).to_string(),
+        body_hash: Some(hash_body(&captured.bytes)),
+        truncated: captured.truncated,
+        bytes_used: captured.bytes.len() as i64,
+        body_read_truncated: captured.body_read_truncated,
+    }
+}
+
 async fn send_request_to_ip(
-    job_id: &str,
-    url: &str,
+    job: &grpc::JobSpec,
     ip_addr: SocketAddr,
-    method: String,
-    headers: HashMap<String, String>,
-    body: Option<String>,
-    timeout_ms: u64,
+    skip_tls_verify: bool,
+    decompress: bool,
+    clients: &ClientCache,
 ) -> Result<reqwest::Response, String> {
-    let url = url::Url::parse(url).replace_err("Invalid URL")?;
+    let url = url::Url::parse(&job.url).replace_err("Invalid URL")?;
     let host = url.host_str().ok_or("Invalid host.")?;
 
-    let client = Client::builder()
-        .resolve(host, ip_addr)
-        .timeout(Duration::from_millis(timeout_ms))
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .replace_err("Unable to build client.")?;
+    let client = clients.client(
+        ClientKey {
+            host: host.to_string(),
+            ip_addr,
+            skip_tls_verify,
+            follow_redirects: job.follow_redirects,
+            decompress,
+            allow_private_addrs: GLOBAL_CONFIG.get().is_some_and(|config| config.is_dev),
+        },
+        job.fresh_connection,
+    )?;
 
-    let method = method.parse().replace_err("Invalid method.")?;
+    let method = job.method.parse().replace_err("Invalid method.")?;
 
-    let mut req = client.request(method, url);
+    let mut headers = build_header_map(&job.job_id, job.headers.clone())?;
 
-    for (header_name, value) in headers {
-        req = req.header(header_name, value);
-    }
+    // Framing follows from the body below, so stored framing headers could
+    // only contradict what is actually sent.
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(TRANSFER_ENCODING);
 
-    req = req.header("Rocktick-Job-Id", job_id);
+    let mut req = client
+        .request(method, url)
+        .timeout(request_timeout(job))
+        .headers(headers);
 
-    if let Some(body) = body {
-        req = req.body(body);
+    req = req.header("Rocktick-Job-Id", &job.job_id);
+
+    if let Some(body) = job.body.clone() {
+        req = req.body(request_body(body, &job.transfer_encoding));
     }
 
     let response = req.send().await.map_err(|err| {
@@ -51,17 +334,77 @@ async fn send_request_to_ip(
             return format!("Request timed out: {err:?}");
         }
 
+        if let Some(redirect_loop) = redirect_loop_error(&err) {
+            return redirect_loop;
+        }
+
         format!("Error sending request {err:?}")
     })?;
 
     Ok(response)
 }
 
-async fn run_job(job: grpc::JobSpec, state: DroneState) {
+fn failure_snippet(status: i64, body: &str, max_bytes: usize) -> String {
+    if body.len() <= max_bytes {
+        return format!("Received status {status}: {body}");
+    }
+
+    let mut end = max_bytes;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("Received status {status}: {}... (truncated)", &body[..end])
+}
+
+/// Reads a `Retry-After` value, either delay seconds or an http date, as
+/// the seconds left to wait from `now`.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<i64> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Some(seconds.max(0));
+    }
+
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((retry_at.with_timezone(&Utc) - now).num_seconds().max(0))
+}
+
+fn hash_body(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Substitutes `{{scheduled_at}}` (unix seconds) and `{{job_id}}` in the
+/// template. Any other `{{...}}` is left as written.
+fn render_template(template: &str, job: &grpc::JobSpec) -> String {
+    template
+        .replace("{{scheduled_at}}", &job.scheduled_at.to_string())
+        .replace("{{job_id}}", &job.job_id)
+}
+
+async fn run_job(job: grpc::JobSpec, state: DroneState, _permit: OwnedSemaphorePermit) {
+    let span = tracing::info_span!("Drone::run_job", job_id = %job.job_id);
+    telemetry::set_parent(&span, job.traceparent.as_deref());
+
+    execute_job(job, state.clone()).instrument(span).await;
+
+    state.jobs_in_flight.fetch_sub(1, Ordering::SeqCst);
+}
+
+async fn execute_job(mut job: grpc::JobSpec, state: DroneState) {
+    job.url = render_template(&job.url, &job);
+    job.body = job.body.as_deref().map(|body| render_template(body, &job));
+
     // check if the ip address is unallowed
-    let public_addr = resolve_public_ip(&job.url)
-        .await
-        .ok_or("Unable to resolve a public ip address.");
+    let public_addr = match job.resolve_override.as_deref() {
+        Some(resolve_override) => resolve_override_addr(
+            &job.url,
+            resolve_override,
+            GLOBAL_CONFIG.get().is_some_and(|config| config.is_dev),
+        ),
+        None => resolve_public_ip(&job.url).await,
+    }
+    .ok_or("Unable to resolve a public ip address.");
 
     let mut millis_until = 0;
     let now = Utc::now().timestamp_millis();
@@ -81,73 +424,119 @@ async fn run_job(job: grpc::JobSpec, state: DroneState) {
       "Executing job."
     };
     let executed_at = Utc::now().timestamp();
-    let response = match public_addr {
-        Ok(addr) => {
-            send_request_to_ip(
-                &job.job_id,
-                &job.url,
+    let skip_verify = skip_tls_verify(&job, state.allow_insecure_jobs);
+    let tls_verify_skipped = skip_verify == Ok(true);
+
+    if state.record_started_executions {
+        let started = grpc::JobExecution {
+            job_id: job.job_id.clone(),
+            lock_nonce: job.lock_nonce,
+            req_method: job.method.clone(),
+            req_url: job.url.clone(),
+            req_headers: job.headers.clone(),
+            req_body: job.body.clone(),
+            executed_at,
+            tls_verify_skipped,
+            ..Default::default()
+        };
+
+        if let Err(error) = state.store.record_execution_start(&started).await {
+            tracing::error! {
+              %error,
+              job_id = job.job_id,
+              "Failed to record job execution start."
+            };
+        }
+    }
+
+    if tls_verify_skipped {
+        tracing::warn! {
+          job_id = job.job_id,
+          url = job.url,
+          "SKIPPING TLS CERTIFICATE VERIFICATION for insecure job."
+        };
+    }
+
+    let mut duration_ms = None;
+
+    let response = match (public_addr, skip_verify) {
+        (Ok(addr), Ok(skip_verify)) => {
+            let sent_at = Instant::now();
+            let response = send_request_to_ip(
+                &job,
                 addr,
-                job.method.clone(),
-                job.headers.clone(),
-                job.body.clone(),
-                job.timeout_ms as u64,
+                skip_verify,
+                state.decompress_responses,
+                &state.clients,
             )
-            .await
+            .await;
+
+            duration_ms = Some(sent_at.elapsed().as_millis() as i64);
+            response
         }
-        Err(err) => Err(err.to_string()),
+        (Err(err), _) => Err(err.to_string()),
+        (_, Err(err)) => Err(err),
     };
 
     let execution = match response {
         Ok(res) => {
-            let success = res.status().is_success();
+            let success = response_succeeded(res.status(), &job);
             let status = res.status().as_u16() as i64;
+            let retry_after_secs = if matches!(status, 429 | 503) {
+                res.headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, Utc::now()))
+            } else {
+                None
+            };
             let headers = res
                 .headers()
                 .iter()
                 .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
                 .collect();
 
-            let mut body_bytes = Vec::new();
-            let mut stream = res.bytes_stream();
-
-            while let Some(item) = stream.next().await {
-                if let Ok(chunk) = item {
-                    if let Some(max_response_bytes) = job.max_response_bytes {
-                        let limit_left = max_response_bytes as usize - body_bytes.len();
-
-                        if limit_left == 0 {
-                            break;
-                        }
-
-                        if chunk.len() > limit_left {
-                            body_bytes.extend_from_slice(&chunk[..limit_left]);
-                            break;
-                        }
-                    }
+            let captured = read_body(
+                res.bytes_stream(),
+                response_bytes_limit(&job),
+                body_read_budget(&job),
+            )
+            .await;
 
-                    body_bytes.extend_from_slice(&chunk);
-                } else {
-                    break;
-                }
+            if captured.body_read_truncated {
+                tracing::warn! {
+                  job_id = job.job_id,
+                  "Stopped reading slow response body at the job's body read timeout."
+                };
             }
 
-            let text = String::from_utf8_lossy(&body_bytes).to_string();
+            let response = captured_response(status, headers, &captured);
+
+            let response_error = if success {
+                None
+            } else {
+                Some(failure_snippet(
+                    status,
+                    &response.body,
+                    state.max_body_log_bytes,
+                ))
+            };
 
             grpc::JobExecution {
                 job_id: job.job_id,
                 success,
                 lock_nonce: job.lock_nonce,
-                response: Some(grpc::Response {
-                    status,
-                    headers,
-                    body: text,
-                }),
-                response_error: None,
+                response: Some(response),
+                response_error,
                 req_method: job.method,
                 req_url: job.url,
                 req_headers: job.headers,
                 req_body: job.body,
                 executed_at,
+                retry_after_secs,
+                tls_verify_skipped,
+                drone_id: Some(state.id.clone()),
+                duration_ms,
             }
         }
         Err(error) => grpc::JobExecution {
@@ -161,18 +550,37 @@ async fn run_job(job: grpc::JobSpec, state: DroneState) {
             req_headers: job.headers,
             req_body: job.body,
             executed_at,
+            retry_after_secs: None,
+            tls_verify_skipped,
+            drone_id: Some(state.id.clone()),
+            duration_ms,
         },
     };
 
-    state.exec_results.lock().await.push(execution);
+    let job_id = execution.job_id.clone();
+
+    if let Err(error) = state.store.insert_execution(execution, true).await {
+        tracing::error! {
+          %error,
+          job_id,
+          "Failed to store job execution."
+        };
+    }
 }
 
 async fn fetch_and_start_jobs(state: DroneState) -> anyhow::Result<()> {
-    let mut client = BrokerClient::connect(state.broker_url.clone()).await?;
+    let capacity = state.job_capacity();
+
+    if capacity == 0 {
+        return Ok(());
+    }
+
+    let mut client = state.broker_client().await?;
     let mut jobs_stream = client
-        .get_jobs(Request::new(grpc::GetJobsRequest {
+        .get_jobs(state.broker_request(grpc::GetJobsRequest {
             region: state.region.clone(),
-        }))
+            max_jobs: Some(capacity),
+        })?)
         .await?
         .into_inner();
 
@@ -187,7 +595,16 @@ async fn fetch_and_start_jobs(state: DroneState) -> anyhow::Result<()> {
                     break;
                 }
                 Ok(Some(job)) => {
-                    tokio::spawn(run_job(job, state.clone()));
+                    state.jobs_in_flight.fetch_add(1, Ordering::SeqCst);
+
+                    // Waiting here holds back the rest of the stream until a
+                    // running job finishes.
+                    let Ok(permit) = state.job_permits.clone().acquire_owned().await else {
+                        state.jobs_in_flight.fetch_sub(1, Ordering::SeqCst);
+                        break;
+                    };
+
+                    tokio::spawn(run_job(job, state.clone(), permit));
                 }
             }
         }
@@ -203,35 +620,52 @@ async fn poll_jobs_loop(state: DroneState) -> anyhow::Result<()> {
     }
 }
 
-async fn submit_job_results(state: DroneState) -> anyhow::Result<()> {
-    let mut client = BrokerClient::connect(state.broker_url.clone()).await?;
-    let execution_results: Vec<grpc::JobExecution> =
-        state.exec_results.lock().await.drain(..).collect();
+const MAX_RESULTS_PER_SUBMISSION: usize = 500;
+
+/// Marks up to a submission's worth of stored executions as pending under
+/// `nonce`. Whatever the broker doesn't acknowledge goes back to local once
+/// the submission ends.
+async fn claim_unsynced_results(
+    store: &DroneStore,
+    nonce: i64,
+) -> anyhow::Result<Vec<grpc::JobExecution>> {
+    let mut execution_results = Vec::new();
+
+    while execution_results.len() < MAX_RESULTS_PER_SUBMISSION {
+        match store.get_job_to_sync(nonce).await? {
+            Some(execution) => execution_results.push(execution),
+            None => break,
+        }
+    }
+
+    Ok(execution_results)
+}
+
+/// Sends stored results to the broker, returning the task that waits for
+/// its acknowledgements when there was anything to send.
+async fn submit_job_results(state: DroneState) -> anyhow::Result<Option<JoinHandle<()>>> {
+    let mut client = state.broker_client().await?;
+
+    let nonce = random::<i64>();
+    let execution_results = claim_unsynced_results(&state.store, nonce).await?;
 
     if !execution_results.is_empty() {
         let (tx, rx) = mpsc::channel(8);
 
-        let nonce = random::<i64>();
-
-        let iter_state = state.clone();
         tokio::spawn(async move {
-            let mut remaining = Vec::new();
-
             for item in execution_results {
-                if tx.send(item.clone()).await.is_err() {
-                    remaining.push(item);
+                if tx.send(item).await.is_err() {
+                    break;
                 }
             }
-
-            if !remaining.is_empty() {
-                iter_state.exec_results.lock().await.append(&mut remaining);
-            }
         });
 
-        let submission_state = state.clone();
-        tokio::spawn(async move {
-            let req = Request::new(ReceiverStream::new(rx));
+        let mut req = state.broker_request(ReceiverStream::new(rx))?;
+        req.metadata_mut()
+            .insert(DRONE_ID_METADATA, state.id.parse()?);
 
+        let submission_state = state.clone();
+        let submission = tokio::spawn(async move {
             match client.record_execution(req).await {
                 Err(error) => {
                     tracing::error! {
@@ -282,6 +716,19 @@ async fn submit_job_results(state: DroneState) -> anyhow::Result<()> {
                 };
             }
         });
+
+        return Ok(Some(submission));
+    }
+
+    Ok(None)
+}
+
+/// Submits stored results and waits for the broker to acknowledge them.
+/// Used while draining, once no more jobs will start. Anything that doesn't
+/// fit in the submission stays in the store for the next start.
+pub async fn flush_job_results(state: DroneState) -> anyhow::Result<()> {
+    if let Some(submission) = submit_job_results(state).await? {
+        submission.await?;
     }
 
     Ok(())
@@ -291,6 +738,13 @@ async fn submit_job_results_loop(state: DroneState) -> anyhow::Result<()> {
     loop {
         tokio::time::sleep(Duration::from_secs(2)).await;
         submit_job_results(state.clone()).await?;
+
+        if let Err(error) = state.store.cleanup_executions().await {
+            tracing::error! {
+              %error,
+              "Error cleaning up synced executions."
+            };
+        }
     }
 }
 
@@ -302,3 +756,539 @@ pub async fn start_job_executor(state: DroneState) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_unsent_executions_survive_restart() -> anyhow::Result<()> {
+        let store_path =
+            std::env::temp_dir().join(format!("{}.db", crate::id::generate("drone_store")));
+
+        let store = DroneStore::from_filename(store_path.clone()).await?;
+        store
+            .insert_execution(
+                grpc::JobExecution {
+                    job_id: "scheduled_job_1".to_string(),
+                    lock_nonce: 1,
+                    response: Some(grpc::Response {
+                        status: 500,
+                        headers: HashMap::new(),
+                        body: "boom".to_string(),
+                        body_hash: None,
+                        truncated: false,
+                        bytes_used: 4,
+                        body_read_truncated: false,
+                    }),
+                    response_error: Some("Received status 500: boom".to_string()),
+                    req_method: "GET".to_string(),
+                    req_url: "https://example.com".to_string(),
+                    executed_at: 1_700_000_000,
+                    ..Default::default()
+                },
+                true,
+            )
+            .await?;
+
+        // The submission starts, then the drone dies before any acknowledgement.
+        assert_eq!(claim_unsynced_results(&store, 1).await?.len(), 1);
+        assert!(claim_unsynced_results(&store, 2).await?.is_empty());
+        drop(store);
+
+        let store = DroneStore::from_filename(store_path.clone()).await?;
+        let replayed = claim_unsynced_results(&store, 3).await?;
+        drop(store);
+        let _ = std::fs::remove_file(&store_path);
+
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].job_id, "scheduled_job_1");
+        assert_eq!(
+            replayed[0].response.as_ref().map(|res| res.status),
+            Some(500)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_started_execution_is_interrupted_after_restart() -> anyhow::Result<()> {
+        let store_path =
+            std::env::temp_dir().join(format!("{}.db", crate::id::generate("drone_store")));
+
+        let started = |job_id: &str| grpc::JobExecution {
+            job_id: job_id.to_string(),
+            lock_nonce: 7,
+            req_method: "POST".to_string(),
+            req_url: "https://example.com".to_string(),
+            req_body: Some("payload".to_string()),
+            executed_at: 1_700_000_000,
+            ..Default::default()
+        };
+
+        let store = DroneStore::from_filename(store_path.clone()).await?;
+        store
+            .record_execution_start(&started("job_cut_short"))
+            .await?;
+        store
+            .record_execution_start(&started("job_finished"))
+            .await?;
+        store
+            .insert_execution(
+                grpc::JobExecution {
+                    success: true,
+                    response: Some(grpc::Response {
+                        status: 200,
+                        ..Default::default()
+                    }),
+                    ..started("job_finished")
+                },
+                true,
+            )
+            .await?;
+        // The drone dies while job_cut_short's request is in flight.
+        drop(store);
+
+        let store = DroneStore::from_filename(store_path.clone()).await?;
+        let mut submitted = claim_unsynced_results(&store, 1).await?;
+        drop(store);
+        let _ = std::fs::remove_file(&store_path);
+
+        submitted.sort_by(|a, b| a.job_id.cmp(&b.job_id));
+        assert_eq!(submitted.len(), 2);
+
+        assert_eq!(submitted[0].job_id, "job_cut_short");
+        assert!(!submitted[0].success);
+        assert_eq!(submitted[0].lock_nonce, 7);
+        assert_eq!(submitted[0].req_body.as_deref(), Some("payload"));
+        assert_eq!(
+            submitted[0].response_error.as_deref(),
+            Some(crate::drone::store::executions::INTERRUPTED_ERROR)
+        );
+
+        assert_eq!(submitted[1].job_id, "job_finished");
+        assert!(submitted[1].success);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_negative_limits_are_clamped_to_zero() {
+        let job = grpc::JobSpec {
+            timeout_ms: -1,
+            max_response_bytes: Some(-1),
+            body_read_timeout_ms: Some(-1),
+            ..Default::default()
+        };
+
+        assert_eq!(request_timeout(&job), Duration::ZERO);
+        assert_eq!(response_bytes_limit(&job), Some(0));
+        assert_eq!(body_read_budget(&job), Some(Duration::ZERO));
+
+        let job = grpc::JobSpec {
+            timeout_ms: 1500,
+            max_response_bytes: Some(1024),
+            body_read_timeout_ms: Some(500),
+            ..Default::default()
+        };
+
+        assert_eq!(request_timeout(&job), Duration::from_millis(1500));
+        assert_eq!(response_bytes_limit(&job), Some(1024));
+        assert_eq!(body_read_budget(&job), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_append_capped_reports_truncation() {
+        let mut body = Vec::new();
+        assert!(!append_capped(&mut body, b"hello", Some(8)));
+        assert!(append_capped(&mut body, b"world", Some(8)));
+        assert_eq!(body, b"hellowor");
+
+        let mut body = Vec::new();
+        assert!(!append_capped(&mut body, b"exactly", Some(7)));
+        assert!(!append_capped(&mut body, b"anything", None));
+        assert_eq!(body.len(), 15);
+    }
+
+    #[tokio::test]
+    async fn test_read_body_stops_at_time_budget() {
+        let slow = Box::pin(futures::stream::unfold((), |()| async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Some((Ok::<_, std::io::Error>(b"chunk".to_vec()), ()))
+        }));
+
+        let captured = read_body(slow, Some(1024), Some(Duration::from_millis(250))).await;
+
+        assert!(captured.body_read_truncated);
+        assert!(!captured.truncated);
+        assert!(captured.bytes.starts_with(b"chunk"));
+        assert!(captured.bytes.len() < 1024);
+
+        let fast = tokio_stream::iter([Ok::<_, std::io::Error>(b"hello".to_vec())]);
+        let captured = read_body(fast, Some(1024), Some(Duration::from_millis(250))).await;
+
+        assert!(!captured.body_read_truncated);
+        assert_eq!(captured.bytes, b"hello");
+    }
+
+    #[test]
+    fn test_captured_response_counts_raw_bytes() {
+        let captured = CapturedBody {
+            bytes: vec![b'o', b'k', 0xff, 0xfe],
+            ..Default::default()
+        };
+        let response = captured_response(200, HashMap::new(), &captured);
+
+        assert_eq!(response.bytes_used, 4);
+        assert_eq!(response.body, "ok\u{fffd}\u{fffd}");
+        assert_ne!(response.body.len() as i64, response.bytes_used);
+    }
+
+    #[test]
+    fn test_fresh_connection_disables_idle_pool() {
+        assert_eq!(max_idle_per_host(true), 0);
+        assert!(max_idle_per_host(false) > 0);
+    }
+
+    #[test]
+    fn test_skip_tls_verify_requires_insecure_jobs_allowed() {
+        let secure = grpc::JobSpec::default();
+        let insecure = grpc::JobSpec {
+            insecure_skip_tls_verify: true,
+            ..Default::default()
+        };
+
+        assert_eq!(skip_tls_verify(&secure, false), Ok(false));
+        assert_eq!(skip_tls_verify(&secure, true), Ok(false));
+        assert!(skip_tls_verify(&insecure, false).is_err());
+        assert_eq!(skip_tls_verify(&insecure, true), Ok(true));
+    }
+
+    #[test]
+    fn test_not_modified_succeeds_only_for_conditional_jobs() {
+        let plain = grpc::JobSpec::default();
+        let conditional = grpc::JobSpec {
+            conditional: true,
+            ..Default::default()
+        };
+
+        assert!(response_succeeded(StatusCode::OK, &plain));
+        assert!(!response_succeeded(StatusCode::NOT_MODIFIED, &plain));
+        assert!(response_succeeded(StatusCode::NOT_MODIFIED, &conditional));
+        assert!(!response_succeeded(StatusCode::NOT_FOUND, &conditional));
+    }
+
+    async fn echo_framing(headers: http::HeaderMap) -> String {
+        let values = |name: HeaderName| {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        format!(
+            "content-length={} transfer-encoding={}",
+            values(CONTENT_LENGTH),
+            values(TRANSFER_ENCODING)
+        )
+    }
+
+    #[tokio::test]
+    async fn test_body_framing_follows_transfer_encoding() -> anyhow::Result<()> {
+        let target = axum::Router::new().route("/", axum::routing::post(echo_framing));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, target).await });
+
+        for (transfer_encoding, framing) in [
+            ("", "content-length=5 transfer-encoding="),
+            ("auto", "content-length=5 transfer-encoding="),
+            ("length", "content-length=5 transfer-encoding="),
+            ("chunked", "content-length= transfer-encoding=chunked"),
+        ] {
+            let job = grpc::JobSpec {
+                job_id: "job_1".to_string(),
+                method: "POST".to_string(),
+                url: format!("http://localhost:{}/", addr.port()),
+                headers: headers(&[("Content-Length", "999"), ("Transfer-Encoding", "gzip")]),
+                body: Some("hello".to_string()),
+                timeout_ms: 5000,
+                transfer_encoding: transfer_encoding.to_string(),
+                ..Default::default()
+            };
+
+            let response = send_request_to_ip(&job, addr, false, true, &ClientCache::default())
+                .await
+                .map_err(anyhow::Error::msg)?;
+
+            assert_eq!(response.text().await?, framing, "{transfer_encoding:?}");
+        }
+
+        Ok(())
+    }
+
+    /// "hello rocktick", gzipped.
+    const GZIPPED_BODY: [u8; 34] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0x57, 0x28, 0xca, 0x4f, 0xce, 0x2e, 0xc9, 0x4c, 0xce, 0x06, 0x00, 0x24, 0xaf, 0x8a, 0x6c,
+        0x0e, 0x00, 0x00, 0x00,
+    ];
+
+    #[tokio::test]
+    async fn test_compressed_responses_are_decoded_unless_disabled() -> anyhow::Result<()> {
+        let target = axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                ([(http::header::CONTENT_ENCODING, "gzip")], GZIPPED_BODY)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, target).await });
+
+        let job = grpc::JobSpec {
+            job_id: "job_1".to_string(),
+            method: "GET".to_string(),
+            url: format!("http://localhost:{}/", addr.port()),
+            timeout_ms: 5000,
+            max_response_bytes: Some(5),
+            ..Default::default()
+        };
+
+        let decoded = send_request_to_ip(&job, addr, false, true, &ClientCache::default())
+            .await
+            .map_err(anyhow::Error::msg)?;
+        let captured = read_body(decoded.bytes_stream(), response_bytes_limit(&job), None).await;
+        assert_eq!(captured.bytes, b"hello");
+        assert!(captured.truncated);
+
+        let raw = send_request_to_ip(&job, addr, false, false, &ClientCache::default())
+            .await
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(raw.bytes().await?.as_ref(), GZIPPED_BODY);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_followed_up_to_the_job_limit() -> anyhow::Result<()> {
+        use axum::response::Redirect;
+
+        let target = axum::Router::new()
+            .route(
+                "/",
+                axum::routing::get(|| async { Redirect::temporary("/hop") }),
+            )
+            .route(
+                "/hop",
+                axum::routing::get(|| async { Redirect::permanent("/final") }),
+            )
+            .route("/final", axum::routing::get(|| async { "done" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, target).await });
+
+        let job = |follow_redirects| grpc::JobSpec {
+            job_id: "job_1".to_string(),
+            method: "GET".to_string(),
+            url: format!("http://localhost:{}/", addr.port()),
+            timeout_ms: 5000,
+            follow_redirects,
+            ..Default::default()
+        };
+
+        let unfollowed = send_request_to_ip(&job(None), addr, false, true, &ClientCache::default())
+            .await
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(unfollowed.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let followed =
+            send_request_to_ip(&job(Some(2)), addr, false, true, &ClientCache::default())
+                .await
+                .map_err(anyhow::Error::msg)?;
+        assert_eq!(followed.status(), StatusCode::OK);
+        assert_eq!(followed.text().await?, "done");
+
+        assert!(
+            send_request_to_ip(&job(Some(1)), addr, false, true, &ClientCache::default())
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loops_are_stopped() -> anyhow::Result<()> {
+        use axum::response::Redirect;
+
+        let target = axum::Router::new()
+            .route(
+                "/a",
+                axum::routing::get(|| async { Redirect::temporary("/b") }),
+            )
+            .route(
+                "/b",
+                axum::routing::get(|| async { Redirect::temporary("/a") }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, target).await });
+
+        let job = grpc::JobSpec {
+            job_id: "job_1".to_string(),
+            method: "GET".to_string(),
+            url: format!("http://localhost:{}/a", addr.port()),
+            timeout_ms: 5000,
+            follow_redirects: Some(10),
+            ..Default::default()
+        };
+
+        let err = send_request_to_ip(&job, addr, false, true, &ClientCache::default())
+            .await
+            .unwrap_err();
+        assert!(err.starts_with("redirect_loop: "), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_redirect_refuses_private_addresses() {
+        let url = |s: &str| reqwest::Url::parse(s).unwrap();
+        let hops = |n: usize| vec![url("https://example.com/start"); n];
+
+        assert!(check_redirect(&url("https://example.com/"), &hops(1), 1, false).is_ok());
+        assert!(check_redirect(&url("https://example.com/"), &hops(2), 1, false).is_err());
+        assert!(check_redirect(&url("http://10.0.0.1/"), &hops(1), 5, false).is_err());
+        assert!(check_redirect(&url("http://[::1]:8080/"), &hops(1), 5, false).is_err());
+        assert!(check_redirect(&url("http://10.0.0.1/"), &hops(1), 5, true).is_ok());
+        assert!(check_redirect(&url("http://93.184.216.34/"), &hops(1), 5, false).is_ok());
+    }
+
+    #[test]
+    fn test_check_redirect_refuses_revisited_urls() {
+        let url = |s: &str| reqwest::Url::parse(s).unwrap();
+        let previous = [url("https://example.com/a"), url("https://example.com/b")];
+
+        let err = check_redirect(&url("https://example.com/a"), &previous, 5, false).unwrap_err();
+        assert!(err.starts_with(REDIRECT_LOOP_ERROR));
+        assert!(check_redirect(&url("https://example.com/c"), &previous, 5, false).is_ok());
+    }
+
+    #[test]
+    fn test_render_template_substitutes_known_placeholders() {
+        let job = grpc::JobSpec {
+            job_id: "scheduled_job_1".to_string(),
+            scheduled_at: 1_700_000_000,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_template(
+                "https://example.com/run?at={{scheduled_at}}&id={{job_id}}&x={{unknown}}",
+                &job
+            ),
+            "https://example.com/run?at=1700000000&id=scheduled_job_1&x={{unknown}}"
+        );
+        assert_eq!(render_template("no placeholders", &job), "no placeholders");
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(parse_retry_after("120", now), Some(120));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(0));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:30 GMT", now),
+            Some(150)
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(0)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_hash_body() {
+        assert_eq!(
+            hash_body(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_ne!(hash_body(b"hello"), hash_body(b"hello!"));
+    }
+
+    #[test]
+    fn test_failure_snippet_truncates_body() {
+        let body = "internal error ".repeat(20);
+        let snippet = failure_snippet(500, &body, 14);
+
+        assert_eq!(
+            snippet,
+            "Received status 500: internal error... (truncated)"
+        );
+    }
+
+    #[test]
+    fn test_failure_snippet_keeps_short_body() {
+        let snippet = failure_snippet(500, "boom", 16);
+
+        assert_eq!(snippet, "Received status 500: boom");
+    }
+
+    #[test]
+    fn test_failure_snippet_respects_char_boundaries() {
+        let snippet = failure_snippet(502, "ééé", 3);
+
+        assert_eq!(snippet, "Received status 502: é... (truncated)");
+    }
+
+    #[test]
+    fn test_build_header_map_accepts_valid_headers() {
+        let map = build_header_map(
+            "job_1",
+            headers(&[("Content-Type", "application/json"), ("X-Token", "abc")]),
+        )
+        .unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["content-type"], "application/json");
+    }
+
+    #[test]
+    fn test_build_header_map_rejects_newline_in_value() {
+        let result = build_header_map(
+            "job_1",
+            headers(&[("X-Custom", "value\r\nX-Injected: evil")]),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_header_map_rejects_invalid_name() {
+        let result = build_header_map("job_1", headers(&[("X Bad\nName", "value")]));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_header_map_rejects_oversized_value() {
+        let value = "a".repeat(MAX_HEADER_VALUE_BYTES + 1);
+        let result = build_header_map("job_1", headers(&[("X-Large", &value)]));
+
+        assert!(result.is_err());
+    }
+}
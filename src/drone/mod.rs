@@ -1,18 +1,42 @@
 mod actors;
+mod clients;
 mod dronesync;
 mod jobs;
+mod status;
 pub mod store;
-mod util;
+pub mod util;
 mod workflows;
 
-use std::{net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
 
+use anyhow::anyhow;
 use tokio::{
-    select,
-    sync::{Mutex, RwLock, mpsc},
+    fs, select,
+    signal::unix::{SignalKind, signal},
+    sync::{RwLock, Semaphore, mpsc},
+    time::Instant,
+};
+use tonic::{
+    Request,
+    codec::CompressionEncoding,
+    transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity},
 };
 
-use crate::{DroneOptions, drone::store::DroneStore, grpc};
+use crate::{
+    DroneOptions,
+    broker::DRONE_AUTH_METADATA,
+    drone::store::DroneStore,
+    grpc::{self, broker_client::BrokerClient},
+};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -22,6 +46,20 @@ pub struct Config {
     ip: IpAddr,
     port: usize,
     store_location: PathBuf,
+    max_body_log_bytes: usize,
+    region_affinities: HashMap<String, i32>,
+    broker_ca: Option<PathBuf>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    startup_checkin_timeout: Duration,
+    drone_auth_key: Option<String>,
+    max_concurrent_jobs: u32,
+    allow_insecure_jobs: bool,
+    drain_timeout: Duration,
+    record_started_executions: bool,
+    status_addr: Option<SocketAddr>,
+    decompress_responses: bool,
+    grpc_compression: bool,
 }
 
 impl Config {
@@ -33,6 +71,20 @@ impl Config {
             ip: options.ip,
             port: options.port,
             store_location: options.store_path,
+            max_body_log_bytes: options.max_body_log_bytes,
+            region_affinities: options.region_affinity.into_iter().collect(),
+            broker_ca: options.broker_ca,
+            tls_cert: options.tls_cert,
+            tls_key: options.tls_key,
+            startup_checkin_timeout: Duration::from_secs(options.startup_checkin_timeout_secs),
+            drone_auth_key: options.drone_auth_key,
+            max_concurrent_jobs: options.max_concurrent_jobs,
+            allow_insecure_jobs: options.allow_insecure_jobs,
+            drain_timeout: Duration::from_secs(options.drain_timeout_secs),
+            record_started_executions: options.record_started_executions,
+            status_addr: options.status_addr,
+            decompress_responses: !options.disable_response_decompression,
+            grpc_compression: options.grpc_compression,
         }
     }
 }
@@ -50,12 +102,117 @@ struct DroneState {
     id: String,
     ip: IpAddr,
     port: usize,
-    exec_results: Arc<Mutex<Vec<grpc::JobExecution>>>,
-    broker_url: String,
+    broker: Endpoint,
     region: String,
     store: store::DroneStore,
     drones: Arc<RwLock<Vec<Drone>>>,
     error_tx: mpsc::Sender<anyhow::Error>,
+    max_body_log_bytes: usize,
+    region_affinities: HashMap<String, i32>,
+    startup_checkin_timeout: Duration,
+    drone_auth_key: Option<String>,
+    max_concurrent_jobs: u32,
+    jobs_in_flight: Arc<AtomicU32>,
+    /// Held by each running job, so no more than max_concurrent_jobs run
+    /// at once even if the broker sends more than were asked for.
+    job_permits: Arc<Semaphore>,
+    allow_insecure_jobs: bool,
+    drain_timeout: Duration,
+    record_started_executions: bool,
+    decompress_responses: bool,
+    grpc_compression: bool,
+    clients: clients::ClientCache,
+}
+
+impl DroneState {
+    /// Connects to the broker, gzipping what's sent when configured to.
+    async fn broker_client(&self) -> anyhow::Result<BrokerClient<Channel>> {
+        let client = BrokerClient::connect(self.broker.clone())
+            .await?
+            .accept_compressed(CompressionEncoding::Gzip);
+
+        if self.grpc_compression {
+            Ok(client.send_compressed(CompressionEncoding::Gzip))
+        } else {
+            Ok(client)
+        }
+    }
+
+    /// Builds a broker request carrying this drone's auth key, if configured.
+    fn broker_request<T>(&self, message: T) -> anyhow::Result<Request<T>> {
+        let mut req = Request::new(message);
+
+        if let Some(auth_key) = &self.drone_auth_key {
+            req.metadata_mut()
+                .insert(DRONE_AUTH_METADATA, format!("Bearer {auth_key}").parse()?);
+        }
+
+        Ok(req)
+    }
+
+    /// How many more jobs this drone can start right now.
+    fn job_capacity(&self) -> u32 {
+        self.max_concurrent_jobs
+            .saturating_sub(self.jobs_in_flight.load(Ordering::SeqCst))
+    }
+}
+
+async fn broker_endpoint(config: &Config) -> anyhow::Result<Endpoint> {
+    let endpoint = Endpoint::from_shared(config.broker_url.clone())?;
+
+    let Some(ca_path) = &config.broker_ca else {
+        return Ok(endpoint);
+    };
+
+    if endpoint.uri().scheme_str() != Some("https") {
+        return Err(anyhow!(
+            "Broker url {} must use https when a broker CA is configured",
+            config.broker_url
+        ));
+    }
+
+    let mut tls_config =
+        ClientTlsConfig::new().ca_certificate(Certificate::from_pem(fs::read(ca_path).await?));
+
+    if let (Some(cert_path), Some(key_path)) = (&config.tls_cert, &config.tls_key) {
+        tls_config = tls_config.identity(Identity::from_pem(
+            fs::read(cert_path).await?,
+            fs::read(key_path).await?,
+        ));
+    }
+
+    Ok(endpoint.tls_config(tls_config)?)
+}
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Waits for running jobs to finish, hands their results to the broker and
+/// unregisters, so none of the drone's jobs wait on the broker's cleanup.
+async fn drain(state: &DroneState) -> anyhow::Result<()> {
+    let deadline = Instant::now() + state.drain_timeout;
+
+    loop {
+        let in_flight = state.jobs_in_flight.load(Ordering::SeqCst);
+
+        if in_flight == 0 {
+            break;
+        }
+
+        if Instant::now() >= deadline {
+            tracing::warn!(
+                in_flight,
+                "Drain timed out, leaving running jobs to the broker's cleanup."
+            );
+            break;
+        }
+
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+
+    jobs::flush_job_results(state.clone()).await?;
+    dronesync::unregister(state).await?;
+
+    Ok(())
 }
 
 pub async fn start(config: Config) -> anyhow::Result<()> {
@@ -63,30 +220,95 @@ pub async fn start(config: Config) -> anyhow::Result<()> {
 
     let (error_tx, mut error_rx) = mpsc::channel(1);
 
+    let broker = broker_endpoint(&config).await?;
     let store = DroneStore::from_filename(config.store_location).await?;
 
     let state = DroneState {
         id: config.id,
         ip: config.ip,
         port: config.port,
-        exec_results: Arc::new(Mutex::new(Vec::new())),
-        broker_url: config.broker_url.clone(),
+        broker,
         region: config.region.clone(),
         store,
         drones: Arc::new(RwLock::new(Vec::new())),
         error_tx,
+        max_body_log_bytes: config.max_body_log_bytes,
+        region_affinities: config.region_affinities,
+        startup_checkin_timeout: config.startup_checkin_timeout,
+        drone_auth_key: config.drone_auth_key,
+        max_concurrent_jobs: config.max_concurrent_jobs,
+        jobs_in_flight: Arc::new(AtomicU32::new(0)),
+        job_permits: Arc::new(Semaphore::new(config.max_concurrent_jobs as usize)),
+        allow_insecure_jobs: config.allow_insecure_jobs,
+        drain_timeout: config.drain_timeout,
+        record_started_executions: config.record_started_executions,
+        decompress_responses: config.decompress_responses,
+        grpc_compression: config.grpc_compression,
+        clients: clients::ClientCache::default(),
     };
 
+    let status_addr = config.status_addr;
+    let time_to_next_checkin = dronesync::initial_check_in(&state).await?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+
     select! {
       jobs_res = jobs::start_job_executor(state.clone()) => {jobs_res?;},
       workflows_res = workflows::start_workflow_executor(state.clone()) => {workflows_res?;},
       actor_res = actors::start_actor_executor(state.clone()) => {actor_res?;},
-      checkin_res = dronesync::start_checkin_loop(state.clone()) => {checkin_res?;},
+      checkin_res = dronesync::start_checkin_loop(state.clone(), time_to_next_checkin) => {checkin_res?;},
       drone_refresh_res = dronesync::start_refresh_loop(state.clone()) => {drone_refresh_res?;},
+      status_res = status::serve(state.store.clone(), status_addr) => {status_res?;},
       Some(err) = error_rx.recv() => {
         return Err(err);
       }
+      _ = sigterm.recv() => {
+        tracing::info!("Received SIGTERM, draining drone.");
+        drain(&state).await?;
+        tracing::info!("Drone drained.");
+      }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(broker_url: &str, broker_ca: Option<&str>) -> Config {
+        Config {
+            broker_url: broker_url.to_string(),
+            region: "na-east".to_string(),
+            id: "drone_a".to_string(),
+            ip: "127.0.0.1".parse().unwrap(),
+            port: 30002,
+            store_location: PathBuf::from("drone.db"),
+            max_body_log_bytes: 1024,
+            region_affinities: HashMap::new(),
+            broker_ca: broker_ca.map(PathBuf::from),
+            tls_cert: None,
+            tls_key: None,
+            startup_checkin_timeout: Duration::from_secs(60),
+            drone_auth_key: None,
+            max_concurrent_jobs: 100,
+            allow_insecure_jobs: false,
+            drain_timeout: Duration::from_secs(90),
+            record_started_executions: false,
+            status_addr: None,
+            decompress_responses: true,
+            grpc_compression: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broker_endpoint_is_plaintext_without_ca() {
+        let endpoint = broker_endpoint(&config("http://[::1]:30001", None)).await;
+        assert!(endpoint.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_broker_endpoint_with_ca_requires_https() {
+        let endpoint = broker_endpoint(&config("http://[::1]:30001", Some("ca.pem"))).await;
+        assert!(endpoint.is_err_and(|err| err.to_string().contains("must use https")));
+    }
+}
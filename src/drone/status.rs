@@ -0,0 +1,160 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::get,
+};
+use http::StatusCode;
+use serde::Serialize;
+
+use crate::drone::store::{DroneStore, executions::SyncStatus};
+
+#[derive(Debug, Serialize)]
+struct LocalResponse {
+    status: i64,
+    headers: HashMap<String, String>,
+    body: String,
+    truncated: bool,
+}
+
+/// An execution as this drone recorded it, with where it is in syncing to
+/// the broker.
+#[derive(Debug, Serialize)]
+struct LocalExecution {
+    job_id: String,
+    success: bool,
+    lock_nonce: i64,
+    executed_at: i64,
+    req_method: String,
+    req_url: String,
+    req_headers: HashMap<String, String>,
+    req_body: Option<String>,
+    response: Option<LocalResponse>,
+    response_error: Option<String>,
+    tls_verify_skipped: bool,
+    is_local: bool,
+    replicated_times: i64,
+    sync_status: &'static str,
+    sync_nonce: i64,
+}
+
+fn sync_status_name(sync_status: &SyncStatus) -> &'static str {
+    match sync_status {
+        SyncStatus::Local => "local",
+        SyncStatus::Pending => "pending",
+        SyncStatus::Synced => "synced",
+    }
+}
+
+async fn get_execution(
+    State(store): State<DroneStore>,
+    Path(job_id): Path<String>,
+) -> Result<Json<LocalExecution>, StatusCode> {
+    let (execution, metadata) = store.get_execution(job_id).await.map_err(|err| match err
+        .downcast_ref::<sqlx::Error>(
+    ) {
+        Some(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+        _ => {
+            tracing::error!(%err, "Error reading execution from the drone store.");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Ok(Json(LocalExecution {
+        job_id: execution.job_id,
+        success: execution.success,
+        lock_nonce: execution.lock_nonce,
+        executed_at: execution.executed_at,
+        req_method: execution.req_method,
+        req_url: execution.req_url,
+        req_headers: execution.req_headers,
+        req_body: execution.req_body,
+        response: execution.response.map(|response| LocalResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+            truncated: response.truncated,
+        }),
+        response_error: execution.response_error,
+        tls_verify_skipped: execution.tls_verify_skipped,
+        is_local: metadata.is_local,
+        replicated_times: metadata.replicated_times,
+        sync_status: sync_status_name(&metadata.sync_status),
+        sync_nonce: metadata.sync_nonce,
+    }))
+}
+
+fn router(store: DroneStore) -> Router {
+    Router::new()
+        .route("/executions/{job_id}", get(get_execution))
+        .with_state(store)
+}
+
+/// Serves the drone's local debugging endpoints on `addr`, or never
+/// resolves when no address is configured.
+pub async fn serve(store: DroneStore, addr: Option<SocketAddr>) -> anyhow::Result<()> {
+    let Some(addr) = addr else {
+        return std::future::pending().await;
+    };
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "Drone status server listening.");
+    axum::serve(listener, router(store)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::{drone::store::MIGRATOR, grpc};
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_get_execution_returns_sync_state(pool: SqlitePool) -> anyhow::Result<()> {
+        let store = DroneStore::from_pool(pool);
+
+        store
+            .insert_execution(
+                grpc::JobExecution {
+                    job_id: "scheduled_job_1".to_string(),
+                    success: true,
+                    lock_nonce: 3,
+                    response: Some(grpc::Response {
+                        status: 200,
+                        headers: HashMap::new(),
+                        body: "ok".to_string(),
+                        body_hash: None,
+                        truncated: false,
+                        bytes_used: 2,
+                        body_read_truncated: false,
+                    }),
+                    req_method: "GET".to_string(),
+                    req_url: "https://example.com".to_string(),
+                    executed_at: 1_700_000_000,
+                    ..Default::default()
+                },
+                true,
+            )
+            .await?;
+
+        let Json(execution) =
+            get_execution(State(store.clone()), Path("scheduled_job_1".to_string()))
+                .await
+                .unwrap();
+
+        assert!(execution.success);
+        assert_eq!(execution.lock_nonce, 3);
+        assert_eq!(execution.response.map(|res| res.status), Some(200));
+        assert!(execution.is_local);
+        assert_eq!(execution.replicated_times, 0);
+        assert_eq!(execution.sync_status, "local");
+
+        let missing = get_execution(State(store), Path("scheduled_job_2".to_string())).await;
+        assert!(missing.is_err_and(|code| code == StatusCode::NOT_FOUND));
+
+        Ok(())
+    }
+}
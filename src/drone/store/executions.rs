@@ -22,7 +22,11 @@ impl DroneStore {
               exec.*,
               res.status as res_status,
               res.header_map as res_header_map,
-              res.body as res_body
+              res.body as res_body,
+              res.body_hash as res_body_hash,
+              res.truncated as res_truncated,
+              res.bytes_used as res_bytes_used,
+              res.body_read_truncated as res_body_read_truncated
             FROM executions exec
             LEFT JOIN execution_responses res
               ON exec.response_id = res.id
@@ -56,15 +60,19 @@ impl DroneStore {
             sqlx::query(
                 r#"
                 INSERT INTO execution_responses
-                  (id, status, header_map, body)
+                  (id, status, header_map, body, body_hash, truncated, bytes_used, body_read_truncated)
                 VALUES
-                  ($1, $2, $3, $4);
+                  ($1, $2, $3, $4, $5, $6, $7, $8);
             "#,
             )
             .bind(id)
             .bind(res.status)
             .bind(res_headers)
             .bind(res.body)
+            .bind(res.body_hash)
+            .bind(res.truncated)
+            .bind(res.bytes_used)
+            .bind(res.body_read_truncated)
             .execute(&mut *tx)
             .await?;
         }
@@ -88,11 +96,15 @@ impl DroneStore {
             replicated_times,
             sync_status,
             sync_time,
-            sync_nonce)
-          VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15);
+            sync_nonce,
+            retry_after_secs,
+            tls_verify_skipped,
+            drone_id,
+            duration_ms)
+          VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19);
         "#,
         )
-        .bind(exec.job_id)
+        .bind(&exec.job_id)
         .bind(exec.success)
         .bind(exec.lock_nonce)
         .bind(response_id)
@@ -107,14 +119,87 @@ impl DroneStore {
         .bind("local")
         .bind::<Option<i64>>(None)
         .bind::<Option<i64>>(None)
+        .bind(exec.retry_after_secs)
+        .bind(exec.tls_verify_skipped)
+        .bind(exec.drone_id)
+        .bind(exec.duration_ms)
         .execute(&mut *tx)
         .await?;
 
+        sqlx::query("DELETE FROM started_executions WHERE job_id = $1;")
+            .bind(&exec.job_id)
+            .execute(&mut *tx)
+            .await?;
+
         tx.commit().await?;
 
         Ok(())
     }
 
+    /// Notes that `exec`'s request is about to be sent. The marker is replaced
+    /// once the full execution is inserted, or reported as interrupted by
+    /// `interrupt_started_executions` if the drone stops first.
+    pub async fn record_execution_start(&self, exec: &grpc::JobExecution) -> anyhow::Result<()> {
+        let req_headers = serde_json::to_string(&exec.req_headers)?;
+
+        sqlx::query(
+            r#"
+          INSERT OR REPLACE INTO started_executions
+            (job_id, lock_nonce, req_method, req_url, req_header_map, req_body, executed_at, tls_verify_skipped)
+          VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+        "#,
+        )
+        .bind(&exec.job_id)
+        .bind(exec.lock_nonce)
+        .bind(&exec.req_method)
+        .bind(&exec.req_url)
+        .bind(req_headers)
+        .bind(&exec.req_body)
+        .bind(exec.executed_at)
+        .bind(exec.tls_verify_skipped)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Turns every attempt that was started but never finished into a failed
+    /// execution, ready to be submitted. Returns how many there were.
+    pub async fn interrupt_started_executions(&self) -> anyhow::Result<usize> {
+        let started: Vec<StartedExecution> =
+            sqlx::query_as("SELECT * FROM started_executions ORDER BY executed_at ASC;")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let count = started.len();
+
+        for started in started {
+            let req_headers = serde_json::from_str(&started.req_header_map)
+                .context("Failed to deserialize request headers")?;
+
+            let execution = JobExecution {
+                job_id: started.job_id,
+                success: false,
+                lock_nonce: started.lock_nonce,
+                response: None,
+                response_error: Some(INTERRUPTED_ERROR.to_string()),
+                req_method: started.req_method,
+                req_url: started.req_url,
+                req_headers,
+                req_body: started.req_body,
+                executed_at: started.executed_at,
+                retry_after_secs: None,
+                tls_verify_skipped: started.tls_verify_skipped,
+                drone_id: None,
+                duration_ms: None,
+            };
+
+            self.insert_execution(execution, true).await?;
+        }
+
+        Ok(count)
+    }
+
     pub async fn record_replication(&self, job_id: String) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
@@ -149,6 +234,26 @@ impl DroneStore {
         Ok(())
     }
 
+    /// Puts executions from syncs that never finished, e.g. because the drone
+    /// stopped mid-submission, back in line to be sent.
+    pub async fn release_pending_syncs(&self) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+        UPDATE executions
+        SET
+          sync_status = 'local',
+          sync_time = NULL,
+          sync_nonce = NULL
+        WHERE
+          sync_status = 'pending'
+        "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn cleanup_executions(&self) -> Result<(), sqlx::Error> {
         let cleanup_before = (Utc::now() - Duration::hours(1)).timestamp();
 
@@ -224,7 +329,11 @@ impl DroneStore {
                   exec.*,
                   res.status as res_status,
                   res.header_map as res_header_map,
-                  res.body as res_body
+                  res.body as res_body,
+                  res.body_hash as res_body_hash,
+                  res.truncated as res_truncated,
+                  res.bytes_used as res_bytes_used,
+                  res.body_read_truncated as res_body_read_truncated
                 FROM executions exec
                 LEFT JOIN execution_responses res
                   ON exec.response_id = res.id
@@ -265,6 +374,20 @@ impl DroneStore {
     }
 }
 
+pub const INTERRUPTED_ERROR: &str = "drone was interrupted before the request completed";
+
+#[derive(Debug, Clone, FromRow)]
+struct StartedExecution {
+    job_id: String,
+    lock_nonce: i64,
+    req_method: String,
+    req_url: String,
+    req_header_map: String,
+    req_body: Option<String>,
+    executed_at: i64,
+    tls_verify_skipped: bool,
+}
+
 #[derive(Debug, Clone, FromRow)]
 struct IntermediateExecution {
     job_id: String,
@@ -282,9 +405,17 @@ struct IntermediateExecution {
     sync_status: String,
     sync_time: Option<i64>,
     sync_nonce: Option<i64>,
+    retry_after_secs: Option<i64>,
+    tls_verify_skipped: bool,
+    drone_id: Option<String>,
+    duration_ms: Option<i64>,
     res_status: Option<i64>,
     res_header_map: Option<String>,
     res_body: Option<String>,
+    res_body_hash: Option<String>,
+    res_truncated: Option<bool>,
+    res_bytes_used: Option<i64>,
+    res_body_read_truncated: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
@@ -332,6 +463,10 @@ fn intermediate_to_execution(
             status,
             headers,
             body,
+            body_hash: exec.res_body_hash,
+            truncated: exec.res_truncated.unwrap_or(false),
+            bytes_used: exec.res_bytes_used.unwrap_or(0),
+            body_read_truncated: exec.res_body_read_truncated.unwrap_or(false),
         })
     } else {
         None
@@ -366,6 +501,10 @@ fn intermediate_to_execution(
             req_headers,
             req_body: exec.req_body,
             executed_at: exec.executed_at,
+            retry_after_secs: exec.retry_after_secs,
+            tls_verify_skipped: exec.tls_verify_skipped,
+            drone_id: exec.drone_id,
+            duration_ms: exec.duration_ms,
         },
         ExecutionMetadata {
             is_local: exec.is_local,
@@ -403,6 +542,10 @@ mod tests {
                 status: 200,
                 headers: res_headers,
                 body: "{\"status\": \"ok\"}".to_string(),
+                body_hash: Some("a1b2c3".to_string()),
+                truncated: true,
+                bytes_used: 16,
+                body_read_truncated: true,
             }),
             response_error: None,
             req_method: "POST".to_string(),
@@ -410,6 +553,10 @@ mod tests {
             req_headers,
             req_body: Some("{\"data\": 1}".to_string()),
             executed_at: 1234567890,
+            retry_after_secs: Some(30),
+            tls_verify_skipped: true,
+            drone_id: Some("drone_a".to_string()),
+            duration_ms: Some(250),
         };
 
         store.insert_execution(execution.clone(), true).await?;
@@ -423,12 +570,20 @@ mod tests {
         assert_eq!(fetched_execution.req_url, execution.req_url);
         assert_eq!(fetched_execution.req_body, execution.req_body);
         assert_eq!(fetched_execution.req_headers, execution.req_headers);
+        assert_eq!(fetched_execution.retry_after_secs, Some(30));
+        assert!(fetched_execution.tls_verify_skipped);
+        assert_eq!(fetched_execution.drone_id.as_deref(), Some("drone_a"));
+        assert_eq!(fetched_execution.duration_ms, Some(250));
 
         let fetched_response = fetched_execution.response.unwrap();
         let expected_response = execution.response.unwrap();
         assert_eq!(fetched_response.status, expected_response.status);
         assert_eq!(fetched_response.body, expected_response.body);
         assert_eq!(fetched_response.headers, expected_response.headers);
+        assert_eq!(fetched_response.body_hash, expected_response.body_hash);
+        assert!(fetched_response.truncated);
+        assert_eq!(fetched_response.bytes_used, 16);
+        assert!(fetched_response.body_read_truncated);
 
         assert!(metadata.is_local);
         assert_eq!(metadata.replicated_times, 0);
@@ -454,6 +609,10 @@ mod tests {
             req_headers: HashMap::new(),
             req_body: None,
             executed_at: 987654321,
+            retry_after_secs: None,
+            tls_verify_skipped: false,
+            drone_id: None,
+            duration_ms: None,
         };
 
         store.insert_execution(execution, false).await?;
@@ -495,6 +654,10 @@ mod tests {
                 status: 201,
                 headers: res_headers.clone(),
                 body: response_body.clone(),
+                body_hash: None,
+                truncated: false,
+                bytes_used: response_body.len() as i64,
+                body_read_truncated: false,
             }),
             response_error: None,
             req_method: "PUT".to_string(),
@@ -502,6 +665,10 @@ mod tests {
             req_headers: req_headers.clone(),
             req_body: Some(req_body.clone()),
             executed_at: 1111111111,
+            retry_after_secs: None,
+            tls_verify_skipped: false,
+            drone_id: None,
+            duration_ms: None,
         };
 
         store.insert_execution(execution.clone(), true).await?;
@@ -720,6 +887,37 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_release_pending_syncs(pool: SqlitePool) -> anyhow::Result<()> {
+        let store = DroneStore::from_pool(pool);
+
+        insert_dummy_exec(
+            &store.pool,
+            "job_pending".to_string(),
+            "pending".to_string(),
+            Some(1),
+        )
+        .await?;
+        insert_dummy_exec(
+            &store.pool,
+            "job_synced".to_string(),
+            "synced".to_string(),
+            None,
+        )
+        .await?;
+
+        store.release_pending_syncs().await?;
+
+        let (_, pending) = store.get_execution("job_pending".to_string()).await?;
+        assert!(matches!(pending.sync_status, SyncStatus::Local));
+        assert_eq!(pending.sync_nonce, 0);
+
+        let (_, synced) = store.get_execution("job_synced".to_string()).await?;
+        assert!(matches!(synced.sync_status, SyncStatus::Synced));
+
+        Ok(())
+    }
+
     #[sqlx::test(migrator = "MIGRATOR")]
     async fn test_cleanup_executions(pool: SqlitePool) -> anyhow::Result<()> {
         let store = DroneStore::from_pool(pool);
@@ -915,6 +1113,10 @@ mod tests {
             req_headers: HashMap::new(),
             req_body: None,
             executed_at: 100,
+            retry_after_secs: None,
+            tls_verify_skipped: false,
+            drone_id: None,
+            duration_ms: None,
         };
 
         store
@@ -65,6 +65,17 @@ impl DroneStore {
 
         let store = Self { pool: conn };
         store.run_migrations().await?;
+        // Nothing can be mid-sync or mid-request in a store that was just opened.
+        store.release_pending_syncs().await?;
+        let interrupted = store.interrupt_started_executions().await?;
+
+        if interrupted > 0 {
+            tracing::warn! {
+              count = interrupted,
+              "Reporting executions interrupted by the last shutdown."
+            };
+        }
+
         Ok(store)
     }
 }
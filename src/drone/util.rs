@@ -1,10 +1,11 @@
 use std::net::{IpAddr, SocketAddr};
 
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use tokio::net::lookup_host;
 
 use crate::GLOBAL_CONFIG;
 
-fn is_private_ip(ip: &IpAddr) -> bool {
+pub fn is_private_ip(ip: &IpAddr) -> bool {
     match ip {
         IpAddr::V4(ipv4) => ipv4.is_private() || ipv4.is_loopback() || ipv4.is_link_local(),
         IpAddr::V6(ipv6) => {
@@ -54,3 +55,111 @@ pub async fn resolve_public_ip(url: &str) -> Option<SocketAddr> {
 
     public_addr
 }
+
+/// Splits a `host:ip` resolve override. Everything after the first colon is
+/// the address, so ipv6 addresses can be given with or without brackets.
+pub fn parse_resolve_override(resolve_override: &str) -> Option<(&str, IpAddr)> {
+    let (host, ip) = resolve_override.split_once(':')?;
+
+    if host.is_empty() {
+        return None;
+    }
+
+    let ip = ip
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip);
+
+    Some((host, ip.parse().ok()?))
+}
+
+/// Where a job with a resolve override is sent, in place of resolving its
+/// host. The override only counts for the url's own host and may not point
+/// at a private address unless those are allowed.
+pub fn resolve_override_addr(
+    url: &str,
+    resolve_override: &str,
+    allow_private_addrs: bool,
+) -> Option<SocketAddr> {
+    let url = url::Url::parse(url).ok()?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let (host, ip) = parse_resolve_override(resolve_override)?;
+
+    if !url.host_str()?.eq_ignore_ascii_case(host) {
+        return None;
+    }
+
+    if !allow_private_addrs && is_private_ip(&ip) {
+        return None;
+    }
+
+    Some(SocketAddr::new(
+        ip,
+        url.port_or_known_default().unwrap_or(80),
+    ))
+}
+
+/// Resolves the hosts a job is redirected to, leaving out private addresses
+/// so a redirect can't reach anything resolve_public_ip would have refused.
+pub struct PublicResolver {
+    pub allow_private_addrs: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private_addrs = self.allow_private_addrs;
+
+        Box::pin(async move {
+            let addrs = lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private_addrs || !is_private_ip(&addr.ip()))
+                .collect::<Vec<_>>();
+
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_override_pins_the_url_host() {
+        let addr = resolve_override_addr(
+            "https://staging.example.com/hook",
+            "staging.example.com:93.184.216.34",
+            false,
+        );
+        assert_eq!(addr, Some(SocketAddr::from(([93, 184, 216, 34], 443))));
+
+        let addr = resolve_override_addr(
+            "http://example.com:8080",
+            "EXAMPLE.com:[2606:2800:220:1::]",
+            false,
+        );
+        assert_eq!(addr, "[2606:2800:220:1::]:8080".parse().ok());
+
+        let other_host =
+            resolve_override_addr("https://example.com", "example.org:93.184.216.34", false);
+        assert!(other_host.is_none());
+
+        let private = resolve_override_addr("https://example.com", "example.com:10.0.0.5", false);
+        assert!(private.is_none());
+
+        let allowed = resolve_override_addr("https://example.com", "example.com:10.0.0.5", true);
+        assert_eq!(allowed, Some(SocketAddr::from(([10, 0, 0, 5], 443))));
+
+        assert!(parse_resolve_override("example.com").is_none());
+        assert!(parse_resolve_override(":93.184.216.34").is_none());
+        assert!(parse_resolve_override("example.com:not-an-ip").is_none());
+    }
+}
@@ -87,3 +87,20 @@ pub fn gen_for_time(prefix: &str, datetime: DateTime<Utc>) -> String {
 
     format!("{prefix}_{}", id)
 }
+
+/// Whether `id` could have been generated with `prefix`, allowing any
+/// url-safe suffix so ids from other systems can be reused.
+pub fn is_valid(prefix: &str, id: &str) -> bool {
+    let Some(suffix) = id
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('_'))
+    else {
+        return false;
+    };
+
+    id.len() <= 255
+        && !suffix.is_empty()
+        && suffix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -1,12 +1,18 @@
-use std::{net::IpAddr, path::PathBuf, sync::OnceLock};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::OnceLock,
+    time::Duration,
+};
 
 use anyhow::anyhow;
 use clap::{Parser, Subcommand};
 use sqlx::postgres::PgPoolOptions;
 use tokio::select;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::{drone::store::DroneStore, secrets::KeyRing};
+use crate::{broker::InvalidExecutedAtPolicy, drone::store::DroneStore, secrets::KeyRing};
 
 mod api;
 mod broker;
@@ -16,7 +22,10 @@ mod id;
 mod pg;
 mod scheduler;
 mod secrets;
+mod selftest;
+mod shutdown;
 mod signing;
+mod telemetry;
 mod usage;
 mod util;
 
@@ -27,6 +36,16 @@ pub struct GlobalConfig {
 
 pub static GLOBAL_CONFIG: OnceLock<GlobalConfig> = OnceLock::new();
 
+/// 32mb, the most response body a drone buffers unless configured otherwise.
+pub const DEFAULT_MAX_RESPONSE_BYTES_CEILING: i64 = 32 * 1024 * 1024;
+
+/// An hour, long enough for requests signed just before a rotation to land.
+pub const DEFAULT_SIGNING_KEY_GRACE_SECS: u64 = 60 * 60;
+pub const DEFAULT_MAX_VALID_REGIONS: usize = 64;
+
+/// 2mb, the most of a request body the api reads unless configured otherwise.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
 #[derive(Debug, Clone, Parser)]
 #[command(
     version,
@@ -40,6 +59,11 @@ pub struct Cli {
 
     #[command(subcommand)]
     command: Option<Commands>,
+
+    #[arg(long, global = true, env = "OTEL_ENDPOINT")]
+    /// OTLP/HTTP collector to export traces and metrics to, e.g.
+    /// http://localhost:4318.
+    otel_endpoint: Option<String>,
 }
 
 #[derive(Debug, Clone, Subcommand, PartialEq, Eq)]
@@ -59,6 +83,9 @@ pub enum Commands {
     Drone(DroneOptions),
     /// Migrate the postgres database.
     Migrate(MigrationOptions),
+    /// Schedules a job against a local echo server and checks
+    /// that its response is recorded.
+    Selftest(SelftestOptions),
 }
 
 #[derive(Debug, Clone, Parser, PartialEq, Eq)]
@@ -88,6 +115,9 @@ pub struct DevOptions {
     pool_size: u32,
     #[arg(long, default_value_t = true)]
     postgres_temporary: bool,
+    #[arg(long, env = "POSTGRES_STARTUP_TIMEOUT_SECS", default_value_t = 30)]
+    /// How long to wait for postgres to accept connections before migrating.
+    postgres_startup_timeout_secs: u64,
     #[arg(long, env = "AUTH_KEY")]
     auth_key: Option<String>,
     #[arg(
@@ -98,6 +128,10 @@ pub struct DevOptions {
     signing_key: String,
     #[arg(long, value_parser, env = "ROCKTICK_KEY_RING")]
     key_ring: Option<KeyRing>,
+    #[arg(long, env = "ALLOW_INSECURE_JOBS")]
+    /// Lets jobs set insecure_skip_tls_verify to reach targets with
+    /// self-signed certificates.
+    allow_insecure_jobs: bool,
 }
 
 #[derive(Debug, Clone, Parser, PartialEq, Eq)]
@@ -106,12 +140,19 @@ pub struct ServerOptions {
     postgres_url: String,
     #[arg(long, env = "POOL_SIZE", default_value_t = 50)]
     pool_size: u32,
+    #[arg(long, env = "READ_REPLICA_URL")]
+    /// Postgres replica the api's read-only endpoints query.
+    read_replica_url: Option<String>,
     #[arg(long, env = "API_PORT", default_value_t = 3000)]
     api_port: usize,
     #[arg(long, env = "API_HOSTNAME", default_value = "[::0]")]
     api_hostname: String,
     #[arg(long, env = "VALID_REGIONS", num_args = 1, value_delimiter = ',')]
     valid_regions: Vec<String>,
+    #[arg(long, env = "DEFAULT_REGION")]
+    /// The region used when a job doesn't specify one. Only
+    /// required when there is more than one valid region.
+    default_region: Option<String>,
     #[arg(long, env = "AUTH_KEYS", num_args = 1, value_delimiter = ',')]
     /// A comma separated string of auth keys
     auth_keys: Vec<String>,
@@ -137,6 +178,98 @@ pub struct ServerOptions {
     key_rotation_schedulers: usize,
     #[arg(long, default_value_t = 2, env = "WORKFLOW_SCHEDULER_COUNT")]
     workflow_schedulers: usize,
+    #[arg(long, default_value_t = 8, env = "MAX_CONCURRENT_WORKFLOWS")]
+    /// Workflows that may be advanced at once across every workflow
+    /// scheduler, to smooth database load when many become runnable.
+    max_concurrent_workflows: usize,
+    #[arg(long, env = "SCHEDULER_IDLE_MS")]
+    /// How long a scheduler sleeps once it runs out of work, before jitter.
+    /// Each scheduler type keeps its own default when unset.
+    scheduler_idle_ms: Option<u64>,
+    #[arg(
+        long,
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        env = "MAX_RECORD_STREAMS_PER_DRONE"
+    )]
+    /// Concurrent record_execution streams a single drone may hold open.
+    /// Drones are told apart by their client certificate when they present
+    /// one, and by drone id otherwise.
+    max_record_streams_per_drone: usize,
+    #[arg(long, env = "ALLOW_UNSIGNED_DISPATCH")]
+    /// Start the broker even if the key ring cannot decrypt tenant signing secrets.
+    allow_unsigned_dispatch: bool,
+    #[arg(long, default_value_t = DEFAULT_MAX_RESPONSE_BYTES_CEILING, env = "MAX_RESPONSE_BYTES_CEILING")]
+    /// Hard limit on the response bytes a drone buffers for any one job.
+    max_response_bytes_ceiling: i64,
+    #[arg(long, env = "BROKER_TLS_CERT", requires = "broker_tls_key")]
+    /// PEM certificate the broker serves gRPC over TLS with.
+    broker_tls_cert: Option<PathBuf>,
+    #[arg(long, env = "BROKER_TLS_KEY", requires = "broker_tls_cert")]
+    broker_tls_key: Option<PathBuf>,
+    #[arg(long, env = "BROKER_TLS_CLIENT_CA", requires = "broker_tls_cert")]
+    /// PEM CA that drone client certificates must be signed by.
+    broker_tls_client_ca: Option<PathBuf>,
+    #[arg(long, env = "DRONE_AUTH_KEY")]
+    /// Shared secret drones must present to the broker.
+    drone_auth_key: Option<String>,
+    #[arg(long, env = "ALLOW_INSECURE_JOBS")]
+    /// Lets jobs set insecure_skip_tls_verify to skip tls certificate
+    /// verification. Drones must opt in as well.
+    allow_insecure_jobs: bool,
+    #[arg(long, default_value_t = DEFAULT_SIGNING_KEY_GRACE_SECS, env = "SIGNING_KEY_GRACE_SECS")]
+    /// Seconds a tenant's previous signing key still verifies after rotation.
+    signing_key_grace_secs: u64,
+    #[arg(long, default_value_t = DEFAULT_MAX_VALID_REGIONS, env = "MAX_VALID_REGIONS")]
+    /// The most regions VALID_REGIONS may list.
+    max_valid_regions: usize,
+    #[arg(long, env = "API_RATE_LIMIT")]
+    /// Requests a second each tenant may make to the api. Unlimited when unset.
+    api_rate_limit: Option<u32>,
+    #[arg(long, env = "API_RATE_LIMIT_BURST", requires = "api_rate_limit")]
+    /// Requests a tenant may make at once, defaults to API_RATE_LIMIT.
+    api_rate_limit_burst: Option<u32>,
+    #[arg(long, env = "CORS_ALLOW_ORIGIN", num_args = 1, value_delimiter = ',')]
+    /// Origins browsers may call the api from, comma separated, or * for
+    /// any origin. Cross-origin requests are refused when unset.
+    cors_allow_origin: Vec<String>,
+    #[arg(long, default_value_t = DEFAULT_MAX_BODY_BYTES, env = "MAX_BODY_BYTES")]
+    /// The largest request body the api reads, past which it answers 413.
+    max_body_bytes: usize,
+    #[arg(long, default_value_t = 30, env = "API_DRAIN_TIMEOUT_SECS")]
+    /// How long the api keeps serving in-flight requests after Ctrl-C before
+    /// it stops anyway.
+    api_drain_timeout_secs: u64,
+    #[arg(long, default_value_t = 15, env = "BROKER_CLEANUP_INTERVAL_SECS")]
+    /// Seconds between passes releasing jobs drones never reported on.
+    broker_cleanup_interval_secs: u64,
+    #[arg(long, default_value_t = 90, env = "BROKER_CLEANUP_SAFETY_SECS")]
+    /// Seconds past a job's timeout before its lock is released.
+    broker_cleanup_safety_secs: u64,
+    #[arg(
+        long,
+        env = "BROKER_NO_CAPACITY_REGIONS",
+        num_args = 1,
+        value_delimiter = ','
+    )]
+    /// Regions whose due jobs are failed once none of their drones are alive.
+    broker_no_capacity_regions: Vec<String>,
+    #[arg(long, default_value_t = 120, env = "BROKER_NO_CAPACITY_WINDOW_SECS")]
+    /// Seconds without a drone check-in before a region has no capacity.
+    broker_no_capacity_window_secs: u64,
+    #[arg(long, default_value_t = 300, env = "BROKER_NO_CAPACITY_GRACE_SECS")]
+    /// Seconds a job in such a region may be overdue before it's failed.
+    broker_no_capacity_grace_secs: u64,
+    #[arg(long, default_value_t = 1000, env = "BROKER_MIN_CHECKIN_INTERVAL_MS")]
+    /// Check-ins a drone sends faster than this aren't written to the database.
+    broker_min_checkin_interval_ms: u64,
+    #[arg(long, value_enum, default_value_t, env = "BROKER_INVALID_EXECUTED_AT")]
+    /// What to do with executions a drone reports with an invalid time.
+    broker_invalid_executed_at: InvalidExecutedAtPolicy,
+    #[arg(long, env = "GRPC_COMPRESSION")]
+    /// Gzips the job specs sent to drones. Compressed messages from drones
+    /// are accepted either way.
+    grpc_compression: bool,
 }
 
 #[derive(Debug, Clone, Parser, PartialEq, Eq)]
@@ -153,6 +286,72 @@ pub struct BrokerOptions {
     key_ring: KeyRing,
     #[arg(long, env = "FALLBACK_SIGNING_KEY")]
     fallback_signing_key: String,
+    #[arg(
+        long,
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        env = "MAX_RECORD_STREAMS_PER_DRONE"
+    )]
+    /// Concurrent record_execution streams a single drone may hold open.
+    /// Drones are told apart by their client certificate when they present
+    /// one, and by drone id otherwise.
+    max_record_streams_per_drone: usize,
+    #[arg(long, env = "ALLOW_UNSIGNED_DISPATCH")]
+    /// Start the broker even if the key ring cannot decrypt tenant signing
+    /// secrets. Jobs for affected tenants are then dispatched unsigned.
+    allow_unsigned_dispatch: bool,
+    #[arg(long, default_value_t = DEFAULT_MAX_RESPONSE_BYTES_CEILING, env = "MAX_RESPONSE_BYTES_CEILING")]
+    /// Hard limit on the response bytes a drone buffers for any one job. Job
+    /// and tenant limits above it are clamped.
+    max_response_bytes_ceiling: i64,
+    #[arg(long, env = "BROKER_TLS_CERT", requires = "tls_key")]
+    /// PEM certificate the broker serves gRPC over TLS with.
+    tls_cert: Option<PathBuf>,
+    #[arg(long, env = "BROKER_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    #[arg(long, env = "BROKER_TLS_CLIENT_CA", requires = "tls_cert")]
+    /// PEM CA that drone client certificates must be signed by. Drones
+    /// without one are refused, making the connection mutual TLS.
+    tls_client_ca: Option<PathBuf>,
+    #[arg(long, env = "DRONE_AUTH_KEY")]
+    /// Shared secret drones must present to the broker. Any drone is
+    /// accepted when unset.
+    drone_auth_key: Option<String>,
+    #[arg(long, default_value_t = 15, env = "BROKER_CLEANUP_INTERVAL_SECS")]
+    /// Seconds between passes releasing jobs drones never reported on.
+    cleanup_interval_secs: u64,
+    #[arg(long, default_value_t = 90, env = "BROKER_CLEANUP_SAFETY_SECS")]
+    /// Seconds past a job's timeout before its lock is released and the job
+    /// dispatched again. Raise it if drones are slow to report results.
+    cleanup_safety_secs: u64,
+    #[arg(
+        long,
+        env = "BROKER_NO_CAPACITY_REGIONS",
+        num_args = 1,
+        value_delimiter = ','
+    )]
+    /// Regions whose due jobs are failed with "no drone capacity in region"
+    /// once none of their drones have checked in for a while, rather than
+    /// waiting for one. Off for every region by default.
+    no_capacity_regions: Vec<String>,
+    #[arg(long, default_value_t = 120, env = "BROKER_NO_CAPACITY_WINDOW_SECS")]
+    /// Seconds without a drone check-in before a region has no capacity.
+    no_capacity_window_secs: u64,
+    #[arg(long, default_value_t = 300, env = "BROKER_NO_CAPACITY_GRACE_SECS")]
+    /// Seconds a job in such a region may be overdue before it's failed.
+    no_capacity_grace_secs: u64,
+    #[arg(long, default_value_t = 1000, env = "BROKER_MIN_CHECKIN_INTERVAL_MS")]
+    /// Check-ins a drone sends faster than this aren't written to the database.
+    min_checkin_interval_ms: u64,
+    #[arg(long, value_enum, default_value_t, env = "BROKER_INVALID_EXECUTED_AT")]
+    /// What to do with executions a drone reports with an invalid time:
+    /// `reject` leaves them unacknowledged on the drone, `clamp` records them
+    /// at the broker's time and logs an error, `accept` does so silently.
+    invalid_executed_at: InvalidExecutedAtPolicy,
+    #[arg(long, env = "GRPC_COMPRESSION")]
+    /// Gzips the job specs sent to drones. Compressed messages from drones
+    /// are accepted either way.
+    grpc_compression: bool,
 }
 
 impl TryFrom<DevOptions> for BrokerOptions {
@@ -168,6 +367,21 @@ impl TryFrom<DevOptions> for BrokerOptions {
             pool_size: value.pool_size,
             key_ring: value.key_ring.unwrap_or(KeyRing::dev()),
             fallback_signing_key: value.signing_key,
+            max_record_streams_per_drone: 4,
+            allow_unsigned_dispatch: false,
+            max_response_bytes_ceiling: DEFAULT_MAX_RESPONSE_BYTES_CEILING,
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+            drone_auth_key: None,
+            cleanup_interval_secs: 15,
+            cleanup_safety_secs: 90,
+            no_capacity_regions: Vec::new(),
+            no_capacity_window_secs: 120,
+            no_capacity_grace_secs: 300,
+            min_checkin_interval_ms: 1000,
+            invalid_executed_at: InvalidExecutedAtPolicy::default(),
+            grpc_compression: false,
         })
     }
 }
@@ -181,6 +395,21 @@ impl From<ServerOptions> for BrokerOptions {
             pool_size: value.pool_size,
             key_ring: value.key_ring,
             fallback_signing_key: value.fallback_signing_key,
+            max_record_streams_per_drone: value.max_record_streams_per_drone,
+            allow_unsigned_dispatch: value.allow_unsigned_dispatch,
+            max_response_bytes_ceiling: value.max_response_bytes_ceiling,
+            tls_cert: value.broker_tls_cert,
+            tls_key: value.broker_tls_key,
+            tls_client_ca: value.broker_tls_client_ca,
+            drone_auth_key: value.drone_auth_key,
+            cleanup_interval_secs: value.broker_cleanup_interval_secs,
+            cleanup_safety_secs: value.broker_cleanup_safety_secs,
+            no_capacity_regions: value.broker_no_capacity_regions,
+            no_capacity_window_secs: value.broker_no_capacity_window_secs,
+            no_capacity_grace_secs: value.broker_no_capacity_grace_secs,
+            min_checkin_interval_ms: value.broker_min_checkin_interval_ms,
+            invalid_executed_at: value.broker_invalid_executed_at,
+            grpc_compression: value.grpc_compression,
         }
     }
 }
@@ -205,6 +434,14 @@ pub struct SchedulerOptions {
     key_rotation_schedulers: usize,
     #[arg(long, default_value_t = 1, env = "WORKFLOW_SCHEDULER_COUNT")]
     workflow_schedulers: usize,
+    #[arg(long, default_value_t = 8, env = "MAX_CONCURRENT_WORKFLOWS")]
+    /// Workflows that may be advanced at once across every workflow
+    /// scheduler, to smooth database load when many become runnable.
+    max_concurrent_workflows: usize,
+    #[arg(long, env = "SCHEDULER_IDLE_MS")]
+    /// How long a scheduler sleeps once it runs out of work, before jitter.
+    /// Each scheduler type keeps its own default when unset.
+    scheduler_idle_ms: Option<u64>,
     #[arg(long, value_parser, env = "KEY_RING")]
     key_ring: KeyRing,
 }
@@ -225,6 +462,8 @@ impl TryFrom<DevOptions> for SchedulerOptions {
             past_retention_schedulers: 1,
             key_rotation_schedulers: 1,
             workflow_schedulers: 1,
+            max_concurrent_workflows: 8,
+            scheduler_idle_ms: None,
             key_ring: value.key_ring.unwrap_or(KeyRing::dev()),
         })
     }
@@ -242,6 +481,8 @@ impl From<ServerOptions> for SchedulerOptions {
             past_retention_schedulers: value.past_retention_schedulers,
             key_rotation_schedulers: value.key_rotation_schedulers,
             workflow_schedulers: value.workflow_schedulers,
+            max_concurrent_workflows: value.max_concurrent_workflows,
+            scheduler_idle_ms: value.scheduler_idle_ms,
             key_ring: value.key_ring,
         }
     }
@@ -255,15 +496,50 @@ pub struct ApiOptions {
     hostname: String,
     #[arg(long, env = "VALID_REGIONS", num_args = 1, value_delimiter = ',')]
     valid_regions: Vec<String>,
+    #[arg(long, env = "DEFAULT_REGION")]
+    /// The region used when a job doesn't specify one. Only
+    /// required when there is more than one valid region.
+    default_region: Option<String>,
     #[arg(long, env = "ROCKTICK_PG")]
     postgres_url: String,
     #[arg(long, env = "POOL_SIZE", default_value_t = 20)]
     pool_size: u32,
+    #[arg(long, env = "READ_REPLICA_URL")]
+    /// Postgres replica the read-only endpoints query, such as listing jobs
+    /// or tenant usage. Everything else uses ROCKTICK_PG.
+    read_replica_url: Option<String>,
     #[arg(long, env = "AUTH_KEYS", num_args = 1, value_delimiter = ',')]
     /// A comma separated string of auth keys
     auth_keys: Option<Vec<String>>,
     #[arg(long, value_parser, env = "KEY_RING")]
     key_ring: KeyRing,
+    #[arg(long, env = "ALLOW_INSECURE_JOBS")]
+    /// Lets jobs set insecure_skip_tls_verify to skip tls certificate
+    /// verification. Drones must opt in as well.
+    allow_insecure_jobs: bool,
+    #[arg(long, default_value_t = DEFAULT_SIGNING_KEY_GRACE_SECS, env = "SIGNING_KEY_GRACE_SECS")]
+    /// Seconds a tenant's previous signing key still verifies after rotation.
+    signing_key_grace_secs: u64,
+    #[arg(long, default_value_t = DEFAULT_MAX_VALID_REGIONS, env = "MAX_VALID_REGIONS")]
+    /// The most regions VALID_REGIONS may list.
+    max_valid_regions: usize,
+    #[arg(long, env = "API_RATE_LIMIT")]
+    /// Requests a second each tenant may make to the api. Unlimited when unset.
+    api_rate_limit: Option<u32>,
+    #[arg(long, env = "API_RATE_LIMIT_BURST", requires = "api_rate_limit")]
+    /// Requests a tenant may make at once, defaults to API_RATE_LIMIT.
+    api_rate_limit_burst: Option<u32>,
+    #[arg(long, env = "CORS_ALLOW_ORIGIN", num_args = 1, value_delimiter = ',')]
+    /// Origins browsers may call the api from, comma separated, or * for
+    /// any origin. Cross-origin requests are refused when unset.
+    cors_allow_origin: Vec<String>,
+    #[arg(long, default_value_t = DEFAULT_MAX_BODY_BYTES, env = "MAX_BODY_BYTES")]
+    /// The largest request body the api reads, past which it answers 413.
+    max_body_bytes: usize,
+    #[arg(long, default_value_t = 30, env = "API_DRAIN_TIMEOUT_SECS")]
+    /// How long the api keeps serving in-flight requests after Ctrl-C before
+    /// it stops anyway.
+    api_drain_timeout_secs: u64,
 }
 
 impl TryFrom<DevOptions> for ApiOptions {
@@ -277,9 +553,19 @@ impl TryFrom<DevOptions> for ApiOptions {
                 .postgres_url
                 .ok_or(anyhow!("No postgres url provided!"))?,
             pool_size: value.pool_size,
+            read_replica_url: None,
+            default_region: Some(value.region),
             valid_regions: value.valid_regions,
             auth_keys: value.auth_key.map(|s| vec![s]),
             key_ring: value.key_ring.unwrap_or(KeyRing::dev()),
+            allow_insecure_jobs: value.allow_insecure_jobs,
+            signing_key_grace_secs: DEFAULT_SIGNING_KEY_GRACE_SECS,
+            max_valid_regions: DEFAULT_MAX_VALID_REGIONS,
+            api_rate_limit: None,
+            api_rate_limit_burst: None,
+            cors_allow_origin: Vec::new(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            api_drain_timeout_secs: 30,
         })
     }
 }
@@ -290,10 +576,20 @@ impl From<ServerOptions> for ApiOptions {
             port: value.api_port,
             hostname: value.api_hostname,
             valid_regions: value.valid_regions,
+            default_region: value.default_region,
             postgres_url: value.postgres_url,
             pool_size: value.pool_size,
+            read_replica_url: value.read_replica_url,
             auth_keys: Some(value.auth_keys),
             key_ring: value.key_ring,
+            allow_insecure_jobs: value.allow_insecure_jobs,
+            signing_key_grace_secs: value.signing_key_grace_secs,
+            max_valid_regions: value.max_valid_regions,
+            api_rate_limit: value.api_rate_limit,
+            api_rate_limit_burst: value.api_rate_limit_burst,
+            cors_allow_origin: value.cors_allow_origin,
+            max_body_bytes: value.max_body_bytes,
+            api_drain_timeout_secs: value.api_drain_timeout_secs,
         }
     }
 }
@@ -312,6 +608,78 @@ pub struct DroneOptions {
     port: usize,
     #[arg(long, value_parser, env = "DRONE_STORE_PATH")]
     store_path: PathBuf,
+    #[arg(long, default_value_t = 1024, env = "MAX_BODY_LOG_BYTES")]
+    /// Bytes of a non-2xx response body copied into the execution error.
+    max_body_log_bytes: usize,
+    #[arg(
+        long,
+        env = "REGION_AFFINITY",
+        value_delimiter = ',',
+        value_parser = parse_region_affinity
+    )]
+    /// Regions this drone prefers to serve, as region=weight pairs.
+    /// Reported at check-in; dispatch does not use it yet.
+    region_affinity: Vec<(String, i32)>,
+    #[arg(long, env = "BROKER_CA")]
+    /// PEM CA the broker's certificate is verified against. Setting it
+    /// connects to the broker over TLS, so the broker url must be https.
+    broker_ca: Option<PathBuf>,
+    #[arg(
+        long,
+        env = "DRONE_TLS_CERT",
+        requires = "tls_key",
+        requires = "broker_ca"
+    )]
+    /// PEM client certificate presented to a broker that requires mutual TLS.
+    tls_cert: Option<PathBuf>,
+    #[arg(long, env = "DRONE_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    #[arg(long, env = "DRONE_AUTH_KEY")]
+    /// Shared secret presented to the broker on every request.
+    drone_auth_key: Option<String>,
+    #[arg(long, default_value_t = 100, env = "MAX_CONCURRENT_JOBS")]
+    /// The most jobs this drone runs at once. Jobs are only fetched from
+    /// the broker while there's room for them.
+    max_concurrent_jobs: u32,
+    #[arg(long, env = "ALLOW_INSECURE_JOBS")]
+    /// Runs jobs that set insecure_skip_tls_verify without verifying the
+    /// target's certificate. Such jobs fail on drones without it.
+    allow_insecure_jobs: bool,
+    #[arg(long, default_value_t = 90, env = "DRONE_DRAIN_TIMEOUT_SECS")]
+    /// How long to wait for running jobs to finish after SIGTERM before
+    /// submitting results and unregistering from the broker.
+    drain_timeout_secs: u64,
+    #[arg(long, env = "RECORD_STARTED_EXECUTIONS")]
+    /// Records each attempt before its request is sent, so one cut short by
+    /// a crash is reported as interrupted once the drone restarts.
+    record_started_executions: bool,
+    #[arg(long, env = "DRONE_STATUS_ADDR")]
+    /// Serves local debugging endpoints such as GET /executions/{job_id}
+    /// on this address, e.g. 127.0.0.1:30003. Off when unset.
+    status_addr: Option<SocketAddr>,
+    #[arg(long, default_value_t = 60, env = "BROKER_STARTUP_TIMEOUT_SECS")]
+    /// How long to keep retrying the first broker check-in before exiting.
+    startup_checkin_timeout_secs: u64,
+    #[arg(long, env = "DISABLE_RESPONSE_DECOMPRESSION")]
+    /// Records gzip, deflate and brotli responses as the raw compressed
+    /// bytes instead of decoding them.
+    disable_response_decompression: bool,
+    #[arg(long, env = "GRPC_COMPRESSION")]
+    /// Gzips messages sent to the broker, executions especially, trading
+    /// cpu for bandwidth when the broker is far away.
+    grpc_compression: bool,
+}
+
+fn parse_region_affinity(value: &str) -> Result<(String, i32), String> {
+    let (region, weight) = value
+        .split_once('=')
+        .ok_or(format!("{value} is not in the form region=weight"))?;
+    let weight = weight
+        .trim()
+        .parse()
+        .map_err(|_| format!("{weight} is not a valid affinity weight"))?;
+
+    Ok((region.trim().to_string(), weight))
 }
 
 impl TryFrom<DevOptions> for DroneOptions {
@@ -320,13 +688,27 @@ impl TryFrom<DevOptions> for DroneOptions {
     fn try_from(value: DevOptions) -> Result<Self, Self::Error> {
         Ok(Self {
             broker_url: format!("http://[::1]:{}", value.broker_port),
-            region: value.region,
+            region: value.region.clone(),
             id: "dev-drone".to_string(),
             ip: "127.0.0.1"
                 .parse()
                 .expect("127.0.0.1 is not a valid ip apparently???"),
             port: value.drone_port,
             store_path: DroneStore::default_store_location()?,
+            max_body_log_bytes: 1024,
+            region_affinity: vec![(value.region.clone(), 100)],
+            broker_ca: None,
+            tls_cert: None,
+            tls_key: None,
+            startup_checkin_timeout_secs: 60,
+            drone_auth_key: None,
+            max_concurrent_jobs: 100,
+            allow_insecure_jobs: value.allow_insecure_jobs,
+            drain_timeout_secs: 90,
+            record_started_executions: false,
+            status_addr: None,
+            disable_response_decompression: false,
+            grpc_compression: false,
         })
     }
 }
@@ -335,13 +717,114 @@ impl TryFrom<DevOptions> for DroneOptions {
 pub struct MigrationOptions {
     #[arg(long, env = "DATABASE_URL")]
     postgres_url: String,
+    #[arg(long, env = "POSTGRES_STARTUP_TIMEOUT_SECS", default_value_t = 30)]
+    /// How long to wait for postgres to accept connections before migrating.
+    postgres_startup_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Parser, PartialEq, Eq)]
+pub struct SelftestOptions {
+    #[arg(long, env = "ROCKTICK_API_URL")]
+    /// The api of a running stack to test, e.g. http://localhost:9090.
+    /// A dev stack is started in-process when unset.
+    api_url: Option<String>,
+    #[arg(long, default_value = "127.0.0.1")]
+    /// The address drones can reach the echo server on.
+    echo_host: IpAddr,
+    #[arg(long, default_value_t = 0)]
+    /// The port the echo server listens on, picked at random when 0.
+    echo_port: u16,
+    #[arg(long, default_value_t = 90)]
+    /// How long to wait for the job's execution to be recorded.
+    timeout_secs: u64,
+    #[command(flatten)]
+    dev: DevOptions,
+}
+
+async fn run_dev(mut dev_options: DevOptions, shutdown: CancellationToken) -> anyhow::Result<()> {
+    let mut embedded = None;
+
+    if dev_options.postgres_url.is_none()
+        || dev_options
+            .postgres_url
+            .clone()
+            .is_some_and(|val| val.is_empty())
+    {
+        let (connection_url, postgresql) = pg::run_embedded(dev_options.postgres_temporary).await?;
+        embedded = Some(postgresql);
+        println!("Migrating database...");
+        pg::connect_and_migrate(
+            connection_url.clone(),
+            dev_options.pool_size,
+            Duration::from_secs(dev_options.postgres_startup_timeout_secs),
+        )
+        .await?;
+        dev_options.postgres_url = Some(connection_url)
+    }
+
+    let postgres_url = dev_options
+        .postgres_url
+        .clone()
+        .expect("Somehow no postgres url is present.");
+    println!("Connecting to {postgres_url}");
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&postgres_url)
+        .await?;
+
+    let api_config = api::Config::from_cli(dev_options.clone().try_into()?, pool.clone()).await;
+    let broker_config =
+        broker::Config::from_cli(dev_options.clone().try_into()?, pool.clone()).await;
+    let executor_config = drone::Config::from_cli(dev_options.clone().try_into()?).await;
+    let scheduler_config =
+        scheduler::Config::from_cli(dev_options.clone().try_into()?, pool.clone()).await;
+
+    let services = async {
+        select! {
+          api_res = api::start(api_config, shutdown.clone()) => {
+            println!("Api Service Stopped.");
+            api_res
+          },
+          broker_res = broker::start(broker_config) => {
+            println!("Broker Service Stopped.");
+            broker_res
+          },
+          executor_res = drone::start(executor_config) => {
+            println!("Executor Service Stopped.");
+            executor_res
+          },
+          scheduler_res = scheduler::start(scheduler_config) => {
+            println!("Scheduler Service Stopped.");
+            scheduler_res
+          },
+        }
+    };
+
+    let cleanup = async {
+        pg::close_pool(pool).await;
+
+        if let Some(postgresql) = embedded {
+            pg::stop_embedded(postgresql).await;
+        }
+    };
+
+    shutdown::run_until_drained(services, cleanup).await
 }
 
 impl Cli {
-    async fn run(self) -> anyhow::Result<()> {
-        let is_dev = self.command.is_none() || matches!(self.command, Some(Commands::Dev(_)));
+    fn is_dev(&self) -> bool {
+        match &self.command {
+            None | Some(Commands::Dev(_)) => true,
+            Some(Commands::Selftest(options)) => options.api_url.is_none(),
+            Some(_) => false,
+        }
+    }
 
-        let global_config = GlobalConfig { is_dev };
+    async fn run(self) -> anyhow::Result<()> {
+        let global_config = GlobalConfig {
+            is_dev: self.is_dev(),
+        };
 
         GLOBAL_CONFIG
             .set(global_config)
@@ -349,7 +832,7 @@ impl Cli {
 
         match self.command {
             None | Some(Commands::Dev(_)) => {
-                let mut dev_options = self
+                let dev_options = self
                     .command
                     .and_then(|cmd| {
                         if let Commands::Dev(dev_opts) = cmd {
@@ -360,60 +843,7 @@ impl Cli {
                     })
                     .unwrap_or(self.dev);
 
-                if dev_options.postgres_url.is_none()
-                    || dev_options
-                        .postgres_url
-                        .clone()
-                        .is_some_and(|val| val.is_empty())
-                {
-                    let connection_url = pg::run_embedded(dev_options.postgres_temporary).await?;
-                    let temp_pool =
-                        pg::create_pool(connection_url.clone(), dev_options.pool_size).await?;
-                    println!("Migrating database...");
-                    pg::migrate_pg(&temp_pool).await?;
-                    dev_options.postgres_url = Some(connection_url)
-                }
-
-                let postgres_url = dev_options
-                    .postgres_url
-                    .clone()
-                    .expect("Somehow no postgres url is present.");
-                println!("Connecting to {postgres_url}");
-
-                let pool = PgPoolOptions::new()
-                    .max_connections(5)
-                    .connect(&postgres_url)
-                    .await?;
-
-                let api_config =
-                    api::Config::from_cli(dev_options.clone().try_into()?, pool.clone()).await;
-                let broker_config =
-                    broker::Config::from_cli(dev_options.clone().try_into()?, pool.clone()).await;
-                let executor_config =
-                    drone::Config::from_cli(dev_options.clone().try_into()?).await;
-                let scheduler_config =
-                    scheduler::Config::from_cli(dev_options.clone().try_into()?, pool.clone())
-                        .await;
-
-                select! {
-                  api_res = api::start(api_config) => {
-                    println!("Api Service Stopped.");
-                    api_res?;
-                  },
-                  broker_res = broker::start(broker_config) => {
-                    println!("Broker Service Stopped.");
-                    broker_res?;
-                  },
-                  executor_res = drone::start(executor_config) => {
-                    println!("Executor Service Stopped.");
-                    executor_res?;
-                  },
-                  scheduler_res = scheduler::start(scheduler_config) => {
-                    println!("Scheduler Service Stopped.");
-                    scheduler_res?;
-                  },
-                  _ = tokio::signal::ctrl_c() => println!("Received Ctrl-C.")
-                }
+                run_dev(dev_options, shutdown::on_ctrl_c()).await?;
             }
             Some(Commands::Server(server_config)) => {
                 let pool =
@@ -427,36 +857,49 @@ impl Cli {
                 let scheduler_config =
                     scheduler::Config::from_cli(server_config.clone().into(), pool.clone()).await;
 
-                select! {
-                  api_res = api::start(api_config) => {
-                    println!("Api Service Stopped.");
-                    api_res?;
-                  },
-                  broker_res = broker::start(broker_config) => {
-                    println!("Broker Service Stopped.");
-                    broker_res?;
-                  },
-                  scheduler_res = scheduler::start(scheduler_config) => {
-                    println!("Scheduler Service Stopped.");
-                    scheduler_res?;
-                  },
-                  _ = tokio::signal::ctrl_c() => println!("Received Ctrl-C.")
-                }
+                let shutdown = shutdown::on_ctrl_c();
+                let services = async {
+                    select! {
+                      api_res = api::start(api_config, shutdown.clone()) => {
+                        println!("Api Service Stopped.");
+                        api_res
+                      },
+                      broker_res = broker::start(broker_config) => {
+                        println!("Broker Service Stopped.");
+                        broker_res
+                      },
+                      scheduler_res = scheduler::start(scheduler_config) => {
+                        println!("Scheduler Service Stopped.");
+                        scheduler_res
+                      },
+                    }
+                };
+
+                shutdown::run_until_drained(services, pg::close_pool(pool)).await?;
             }
             Some(Commands::Api(api_config)) => {
                 let pool =
                     pg::create_pool(api_config.postgres_url.clone(), api_config.pool_size).await?;
-                let config = api::Config::from_cli(api_config, pool).await;
-                api::start(config).await?;
-                println!("Api Service Stopped.");
+                let config = api::Config::from_cli(api_config, pool.clone()).await;
+                let service = async {
+                    let res = api::start(config, shutdown::on_ctrl_c()).await;
+                    println!("Api Service Stopped.");
+                    res
+                };
+                shutdown::run_until_drained(service, pg::close_pool(pool)).await?;
             }
             Some(Commands::Broker(broker_config)) => {
                 let pool =
                     pg::create_pool(broker_config.postgres_url.clone(), broker_config.pool_size)
                         .await?;
-                let config = broker::Config::from_cli(broker_config, pool).await;
-                broker::start(config).await?;
-                println!("Broker Service Stopped.")
+                let config = broker::Config::from_cli(broker_config, pool.clone()).await;
+                let service = async {
+                    let res = broker::start(config).await;
+                    println!("Broker Service Stopped.");
+                    res
+                };
+                shutdown::run_until_shutdown(shutdown::on_ctrl_c(), service, pg::close_pool(pool))
+                    .await?;
             }
             Some(Commands::Scheduler(scheduler_config)) => {
                 let pool = pg::create_pool(
@@ -464,9 +907,14 @@ impl Cli {
                     scheduler_config.pool_size,
                 )
                 .await?;
-                let config = scheduler::Config::from_cli(scheduler_config, pool).await;
-                scheduler::start(config).await?;
-                println!("Scheduler Service Stopped.");
+                let config = scheduler::Config::from_cli(scheduler_config, pool.clone()).await;
+                let service = async {
+                    let res = scheduler::start(config).await;
+                    println!("Scheduler Service Stopped.");
+                    res
+                };
+                shutdown::run_until_shutdown(shutdown::on_ctrl_c(), service, pg::close_pool(pool))
+                    .await?;
             }
             Some(Commands::Drone(executor_config)) => {
                 let config = drone::Config::from_cli(executor_config).await;
@@ -474,20 +922,63 @@ impl Cli {
                 println!("Executor Service Stopped.");
             }
             Some(Commands::Migrate(migrate_config)) => {
-                let pool = pg::create_pool(migrate_config.postgres_url, 2).await?;
-                pg::migrate_pg(&pool).await?;
+                pg::connect_and_migrate(
+                    migrate_config.postgres_url,
+                    2,
+                    Duration::from_secs(migrate_config.postgres_startup_timeout_secs),
+                )
+                .await?;
+            }
+            Some(Commands::Selftest(selftest_config)) => {
+                let api_url = selftest_config
+                    .api_url
+                    .clone()
+                    .unwrap_or(format!("http://127.0.0.1:{}", selftest_config.dev.api_port));
+
+                let options = selftest::Options {
+                    api_url,
+                    auth_key: selftest_config.dev.auth_key.clone(),
+                    echo_addr: (selftest_config.echo_host, selftest_config.echo_port).into(),
+                    timeout: Duration::from_secs(selftest_config.timeout_secs),
+                };
+
+                let report = if selftest_config.api_url.is_some() {
+                    selftest::run(options).await?
+                } else {
+                    select! {
+                      dev_res = run_dev(selftest_config.dev, shutdown::on_ctrl_c()) => {
+                        dev_res?;
+                        return Err(anyhow!("Dev stack stopped before the selftest finished."));
+                      },
+                      report = selftest::run(options) => report?,
+                    }
+                };
+
+                println!("{report}");
             }
         }
 
         Ok(())
     }
 
+    fn service_name(&self) -> &'static str {
+        match self.command {
+            None | Some(Commands::Dev(_)) => "rocktick-dev",
+            Some(Commands::Server(_)) => "rocktick-server",
+            Some(Commands::Broker(_)) => "rocktick-broker",
+            Some(Commands::Scheduler(_)) => "rocktick-scheduler",
+            Some(Commands::Api(_)) => "rocktick-api",
+            Some(Commands::Drone(_)) => "rocktick-drone",
+            Some(Commands::Migrate(_)) => "rocktick-migrate",
+            Some(Commands::Selftest(_)) => "rocktick-selftest",
+        }
+    }
+
     fn layer<S>(&self) -> impl Layer<S>
     where
         S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
     {
-        let is_dev = self.command.is_none() || matches!(self.command, Some(Commands::Dev(_)));
-        if is_dev {
+        if self.is_dev() {
             None
         } else {
             Some(tracing_subscriber::fmt::layer().with_filter(EnvFilter::new("info")))
@@ -511,16 +1002,33 @@ fn main() -> anyhow::Result<()> {
         ));
     }
 
+    let tracer_provider = cli
+        .otel_endpoint
+        .as_deref()
+        .map(|endpoint| telemetry::tracer_provider(endpoint, cli.service_name()))
+        .transpose()?;
+    let meter_provider = cli
+        .otel_endpoint
+        .as_deref()
+        .map(|endpoint| telemetry::meter_provider(endpoint, cli.service_name()))
+        .transpose()?;
+
+    if let Some(meter_provider) = &meter_provider {
+        opentelemetry::global::set_meter_provider(meter_provider.clone());
+    }
+
     let console_layer = console_subscriber::spawn();
     let stdout_layer = cli.layer();
+    let otel_layer = tracer_provider.as_ref().map(telemetry::layer);
 
     tracing_subscriber::registry()
         .with(console_layer)
         .with(stdout_layer)
+        .with(otel_layer)
         .with(sentry::integrations::tracing::layer())
         .init();
 
-    tokio::runtime::Builder::new_multi_thread()
+    let result = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?
         .block_on(async {
@@ -534,5 +1042,19 @@ fn main() -> anyhow::Result<()> {
             }
 
             Ok(())
-        })
+        });
+
+    if let Some(tracer_provider) = tracer_provider
+        && let Err(err) = tracer_provider.shutdown()
+    {
+        eprintln!("Failed to flush traces: {err}");
+    }
+
+    if let Some(meter_provider) = meter_provider
+        && let Err(err) = meter_provider.shutdown()
+    {
+        eprintln!("Failed to flush metrics: {err}");
+    }
+
+    result
 }
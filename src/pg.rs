@@ -1,6 +1,9 @@
+use std::time::Duration;
+
 use indoc::indoc;
 use postgresql_embedded::{PostgreSQL, Settings, VersionReq};
 use sqlx::{Pool, Postgres, migrate::Migrator, postgres::PgPoolOptions};
+use tokio::time::Instant;
 
 include!(concat!(env!("OUT_DIR"), "/embedded_postgres_version.rs"));
 
@@ -10,17 +13,14 @@ pub async fn create_pool(postgres_url: String, count: u32) -> anyhow::Result<Poo
         .connect(&postgres_url)
         .await?;
 
-    let cleanup_pool = pool.clone();
-
-    tokio::spawn(async move {
-        let _ = tokio::signal::ctrl_c().await;
-        println!("Closing postgres connection.");
-        cleanup_pool.close().await;
-    });
-
     Ok(pool)
 }
 
+pub async fn close_pool(pool: Pool<Postgres>) {
+    println!("Closing postgres connection.");
+    pool.close().await;
+}
+
 pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
 
 pub async fn migrate_pg(pool: &Pool<Postgres>) -> anyhow::Result<()> {
@@ -29,7 +29,63 @@ pub async fn migrate_pg(pool: &Pool<Postgres>) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn run_embedded(temporary: bool) -> anyhow::Result<String> {
+const INITIAL_STARTUP_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_STARTUP_BACKOFF: Duration = Duration::from_secs(5);
+
+async fn retry_until<T, F, Fut>(
+    timeout: Duration,
+    initial_backoff: Duration,
+    mut attempt: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut backoff = initial_backoff;
+
+    loop {
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(err) if Instant::now() + backoff < deadline => {
+                println!("Postgres not ready ({err}), retrying in {backoff:?}...");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_STARTUP_BACKOFF);
+            }
+            Err(err) => {
+                return Err(err.context(format!("Postgres was not ready within {timeout:?}")));
+            }
+        }
+    }
+}
+
+/// Waits for postgres to accept connections and then runs migrations,
+/// retrying with backoff until `timeout` has elapsed.
+pub async fn connect_and_migrate(
+    postgres_url: String,
+    count: u32,
+    timeout: Duration,
+) -> anyhow::Result<Pool<Postgres>> {
+    retry_until(timeout, INITIAL_STARTUP_BACKOFF, || {
+        let postgres_url = postgres_url.clone();
+
+        async move {
+            let pool = create_pool(postgres_url, count).await?;
+
+            if let Err(err) = migrate_pg(&pool).await {
+                pool.close().await;
+                return Err(err);
+            }
+
+            Ok(pool)
+        }
+    })
+    .await
+}
+
+/// Starts the embedded postgres, returning its url and the instance to stop
+/// once everything using it has shut down.
+pub async fn run_embedded(temporary: bool) -> anyhow::Result<(String, PostgreSQL)> {
     let mut data_dir = std::env::current_dir()?;
     data_dir.push(".rocktick");
     data_dir.push("pg");
@@ -63,14 +119,56 @@ pub async fn run_embedded(temporary: bool) -> anyhow::Result<String> {
     let settings = postgresql.settings();
     let url = settings.url(db_name);
 
-    tokio::spawn(async move {
-        let _ = tokio::signal::ctrl_c().await;
-        println!("Stopping embedded postgres instance.");
-        postgresql
-            .stop()
-            .await
-            .expect("Failed to gracefully stop postgres.")
-    });
+    Ok((url, postgresql))
+}
+
+pub async fn stop_embedded(postgresql: PostgreSQL) {
+    println!("Stopping embedded postgres instance.");
 
-    Ok(url)
+    if let Err(err) = postgresql.stop().await {
+        println!("Failed to gracefully stop postgres: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_until_recovers_from_transient_failures() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let result = retry_until(Duration::from_secs(5), Duration::from_millis(1), || {
+            let attempts = attempts.clone();
+            async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 3 {
+                    return Err(anyhow!("connection refused"));
+                }
+                Ok("connected")
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "connected");
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_gives_up_after_timeout() {
+        let result: anyhow::Result<()> = retry_until(
+            Duration::from_millis(50),
+            Duration::from_millis(1),
+            || async { Err(anyhow!("connection refused")) },
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
 }
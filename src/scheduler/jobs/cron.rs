@@ -1,8 +1,8 @@
 use std::hash::{DefaultHasher, Hash, Hasher};
 
-use chrono::{TimeDelta, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use croner::{
-    CronIterator, Direction,
+    Cron, CronIterator, Direction,
     parser::{CronParser, Seconds},
 };
 
@@ -14,6 +14,37 @@ use crate::{
 #[derive(Clone, Copy)]
 pub struct CronScheduler;
 
+/// Most missed runs a `fire_all` cron job is backfilled with.
+const MAX_CATCHUP_RUNS: usize = 60;
+
+/// Runs to schedule for occurrences that came due between the last scheduled
+/// run and `now`, as the job's catch-up policy asks. `skip` drops them.
+fn catchup_times(
+    schedule: &Cron,
+    catchup_policy: &str,
+    last_scheduled_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let Some(last_scheduled_at) = last_scheduled_at else {
+        return Vec::new();
+    };
+
+    // Walking back from now keeps the backfill to the most recent runs, so a
+    // long outage doesn't flood the target.
+    let missed = CronIterator::new(schedule.clone(), now, false, Direction::Backward)
+        .take_while(|datetime| *datetime > last_scheduled_at);
+
+    match catchup_policy {
+        "fire_once" => missed.take(1).map(|_| now).collect(),
+        "fire_all" => {
+            let mut times: Vec<_> = missed.take(MAX_CATCHUP_RUNS).collect();
+            times.reverse();
+            times
+        }
+        _ => Vec::new(),
+    }
+}
+
 #[async_trait::async_trait]
 impl Scheduler for CronScheduler {
     #[tracing::instrument(name = "CronScheduler::run_once")]
@@ -41,8 +72,16 @@ impl Scheduler for CronScheduler {
             job.timeout_ms as timeout_ms,
             job.max_retries as max_retries,
             job.max_response_bytes as max_response_bytes,
+            job.fresh_connection as fresh_connection,
+            job.insecure_skip_tls_verify as insecure_skip_tls_verify,
+            job.transfer_encoding as transfer_encoding,
+            job.body_read_timeout_ms as body_read_timeout_ms,
+            job.follow_redirects as follow_redirects,
+            job.catchup_policy as catchup_policy,
+            job.resumed_at as resumed_at,
             job.created_at as created_at,
             job.start_at as start_at,
+            job.end_at as end_at,
             job.request_id as request_id,
             job.tenant_id as tenant_id
           FROM
@@ -58,6 +97,8 @@ impl Scheduler for CronScheduler {
             )
             AND job.error IS NULL
             AND job.deleted_at IS NULL
+            AND job.paused = false
+            AND job.completed_at IS NULL
           LIMIT 1 FOR UPDATE OF job SKIP LOCKED;
           "#
         )
@@ -111,18 +152,35 @@ impl Scheduler for CronScheduler {
             return Ok(());
         }
 
-        let start_time = latest_scheduled
-            .map(|r| r.scheduled_at)
-            .unwrap_or(Utc::now())
-            .max(Utc::now());
+        let now = Utc::now();
+        let last_scheduled_at = latest_scheduled.map(|r| r.scheduled_at);
+        let start_time = last_scheduled_at.unwrap_or(now).max(now);
+
+        // Runs that came due while the job was paused aren't missed ones.
+        let catchup_from = last_scheduled_at.map(|last_scheduled_at| {
+            last_scheduled_at.max(cron_job.resumed_at.unwrap_or(last_scheduled_at))
+        });
 
         let schedule = schedule.unwrap();
         let mut count = 0;
-        let mut times = Vec::new();
-        let now = Utc::now();
+        let mut times = catchup_times(&schedule, &cron_job.catchup_policy, catchup_from, now);
+
+        if !times.is_empty() {
+            tracing::info! {
+              cron_job_id = cron_job.id,
+              count = times.len(),
+              catchup_policy = cron_job.catchup_policy,
+              "Catching up on missed cron runs."
+            };
+        }
+
+        // Nothing runs before the cron job's window opens, but an occurrence right
+        // at start_at still does.
+        let opens_later = cron_job.start_at > start_time;
+        let start_time = start_time.max(cron_job.start_at);
 
         let cron_times =
-            CronIterator::new(schedule, start_time, false, Direction::Forward).take(70);
+            CronIterator::new(schedule, start_time, opens_later, Direction::Forward).take(70);
 
         for datetime in cron_times {
             count += 1;
@@ -139,6 +197,13 @@ impl Scheduler for CronScheduler {
             }
         }
 
+        let ended = cron_job
+            .end_at
+            .is_some_and(|end_at| times.last().is_some_and(|time| *time > end_at));
+        times.retain(|time| {
+            *time >= cron_job.start_at && cron_job.end_at.is_none_or(|end_at| *time <= end_at)
+        });
+
         for scheduled_time in times {
             let new_job_id = id::gen_for_time("scheduled", scheduled_time);
 
@@ -161,7 +226,12 @@ impl Scheduler for CronScheduler {
               request_id,
               timeout_ms,
               max_retries,
-              max_response_bytes
+              max_response_bytes,
+              fresh_connection,
+              insecure_skip_tls_verify,
+              transfer_encoding,
+              body_read_timeout_ms,
+              follow_redirects
             )
           VALUES
             (
@@ -174,7 +244,12 @@ impl Scheduler for CronScheduler {
               $7,
               $8,
               $9,
-              $10
+              $10,
+              $11,
+              $12,
+              $13,
+              $14,
+              $15
             );
           "#,
                 new_job_id,
@@ -187,13 +262,278 @@ impl Scheduler for CronScheduler {
                 cron_job.timeout_ms,
                 cron_job.max_retries,
                 cron_job.max_response_bytes,
+                cron_job.fresh_connection,
+                cron_job.insecure_skip_tls_verify,
+                cron_job.transfer_encoding,
+                cron_job.body_read_timeout_ms,
+                cron_job.follow_redirects,
             )
             .execute(&mut *tx)
             .await?;
         }
 
+        if ended {
+            sqlx::query!(
+                r#"
+          UPDATE cron_jobs
+          SET completed_at = now()
+          WHERE id = $1;
+          "#,
+                cron_job.id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            tracing::info! {
+              cron_job_id = cron_job.id,
+              "Cron job passed its end_at, marking it completed."
+            };
+        }
+
         tx.commit().await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sqlx::PgPool;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::secrets::KeyRing;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_catchup_times_follow_policy() {
+        let hourly = CronParser::builder()
+            .seconds(Seconds::Optional)
+            .build()
+            .parse("0 * * * *")
+            .unwrap();
+        let last_scheduled_at = Some(at("2026-01-01T00:00:00Z"));
+        let now = at("2026-01-01T03:30:00Z");
+
+        assert!(catchup_times(&hourly, "skip", last_scheduled_at, now).is_empty());
+        assert!(catchup_times(&hourly, "fire_all", None, now).is_empty());
+
+        assert_eq!(
+            catchup_times(&hourly, "fire_once", last_scheduled_at, now),
+            vec![now]
+        );
+        assert_eq!(
+            catchup_times(&hourly, "fire_all", last_scheduled_at, now),
+            vec![
+                at("2026-01-01T01:00:00Z"),
+                at("2026-01-01T02:00:00Z"),
+                at("2026-01-01T03:00:00Z"),
+            ]
+        );
+
+        let nothing_missed = Some(at("2026-01-01T04:00:00Z"));
+        assert!(catchup_times(&hourly, "fire_once", nothing_missed, now).is_empty());
+    }
+
+    #[test]
+    fn test_fire_all_keeps_most_recent_runs() {
+        let every_minute = CronParser::builder()
+            .seconds(Seconds::Optional)
+            .build()
+            .parse("* * * * *")
+            .unwrap();
+        let now = at("2026-01-02T00:00:30Z");
+
+        let times = catchup_times(
+            &every_minute,
+            "fire_all",
+            Some(at("2026-01-01T00:00:00Z")),
+            now,
+        );
+
+        assert_eq!(times.len(), MAX_CATCHUP_RUNS);
+        assert_eq!(times.last(), Some(&at("2026-01-02T00:00:00Z")));
+        assert!(times.is_sorted());
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_runs_stay_within_start_and_end(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_a', 'GET', 'https://example.com', '{}')
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO cron_jobs (id, region, request_id, schedule, max_retries, start_at, end_at)
+            VALUES (
+              'cron_a', 'na-east', 'request_a', '* * * * *', 0,
+              now() + interval '3 minutes', now() + interval '8 minutes')
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = SchedulerContext {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(1)),
+        };
+
+        let mut reached_end = false;
+        CronScheduler::run_once(&ctx, &mut reached_end).await?;
+        assert!(!reached_end);
+
+        let job = sqlx::query!(
+            r#"
+            SELECT
+              job.completed_at,
+              COUNT(sj.id) as "scheduled!",
+              bool_and(sj.scheduled_at BETWEEN job.start_at AND job.end_at) as "in_window!"
+            FROM cron_jobs job
+            JOIN scheduled_jobs sj ON sj.cron_job_id = job.id
+            WHERE job.id = 'cron_a'
+            GROUP BY job.id
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert!(job.completed_at.is_some());
+        assert!((4..=6).contains(&job.scheduled));
+        assert!(job.in_window);
+
+        CronScheduler::run_once(&ctx, &mut reached_end).await?;
+        assert!(reached_end);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_paused_cron_is_not_scheduled(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_a', 'GET', 'https://example.com', '{}')
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        // Where a cron job that kept failing is left once it pauses itself.
+        sqlx::query!(
+            r#"
+            INSERT INTO cron_jobs (
+              id, region, request_id, schedule, max_retries, pause_after_failures,
+              consecutive_failures, paused)
+            VALUES ('cron_a', 'na-east', 'request_a', '* * * * *', 0, 3, 3, true)
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = SchedulerContext {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(1)),
+        };
+
+        let mut reached_end = false;
+        CronScheduler::run_once(&ctx, &mut reached_end).await?;
+        assert!(reached_end);
+
+        let scheduled = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM scheduled_jobs"#)
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(scheduled, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_resumed_cron_skips_runs_missed_while_paused(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES ('request_a', 'GET', 'https://example.com', '{}')
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO cron_jobs (
+              id, region, request_id, schedule, max_retries, catchup_policy, start_at, paused)
+            VALUES (
+              'cron_a', 'na-east', 'request_a', '* * * * *', 0, 'fire_all',
+              now() - interval '3 hours', true)
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        // The last run before the pause, which has long since executed.
+        sqlx::query!(
+            r#"
+            INSERT INTO scheduled_jobs
+              (id, hash, region, cron_job_id, scheduled_at, request_id, lock_nonce, max_retries)
+            VALUES
+              ('scheduled_a', 0, 'na-east', 'cron_a', now() - interval '2 hours', 'request_a', 1, 0)
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        // What the resume endpoint does.
+        sqlx::query!(
+            r#"
+            UPDATE cron_jobs
+            SET paused = false, resumed_at = CASE WHEN paused THEN now() ELSE resumed_at END
+            WHERE id = 'cron_a'
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = SchedulerContext {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(1)),
+        };
+
+        let mut reached_end = false;
+        CronScheduler::run_once(&ctx, &mut reached_end).await?;
+        assert!(!reached_end);
+
+        let scheduled = sqlx::query!(
+            r#"
+            SELECT
+              count(*) as "count!",
+              count(*) FILTER (WHERE sj.scheduled_at < job.resumed_at) as "backfilled!"
+            FROM scheduled_jobs sj
+            JOIN cron_jobs job ON job.id = sj.cron_job_id
+            WHERE sj.id != 'scheduled_a'
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert!(scheduled.count > 0);
+        assert_eq!(scheduled.backfilled, 0);
+
+        Ok(())
+    }
+}
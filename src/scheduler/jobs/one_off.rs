@@ -25,6 +25,12 @@ impl Scheduler for OneOffScheduler {
       job.timeout_ms as timeout_ms,
       job.max_retries as max_retries,
       job.max_response_bytes as max_response_bytes,
+      job.fresh_connection as fresh_connection,
+      job.insecure_skip_tls_verify as insecure_skip_tls_verify,
+      job.transfer_encoding as transfer_encoding,
+      job.body_read_timeout_ms as body_read_timeout_ms,
+      job.follow_redirects as follow_redirects,
+      job.resolve_override as resolve_override,
       job.request_id as request_id,
       job.tenant_id as tenant_id
     FROM one_off_jobs as job
@@ -33,6 +39,7 @@ impl Scheduler for OneOffScheduler {
       ON job.id = scheduled.one_off_job_id
     WHERE scheduled.id IS NULL
       AND job.deleted_at IS NULL
+    ORDER BY job.execute_at ASC
     LIMIT 1 FOR UPDATE OF job SKIP LOCKED;
     "#
         )
@@ -75,7 +82,13 @@ impl Scheduler for OneOffScheduler {
           request_id,
           timeout_ms,
           max_retries,
-          max_response_bytes
+          max_response_bytes,
+          fresh_connection,
+          insecure_skip_tls_verify,
+          transfer_encoding,
+          body_read_timeout_ms,
+          follow_redirects,
+          resolve_override
         )
       VALUES
         (
@@ -88,7 +101,13 @@ impl Scheduler for OneOffScheduler {
           $7,
           $8,
           $9,
-          $10
+          $10,
+          $11,
+          $12,
+          $13,
+          $14,
+          $15,
+          $16
         );
       "#,
             new_job_id,
@@ -100,7 +119,13 @@ impl Scheduler for OneOffScheduler {
             to_schedule.request_id,
             to_schedule.timeout_ms,
             to_schedule.max_retries,
-            to_schedule.max_response_bytes
+            to_schedule.max_response_bytes,
+            to_schedule.fresh_connection,
+            to_schedule.insecure_skip_tls_verify,
+            to_schedule.transfer_encoding,
+            to_schedule.body_read_timeout_ms,
+            to_schedule.follow_redirects,
+            to_schedule.resolve_override
         )
         .execute(&mut *tx)
         .await?;
@@ -110,3 +135,68 @@ impl Scheduler for OneOffScheduler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use sqlx::PgPool;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::secrets::KeyRing;
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_imminent_one_off_scheduled_first(pool: PgPool) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let jobs = [
+            ("job_far", now + Duration::days(30)),
+            ("job_soon", now + Duration::seconds(2)),
+        ];
+
+        for (job_id, execute_at) in jobs {
+            let request_id = format!("{job_id}_request");
+
+            sqlx::query!(
+                "
+                INSERT INTO http_requests (id, method, url, headers)
+                VALUES ($1, 'GET', 'https://example.com', '{}')
+                ",
+                request_id
+            )
+            .execute(&pool)
+            .await?;
+
+            sqlx::query!(
+                "
+                INSERT INTO one_off_jobs (id, region, request_id, execute_at, max_retries)
+                VALUES ($1, 'na-east', $2, $3, 0)
+                ",
+                job_id,
+                request_id,
+                execute_at.timestamp()
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        let ctx = SchedulerContext {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(1)),
+        };
+
+        let mut reached_end = false;
+        OneOffScheduler::run_once(&ctx, &mut reached_end).await?;
+
+        let scheduled = sqlx::query_scalar!("SELECT one_off_job_id FROM scheduled_jobs")
+            .fetch_all(&pool)
+            .await?;
+
+        assert_eq!(scheduled, vec![Some("job_soon".to_string())]);
+
+        Ok(())
+    }
+}
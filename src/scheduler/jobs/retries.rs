@@ -3,11 +3,53 @@ use std::{
     time::Duration,
 };
 
+use chrono::{DateTime, Utc};
+
 use crate::{
     id,
     scheduler::{Scheduler, SchedulerContext},
 };
 
+const BASE_RETRY_DELAY_MS: u64 = 60 * 1000;
+const MAX_RETRY_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Exponential backoff off the failed execution, doubling from `base_delay_ms`
+/// up to `max_delay_ms`. Jobs without their own backoff retry after 1m, 2m,
+/// 4m, ... capped at one day.
+fn next_retry_time(
+    executed_at: DateTime<Utc>,
+    attempts_made: i32,
+    base_delay_ms: Option<i32>,
+    max_delay_ms: Option<i32>,
+) -> DateTime<Utc> {
+    let base_delay_ms = base_delay_ms.map_or(BASE_RETRY_DELAY_MS, |ms| ms.max(0) as u64);
+    let max_delay_ms = max_delay_ms
+        .map_or(MAX_RETRY_DELAY_MS, |ms| ms.max(0) as u64)
+        .min(MAX_RETRY_DELAY_MS);
+
+    let factor = 2u64.saturating_pow(attempts_made.max(0) as u32);
+    let wait_time = base_delay_ms.saturating_mul(factor).min(max_delay_ms);
+
+    executed_at + Duration::from_millis(wait_time)
+}
+
+/// Never retry sooner than the target asked for with `Retry-After`, up to
+/// the same one day cap as the backoff.
+fn honor_retry_after(
+    backoff_time: DateTime<Utc>,
+    executed_at: DateTime<Utc>,
+    retry_after_secs: Option<i64>,
+) -> DateTime<Utc> {
+    let Some(retry_after_secs) = retry_after_secs else {
+        return backoff_time;
+    };
+
+    let wait_time = Duration::from_secs(retry_after_secs.max(0) as u64)
+        .min(Duration::from_millis(MAX_RETRY_DELAY_MS));
+
+    backoff_time.max(executed_at + wait_time)
+}
+
 #[derive(Clone, Copy)]
 pub struct RetryScheduler;
 
@@ -27,11 +69,22 @@ impl Scheduler for RetryScheduler {
       job.timeout_ms as timeout_ms,
       job.max_retries as max_retries,
       job.max_response_bytes as max_response_bytes,
+      job.fresh_connection as fresh_connection,
+      job.insecure_skip_tls_verify as insecure_skip_tls_verify,
+      job.transfer_encoding as transfer_encoding,
+      job.body_read_timeout_ms as body_read_timeout_ms,
+      job.follow_redirects as follow_redirects,
+      job.resolve_override as resolve_override,
       job.request_id as request_id,
       job.tenant_id as tenant_id,
-      exec.executed_at as executed_at
+      exec.executed_at as executed_at,
+      exec.retry_after_secs as retry_after_secs,
+      COALESCE(one_off.retry_backoff_ms, cron.retry_backoff_ms) as retry_backoff_ms,
+      COALESCE(one_off.retry_backoff_max_ms, cron.retry_backoff_max_ms) as retry_backoff_max_ms
     FROM scheduled_jobs as job
     INNER JOIN job_executions as exec ON job.execution_id = exec.id
+    LEFT JOIN one_off_jobs as one_off ON job.one_off_job_id = one_off.id
+    LEFT JOIN cron_jobs as cron ON job.cron_job_id = cron.id
     LEFT JOIN
       scheduled_jobs as pending_retry
       ON job.id = pending_retry.retry_for_id
@@ -92,9 +145,16 @@ impl Scheduler for RetryScheduler {
         let attempts_made = retry_query.attempts.unwrap();
         let attempts_remaining = to_retry.max_retries - 1;
 
-        let base_delay_ms = 60 * 1000;
-        let wait_time = base_delay_ms * (2 ^ attempts_made as u64);
-        let next_time = to_retry.executed_at + Duration::from_millis(wait_time);
+        let next_time = honor_retry_after(
+            next_retry_time(
+                to_retry.executed_at,
+                attempts_made,
+                to_retry.retry_backoff_ms,
+                to_retry.retry_backoff_max_ms,
+            ),
+            to_retry.executed_at,
+            to_retry.retry_after_secs,
+        );
 
         let new_job_id = id::gen_for_time("scheduled", next_time);
 
@@ -119,7 +179,13 @@ impl Scheduler for RetryScheduler {
           request_id,
           timeout_ms,
           max_retries,
-          max_response_bytes
+          max_response_bytes,
+          fresh_connection,
+          insecure_skip_tls_verify,
+          transfer_encoding,
+          body_read_timeout_ms,
+          follow_redirects,
+          resolve_override
         )
       VALUES
         (
@@ -134,7 +200,13 @@ impl Scheduler for RetryScheduler {
           $9,
           $10,
           $11,
-          $12
+          $12,
+          $13,
+          $14,
+          $15,
+          $16,
+          $17,
+          $18
         );
       "#,
             new_job_id,
@@ -148,7 +220,13 @@ impl Scheduler for RetryScheduler {
             to_retry.request_id,
             to_retry.timeout_ms,
             attempts_remaining,
-            to_retry.max_response_bytes
+            to_retry.max_response_bytes,
+            to_retry.fresh_connection,
+            to_retry.insecure_skip_tls_verify,
+            to_retry.transfer_encoding,
+            to_retry.body_read_timeout_ms,
+            to_retry.follow_redirects,
+            to_retry.resolve_override
         )
         .execute(&mut *tx)
         .await?;
@@ -158,3 +236,69 @@ impl Scheduler for RetryScheduler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff_doubles() {
+        let executed_at = DateTime::from_timestamp_secs(1_700_000_000).unwrap();
+
+        for (attempts_made, minutes) in [(0, 1), (1, 2), (2, 4), (3, 8), (4, 16), (5, 32)] {
+            assert_eq!(
+                next_retry_time(executed_at, attempts_made, None, None),
+                executed_at + TimeDelta::minutes(minutes),
+                "attempt {attempts_made}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let executed_at = DateTime::from_timestamp_secs(1_700_000_000).unwrap();
+
+        assert_eq!(
+            next_retry_time(executed_at, 200, None, None),
+            executed_at + TimeDelta::days(1)
+        );
+    }
+
+    #[test]
+    fn test_retry_backoff_uses_job_configuration() {
+        let executed_at = DateTime::from_timestamp_secs(1_700_000_000).unwrap();
+
+        for (attempts_made, seconds) in [(0, 5), (1, 10), (2, 20), (3, 30), (10, 30)] {
+            assert_eq!(
+                next_retry_time(executed_at, attempts_made, Some(5_000), Some(30_000)),
+                executed_at + TimeDelta::seconds(seconds),
+                "attempt {attempts_made}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_retry_after_extends_backoff() {
+        let executed_at = DateTime::from_timestamp_secs(1_700_000_000).unwrap();
+        let backoff_time = executed_at + TimeDelta::minutes(1);
+
+        assert_eq!(
+            honor_retry_after(backoff_time, executed_at, None),
+            backoff_time
+        );
+        assert_eq!(
+            honor_retry_after(backoff_time, executed_at, Some(10)),
+            backoff_time
+        );
+        assert_eq!(
+            honor_retry_after(backoff_time, executed_at, Some(300)),
+            executed_at + TimeDelta::minutes(5)
+        );
+        assert_eq!(
+            honor_retry_after(backoff_time, executed_at, Some(i64::MAX)),
+            executed_at + TimeDelta::days(1)
+        );
+    }
+}
@@ -4,11 +4,11 @@ mod tenants;
 mod util;
 mod workflow;
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use futures::future::try_join_all;
 use sqlx::{Pool, Postgres};
-use tokio::task::JoinHandle;
+use tokio::{sync::Semaphore, task::JoinHandle};
 
 use crate::{SchedulerOptions, secrets::KeyRing};
 
@@ -22,6 +22,8 @@ pub struct Config {
     past_retention_count: usize,
     key_rotation_count: usize,
     workflow_count: usize,
+    max_concurrent_workflows: usize,
+    idle_delay: Option<Duration>,
     key_ring: KeyRing,
 }
 
@@ -36,6 +38,9 @@ impl Config {
             past_retention_count: options.past_retention_schedulers,
             key_rotation_count: options.key_rotation_schedulers,
             workflow_count: options.workflow_schedulers,
+            // No permits at all would stall every workflow for good.
+            max_concurrent_workflows: options.max_concurrent_workflows.max(1),
+            idle_delay: options.scheduler_idle_ms.map(Duration::from_millis),
             key_ring: options.key_ring,
         }
     }
@@ -45,23 +50,52 @@ impl Config {
 pub struct SchedulerContext {
     pool: Pool<Postgres>,
     key_ring: KeyRing,
+    /// Overrides every scheduler's `IDLE_DELAY` when set.
+    idle_delay: Option<Duration>,
+    /// Shared by every scheduler that advances workflows, capping how many
+    /// are advanced at once.
+    workflow_permits: Arc<Semaphore>,
 }
 
 #[async_trait::async_trait]
 pub trait Scheduler {
     const WAIT: Duration = Duration::ZERO;
     const IDLE_DELAY: Duration = Duration::from_secs(3);
+    /// Whether each run holds one of the shared workflow permits.
+    const ADVANCES_WORKFLOWS: bool = false;
     async fn run_once(ctx: &SchedulerContext, reached_end: &mut bool) -> anyhow::Result<()>;
 }
 
+/// Adds up to half of `idle_delay` again, so instances that ran out of work
+/// together don't all wake up and contend for the same rows at once.
+fn jittered(idle_delay: Duration) -> Duration {
+    let max_jitter_ms = idle_delay.as_millis() as u64 / 2;
+
+    idle_delay + Duration::from_millis(rand::random_range(0..=max_jitter_ms))
+}
+
+async fn run_once_limited<S: Scheduler>(
+    ctx: &SchedulerContext,
+    reached_end: &mut bool,
+) -> anyhow::Result<()> {
+    let _permit = if S::ADVANCES_WORKFLOWS {
+        Some(ctx.workflow_permits.acquire().await?)
+    } else {
+        None
+    };
+
+    S::run_once(ctx, reached_end).await
+}
+
 async fn scheduling_loop<S: Scheduler>(ctx: &SchedulerContext) -> anyhow::Result<()> {
     let mut reached_end = false;
+    let idle_delay = ctx.idle_delay.unwrap_or(S::IDLE_DELAY);
 
     loop {
-        S::run_once(ctx, &mut reached_end).await?;
+        run_once_limited::<S>(ctx, &mut reached_end).await?;
         if reached_end {
             reached_end = false;
-            tokio::time::sleep(S::IDLE_DELAY).await;
+            tokio::time::sleep(jittered(idle_delay)).await;
         }
     }
 }
@@ -95,6 +129,8 @@ pub async fn start(config: Config) -> anyhow::Result<()> {
     let ctx = SchedulerContext {
         pool: config.pool.clone(),
         key_ring: config.key_ring.clone(),
+        idle_delay: config.idle_delay,
+        workflow_permits: Arc::new(Semaphore::new(config.max_concurrent_workflows)),
     };
 
     let mut all_tasks = Vec::new();
@@ -108,3 +144,71 @@ pub async fn start(config: Config) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    static ADVANCING: AtomicUsize = AtomicUsize::new(0);
+    static MAX_ADVANCING: AtomicUsize = AtomicUsize::new(0);
+
+    struct BurstScheduler;
+
+    #[async_trait::async_trait]
+    impl Scheduler for BurstScheduler {
+        const ADVANCES_WORKFLOWS: bool = true;
+
+        async fn run_once(_ctx: &SchedulerContext, _reached_end: &mut bool) -> anyhow::Result<()> {
+            let advancing = ADVANCING.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_ADVANCING.fetch_max(advancing, Ordering::SeqCst);
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            ADVANCING.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_workflow_advancements_are_capped() -> anyhow::Result<()> {
+        let ctx = SchedulerContext {
+            pool: PgPoolOptions::new().connect_lazy("postgres://localhost/unused")?,
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(3)),
+        };
+
+        let burst = (0..24).map(|_| {
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                let mut reached_end = false;
+                run_once_limited::<BurstScheduler>(&ctx, &mut reached_end).await
+            })
+        });
+
+        for result in try_join_all(burst).await? {
+            result?;
+        }
+
+        assert_eq!(MAX_ADVANCING.load(Ordering::SeqCst), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_the_idle_delay() {
+        let idle_delay = Duration::from_millis(400);
+
+        for _ in 0..100 {
+            let delay = jittered(idle_delay);
+            assert!(delay >= idle_delay);
+            assert!(delay <= Duration::from_millis(600));
+        }
+
+        assert_eq!(jittered(Duration::ZERO), Duration::ZERO);
+    }
+}
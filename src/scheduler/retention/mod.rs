@@ -4,6 +4,7 @@ use crate::scheduler::{Config, SchedulerContext, spawn_scheduler};
 
 mod one_off;
 mod scheduled;
+mod tenants;
 
 pub fn get_retention_schedulers(
     ctx: &SchedulerContext,
@@ -12,5 +13,6 @@ pub fn get_retention_schedulers(
     vec![
         spawn_scheduler::<one_off::OneOffPastRetention>(ctx, config.past_retention_count),
         spawn_scheduler::<scheduled::ScheduledPastRetention>(ctx, config.past_retention_count),
+        spawn_scheduler::<tenants::DeletedTenantPurge>(ctx, config.past_retention_count),
     ]
 }
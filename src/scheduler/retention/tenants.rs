@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use crate::scheduler::{Scheduler, SchedulerContext};
+
+/// Hard-deletes tenants that were deleted with `purge`, along with everything
+/// they own, once their retention has run out.
+#[derive(Clone, Copy)]
+pub struct DeletedTenantPurge;
+
+#[async_trait::async_trait]
+impl Scheduler for DeletedTenantPurge {
+    const WAIT: Duration = Duration::from_mins(30);
+
+    #[tracing::instrument(name = "DeletedTenantPurge::run_once")]
+    async fn run_once(ctx: &SchedulerContext, reached_end: &mut bool) -> anyhow::Result<()> {
+        let mut tx = ctx.pool.begin().await?;
+
+        let tenant_id = sqlx::query_scalar!(
+            r#"
+          SELECT id
+          FROM tenants
+          WHERE deleted_at IS NOT NULL
+            AND purge_after <= now()
+          LIMIT 1 FOR UPDATE SKIP LOCKED;
+          "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(tenant_id) = tenant_id else {
+            *reached_end = true;
+            return Ok(());
+        };
+
+        sqlx::query!(
+            r#"
+          DELETE FROM workflow_dependencies
+          WHERE workflow_execution_id IN (
+              SELECT id FROM workflow_executions WHERE tenant_id = $1)
+            OR child_workflow_id IN (
+              SELECT id FROM workflows WHERE tenant_id = $1);
+          "#,
+            tenant_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let scheduled_jobs = sqlx::query!(
+            r#"
+          DELETE FROM scheduled_jobs
+          WHERE tenant_id = $1
+          RETURNING request_id, execution_id;
+          "#,
+            tenant_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let execution_ids: Vec<String> = scheduled_jobs
+            .iter()
+            .filter_map(|job| job.execution_id.clone())
+            .collect();
+        let mut request_ids: Vec<String> = scheduled_jobs
+            .into_iter()
+            .map(|job| job.request_id)
+            .collect();
+
+        let executions = sqlx::query!(
+            r#"
+          DELETE FROM job_executions
+          WHERE id = ANY($1)
+          RETURNING request_id, response_id;
+          "#,
+            &execution_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let response_ids: Vec<String> = executions
+            .iter()
+            .filter_map(|execution| execution.response_id.clone())
+            .collect();
+        request_ids.extend(executions.into_iter().map(|execution| execution.request_id));
+
+        sqlx::query!(
+            "DELETE FROM workflow_executions WHERE tenant_id = $1;",
+            tenant_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM workflows WHERE tenant_id = $1;", tenant_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let cron_requests = sqlx::query_scalar!(
+            "DELETE FROM cron_jobs WHERE tenant_id = $1 RETURNING request_id;",
+            tenant_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let one_off_requests = sqlx::query_scalar!(
+            "DELETE FROM one_off_jobs WHERE tenant_id = $1 RETURNING request_id;",
+            tenant_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        request_ids.extend(cron_requests.into_iter().chain(one_off_requests));
+
+        sqlx::query!("DELETE FROM api_keys WHERE tenant_id = $1;", tenant_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let signing_keys = sqlx::query!(
+            r#"
+          DELETE FROM tenants
+          WHERE id = $1
+          RETURNING current_signing_key, next_signing_key, previous_signing_key;
+          "#,
+            tenant_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let secret_ids: Vec<String> = [
+            signing_keys.current_signing_key,
+            signing_keys.next_signing_key,
+            signing_keys.previous_signing_key,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        sqlx::query!("DELETE FROM secrets WHERE id = ANY($1);", &secret_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        // Runs of a cron job share its request, so the same id turns up more
+        // than once.
+        request_ids.sort();
+        request_ids.dedup();
+
+        sqlx::query!(
+            "DELETE FROM http_requests WHERE id = ANY($1);",
+            &request_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM http_responses WHERE id = ANY($1);",
+            &response_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info! {
+          tenant_id,
+          "Purged deleted tenant."
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sqlx::PgPool;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::secrets::KeyRing;
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_purges_deleted_tenant_once_due(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO tenants (
+              id, tokens, max_tokens, increment, period,
+              max_timeout, default_retries, max_retries,
+              max_max_response_bytes, max_request_bytes,
+              retain_for_days, max_delay_days, max_cron_jobs,
+              deleted_at, purge_after)
+            VALUES
+              ('tenant_purged', 10, 10, 1, '1 minute', 1000, 0, 0, 1024, 1024, 7, 7, 10,
+                now() - interval '8 days', now() - interval '1 day'),
+              ('tenant_kept', 10, 10, 1, '1 minute', 1000, 0, 0, 1024, 1024, 7, 7, 10,
+                now(), now() + interval '7 days');
+
+            INSERT INTO api_keys (id, tenant_id, key_hash)
+            VALUES ('api_key_a', 'tenant_purged', 'hash_a');
+
+            INSERT INTO http_requests (id, method, url, headers)
+            VALUES
+              ('request_cron', 'GET', 'https://example.com', '{}'),
+              ('request_exec', 'GET', 'https://example.com', '{}');
+            INSERT INTO http_responses (id, status, headers, body)
+            VALUES ('response_a', 200, '{}', 'ok');
+
+            INSERT INTO cron_jobs (id, region, tenant_id, request_id, schedule, max_retries)
+            VALUES ('cron_a', 'na-east', 'tenant_purged', 'request_cron', '* * * * *', 0);
+            INSERT INTO job_executions (id, executed_at, success, request_id, response_id)
+            VALUES ('execution_a', now() - interval '8 days', true, 'request_exec', 'response_a');
+            INSERT INTO scheduled_jobs (
+              id, hash, region, tenant_id, cron_job_id, scheduled_at, request_id,
+              execution_id, max_retries)
+            VALUES (
+              'scheduled_a', 0, 'na-east', 'tenant_purged', 'cron_a',
+              now() - interval '8 days', 'request_cron', 'execution_a', 0);
+
+            INSERT INTO workflows (
+              id, region, tenant_id, implementation_url, input, context, status, max_retries)
+            VALUES (
+              'workflow_a', 'na-east', 'tenant_purged', 'https://example.com', '{}', '{}',
+              'pending', 0);
+            INSERT INTO workflow_executions (
+              id, region, workflow_id, execution_index, tenant_id, status, is_retry)
+            VALUES ('workflow_execution_a', 'na-east', 'workflow_a', 0, 'tenant_purged', 'waiting', false);
+            INSERT INTO workflow_dependencies (id, workflow_execution_id, wait_name, wait_until)
+            VALUES ('dependency_a', 'workflow_execution_a', 'nap', now());
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let ctx = SchedulerContext {
+            pool: pool.clone(),
+            key_ring: KeyRing::dev(),
+            idle_delay: None,
+            workflow_permits: Arc::new(Semaphore::new(1)),
+        };
+
+        let mut reached_end = false;
+        DeletedTenantPurge::run_once(&ctx, &mut reached_end).await?;
+        assert!(!reached_end);
+
+        let left = sqlx::query!(
+            r#"
+            SELECT
+              (SELECT count(*) FROM tenants) as "tenants!",
+              (SELECT count(*) FROM api_keys) as "api_keys!",
+              (SELECT count(*) FROM cron_jobs) as "cron_jobs!",
+              (SELECT count(*) FROM scheduled_jobs) as "scheduled_jobs!",
+              (SELECT count(*) FROM job_executions) as "job_executions!",
+              (SELECT count(*) FROM workflows) as "workflows!",
+              (SELECT count(*) FROM http_requests) as "http_requests!",
+              (SELECT count(*) FROM http_responses) as "http_responses!"
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert_eq!(left.tenants, 1);
+        assert_eq!(left.api_keys, 0);
+        assert_eq!(left.cron_jobs, 0);
+        assert_eq!(left.scheduled_jobs, 0);
+        assert_eq!(left.job_executions, 0);
+        assert_eq!(left.workflows, 0);
+        assert_eq!(left.http_requests, 0);
+        assert_eq!(left.http_responses, 0);
+
+        // The other tenant's retention hasn't run out yet.
+        DeletedTenantPurge::run_once(&ctx, &mut reached_end).await?;
+        assert!(reached_end);
+
+        Ok(())
+    }
+}
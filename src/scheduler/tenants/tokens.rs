@@ -19,7 +19,9 @@ impl Scheduler for TenantTokenScheduler {
       FROM tenants
       WHERE
         next_increment < now() AND
-        tokens < max_tokens
+        tokens < max_tokens AND
+        deleted_at IS NULL AND
+        NOT suspended
       LIMIT 1 FOR UPDATE SKIP LOCKED;
       "#
         )
@@ -12,6 +12,8 @@ pub struct NoExecutionScheduler {}
 
 #[async_trait::async_trait]
 impl Scheduler for NoExecutionScheduler {
+    const ADVANCES_WORKFLOWS: bool = true;
+
     #[tracing::instrument(name = "NoExecutionScheduler::run_once")]
     async fn run_once(ctx: &SchedulerContext, reached_end: &mut bool) -> anyhow::Result<()> {
         let mut tx = ctx.pool.begin().await?;
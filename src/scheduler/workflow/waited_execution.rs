@@ -11,10 +11,27 @@ use crate::{
     util::workflow::{DbDependency, DbExecution, WorkflowContext},
 };
 
+const BASE_EXECUTION_DELAY: Duration = Duration::from_mins(3);
+const MAX_EXECUTION_DELAY: Duration = Duration::from_hours(1);
+const MAX_BACKOFF_EXPONENT: u32 = 10;
+
+/// Retried executions back off geometrically, capped at an hour.
+fn execution_delay(is_retry: bool, retry_count: u32) -> Duration {
+    let wait_factor = if is_retry {
+        2u32.pow(retry_count.min(MAX_BACKOFF_EXPONENT))
+    } else {
+        1
+    };
+
+    (BASE_EXECUTION_DELAY * wait_factor).min(MAX_EXECUTION_DELAY)
+}
+
 pub struct WaitedExecutionScheduler;
 
 #[async_trait::async_trait]
 impl Scheduler for WaitedExecutionScheduler {
+    const ADVANCES_WORKFLOWS: bool = true;
+
     #[tracing::instrument(name = "WaitedExecutionScheduler::run_once")]
     async fn run_once(ctx: &SchedulerContext, reached_end: &mut bool) -> anyhow::Result<()> {
         let mut tx = ctx.pool.begin().await?;
@@ -156,12 +173,7 @@ impl Scheduler for WaitedExecutionScheduler {
         .execute(&mut *tx)
         .await?;
 
-        let wait_factor = if last_execution.is_retry {
-            2 ^ retry_count
-        } else {
-            1
-        };
-        let wait_time = Duration::from_mins(3) * wait_factor;
+        let wait_time = execution_delay(last_execution.is_retry, retry_count);
         let scheduled_at = Utc::now() + wait_time;
 
         let scheduled_job_id = id::gen_for_time("scheduled_job", scheduled_at);
@@ -215,3 +227,26 @@ impl Scheduler for WaitedExecutionScheduler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_delay_grows_geometrically() {
+        assert_eq!(execution_delay(false, 4), Duration::from_mins(3));
+
+        let delays: Vec<Duration> = (0..5).map(|count| execution_delay(true, count)).collect();
+
+        assert_eq!(delays[0], Duration::from_mins(3));
+        for pair in delays.windows(2) {
+            assert_eq!(pair[1], pair[0] * 2);
+        }
+    }
+
+    #[test]
+    fn test_execution_delay_is_capped() {
+        assert_eq!(execution_delay(true, 5), Duration::from_hours(1));
+        assert_eq!(execution_delay(true, u32::MAX), Duration::from_hours(1));
+    }
+}
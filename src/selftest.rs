@@ -0,0 +1,326 @@
+use std::{collections::HashMap, fmt, net::SocketAddr, time::Duration};
+
+use anyhow::{Context, anyhow};
+use axum::{Router, body::Bytes, extract::State, http::Method};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{net::TcpListener, sync::mpsc, time::Instant};
+
+use crate::id;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub api_url: String,
+    pub auth_key: Option<String>,
+    pub echo_addr: SocketAddr,
+    pub timeout: Duration,
+}
+
+/// What a passing selftest observed.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub job_id: String,
+    pub execution_id: String,
+    pub echo_url: String,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Selftest passed in {:.1}s.", self.elapsed.as_secs_f64())?;
+        writeln!(f, "  echo server: {}", self.echo_url)?;
+        writeln!(f, "  job:         {}", self.job_id)?;
+        write!(f, "  execution:   {}", self.execution_id)
+    }
+}
+
+#[derive(Debug)]
+struct EchoedRequest {
+    method: Method,
+    body: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedJob {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Job {
+    executions: Vec<Execution>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Execution {
+    id: String,
+    executed_at: Option<i64>,
+    success: Option<bool>,
+    response: Option<Response>,
+    response_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    status: i32,
+    body: String,
+}
+
+async fn echo(State(tx): State<mpsc::Sender<EchoedRequest>>, method: Method, body: Bytes) -> Bytes {
+    let _ = tx
+        .send(EchoedRequest {
+            method,
+            body: body.clone(),
+        })
+        .await;
+
+    body
+}
+
+/// Serves every request by replying with its body, forwarding what it
+/// received to the returned channel.
+async fn start_echo_server(
+    addr: SocketAddr,
+) -> anyhow::Result<(SocketAddr, mpsc::Receiver<EchoedRequest>)> {
+    let (tx, rx) = mpsc::channel(16);
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Could not bind the echo server to {addr}"))?;
+    let local_addr = listener.local_addr()?;
+
+    let app = Router::new().fallback(echo).with_state(tx);
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    Ok((local_addr, rx))
+}
+
+async fn wait_for_api(
+    client: &reqwest::Client,
+    api_url: &str,
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    loop {
+        let res = client.get(format!("{api_url}/readyz")).send().await;
+
+        match res {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            _ if Instant::now() >= deadline => {
+                return Err(anyhow!("Api at {api_url} never became ready"));
+            }
+            _ => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Schedules a one-off job against a local echo server through the api at
+/// `api_url`, then waits for the stack to record the echoed response.
+pub async fn run(options: Options) -> anyhow::Result<Report> {
+    let start = Instant::now();
+    let deadline = start + options.timeout;
+    let api_url = options.api_url.trim_end_matches('/');
+
+    let (echo_addr, mut echoed) = start_echo_server(options.echo_addr).await?;
+    let echo_url = format!("http://{echo_addr}/selftest");
+    let nonce = id::generate("selftest");
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(auth_key) = &options.auth_key {
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {auth_key}").parse()?,
+        );
+    }
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    wait_for_api(&client, api_url, deadline).await?;
+
+    let res = client
+        .post(format!("{api_url}/api/jobs"))
+        .json(&json!({
+          "execute_at": Utc::now().timestamp(),
+          "request": {
+            "method": "POST",
+            "url": echo_url,
+            "headers": HashMap::<String, String>::new(),
+            "body": nonce,
+          },
+          "max_retries": 0,
+        }))
+        .send()
+        .await
+        .context("Failed to reach the api to create the selftest job")?;
+
+    if !res.status().is_success() {
+        return Err(anyhow!(
+            "Api rejected the selftest job with {}: {}",
+            res.status(),
+            res.text().await.unwrap_or_default()
+        ));
+    }
+
+    let job: CreatedJob = res.json().await?;
+
+    let execution = loop {
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "Job {} was not executed within {}s. Check that the scheduler, broker, and a drone are running and that the drone can reach {echo_url}",
+                job.id,
+                options.timeout.as_secs()
+            ));
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let res = client
+            .get(format!("{api_url}/api/jobs/{}", job.id))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            continue;
+        }
+
+        let polled: Job = res.json().await?;
+
+        if let Some(execution) = polled
+            .executions
+            .into_iter()
+            .find(|execution| execution.executed_at.is_some())
+        {
+            break execution;
+        }
+    };
+
+    verify_execution(&execution, &nonce)
+        .with_context(|| format!("Execution {} of job {} failed", execution.id, job.id))?;
+
+    let received = echoed
+        .try_recv()
+        .map_err(|_| anyhow!("Echo server never received the job's request"))?;
+
+    if received.method != Method::POST || received.body != nonce.as_bytes() {
+        return Err(anyhow!(
+            "Echo server received {} with an unexpected body",
+            received.method
+        ));
+    }
+
+    Ok(Report {
+        job_id: job.id,
+        execution_id: execution.id,
+        echo_url,
+        elapsed: start.elapsed(),
+    })
+}
+
+fn verify_execution(execution: &Execution, nonce: &str) -> anyhow::Result<()> {
+    if let Some(error) = &execution.response_error {
+        return Err(anyhow!("Drone reported an error: {error}"));
+    }
+
+    let response = execution
+        .response
+        .as_ref()
+        .ok_or(anyhow!("No response was recorded"))?;
+
+    if response.status != 200 {
+        return Err(anyhow!("Echo server responded with {}", response.status));
+    }
+
+    if response.body != nonce {
+        return Err(anyhow!(
+            "Recorded body {:?} does not match the {nonce:?} that was sent",
+            response.body
+        ));
+    }
+
+    if execution.success != Some(true) {
+        return Err(anyhow!("Execution was not marked successful"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use sqlx::PgPool;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::{
+        DevOptions, DroneOptions, GLOBAL_CONFIG, GlobalConfig, api, broker, drone, scheduler,
+    };
+
+    fn execution(status: i32, body: &str) -> Execution {
+        Execution {
+            id: "execution_1".to_string(),
+            executed_at: Some(1_700_000_000),
+            success: Some(true),
+            response: Some(Response {
+                status,
+                body: body.to_string(),
+            }),
+            response_error: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_execution_checks_echoed_body() {
+        assert!(verify_execution(&execution(200, "selftest_1"), "selftest_1").is_ok());
+        assert!(verify_execution(&execution(200, "other"), "selftest_1").is_err());
+        assert!(verify_execution(&execution(500, "selftest_1"), "selftest_1").is_err());
+    }
+
+    #[sqlx::test(migrator = "crate::pg::MIGRATOR")]
+    async fn test_selftest_passes_against_dev_stack(pool: PgPool) -> anyhow::Result<()> {
+        let _ = GLOBAL_CONFIG.set(GlobalConfig { is_dev: true });
+
+        let dev = DevOptions::try_parse_from([
+            "rocktick",
+            "--api-port",
+            "39190",
+            "--broker-port",
+            "39191",
+            "--drone-port",
+            "39192",
+            "--postgres-url",
+            "unused",
+        ])?;
+
+        let mut drone_options: DroneOptions = dev.clone().try_into()?;
+        drone_options.store_path =
+            std::env::temp_dir().join(format!("{}.db", id::generate("selftest_drone")));
+
+        let api_config = api::Config::from_cli(dev.clone().try_into()?, pool.clone()).await;
+        let broker_config = broker::Config::from_cli(dev.clone().try_into()?, pool.clone()).await;
+        let drone_config = drone::Config::from_cli(drone_options).await;
+        let scheduler_config =
+            scheduler::Config::from_cli(dev.clone().try_into()?, pool.clone()).await;
+
+        let options = Options {
+            api_url: "http://127.0.0.1:39190".to_string(),
+            auth_key: None,
+            echo_addr: "127.0.0.1:0".parse()?,
+            timeout: Duration::from_secs(60),
+        };
+
+        let report = tokio::select! {
+          res = api::start(api_config, CancellationToken::new()) => return Err(anyhow!("Api stopped: {res:?}")),
+          res = broker::start(broker_config) => return Err(anyhow!("Broker stopped: {res:?}")),
+          res = drone::start(drone_config) => return Err(anyhow!("Drone stopped: {res:?}")),
+          res = scheduler::start(scheduler_config) => return Err(anyhow!("Scheduler stopped: {res:?}")),
+          report = run(options) => report?,
+        };
+
+        assert!(report.job_id.starts_with("one_off_job"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use tokio_util::sync::CancellationToken;
+
+/// Cancels the returned token on the first ctrl-c. This is the only ctrl-c
+/// listener, anything that has to stop on it waits on the token instead.
+pub fn on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!("Received Ctrl-C.");
+            cancel.cancel();
+        }
+    });
+
+    token
+}
+
+/// Runs `services` until they stop or `shutdown` is cancelled, then runs
+/// `cleanup` once. Cleanup only starts after the services have been dropped,
+/// so nothing they still use is torn down underneath them.
+pub async fn run_until_shutdown<S, C>(
+    shutdown: CancellationToken,
+    services: S,
+    cleanup: C,
+) -> anyhow::Result<()>
+where
+    S: Future<Output = anyhow::Result<()>>,
+    C: Future<Output = ()>,
+{
+    let result = tokio::select! {
+      res = services => res,
+      _ = shutdown.cancelled() => Ok(()),
+    };
+
+    cleanup.await;
+
+    result
+}
+
+/// Runs `services` until they stop, then runs `cleanup` once. For services
+/// that stop themselves when the shutdown token is cancelled, which get to
+/// finish what they're doing rather than being dropped part way through.
+pub async fn run_until_drained<S, C>(services: S, cleanup: C) -> anyhow::Result<()>
+where
+    S: Future<Output = anyhow::Result<()>>,
+    C: Future<Output = ()>,
+{
+    let result = services.await;
+
+    cleanup.await;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signal_runs_cleanup_once() {
+        let shutdown = CancellationToken::new();
+        let cleanups = AtomicUsize::new(0);
+
+        // Every clone sees the same signal, there is nothing else to race.
+        shutdown.clone().cancel();
+        shutdown.clone().cancel();
+
+        let result = run_until_shutdown(shutdown, std::future::pending(), async {
+            cleanups.fetch_add(1, Ordering::SeqCst);
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(cleanups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_draining_service_finishes_before_cleanup() {
+        let shutdown = CancellationToken::new();
+        let drained = AtomicUsize::new(0);
+
+        shutdown.cancel();
+
+        let result = run_until_drained(
+            async {
+                shutdown.cancelled().await;
+                tokio::task::yield_now().await;
+                drained.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            },
+            async {
+                assert_eq!(drained.load(Ordering::SeqCst), 1);
+            },
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failed_service_still_cleans_up() {
+        let cleanups = AtomicUsize::new(0);
+
+        let result = run_until_shutdown(
+            CancellationToken::new(),
+            async { Err(anyhow!("broker stopped")) },
+            async {
+                cleanups.fetch_add(1, Ordering::SeqCst);
+            },
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(cleanups.load(Ordering::SeqCst), 1);
+    }
+}
@@ -17,24 +17,46 @@ pub struct SignatureBuilder {
 type HmacSha256 = Hmac<Sha256>;
 
 impl SignatureBuilder {
-    pub fn signature_header(self) -> anyhow::Result<String> {
-        let scheduled_at = self.time.timestamp();
+    /// The canonical string that gets signed.
+    pub fn signing_string(&self) -> anyhow::Result<String> {
         let url = Url::parse(&self.url)?;
-        let pathname = url.path();
-
-        let mut mac = HmacSha256::new_from_slice(self.signing_key.as_bytes())
-            .expect("Hmac could not take signing key?");
 
         let mut message = self.method.to_lowercase();
-        message.push_str(&format!(".{}", scheduled_at));
-        message.push_str(&format!(".{}", &pathname));
+        message.push_str(&format!(".{}", self.time.timestamp()));
+        message.push_str(&format!(".{}", url.path()));
 
-        if let Some(body) = self.body {
+        if let Some(body) = &self.body {
             message.push_str(&format!(".{}", body));
         }
 
+        Ok(message)
+    }
+
+    fn mac(&self) -> anyhow::Result<HmacSha256> {
+        let mut mac = HmacSha256::new_from_slice(self.signing_key.as_bytes())
+            .expect("Hmac could not take signing key?");
+
+        let message = self.signing_string()?;
         mac.update(message.as_bytes());
-        let result = mac.finalize();
+
+        Ok(mac)
+    }
+
+    /// Checks a hex `v1` signature against this request in constant time.
+    pub fn verify(&self, hex_signature: &str) -> anyhow::Result<bool> {
+        let Ok(signature) = hex::decode(hex_signature) else {
+            return Ok(false);
+        };
+
+        Ok(self.mac()?.verify_slice(&signature).is_ok())
+    }
+
+    pub fn signature_header(self) -> anyhow::Result<String> {
+        let scheduled_at = self.time.timestamp();
+        let url = Url::parse(&self.url)?;
+        let pathname = url.path();
+
+        let result = self.mac()?.finalize();
         let code_bytes = result.into_bytes();
         let hex_signature = hex::encode(code_bytes);
 
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use opentelemetry::{global, trace::TracerProvider};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource, metrics::SdkMeterProvider, propagation::TraceContextPropagator,
+    trace::SdkTracerProvider,
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+const TRACEPARENT: &str = "traceparent";
+
+/// Builds a tracer provider exporting spans over OTLP/HTTP to `endpoint`,
+/// e.g. `http://localhost:4318`.
+pub fn tracer_provider(endpoint: &str, service_name: &str) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// Builds a meter provider exporting metrics over OTLP/HTTP to `endpoint`.
+pub fn meter_provider(endpoint: &str, service_name: &str) -> anyhow::Result<SdkMeterProvider> {
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/metrics", endpoint.trim_end_matches('/')))
+        .build()?;
+
+    Ok(SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// A tracing layer forwarding spans to `provider`. Also installs the w3c
+/// trace context propagator used to carry traces between services.
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S> + use<S>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("rocktick"))
+}
+
+/// The current span's trace context as a w3c `traceparent` value, if it's
+/// part of a trace.
+pub fn current_traceparent() -> Option<String> {
+    let context = Span::current().context();
+    let mut carrier = HashMap::new();
+
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+
+    carrier.remove(TRACEPARENT)
+}
+
+/// Continues the trace an upstream service passed along as `traceparent`.
+pub fn set_parent(span: &Span, traceparent: Option<&str>) {
+    let Some(traceparent) = traceparent else {
+        return;
+    };
+
+    let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.to_string())]);
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+
+    let _ = span.set_parent(context);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use axum::{Router, extract::State, routing::post};
+    use http::StatusCode;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    async fn collect(State(received): State<Arc<AtomicUsize>>) -> StatusCode {
+        received.fetch_add(1, Ordering::SeqCst);
+        StatusCode::OK
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_to_collector() -> anyhow::Result<()> {
+        let received = Arc::new(AtomicUsize::new(0));
+
+        let collector = Router::new()
+            .route("/v1/traces", post(collect))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        // The OTLP/HTTP exporter uses a blocking client, so it's driven from
+        // its own thread like it is in main.
+        let (traceparent, flushed) = std::thread::spawn(move || -> anyhow::Result<_> {
+            let provider = tracer_provider(&endpoint, "rocktick-test")?;
+            let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+            let traceparent = tracing::subscriber::with_default(subscriber, || {
+                let _span = tracing::info_span!("Broker::get_jobs").entered();
+                current_traceparent()
+            });
+
+            let flushed = provider.force_flush();
+            provider.shutdown()?;
+
+            Ok((traceparent, flushed))
+        })
+        .join()
+        .expect("Exporter thread panicked.")?;
+
+        flushed?;
+        assert!(traceparent.is_some_and(|value| value.starts_with("00-")));
+        assert!(received.load(Ordering::SeqCst) > 0);
+
+        Ok(())
+    }
+}